@@ -0,0 +1,264 @@
+use crate::caldav;
+use crate::ctx::Ctx;
+use crate::source::Source;
+use chrono::NaiveDateTime;
+
+/// URL prefix for feeds printed by a shell command (e.g., "command:~/bin/calendar-ics").
+const COMMAND_PREFIX: &str = "command:";
+
+/// What a fetch needs beyond the source itself.
+#[derive(Debug, Clone, Default)]
+pub struct FetchRequest<'a> {
+    /// `[from, to)` in UTC that events are wanted for (CalDAV asks the server for just this).
+    pub range_utc: Option<(NaiveDateTime, NaiveDateTime)>,
+    pub max_payload_bytes: u64,
+    /// Send the validators of the events already held, so an unchanged feed answers 304.
+    pub conditional: bool,
+    /// Save the body here (debug builds), then read it back.
+    pub save_to: Option<&'a str>,
+}
+
+/// A command to run for a fetch, and the context its result comes back with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchCommand {
+    pub args: Vec<String>,
+    pub ctx: Ctx,
+}
+
+/// How a source's events are fetched, and its output turned into ICS data. New kinds of
+/// feeds implement this and join `REGISTRY`; `State` only runs the command it returns.
+pub trait Backend: Sync {
+    /// Shown in the debug view.
+    fn kind(&self) -> &'static str;
+
+    /// Whether this backend fetches `source`.
+    fn handles(&self, source: &Source) -> bool;
+
+    /// Whether fetches need `FetchRequest::range_utc` (they wait for the time until then).
+    fn needs_range(&self) -> bool {
+        false
+    }
+
+    /// Whether `FetchRequest::conditional` is honored.
+    fn supports_conditional(&self) -> bool {
+        false
+    }
+
+    fn fetch_command(&self, source: &Source, request: &FetchRequest) -> FetchCommand;
+
+    /// ICS data from the command's output.
+    fn extract(&self, output: Vec<u8>) -> Result<Vec<u8>, String> {
+        Ok(output)
+    }
+
+    /// How a result is logged, and how its errors begin: ("Fetched ICS", "Fetch failed").
+    fn labels(&self) -> (&'static str, &'static str) {
+        ("Fetched ICS", "Fetch failed")
+    }
+}
+
+/// Every backend, most specific first; the first that handles a source fetches it.
+static REGISTRY: &[&dyn Backend] = &[&Command, &File, &CalDav, &IcsHttp];
+
+/// The backend fetching `source` (plain HTTP unless another claims it).
+pub fn for_source(source: &Source) -> &'static dyn Backend {
+    REGISTRY
+        .iter()
+        .copied()
+        .find(|backend| backend.handles(source))
+        .unwrap_or(&IcsHttp)
+}
+
+/// An ICS feed downloaded with curl.
+struct IcsHttp;
+
+impl Backend for IcsHttp {
+    fn kind(&self) -> &'static str {
+        "http"
+    }
+
+    fn handles(&self, _source: &Source) -> bool {
+        true
+    }
+
+    fn supports_conditional(&self) -> bool {
+        true
+    }
+
+    fn fetch_command(&self, source: &Source, request: &FetchRequest) -> FetchCommand {
+        curl(source, request, Vec::new())
+    }
+}
+
+/// A CalDAV collection, queried with a REPORT for the events in range.
+struct CalDav;
+
+impl Backend for CalDav {
+    fn kind(&self) -> &'static str {
+        "caldav"
+    }
+
+    fn handles(&self, source: &Source) -> bool {
+        source.caldav
+    }
+
+    fn needs_range(&self) -> bool {
+        true
+    }
+
+    fn fetch_command(&self, source: &Source, request: &FetchRequest) -> FetchCommand {
+        let report = request
+            .range_utc
+            .map(|(from, to)| caldav::report_args(from, to))
+            .unwrap_or_default();
+        // CalDAV ranges move with time, so there's nothing to be conditional on
+        let request = FetchRequest {
+            conditional: false,
+            ..request.clone()
+        };
+        curl(source, &request, report)
+    }
+
+    fn extract(&self, output: Vec<u8>) -> Result<Vec<u8>, String> {
+        caldav::extract_calendar_data(&output)
+    }
+}
+
+/// Files on disk: a path, a glob, or a vdir collection directory.
+struct File;
+
+impl Backend for File {
+    fn kind(&self) -> &'static str {
+        "file"
+    }
+
+    fn handles(&self, source: &Source) -> bool {
+        source.local_path().is_some()
+    }
+
+    fn fetch_command(&self, source: &Source, _request: &FetchRequest) -> FetchCommand {
+        let path = source.local_path().unwrap_or_default();
+        FetchCommand {
+            args: Source::read_command(path).map(String::from).to_vec(),
+            ctx: Ctx::IcsFetch {
+                calendar: source.name.clone(),
+            },
+        }
+    }
+
+    fn labels(&self) -> (&'static str, &'static str) {
+        ("Read ICS", "Read failed")
+    }
+}
+
+/// A shell command printing ICS data (`command:...`).
+struct Command;
+
+impl Backend for Command {
+    fn kind(&self) -> &'static str {
+        "command"
+    }
+
+    fn handles(&self, source: &Source) -> bool {
+        source.url.trim().starts_with(COMMAND_PREFIX)
+    }
+
+    fn fetch_command(&self, source: &Source, _request: &FetchRequest) -> FetchCommand {
+        let command = source.url.trim().trim_start_matches(COMMAND_PREFIX).trim();
+        FetchCommand {
+            args: vec!["sh".to_string(), "-c".to_string(), command.to_string()],
+            ctx: Ctx::IcsFetch {
+                calendar: source.name.clone(),
+            },
+        }
+    }
+
+    fn labels(&self) -> (&'static str, &'static str) {
+        ("Ran command", "Command failed")
+    }
+}
+
+/// The curl fetch shared by HTTP and CalDAV, with `request_args` before the URL.
+fn curl(source: &Source, request: &FetchRequest, mut request_args: Vec<String>) -> FetchCommand {
+    let calendar = source.name.clone();
+    let ctx = match request.save_to {
+        Some(path) => {
+            request_args.extend(["--create-dirs", "--output", path].map(String::from));
+            Ctx::IcsFetchFile {
+                calendar,
+                path: path.to_string(),
+            }
+        }
+        None => Ctx::IcsFetch { calendar },
+    };
+    // Debug saves need the body, so they always fetch in full
+    let conditional = request.conditional && request.save_to.is_none();
+    FetchCommand {
+        args: source.fetch_command(request.max_payload_bytes, &request_args, conditional),
+        ctx,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CalendarConfig, FetchAuth};
+
+    fn source(url: &str, caldav: bool) -> Source {
+        Source::from(CalendarConfig {
+            name: "work".to_string(),
+            url: url.to_string(),
+            color: None,
+            label: None,
+            overlay: false,
+            caldav,
+            auth: FetchAuth::default(),
+        })
+    }
+
+    #[test]
+    fn test_for_source() {
+        let kind = |url, caldav| for_source(&source(url, caldav)).kind();
+        assert_eq!(kind("https://example.com/work.ics", false), "http");
+        assert_eq!(kind("https://dav.example.com/cal/", true), "caldav");
+        assert_eq!(kind("~/.calendars/work/", false), "file");
+        assert_eq!(kind("command:~/bin/calendar-ics", false), "command");
+    }
+
+    #[test]
+    fn test_fetch_command() {
+        let range = crate::calendar::parse_datetime("2024-01-15 00:00")
+            .zip(crate::calendar::parse_datetime("2024-02-15 00:00"));
+        let request = FetchRequest {
+            range_utc: range,
+            max_payload_bytes: 1024,
+            conditional: true,
+            save_to: None,
+        };
+        let ctx = Ctx::IcsFetch {
+            calendar: "work".to_string(),
+        };
+
+        let http = source("https://example.com/work.ics", false);
+        let cmd = for_source(&http).fetch_command(&http, &request);
+        assert_eq!(cmd.ctx, ctx);
+        assert_eq!(cmd.args, http.fetch_command(1024, &[], true));
+
+        let dav = source("https://dav.example.com/cal/", true);
+        let cmd = for_source(&dav).fetch_command(&dav, &request);
+        assert!(cmd.args.iter().any(|a| a == "REPORT"));
+
+        let saved = FetchRequest {
+            save_to: Some("/tmp/zj-cal/work.ics"),
+            ..request.clone()
+        };
+        let cmd = for_source(&http).fetch_command(&http, &saved);
+        assert!(matches!(cmd.ctx, Ctx::IcsFetchFile { .. }));
+        assert_eq!(cmd.args, http.fetch_command(1024, &cmd.args[6..9], false));
+
+        let shell = source("command: ~/bin/calendar-ics", false);
+        let cmd = for_source(&shell).fetch_command(&shell, &request);
+        assert_eq!(cmd.args, ["sh", "-c", "~/bin/calendar-ics"]);
+        assert_eq!(cmd.ctx, ctx);
+    }
+}
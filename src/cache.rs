@@ -0,0 +1,294 @@
+use crate::calendar::Event;
+use crate::command;
+use crate::source::Validators;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Cache directory on the host (expanded by `sh`, since the plugin can't see host env vars).
+const CACHE_DIR: &str = "${XDG_CACHE_HOME:-$HOME/.cache}/zj-cal";
+
+/// Parsed events for one calendar, as stored on disk.
+#[derive(Serialize, Deserialize)]
+pub struct CachedEvents {
+    /// Hash of the calendar URL, so a changed URL doesn't restore another feed's events.
+    url_hash: u64,
+    pub events: Vec<Event>,
+    /// Birthdays and anniversaries, kept apart from `events`.
+    #[serde(default)]
+    pub occasions: Vec<Event>,
+    /// When the events were fetched (missing in caches from older versions).
+    #[serde(default)]
+    pub fetched_at: Option<NaiveDateTime>,
+    /// Validators of the response the events were parsed from.
+    #[serde(default)]
+    pub validators: Validators,
+    /// Hash of the filter settings the events were stored with. A 304 keeps cached events
+    /// as-is, so validators only carry over while the settings are unchanged.
+    #[serde(default)]
+    settings_hash: u64,
+}
+
+/// Stable FNV-1a hash (std's `DefaultHasher` may change between Rust releases).
+fn hash(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Cache file name for a calendar, restricted to safe characters.
+fn file_name(calendar: &str) -> String {
+    let name: String = calendar
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("events-{}.json", name)
+}
+
+pub fn encode(
+    url: &str,
+    settings: &str,
+    events: &[Event],
+    occasions: &[Event],
+    fetched_at: Option<NaiveDateTime>,
+    validators: &Validators,
+) -> Result<String, String> {
+    let cached = CachedEvents {
+        url_hash: hash(url),
+        events: events.to_vec(),
+        occasions: occasions.to_vec(),
+        fetched_at,
+        validators: validators.clone(),
+        settings_hash: hash(settings),
+    };
+    serde_json::to_string(&cached).map_err(|e| e.to_string())
+}
+
+/// Returns the cache if the data is valid and was cached for `url`. Validators are dropped
+/// when the events were stored under different `settings`, forcing a full fetch.
+pub fn decode(data: &[u8], url: &str, settings: &str) -> Option<CachedEvents> {
+    let mut cached: CachedEvents = serde_json::from_slice(data).ok()?;
+    if cached.url_hash != hash(url) {
+        return None;
+    }
+    if cached.settings_hash != hash(settings) {
+        cached.validators = Validators::default();
+    }
+    Some(cached)
+}
+
+/// Shell command that prints a calendar's cache file.
+pub fn read_command(calendar: &str) -> Vec<String> {
+    let script = format!("cat \"{}/$1\"", CACHE_DIR);
+    vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        script,
+        "sh".to_string(),
+        file_name(calendar),
+    ]
+}
+
+/// Shell command that (over)writes a calendar's cache file with `data`.
+/// Values are passed as positional args, never interpolated into the script; `data` in
+/// pieces (see `command::chunks`), since a busy calendar's cache outgrows one argument.
+pub fn write_command(calendar: &str, data: String) -> Vec<String> {
+    let script = format!(
+        "f=$1; shift; mkdir -p \"{dir}\" && printf '%s' \"$@\" > \"{dir}/$f\"",
+        dir = CACHE_DIR
+    );
+    let args = std::iter::once(file_name(calendar)).chain(command::chunks(&data));
+    command::sh(&script, args)
+}
+
+/// Pinned events' IDs, one per line, next to the event caches.
+const PINS_FILE: &str = "pins.txt";
+
+/// IDs of recurring series hidden for good (`X`), one per line, next to the event caches.
+const HIDDEN_SERIES_FILE: &str = "hidden-series.txt";
+
+/// Shell command that prints an ID list file in the cache directory.
+fn read_ids_command(file: &str) -> Vec<String> {
+    let script = format!("cat \"{}/{}\"", CACHE_DIR, file);
+    vec!["sh".to_string(), "-c".to_string(), script]
+}
+
+/// Shell command that (over)writes an ID list file in the cache directory.
+fn write_ids_command(file: &str, ids: &[String]) -> Vec<String> {
+    let script = format!(
+        "mkdir -p \"{dir}\" && printf '%s' \"$1\" > \"{dir}/{file}\"",
+        dir = CACHE_DIR,
+        file = file
+    );
+    vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        script,
+        "sh".to_string(),
+        ids.join("\n"),
+    ]
+}
+
+/// Shell command that prints the pinned event IDs.
+pub fn read_pins_command() -> Vec<String> {
+    read_ids_command(PINS_FILE)
+}
+
+/// Shell command that (over)writes the pinned event IDs.
+pub fn write_pins_command(pins: &[String]) -> Vec<String> {
+    write_ids_command(PINS_FILE, pins)
+}
+
+/// Shell command that prints the hidden series' IDs.
+pub fn read_hidden_series_command() -> Vec<String> {
+    read_ids_command(HIDDEN_SERIES_FILE)
+}
+
+/// Shell command that (over)writes the hidden series' IDs.
+pub fn write_hidden_series_command(ids: &[String]) -> Vec<String> {
+    write_ids_command(HIDDEN_SERIES_FILE, ids)
+}
+
+/// Parses an ID list file (pins, hidden series), skipping blank lines.
+pub fn parse_ids(data: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(data)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Default calendar's URL as last set with `U`, with `persist_url`.
+const URL_FILE: &str = "ics_url.txt";
+
+/// Shell command that prints the saved calendar URL.
+pub fn read_url_command() -> Vec<String> {
+    let script = format!("cat \"{}/{}\"", CACHE_DIR, URL_FILE);
+    vec!["sh".to_string(), "-c".to_string(), script]
+}
+
+/// Shell command that (over)writes the saved calendar URL, readable only by the user since
+/// secret feed URLs are credentials.
+pub fn write_url_command(url: &str) -> Vec<String> {
+    let script = format!(
+        "umask 077 && mkdir -p \"{dir}\" && printf '%s' \"$1\" > \"{dir}/{file}\"",
+        dir = CACHE_DIR,
+        file = URL_FILE
+    );
+    vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        script,
+        "sh".to_string(),
+        url.to_string(),
+    ]
+}
+
+/// Shell command that deletes every cached calendar.
+pub fn clear_command() -> Vec<String> {
+    let script = format!("rm -f \"{}\"/events-*.json", CACHE_DIR);
+    vec!["sh".to_string(), "-c".to_string(), script]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::parse_datetime;
+
+    #[test]
+    fn test_write_large() {
+        // Far over the 128 KiB one argument can hold
+        let dir = std::env::temp_dir().join(format!("zj-cal-cache-{}", std::process::id()));
+        let data = "{\"summary\":\"$(id) é\"},".repeat(20_000);
+        let cmd = write_command("Work cal", data.clone());
+        assert_eq!(command::check(&cmd), Ok(()));
+        let output = std::process::Command::new(&cmd[0])
+            .args(&cmd[1..])
+            .env("HOME", &dir)
+            .env_remove("XDG_CACHE_HOME")
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        let written = std::fs::read_to_string(dir.join(".cache/zj-cal/events-Work_cal.json"));
+        assert_eq!(written.unwrap(), data);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let events = vec![Event {
+            summary: "Standup".into(),
+            start: parse_datetime("2024-01-15 10:00").unwrap(),
+            end: parse_datetime("2024-01-15 10:15"),
+            ..Default::default()
+        }];
+        let fetched_at = parse_datetime("2024-01-15 09:00");
+        let validators = Validators {
+            etag: Some("\"abc\"".to_string()),
+            last_modified: None,
+        };
+        let url = "https://example.com/a.ics";
+        let occasions = vec![Event {
+            summary: "Sam's birthday".into(),
+            categories: vec!["Birthday".into()],
+            ..Default::default()
+        }];
+        let data = encode(
+            url,
+            "private=false",
+            &events,
+            &occasions,
+            fetched_at,
+            &validators,
+        )
+        .unwrap();
+
+        let restored = decode(data.as_bytes(), url, "private=false").unwrap();
+        assert_eq!(restored.events.len(), 1);
+        assert!(restored.occasions[0].is_occasion());
+        assert_eq!(restored.events[0].summary, "Standup");
+        assert_eq!(restored.events[0].end, events[0].end);
+        assert_eq!(restored.fetched_at, fetched_at);
+        assert_eq!(restored.validators, validators);
+
+        // Events stored under other settings still restore, but get re-fetched in full
+        let restored = decode(data.as_bytes(), url, "private=true").unwrap();
+        assert_eq!(restored.events.len(), 1);
+        assert_eq!(restored.validators, Validators::default());
+
+        // Caches written before fetch times (or occasions) were recorded still restore
+        let old = format!(r#"{{"url_hash":{},"events":[]}}"#, hash("x"));
+        let old = decode(old.as_bytes(), "x", "").unwrap();
+        assert!(old.fetched_at.is_none());
+        assert!(old.occasions.is_empty());
+
+        // Different URL or garbage data: no restore
+        assert!(decode(
+            data.as_bytes(),
+            "https://example.com/b.ics",
+            "private=false"
+        )
+        .is_none());
+        assert!(decode(b"not json", url, "private=false").is_none());
+    }
+
+    #[test]
+    fn test_parse_ids() {
+        let pins = ["abc@example.com".to_string(), "Team sync".to_string()];
+        let data = write_pins_command(&pins).pop().unwrap();
+        assert_eq!(parse_ids(data.as_bytes()), pins);
+        assert!(parse_ids(b"\n  \n").is_empty());
+    }
+
+    #[test]
+    fn test_file_name_is_sanitized() {
+        assert_eq!(file_name("work"), "events-work.json");
+        assert_eq!(file_name("../etc/passwd"), "events-___etc_passwd.json");
+    }
+}
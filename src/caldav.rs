@@ -0,0 +1,234 @@
+use chrono::NaiveDateTime;
+use regex::Regex;
+
+/// `calendar-query` REPORT body asking for every VEVENT overlapping `[from, to)` (UTC).
+pub fn report_body(from_utc: NaiveDateTime, to_utc: NaiveDateTime) -> String {
+    let fmt = |t: NaiveDateTime| t.format("%Y%m%dT%H%M%SZ").to_string();
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<C:calendar-query xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+  <D:prop><C:calendar-data/></D:prop>
+  <C:filter>
+    <C:comp-filter name="VCALENDAR">
+      <C:comp-filter name="VEVENT">
+        <C:time-range start="{}" end="{}"/>
+      </C:comp-filter>
+    </C:comp-filter>
+  </C:filter>
+</C:calendar-query>"#,
+        fmt(from_utc),
+        fmt(to_utc)
+    )
+}
+
+/// Splits `[from, to)` into ranges of `days` each (the last may be shorter), soonest first,
+/// so a slow server's first answer covers the days that matter most.
+pub fn chunk_range(
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+    days: u32,
+) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+    let step = chrono::Duration::days(days.max(1) as i64);
+    let mut chunks = Vec::new();
+    let mut start = from;
+    while start < to {
+        let end = (start + step).min(to);
+        chunks.push((start, end));
+        start = end;
+    }
+    chunks
+}
+
+/// curl args turning a fetch into the REPORT request (they precede the URL).
+pub fn report_args(from_utc: NaiveDateTime, to_utc: NaiveDateTime) -> Vec<String> {
+    [
+        "--request",
+        "REPORT",
+        "--header",
+        "Depth: 1",
+        "--header",
+        "Content-Type: application/xml; charset=utf-8",
+        "--data-binary",
+        &report_body(from_utc, to_utc),
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+/// Pulls the ICS payloads out of a multistatus response, one VCALENDAR after another
+/// (`parse_ics` splits concatenated calendars).
+pub fn extract_calendar_data(xml: &[u8]) -> Result<Vec<u8>, String> {
+    let xml = String::from_utf8_lossy(xml);
+    if !xml.contains("multistatus") {
+        return Err("Not a CalDAV multistatus response".to_string());
+    }
+    // `calendar-data` elements under any namespace prefix (`C:`, `cal:`, or a default xmlns)
+    let calendar_data = Regex::new(
+        r"(?s)<(?:[\w.-]+:)?calendar-data\b[^>]*?(?:/>|>(.*?)</(?:[\w.-]+:)?calendar-data\s*>)",
+    )
+    .unwrap();
+    let mut ics = String::new();
+    for payload in calendar_data.captures_iter(&xml).filter_map(|c| c.get(1)) {
+        let data = unescape(payload.as_str());
+        let data = data.trim();
+        if !data.is_empty() {
+            ics.push_str(data);
+            ics.push('\n');
+        }
+    }
+    Ok(ics.into_bytes())
+}
+
+/// Decodes XML text content: CDATA sections and the predefined and numeric entities.
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(idx) = rest.find(['&', '<']) {
+        out.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+        if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let end = cdata.find("]]>").unwrap_or(cdata.len());
+            out.push_str(&cdata[..end]);
+            rest = cdata.get(end + 3..).unwrap_or_default();
+            continue;
+        }
+        let entity = rest
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| Some((decode_entity(&rest[1..end])?, end)));
+        match entity {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    match name {
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "amp" => Some('&'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        _ => {
+            let code = match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => name.strip_prefix('#')?.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::{parse_datetime, parse_ics};
+    use crate::timezone::LocalZone;
+    use indoc::indoc;
+
+    const MULTISTATUS: &str = indoc! {r#"
+        <?xml version="1.0" encoding="utf-8"?>
+        <d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+          <d:response>
+            <d:href>/dav/calendars/me/work/standup.ics</d:href>
+            <d:propstat>
+              <d:prop>
+                <cal:calendar-data>BEGIN:VCALENDAR
+        VERSION:2.0
+        BEGIN:VEVENT
+        UID:standup
+        SUMMARY:Standup &amp; planning
+        DTSTART:20240115T100000
+        DTEND:20240115T101500
+        END:VEVENT
+        END:VCALENDAR
+        </cal:calendar-data>
+              </d:prop>
+              <d:status>HTTP/1.1 200 OK</d:status>
+            </d:propstat>
+          </d:response>
+          <d:response>
+            <d:href>/dav/calendars/me/work/review.ics</d:href>
+            <d:propstat>
+              <d:prop>
+                <calendar-data xmlns="urn:ietf:params:xml:ns:caldav"><![CDATA[BEGIN:VCALENDAR
+        VERSION:2.0
+        BEGIN:VEVENT
+        UID:review
+        SUMMARY:Review <draft>
+        DTSTART:20240115T140000
+        DTEND:20240115T150000
+        END:VEVENT
+        END:VCALENDAR]]></calendar-data>
+              </d:prop>
+              <d:status>HTTP/1.1 200 OK</d:status>
+            </d:propstat>
+          </d:response>
+          <d:response>
+            <d:href>/dav/calendars/me/work/deleted.ics</d:href>
+            <d:propstat>
+              <d:prop><cal:calendar-data/></d:prop>
+              <d:status>HTTP/1.1 404 Not Found</d:status>
+            </d:propstat>
+          </d:response>
+        </d:multistatus>
+    "#};
+
+    #[test]
+    fn test_extract_calendar_data() {
+        let ics = extract_calendar_data(MULTISTATUS.as_bytes()).unwrap();
+        let events = parse_ics(&ics, &LocalZone::default()).unwrap();
+        let summaries: Vec<&str> = events.iter().map(|e| e.summary.as_str()).collect();
+        assert_eq!(summaries, ["Standup & planning", "Review <draft>"]);
+
+        // Servers usually send CRLF line endings
+        let crlf = MULTISTATUS.replace('\n', "\r\n");
+        let ics = extract_calendar_data(crlf.as_bytes()).unwrap();
+        assert_eq!(parse_ics(&ics, &LocalZone::default()).unwrap().len(), 2);
+
+        let empty = r#"<d:multistatus xmlns:d="DAV:"></d:multistatus>"#;
+        assert!(extract_calendar_data(empty.as_bytes()).unwrap().is_empty());
+        assert!(extract_calendar_data(b"<html>Login</html>").is_err());
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape("a &lt;b&gt; &amp;&#13;&#x41;"), "a <b> &\rA");
+        assert_eq!(unescape("R&D; 5 & up"), "R&D; 5 & up");
+        assert_eq!(unescape("<![CDATA[x &amp; y]]> &amp;"), "x &amp; y &");
+    }
+
+    #[test]
+    fn test_report_body() {
+        let body = report_body(
+            parse_datetime("2024-01-15 08:00").unwrap(),
+            parse_datetime("2024-02-14 08:00").unwrap(),
+        );
+        assert!(body.contains(r#"<C:time-range start="20240115T080000Z" end="20240214T080000Z"/>"#));
+    }
+
+    #[test]
+    fn test_chunk_range() {
+        let at = |s| parse_datetime(s).unwrap();
+        let chunks = chunk_range(at("2024-01-15 08:00"), at("2024-01-31 08:00"), 7);
+        assert_eq!(
+            chunks,
+            [
+                (at("2024-01-15 08:00"), at("2024-01-22 08:00")),
+                (at("2024-01-22 08:00"), at("2024-01-29 08:00")),
+                (at("2024-01-29 08:00"), at("2024-01-31 08:00")),
+            ]
+        );
+        assert!(chunk_range(at("2024-01-15 08:00"), at("2024-01-15 08:00"), 7).is_empty());
+    }
+}
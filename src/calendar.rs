@@ -1,31 +1,490 @@
-use chrono::{NaiveDate, NaiveDateTime, Timelike};
+use crate::locale::Language;
+use crate::recurrence::{fmt_rule, RRule, Recurrence};
+use crate::timezone::{LocalZone, Timezones};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
 use icalendar::CalendarDateTime;
-use icalendar::{Calendar, CalendarComponent, Component, DatePerhapsTime, EventLike};
+use icalendar::{
+    Calendar, CalendarComponent, Class, Component, DatePerhapsTime, EventLike, Property,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
 const DATETIME_FMT: &str = "%Y-%m-%d %H:%M";
+const DATETIME_SECS_FMT: &str = "%Y-%m-%d %H:%M:%S";
 
+/// Hosts recognized as video-call links.
+const VIDEO_CALL_HOSTS: &[&str] = &["zoom", "meet.google", "teams"];
+
+/// Summary keywords that mark an event as out-of-office when no busy status says so.
+const OOO_KEYWORDS: &[&str] = &["ooo", "out of office", "pto", "vacation", "holiday"];
+
+/// Events changed within this many hours are badged "new" (with `new_badge`).
+const NEW_EVENT_HOURS: i64 = 24;
+
+/// Free/busy classification, from `X-MICROSOFT-CDO-BUSYSTATUS` or `TRANSP`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BusyStatus {
+    Free,
+    Tentative,
+    #[default]
+    Busy,
+    OutOfOffice,
+}
+
+impl BusyStatus {
+    /// Outlook's busy-status extension wins; otherwise `TRANSP:TRANSPARENT` means free.
+    fn from_props(busy_status: Option<&str>, transp: Option<&str>) -> Self {
+        match busy_status
+            .map(|s| s.trim().to_ascii_uppercase())
+            .as_deref()
+        {
+            Some("FREE") | Some("WORKINGELSEWHERE") => Self::Free,
+            Some("TENTATIVE") => Self::Tentative,
+            Some("OOF") => Self::OutOfOffice,
+            Some("BUSY") => Self::Busy,
+            _ if transp.is_some_and(|t| t.trim().eq_ignore_ascii_case("TRANSPARENT")) => Self::Free,
+            _ => Self::Busy,
+        }
+    }
+}
+
+/// Length class of a timed event, for tinting long meetings (see `Event::duration_grade`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DurationGrade {
+    /// Under an hour.
+    Short,
+    /// One to two hours.
+    Medium,
+    /// Over two hours.
+    Long,
+}
+
+/// Configurable event icons (see `Event::icon`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Icons {
+    pub video: String,
+    pub in_person: String,
+    pub all_day: String,
+}
+
+impl Default for Icons {
+    fn default() -> Self {
+        Icons {
+            video: "📹".to_string(),
+            in_person: "•".to_string(),
+            all_day: "•".to_string(),
+        }
+    }
+}
+
+/// An invited participant (`ATTENDEE`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attendee {
+    /// Lowercased, without the `mailto:` prefix.
+    pub email: String,
+    /// Participation status (e.g., "ACCEPTED", "DECLINED"), uppercased.
+    pub partstat: Option<String>,
+    /// Display name (`CN`).
+    pub name: Option<String>,
+}
+
+impl Attendee {
+    pub fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.email)
+    }
+}
+
+/// Config-driven rules for hiding events (see `filter_events`).
+pub struct EventFilter {
+    /// Keep only events whose summary matches.
+    pub include: Option<Regex>,
+    /// Drop events whose summary matches.
+    pub exclude: Option<Regex>,
+    /// Drop events this attendee declined.
+    pub my_email: Option<String>,
+    /// Drop meetings of these sizes.
+    pub hide_sizes: Vec<MeetingSize>,
+    /// Meetings with at least this many people are large.
+    pub large_meeting: usize,
+    /// Keep 1:1s even when the summary patterns or `hide_sizes` would drop them.
+    pub keep_one_on_ones: bool,
+    /// `Some(true)` keeps only events `my_email` organizes, `Some(false)` only the rest.
+    pub organized: Option<bool>,
+}
+
+impl Default for EventFilter {
+    fn default() -> Self {
+        EventFilter {
+            include: None,
+            exclude: None,
+            my_email: None,
+            hide_sizes: Vec::new(),
+            large_meeting: DEFAULT_LARGE_MEETING,
+            keep_one_on_ones: false,
+            organized: None,
+        }
+    }
+}
+
+/// Meetings with at least this many people count as large, unless `large_meeting` says otherwise.
+pub const DEFAULT_LARGE_MEETING: usize = 10;
+
+/// Size class of a meeting by headcount (see `Event::meeting_size`). Configured with
+/// `hide_meetings` and `dim_meetings` ("1:1", "small", "large"), and `large_meeting`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeetingSize {
+    /// Two people.
+    OneOnOne,
+    /// Three up to `large_meeting`.
+    Small,
+    /// `large_meeting` or more (all-hands and the like).
+    Large,
+}
+
+impl MeetingSize {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "1:1" | "1on1" | "one_on_one" => Some(Self::OneOnOne),
+            "small" => Some(Self::Small),
+            "large" => Some(Self::Large),
+            _ => None,
+        }
+    }
+
+    /// Parses a comma-separated list of sizes; unknown names are skipped.
+    pub fn parse_list(s: &str) -> Vec<Self> {
+        s.split(',').filter_map(Self::parse).collect()
+    }
+}
+
+/// A named set of display rules (`preset_include_<name>` and friends), switched at runtime.
+/// Unlike `EventFilter` it only hides events from view, so switching back needs no refetch.
+#[derive(Debug, Clone, Default)]
+pub struct FilterPreset {
+    pub name: String,
+    /// Keep only events whose summary matches.
+    pub include: Option<Regex>,
+    /// Drop events whose summary matches.
+    pub exclude: Option<Regex>,
+    /// Names of the calendars to show; empty shows them all.
+    pub calendars: Vec<String>,
+    /// Keep only timed events at least this many minutes long.
+    pub min_minutes: Option<i64>,
+    /// Keep only timed events at most this many minutes long.
+    pub max_minutes: Option<i64>,
+    /// Key switching straight to this preset.
+    pub key: Option<char>,
+}
+
+impl FilterPreset {
+    /// Whether `event`, from the calendar named `calendar`, is shown under this preset.
+    /// With a length limit, all-day events and events without an end are hidden.
+    pub fn allows(&self, event: &Event, calendar: &str) -> bool {
+        if !self.calendars.is_empty() && !self.calendars.iter().any(|c| c == calendar) {
+            return false;
+        }
+        if self
+            .include
+            .as_ref()
+            .is_some_and(|re| !re.is_match(&event.summary))
+            || self
+                .exclude
+                .as_ref()
+                .is_some_and(|re| re.is_match(&event.summary))
+        {
+            return false;
+        }
+        if self.min_minutes.is_none() && self.max_minutes.is_none() {
+            return true;
+        }
+        let Some(minutes) = event
+            .end
+            .filter(|_| !event.is_all_day)
+            .map(|end| (end - event.start).num_minutes())
+        else {
+            return false;
+        };
+        self.min_minutes.is_none_or(|min| minutes >= min)
+            && self.max_minutes.is_none_or(|max| minutes <= max)
+    }
+}
+
+/// Events listed at most, unless `max_events` says otherwise.
+pub const DEFAULT_MAX_EVENTS: usize = 20;
+
+/// Longest `lookahead_days` and `birthday_lookahead_days` honored (about ten years), so
+/// recurrences and CalDAV ranges stay bounded.
+pub const MAX_LOOKAHEAD_DAYS: u32 = 3650;
+
+/// Which upcoming events are kept. Configured with `lookahead_days`, `max_events`,
+/// `keep_past_minutes`, and `birthday_lookahead_days`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventWindow {
+    /// Days after today to include (0 = today only; `None` = no limit).
+    pub lookahead_days: Option<u32>,
+    /// Events the views list at most; queries like availability see them all.
+    pub max_events: usize,
+    /// How long an ended event stays listed (dimmed).
+    pub keep_past_minutes: u32,
+    /// Days after today birthdays and anniversaries are listed in their own section
+    /// (0 = no section; they stay among the other events).
+    pub occasion_days: u32,
+}
+
+impl Default for EventWindow {
+    fn default() -> Self {
+        EventWindow {
+            lookahead_days: None,
+            max_events: DEFAULT_MAX_EVENTS,
+            keep_past_minutes: 0,
+            occasion_days: 0,
+        }
+    }
+}
+
+impl EventWindow {
+    /// Events ending after this are still listed.
+    pub fn keep_from(&self, now: NaiveDateTime) -> NaiveDateTime {
+        now - chrono::Duration::minutes(self.keep_past_minutes as i64)
+    }
+
+    /// Events starting at or after this are out of range (end of the last included day).
+    /// A lookahead past the last representable date has no limit.
+    pub fn until(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let days = chrono::Days::new(self.lookahead_days? as u64 + 1);
+        Some(now.date().checked_add_days(days)?.and_time(NaiveTime::MIN))
+    }
+
+    /// Birthdays and anniversaries starting at or after this are out of range (the end of
+    /// the last included day, like `until`).
+    pub fn occasions_until(&self, now: NaiveDateTime) -> NaiveDateTime {
+        let days = chrono::Days::new(self.occasion_days as u64 + 1);
+        now.date()
+            .checked_add_days(days)
+            .map_or(NaiveDateTime::MAX, |day| day.and_time(NaiveTime::MIN))
+    }
+}
+
+/// Flight details recognized from an event's summary/description.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Flight {
+    /// Airline code + number, normalized without spaces (e.g., "AA123").
+    pub number: String,
+    /// Departure and arrival airport codes, if present (e.g., ("SFO", "JFK")).
+    pub route: Option<(String, String)>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct Event {
     pub summary: String,
     pub start: NaiveDateTime,
     #[allow(dead_code)]
     pub end: Option<NaiveDateTime>,
     pub location: Option<String>,
+    pub description: Option<String>,
     pub is_all_day: bool,
+    /// Event comes from a busy overlay calendar (details stripped).
+    pub is_overlay: bool,
+    pub busy_status: BusyStatus,
+    pub uid: Option<String>,
+    /// Set for recurring events (both the series definition and expanded occurrences).
+    pub recurrence: Option<Recurrence>,
+    /// Original start of the occurrence this event overrides (`RECURRENCE-ID`).
+    pub recurrence_id: Option<NaiveDateTime>,
+    /// Minutes before start at which the event's VALARMs trigger.
+    pub alarms: Vec<i64>,
+    /// `CLASS:PRIVATE` or `CLASS:CONFIDENTIAL`.
+    pub is_private: bool,
+    /// `STATUS:CANCELLED`.
+    pub is_cancelled: bool,
+    pub attendees: Vec<Attendee>,
+    pub organizer: Option<Attendee>,
+    /// Color hint: the event's `COLOR`, else its calendar's `COLOR` or `X-APPLE-CALENDAR-COLOR`
+    /// (a CSS color name or hex, mapped to a terminal color when rendering).
+    pub color: Option<String>,
+    /// When the event was last changed: `LAST-MODIFIED`, else `CREATED`, else `DTSTAMP`
+    /// (unless the feed stamps every event alike, as exports do), in local time.
+    pub modified: Option<NaiveDateTime>,
+    /// `CATEGORIES`, e.g. "Birthday".
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Index of the source this event was fetched from (see `State::sources`).
+    #[serde(skip)]
+    pub source: usize,
 }
 
 impl Event {
+    /// Strips terminal control sequences from the feed's text, which is printed straight to
+    /// the terminal. Only descriptions keep their line breaks.
+    pub fn sanitize(&mut self) {
+        let line = |s: &mut String| *s = sanitize_text(s, false);
+        line(&mut self.summary);
+        self.location.as_mut().map(line);
+        self.uid.as_mut().map(line);
+        self.color.as_mut().map(line);
+        if let Some(description) = self.description.as_mut() {
+            *description = sanitize_text(description, true);
+        }
+        for person in self.attendees.iter_mut().chain(self.organizer.as_mut()) {
+            line(&mut person.email);
+            person.name.as_mut().map(line);
+        }
+    }
+
     pub fn is_video_call(&self) -> bool {
         self.location
             .as_ref()
-            .map(|l| l.contains("zoom") || l.contains("meet.google") || l.contains("teams"))
+            .map(|l| VIDEO_CALL_HOSTS.iter().any(|host| l.contains(host)))
             .unwrap_or(false)
     }
 
+    /// Finds the meeting link in LOCATION or DESCRIPTION, preferring video-call URLs.
+    pub fn meeting_url(&self) -> Option<String> {
+        let urls: Vec<&str> = [&self.location, &self.description]
+            .into_iter()
+            .flatten()
+            .flat_map(|text| find_urls(text))
+            .collect();
+        urls.iter()
+            .find(|url| VIDEO_CALL_HOSTS.iter().any(|host| url.contains(host)))
+            .or(urls.first())
+            .map(|url| url.to_string())
+    }
+
+    /// Whether the event was created or changed within the last day, so fresh invites stand out.
+    pub fn is_new(&self, now: NaiveDateTime) -> bool {
+        self.modified
+            .is_some_and(|modified| (now - modified).num_hours() < NEW_EVENT_HOURS)
+    }
+
+    /// Returns true if the event blocks time (busy, tentative, or out of office).
+    pub fn is_busy(&self) -> bool {
+        self.busy_status != BusyStatus::Free
+    }
+
+    /// A birthday or anniversary, by its `CATEGORIES`.
+    pub fn is_occasion(&self) -> bool {
+        self.categories.iter().any(|c| {
+            let c = c.to_lowercase();
+            c.contains("birthday") || c.contains("anniversary")
+        })
+    }
+
+    /// Returns true if the event marks time out of office (busy status or summary keyword).
+    pub fn is_out_of_office(&self) -> bool {
+        if self.busy_status == BusyStatus::OutOfOffice {
+            return true;
+        }
+        let summary = self.summary.to_lowercase();
+        let words: Vec<&str> = summary
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .collect();
+        OOO_KEYWORDS.iter().any(|kw| {
+            let kw: Vec<&str> = kw.split(' ').collect();
+            words.windows(kw.len()).any(|w| w == kw.as_slice())
+        })
+    }
+
+    /// Detects flight events, e.g. Gmail's "Flight to London (BA 117)" or "AA123 SFO-JFK".
+    /// A number alone isn't enough ("FY2025 kickoff"): the word "flight" or a route must be
+    /// there too.
+    pub fn flight(&self) -> Option<Flight> {
+        let mentions_flight = self.summary.to_lowercase().contains("flight");
+        let route =
+            find_route(&self.summary).or_else(|| self.description.as_deref().and_then(find_route));
+        if !mentions_flight && route.is_none() {
+            return None;
+        }
+        let number = find_flight_number(&self.summary, mentions_flight)?;
+        Some(Flight { number, route })
+    }
+
+    /// Icon shown before the summary: private, flight, video call, all-day, or in-person.
+    pub fn icon<'a>(&self, icons: &'a Icons) -> &'a str {
+        if self.is_private {
+            "🔒"
+        } else if self.flight().is_some() {
+            "✈"
+        } else if self.is_video_call() {
+            &icons.video
+        } else if self.is_all_day {
+            &icons.all_day
+        } else {
+            &icons.in_person
+        }
+    }
+
+    /// Whether the event is an occurrence of a recurring series (expanded or overridden).
+    pub fn is_recurring(&self) -> bool {
+        self.recurrence.is_some() || self.recurrence_id.is_some()
+    }
+
+    /// Describes the event's series (e.g., "weekly on mon/wed"); `None` for one-off events.
+    pub fn fmt_series(&self) -> Option<String> {
+        if !self.is_recurring() {
+            return None;
+        }
+        let rule = self.recurrence.as_ref().and_then(|r| r.rule.as_ref());
+        Some(rule.map_or_else(|| "repeats".to_string(), fmt_rule))
+    }
+
+    /// Whether `email` is the event's organizer.
+    pub fn is_organized_by(&self, email: &str) -> bool {
+        self.organizer
+            .as_ref()
+            .is_some_and(|o| o.email.eq_ignore_ascii_case(email))
+    }
+
+    /// People in the meeting: its attendees, plus the organizer when not listed among them.
+    pub fn headcount(&self) -> usize {
+        let organizer_listed = self.organizer.as_ref().is_none_or(|organizer| {
+            self.attendees
+                .iter()
+                .any(|a| a.email.eq_ignore_ascii_case(&organizer.email))
+        });
+        self.attendees.len() + usize::from(!organizer_listed)
+    }
+
+    /// Size class by headcount; `None` for events without guests.
+    pub fn meeting_size(&self, large_from: usize) -> Option<MeetingSize> {
+        match self.headcount() {
+            ..2 => None,
+            2 => Some(MeetingSize::OneOnOne),
+            n if n >= large_from => Some(MeetingSize::Large),
+            _ => Some(MeetingSize::Small),
+        }
+    }
+
+    /// Length class of a timed event; `None` for all-day events and events without an end.
+    pub fn duration_grade(&self) -> Option<DurationGrade> {
+        if self.is_all_day {
+            return None;
+        }
+        let minutes = (self.end? - self.start).num_minutes();
+        Some(match minutes {
+            ..60 => DurationGrade::Short,
+            60..=120 => DurationGrade::Medium,
+            _ => DurationGrade::Long,
+        })
+    }
+
     /// Returns true if the event is currently in progress (started and not ended).
     pub fn is_in_progress(&self, now: NaiveDateTime) -> bool {
         self.end.is_some_and(|end| self.start <= now && now < end)
     }
 
+    /// Identifies the event (its series, for recurring ones) across refreshes, for pinning.
+    pub fn pin_id(&self) -> &str {
+        self.uid.as_deref().unwrap_or(&self.summary)
+    }
+
+    /// Returns true if a timed event is over (kept around by `keep_past_minutes`).
+    pub fn has_ended(&self, now: NaiveDateTime) -> bool {
+        !self.is_all_day && self.end.is_some_and(|end| end <= now)
+    }
+
     /// Returns true if the event should be considered active on the given date.
     pub fn is_active_on(&self, date: NaiveDate) -> bool {
         let start_date = self.start.date();
@@ -39,33 +498,234 @@ impl Event {
             None => start_date == date,
         }
     }
+
+    /// Returns true if a timed event started on an earlier day and runs into `date`.
+    pub fn continues_on(&self, date: NaiveDate) -> bool {
+        !self.is_all_day && self.start.date() < date && self.is_active_on(date)
+    }
+}
+
+/// Finds http(s) URLs in free text, trimming surrounding punctuation.
+fn find_urls(text: &str) -> Vec<&str> {
+    text.split(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '(' | ')'))
+        .map(|token| token.trim_end_matches(['.', ',', ';']))
+        .filter(|token| token.starts_with("https://") || token.starts_with("http://"))
+        .collect()
+}
+
+/// Finds an airline flight number like "AA123" (or "AA 123" when `allow_space`).
+/// Airline codes are two characters with at least one letter (e.g., "B6").
+fn find_flight_number(text: &str, allow_space: bool) -> Option<String> {
+    let tokens: Vec<&str> = text
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .collect();
+    let is_airline = |code: &str| {
+        code.len() == 2
+            && code
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+            && code.chars().any(|c| c.is_ascii_uppercase())
+    };
+    let is_number =
+        |num: &str| (1..=4).contains(&num.len()) && num.chars().all(|c| c.is_ascii_digit());
+
+    for (i, token) in tokens.iter().enumerate() {
+        if token.len() > 2 && is_airline(&token[..2]) && is_number(&token[2..]) {
+            return Some(token.to_string());
+        }
+        if allow_space && is_airline(token) {
+            if let Some(num) = tokens.get(i + 1).filter(|n| is_number(n)) {
+                return Some(format!("{}{}", token, num));
+            }
+        }
+    }
+    None
+}
+
+/// Finds an airport route like "SFO-JFK", "SFO → JFK", or "SFO to JFK".
+fn find_route(text: &str) -> Option<(String, String)> {
+    let is_airport = |t: &str| t.len() == 3 && t.chars().all(|c| c.is_ascii_uppercase());
+    let tokens: Vec<&str> = text
+        .split(|c: char| c.is_whitespace() || matches!(c, '-' | '→' | '>' | '(' | ')' | ','))
+        .filter(|t| !t.is_empty())
+        .collect();
+    tokens.windows(2).chain(tokens.windows(3)).find_map(|w| {
+        let (from, to) = (w[0], w[w.len() - 1]);
+        let joined = w.len() == 2 || w[1].eq_ignore_ascii_case("to");
+        (joined && is_airport(from) && is_airport(to)).then(|| (from.to_string(), to.to_string()))
+    })
+}
+
+/// Removes escape sequences (CSI, OSC, and the rest) and other control characters. Tabs
+/// become spaces; line breaks too, unless `keep_newlines`.
+pub fn sanitize_text(s: &str, keep_newlines: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' if keep_newlines => out.push('\n'),
+            '\n' | '\t' => out.push(' '),
+            // CSI, with ESC or as the 8-bit C1 form: parameters up to a final byte
+            '\x1b' if chars.peek() == Some(&'[') => {
+                chars.next();
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            '\u{9b}' => {
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC (titles, hyperlinks, clipboard): up to BEL or ESC \
+            '\x1b' if chars.peek() == Some(&']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            // Other escapes are a single character after ESC
+            '\x1b' => {
+                chars.next();
+            }
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+    out
 }
 
-/// Parses ICS calendar data into a list of events.
-pub fn parse_ics(data: &[u8], utc_offset_minutes: i32) -> Result<Vec<Event>, String> {
+/// Parses ICS calendar data into a list of events, converted to the user's local time.
+pub fn parse_ics(data: &[u8], local: &LocalZone) -> Result<Vec<Event>, String> {
     let content = String::from_utf8_lossy(data);
+    if is_web_page(&content) {
+        return Err("Server returned a web page — auth expired?".to_string());
+    }
+    // Local sources can concatenate several files, each its own VCALENDAR. One that doesn't
+    // parse (in a vdirsyncer directory of hundreds) is skipped, unless none parse.
+    let mut events = Vec::new();
+    let mut first_err = None;
+    let mut parsed = 0;
+    for calendar in split_calendars(&content) {
+        match parse_calendar(calendar, local) {
+            Ok(found) => {
+                events.extend(found);
+                parsed += 1;
+            }
+            Err(err) => {
+                log!("Skipping a calendar that doesn't parse: {}", err);
+                first_err.get_or_insert(err);
+            }
+        }
+    }
+    match first_err {
+        Some(err) if parsed == 0 => Err(err),
+        _ => Ok(events),
+    }
+}
+
+/// Splits concatenated ICS data at each line starting `BEGIN:VCALENDAR`.
+fn split_calendars(content: &str) -> Vec<&str> {
+    let mut starts: Vec<usize> = content
+        .match_indices("BEGIN:VCALENDAR")
+        .map(|(i, _)| i)
+        .filter(|&i| i == 0 || content[..i].ends_with('\n'))
+        .collect();
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+    }
+    starts
+        .iter()
+        .zip(starts.iter().skip(1).chain([&content.len()]))
+        .map(|(&start, &end)| &content[start..end])
+        .filter(|calendar| !calendar.trim().is_empty())
+        .collect()
+}
+
+fn parse_calendar(content: &str, local: &LocalZone) -> Result<Vec<Event>, String> {
     let calendar: Calendar = content.parse().map_err(|e| format!("Parse error: {}", e))?;
+    let tz = Timezones::new(&calendar, local);
+    let calendar_color = calendar
+        .property_value("COLOR")
+        .or_else(|| calendar.property_value("X-APPLE-CALENDAR-COLOR"));
 
-    let events: Vec<Event> = calendar
+    let mut events: Vec<Event> = calendar
         .components
         .iter()
         .filter_map(|component| {
             if let CalendarComponent::Event(event) = component {
                 let summary = event.get_summary().unwrap_or("(no title)").to_string();
                 let start_raw = event.get_start()?;
-                let is_all_day = matches!(&start_raw, DatePerhapsTime::Date(_));
-                let start = parse_date_perhaps_time(start_raw, utc_offset_minutes);
-                let end = event
-                    .get_end()
-                    .map(|dt| parse_date_perhaps_time(dt, utc_offset_minutes));
+                let end_raw = event.get_end();
+                let midnight_dates = midnight_all_day(event, &start_raw, end_raw.as_ref());
+                let is_all_day =
+                    matches!(&start_raw, DatePerhapsTime::Date(_)) || midnight_dates.is_some();
+                let (start, end) = match midnight_dates {
+                    Some((start, end)) => (
+                        start.and_time(NaiveTime::MIN),
+                        Some(end.and_time(NaiveTime::MIN)),
+                    ),
+                    None => (
+                        parse_date_perhaps_time(start_raw, &tz),
+                        end_raw.map(|dt| parse_date_perhaps_time(dt, &tz)),
+                    ),
+                };
                 let location = event.get_location().map(|s| s.to_string());
+                let description = event.get_description().map(|s| s.to_string());
+                let busy_status = BusyStatus::from_props(
+                    event.property_value("X-MICROSOFT-CDO-BUSYSTATUS"),
+                    event.property_value("TRANSP"),
+                );
+                let uid = event.get_uid().map(|s| s.to_string());
+                let recurrence_id = event
+                    .get_recurrence_id()
+                    .map(|dt| parse_date_perhaps_time(dt, &tz));
+                let rule = event
+                    .property_value("RRULE")
+                    .and_then(|r| RRule::parse(r, tz.utc_offset_minutes()).ok());
+                let rdates = parse_multi_dates(event, "RDATE", &tz);
+                let recurrence = (rule.is_some() || !rdates.is_empty()).then(|| Recurrence {
+                    rule,
+                    exdates: parse_multi_dates(event, "EXDATE", &tz),
+                    rdates,
+                });
 
                 Some(Event {
                     summary,
                     start,
                     end,
                     location,
+                    description,
                     is_all_day,
+                    busy_status,
+                    uid,
+                    recurrence,
+                    recurrence_id,
+                    alarms: parse_alarms(event),
+                    is_private: event
+                        .get_class()
+                        .is_some_and(|class| matches!(class, Class::Private | Class::Confidential)),
+                    is_cancelled: event
+                        .property_value("STATUS")
+                        .is_some_and(|s| s.trim().eq_ignore_ascii_case("CANCELLED")),
+                    attendees: parse_attendees(event),
+                    organizer: event.properties().get("ORGANIZER").map(parse_attendee),
+                    color: event
+                        .property_value("COLOR")
+                        .or(calendar_color)
+                        .map(|c| c.trim().to_string()),
+                    modified: event
+                        .get_last_modified()
+                        .or_else(|| event.get_created())
+                        .map(|dt| tz.utc_to_local(dt.naive_utc())),
+                    categories: parse_categories(event),
+                    ..Default::default()
                 })
             } else {
                 None
@@ -73,274 +733,2971 @@ pub fn parse_ics(data: &[u8], utc_offset_minutes: i32) -> Result<Vec<Event>, Str
         })
         .collect();
 
+    // A DTSTAMP only says when an event changed if the feed didn't stamp them all at export
+    let stamps: Vec<Option<NaiveDateTime>> = calendar
+        .components
+        .iter()
+        .filter_map(|component| match component {
+            CalendarComponent::Event(event) if event.get_start().is_some() => Some(
+                event
+                    .get_timestamp()
+                    .map(|dt| tz.utc_to_local(dt.naive_utc())),
+            ),
+            _ => None,
+        })
+        .collect();
+    let all_alike = stamps.len() > 1 && stamps.windows(2).all(|w| w[0] == w[1]);
+    if !all_alike {
+        for (event, stamp) in events.iter_mut().zip(stamps) {
+            event.modified = event.modified.or(stamp);
+        }
+    }
+    for event in &mut events {
+        event.sanitize();
+    }
+
     Ok(events)
 }
 
-/// Removes past events (keeps in-progress), sorts by start time, truncates to `limit`.
-pub fn filter_future(
-    mut events: Vec<Event>,
-    current_time: Option<NaiveDateTime>,
-    limit: usize,
-) -> Vec<Event> {
-    events.sort_by(|a, b| a.start.cmp(&b.start));
-    if let Some(now) = current_time {
-        events.retain(|e| e.start >= now || e.end.is_some_and(|end| end > now));
+/// Every `CATEGORIES` value, split on commas.
+fn parse_categories(event: &icalendar::Event) -> Vec<String> {
+    let Some(properties) = event.multi_properties().get("CATEGORIES") else {
+        return Vec::new();
+    };
+    properties
+        .iter()
+        .flat_map(|p| p.value().split(','))
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn parse_attendees(event: &icalendar::Event) -> Vec<Attendee> {
+    let Some(properties) = event.multi_properties().get("ATTENDEE") else {
+        return Vec::new();
+    };
+    properties.iter().map(parse_attendee).collect()
+}
+
+/// Parses an `ATTENDEE` or `ORGANIZER` property.
+fn parse_attendee(prop: &icalendar::Property) -> Attendee {
+    let value = prop.value().trim();
+    let email = match value.get(..7) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("mailto:") => &value[7..],
+        _ => value,
+    };
+    let param = |key: &str| prop.params().get(key).map(|p| p.value().trim_matches('"'));
+    Attendee {
+        email: email.to_lowercase(),
+        partstat: param("PARTSTAT").map(|p| p.to_ascii_uppercase()),
+        name: param("CN").filter(|n| !n.is_empty()).map(String::from),
     }
-    events.truncate(limit);
-    events
 }
 
-/// Converts ICS DatePerhapsTime to NaiveDateTime in local time.
-/// All-day events get 00:00.
-///
-/// Note: UTC offset is based on current time, not event time. Events crossing a DST
-/// boundary may be off by 1 hour. Acceptable for a near-term calendar widget.
-fn parse_date_perhaps_time(dt: DatePerhapsTime, utc_offset_minutes: i32) -> NaiveDateTime {
-    match dt {
-        DatePerhapsTime::DateTime(cdt) => match cdt {
-            CalendarDateTime::Floating(dt) => dt,
-            CalendarDateTime::Utc(dt) => {
-                dt.naive_utc() + chrono::Duration::minutes(utc_offset_minutes as i64)
+/// Lead times of an event's VALARMs, in minutes before start.
+/// Only start-relative triggers count; absolute and end-relative ones are skipped.
+fn parse_alarms(event: &icalendar::Event) -> Vec<i64> {
+    event
+        .components()
+        .iter()
+        .filter(|c| c.component_kind() == "VALARM")
+        .filter_map(|alarm| {
+            let trigger = alarm.properties().get("TRIGGER")?;
+            let param_is = |key: &str, value: &str| {
+                trigger
+                    .params()
+                    .get(key)
+                    .is_some_and(|p| p.value().eq_ignore_ascii_case(value))
+            };
+            if param_is("RELATED", "END") || param_is("VALUE", "DATE-TIME") {
+                return None;
             }
-            CalendarDateTime::WithTimezone { date_time, .. } => date_time,
-        },
-        DatePerhapsTime::Date(date) => date.and_hms_opt(0, 0, 0).unwrap(),
+            let minutes = parse_duration_minutes(trigger.value())?;
+            (minutes <= 0).then_some(-minutes)
+        })
+        .collect()
+}
+
+/// Parses an ICS DURATION (e.g., "-PT15M", "P1DT2H", "-P1W") into whole minutes.
+fn parse_duration_minutes(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let (sign, rest) = match value.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, value.trim_start_matches('+')),
+    };
+    let mut minutes = None;
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in rest.strip_prefix('P')?.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => in_time = true,
+            _ => {
+                let n: i64 = number.parse().ok()?;
+                number.clear();
+                *minutes.get_or_insert(0) += match (c, in_time) {
+                    ('W', false) => n * 7 * 24 * 60,
+                    ('D', false) => n * 24 * 60,
+                    ('H', true) => n * 60,
+                    ('M', true) => n,
+                    ('S', true) => n / 60,
+                    _ => return None,
+                };
+            }
+        }
     }
+    minutes.filter(|_| number.is_empty()).map(|m| sign * m)
 }
 
-/// Parses UTC offset string (e.g., "-0500", "+0530") to minutes.
-pub fn parse_utc_offset(s: &str) -> Option<i32> {
-    let s = s.trim();
-    if s.len() != 5 {
-        return None;
+/// Detects HTML (login or error pages) served in place of a calendar.
+fn is_web_page(content: &str) -> bool {
+    let head = content.trim_start_matches('\u{feff}').trim_start();
+    if head
+        .get(..15)
+        .is_some_and(|h| h.eq_ignore_ascii_case("BEGIN:VCALENDAR"))
+    {
+        return false;
     }
-    let sign = match s.chars().next()? {
-        '+' => 1,
-        '-' => -1,
-        _ => return None,
-    };
-    let hours: i32 = s[1..3].parse().ok()?;
-    let minutes: i32 = s[3..5].parse().ok()?;
-    Some(sign * (hours * 60 + minutes))
+    let head: String = head
+        .chars()
+        .take(512)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    head.starts_with('<') || head.contains("<html") || head.contains("<!doctype")
 }
 
-/// Parses "YYYY-MM-DD HH:MM" string (from shell `date` command) to NaiveDateTime.
-pub fn parse_datetime(dt: &str) -> Option<NaiveDateTime> {
-    NaiveDateTime::parse_from_str(dt, DATETIME_FMT).ok()
+/// Parses a multi-valued date property like EXDATE/RDATE (comma-separated, possibly repeated).
+fn parse_multi_dates(event: &icalendar::Event, key: &str, tz: &Timezones) -> Vec<NaiveDateTime> {
+    let Some(properties) = event.multi_properties().get(key) else {
+        return Vec::new();
+    };
+    properties
+        .iter()
+        .flat_map(|prop| {
+            prop.value().split(',').filter_map(move |value| {
+                // Re-wrap each value with the original params (VALUE=DATE, TZID)
+                let mut single = Property::new(key, value.trim());
+                for param in prop.params().values() {
+                    single.add_parameter(param.key(), param.value());
+                }
+                DatePerhapsTime::from_property(&single)
+            })
+        })
+        .map(|dt| parse_date_perhaps_time(dt, tz))
+        .collect()
 }
 
-/// Formats hour/minute as "HH:MM" or "H:MM am/pm".
-pub fn fmt_time(hour: u32, minute: u32, use_12h: bool) -> String {
-    if !use_12h {
-        return format!("{:02}:{:02}", hour, minute);
-    }
+/// Expands recurring events into their occurrences overlapping `[from, to)`.
+/// Honors EXDATE/RDATE, and skips occurrences overridden by a RECURRENCE-ID instance.
+pub fn expand_recurring(events: Vec<Event>, from: NaiveDateTime, to: NaiveDateTime) -> Vec<Event> {
+    let overrides: HashSet<(String, NaiveDateTime)> = events
+        .iter()
+        .filter_map(|e| Some((e.uid.clone()?, e.recurrence_id?)))
+        .collect();
 
-    let (hour_12, period) = match hour {
-        0 => (12, "am"),
-        1..=11 => (hour, "am"),
-        12 => (12, "pm"),
-        _ => (hour - 12, "pm"),
-    };
+    let mut expanded = Vec::new();
+    for event in events {
+        let recurrence = match &event.recurrence {
+            Some(recurrence) if event.recurrence_id.is_none() => recurrence,
+            _ => {
+                expanded.push(event);
+                continue;
+            }
+        };
 
-    format!("{}:{:02} {}", hour_12, minute, period)
-}
+        // Start the window early by one event duration, to keep in-progress occurrences
+        let duration = event.end.map(|end| end - event.start).unwrap_or_default();
+        let window_start = from - duration;
+        let in_window = |start: &NaiveDateTime| *start >= window_start && *start < to;
 
-/// Formats datetime as absolute display.
-/// (e.g., "jan 15 10:00 am" or "jan 15" for all-day)
-pub fn fmt_datetime(dt: NaiveDateTime, use_12h: bool) -> String {
-    let is_all_day = dt.hour() == 0 && dt.minute() == 0;
-    let date = dt.format("%b %-d").to_string().to_lowercase();
+        let mut starts = match &recurrence.rule {
+            Some(rule) => rule.occurrences(event.start, window_start, to),
+            None => Vec::new(),
+        };
+        // DTSTART is always the first instance, even if the rule doesn't match it
+        starts.extend(std::iter::once(event.start).filter(in_window));
+        starts.extend(recurrence.rdates.iter().copied().filter(in_window));
+        starts.sort();
+        starts.dedup();
 
-    if is_all_day {
-        date
-    } else {
-        format!("{} {}", date, fmt_time(dt.hour(), dt.minute(), use_12h))
+        let is_overridden = |start: &NaiveDateTime| {
+            event
+                .uid
+                .as_ref()
+                .is_some_and(|uid| overrides.contains(&(uid.clone(), *start)))
+        };
+        for start in starts {
+            if recurrence.exdates.contains(&start) || is_overridden(&start) {
+                continue;
+            }
+            expanded.push(Event {
+                start,
+                end: event.end.map(|_| start + duration),
+                ..event.clone()
+            });
+        }
     }
+    expanded
 }
 
-/// Formats a date as a day group header.
-/// (e.g., "today", "tomorrow", or "tuesday, jan 22")
-pub fn fmt_day_header(event_date: NaiveDate, today: NaiveDate) -> String {
-    let days_diff = (event_date - today).num_days();
-    match days_diff {
-        0 => "today".to_string(),
-        1 => "tomorrow".to_string(),
-        _ => event_date.format("%A, %b %-d").to_string().to_lowercase(),
-    }
+/// Strips identifying details from overlay events, keeping only their busy time blocks.
+pub fn anonymize(events: Vec<Event>) -> Vec<Event> {
+    events
+        .into_iter()
+        .filter(|e| e.is_busy())
+        .map(|e| Event {
+            summary: "busy".to_string(),
+            location: None,
+            description: None,
+            attendees: Vec::new(),
+            organizer: None,
+            is_overlay: true,
+            ..e
+        })
+        .collect()
 }
 
-/// Formats event time for display within a day group.
-/// All-day events return "all day". Today uses relative time, other days just the time.
-pub fn fmt_time_in_group(
-    event_dt: NaiveDateTime,
-    now_dt: NaiveDateTime,
-    is_today: bool,
-    is_all_day: bool,
-    use_12h: bool,
-) -> String {
-    if is_all_day {
-        return "all day".to_string();
+/// Drops cancelled events, events `my_email` declined, events failing the summary patterns,
+/// meetings of hidden sizes, and events left out by `organized`. With `keep_one_on_ones`, only
+/// the first two apply to 1:1s.
+pub fn filter_events(events: Vec<Event>, filter: &EventFilter) -> Vec<Event> {
+    events
+        .into_iter()
+        .filter(|e| !e.is_cancelled)
+        .filter(|e| {
+            let Some(ref me) = filter.my_email else {
+                return true;
+            };
+            !e.attendees.iter().any(|a| {
+                a.email.eq_ignore_ascii_case(me) && a.partstat.as_deref() == Some("DECLINED")
+            })
+        })
+        .filter(|e| {
+            let size = e.meeting_size(filter.large_meeting);
+            if filter.keep_one_on_ones && size == Some(MeetingSize::OneOnOne) {
+                return true;
+            }
+            filter
+                .include
+                .as_ref()
+                .is_none_or(|re| re.is_match(&e.summary))
+                && !filter
+                    .exclude
+                    .as_ref()
+                    .is_some_and(|re| re.is_match(&e.summary))
+                && !size.is_some_and(|size| filter.hide_sizes.contains(&size))
+                && filter.organized.is_none_or(|mine| {
+                    let me = filter.my_email.as_deref().unwrap_or_default();
+                    e.is_organized_by(me) == mine
+                })
+        })
+        .collect()
+}
+
+/// Hides the details of private events, as shared calendars do.
+pub fn hide_private(events: Vec<Event>) -> Vec<Event> {
+    events
+        .into_iter()
+        .map(|e| {
+            if !e.is_private {
+                return e;
+            }
+            Event {
+                summary: "private".to_string(),
+                location: None,
+                description: None,
+                attendees: Vec::new(),
+                organizer: None,
+                ..e
+            }
+        })
+        .collect()
+}
+
+/// Keeps only the next occurrence of each recurring series, so daily events take one line.
+/// Occurrences that already ended don't count (they're left for `filter_future` to drop).
+pub fn collapse_series(mut events: Vec<Event>, current_time: Option<NaiveDateTime>) -> Vec<Event> {
+    events.sort_by_key(|e| e.start);
+    let mut seen = HashSet::new();
+    events.retain(|e| {
+        let ended =
+            current_time.is_some_and(|now| e.start < now && e.end.is_none_or(|end| end <= now));
+        !e.is_recurring()
+            || ended
+            || seen.insert(e.uid.as_deref().unwrap_or(&e.summary).to_string())
+    });
+    events
+}
+
+/// Removes past events (keeps in-progress, and ones that ended within `keep_past_minutes`)
+/// and ones beyond the lookahead, and sorts by start time.
+pub fn filter_future(
+    mut events: Vec<Event>,
+    current_time: Option<NaiveDateTime>,
+    window: &EventWindow,
+) -> Vec<Event> {
+    events.sort_by_key(|e| e.start);
+    if let Some(now) = current_time {
+        let keep_from = window.keep_from(now);
+        let until = window.until(now);
+        events.retain(|e| {
+            let current = e.start >= now || e.end.is_some_and(|end| end > keep_from);
+            current && until.is_none_or(|until| e.start < until)
+        });
     }
+    events
+}
 
-    if is_today {
-        fmt_relative_time(event_dt, now_dt, use_12h)
+/// Returns when the current out-of-office period ends, if `now` falls inside one.
+pub fn ooo_until(events: &[Event], now: NaiveDateTime) -> Option<NaiveDateTime> {
+    events
+        .iter()
+        .filter(|e| e.is_out_of_office() && !e.is_overlay)
+        .filter(|e| {
+            if e.is_all_day {
+                e.is_active_on(now.date())
+            } else {
+                e.is_in_progress(now)
+            }
+        })
+        .filter_map(|e| e.end)
+        .max()
+}
+
+/// Converts ICS DatePerhapsTime to NaiveDateTime in local time.
+/// All-day events get 00:00.
+///
+/// Note: TZID times are converted with the feed's VTIMEZONE rules for the event's date. UTC
+/// times use the current offset unless the feed also defines the user's zone, so they may be
+/// off by 1 hour across a DST boundary. Acceptable for a near-term calendar widget.
+/// Dates of an all-day event sent as DATE-TIMEs at midnight in the feed's zone, which
+/// converted to local time would land on the wrong day. Such events are flagged by Outlook
+/// (`X-MICROSOFT-CDO-ALLDAYEVENT`) or span whole days from midnight to midnight.
+fn midnight_all_day(
+    event: &icalendar::Event,
+    start: &DatePerhapsTime,
+    end: Option<&DatePerhapsTime>,
+) -> Option<(NaiveDate, NaiveDate)> {
+    let start = wall_time(start)?;
+    let end = end.and_then(wall_time);
+    let is_midnight = |t: NaiveDateTime| t.time() == NaiveTime::MIN;
+    let flagged = event
+        .property_value("X-MICROSOFT-CDO-ALLDAYEVENT")
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("TRUE"));
+    let spans_days =
+        is_midnight(start) && end.is_some_and(|end| is_midnight(end) && end.date() > start.date());
+    if !flagged && !spans_days {
+        return None;
+    }
+    let first_day_after = start.date().succ_opt()?;
+    let end = end.map_or(first_day_after, |end| {
+        // An end within the last day (e.g., 23:59) still covers it
+        let end_date = if is_midnight(end) {
+            end.date()
+        } else {
+            end.date().succ_opt().unwrap_or(end.date())
+        };
+        end_date.max(first_day_after)
+    });
+    Some((start.date(), end))
+}
+
+/// A DATE-TIME's wall-clock time in its own zone, before conversion to local time.
+fn wall_time(dt: &DatePerhapsTime) -> Option<NaiveDateTime> {
+    match dt {
+        DatePerhapsTime::DateTime(CalendarDateTime::Floating(dt)) => Some(*dt),
+        DatePerhapsTime::DateTime(CalendarDateTime::Utc(dt)) => Some(dt.naive_utc()),
+        DatePerhapsTime::DateTime(CalendarDateTime::WithTimezone { date_time, .. }) => {
+            Some(*date_time)
+        }
+        DatePerhapsTime::Date(_) => None,
+    }
+}
+
+fn parse_date_perhaps_time(dt: DatePerhapsTime, tz: &Timezones) -> NaiveDateTime {
+    match dt {
+        DatePerhapsTime::DateTime(cdt) => match cdt {
+            CalendarDateTime::Floating(dt) => dt,
+            CalendarDateTime::Utc(dt) => tz.utc_to_local(dt.naive_utc()),
+            CalendarDateTime::WithTimezone { date_time, tzid } => {
+                tz.zoned_to_local(date_time, &tzid)
+            }
+        },
+        DatePerhapsTime::Date(date) => date.and_hms_opt(0, 0, 0).unwrap(),
+    }
+}
+
+/// The event for one-line displays: the one in progress, else the next upcoming one.
+/// Busy overlay blocks and all-day events are skipped.
+pub fn next_event(events: &[Event], now: NaiveDateTime) -> Option<&Event> {
+    upcoming_events(events, now).next()
+}
+
+/// Events in progress or still to come, soonest first, as `next_event` picks them.
+pub fn upcoming_events(events: &[Event], now: NaiveDateTime) -> impl Iterator<Item = &Event> {
+    events
+        .iter()
+        .filter(|e| !e.is_overlay && !e.is_all_day)
+        .filter(move |e| e.start >= now || e.is_in_progress(now))
+}
+
+/// The timed, busy event in progress, if any (what "in meeting" refers to).
+pub fn current_event(events: &[Event], now: NaiveDateTime) -> Option<&Event> {
+    events
+        .iter()
+        .find(|e| !e.is_all_day && e.is_busy() && e.end.is_some() && e.is_in_progress(now))
+}
+
+/// Minutes ahead within which a meeting counts as "soon" for `status_glyph`.
+const SOON_MINUTES: i64 = 10;
+
+/// Free/busy state at a glance: 🔴 in a meeting, 🟡 one starts soon, 🟢 free.
+pub fn status_glyph(events: &[Event], now: NaiveDateTime) -> &'static str {
+    if current_event(events, now).is_some() {
+        return "🔴";
+    }
+    let soon = now + chrono::Duration::minutes(SOON_MINUTES);
+    let starting_soon = events
+        .iter()
+        .any(|e| !e.is_all_day && e.is_busy() && e.start > now && e.start <= soon);
+    if starting_soon {
+        "🟡"
     } else {
-        fmt_time(event_dt.hour(), event_dt.minute(), use_12h)
+        "🟢"
     }
 }
 
-/// Formats event time relative to now.
-/// (e.g., "now", "in 30 min", "today 5 pm", "tmrw 9:00 am", or absolute)
-/// Note: Caller should handle all-day events before calling this function.
-pub fn fmt_relative_time(event_dt: NaiveDateTime, now_dt: NaiveDateTime, use_12h: bool) -> String {
-    let minutes = event_dt.signed_duration_since(now_dt).num_minutes();
+/// Shortest free stretch worth listing in the free-gaps footer.
+const MIN_FREE_GAP_MINUTES: i64 = 15;
 
-    // Past events or >24h away: absolute format
-    if !(0..=24 * 60).contains(&minutes) {
-        return fmt_datetime(event_dt, use_12h);
+/// Busy time block of a timed event, for overlap math (`None` for all-day, free, or open events).
+fn busy_block(event: &Event) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    if event.is_all_day || event.is_overlay || !event.is_busy() {
+        return None;
+    }
+    Some((event.start, event.end?))
+}
+
+/// Flags events whose busy time overlaps another event's (double bookings).
+pub fn find_conflicts(events: &[&Event]) -> Vec<bool> {
+    let blocks: Vec<_> = events.iter().map(|e| busy_block(e)).collect();
+    blocks
+        .iter()
+        .enumerate()
+        .map(|(i, block)| {
+            let Some((start, end)) = block else {
+                return false;
+            };
+            blocks.iter().enumerate().any(|(j, other)| {
+                i != j
+                    && other.is_some_and(|(other_start, other_end)| {
+                        *start < other_end && other_start < *end
+                    })
+            })
+        })
+        .collect()
+}
+
+/// Free stretches from `now` to the end of today; the last one may run to midnight.
+pub fn free_gaps(events: &[Event], now: NaiveDateTime) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+    let day_end = now.date().and_hms_opt(0, 0, 0).unwrap() + chrono::Duration::days(1);
+    let mut blocks: Vec<_> = events
+        .iter()
+        .filter_map(busy_block)
+        .filter(|(start, end)| *end > now && *start < day_end)
+        .collect();
+    blocks.sort();
+
+    let mut gaps = Vec::new();
+    let mut free_from = now;
+    for (start, end) in blocks.into_iter().chain([(day_end, day_end)]) {
+        if (start - free_from).num_minutes() >= MIN_FREE_GAP_MINUTES {
+            gaps.push((free_from, start));
+        }
+        free_from = free_from.max(end);
+    }
+    gaps
+}
+
+/// When a focus timer started at `now` ends: `buffer_minutes` before the next busy block, so
+/// it can't run into a meeting. Fails while busy or without a meeting ahead to time against.
+pub fn focus_timer_end(
+    events: &[Event],
+    now: NaiveDateTime,
+    buffer_minutes: u32,
+) -> Result<NaiveDateTime, String> {
+    let blocks: Vec<_> = events
+        .iter()
+        .filter_map(busy_block)
+        .filter(|(_, end)| *end > now)
+        .collect();
+    if blocks.iter().any(|(start, _)| *start <= now) {
+        return Err("In a meeting".to_string());
+    }
+    let next = blocks
+        .iter()
+        .map(|(start, _)| *start)
+        .min()
+        .ok_or("No meeting ahead to time against")?;
+    let end = next - chrono::Duration::minutes(buffer_minutes as i64);
+    if (end - now).num_minutes() < 1 {
+        return Err("Too close to the next meeting".to_string());
+    }
+    Ok(end)
+}
+
+/// Formats free gaps for the footer. (e.g., "free now–2p, 3:30p–4p, 5p+")
+pub fn fmt_free_gaps(
+    gaps: &[(NaiveDateTime, NaiveDateTime)],
+    now: NaiveDateTime,
+    use_12h: bool,
+) -> String {
+    if gaps.is_empty() {
+        return "no free time left today".to_string();
+    }
+    let time = |t: NaiveDateTime| fmt_time_compact(t.hour(), t.minute(), use_12h);
+    let midnight = now.date().and_hms_opt(0, 0, 0).unwrap() + chrono::Duration::days(1);
+    let ranges: Vec<String> = gaps
+        .iter()
+        .map(|&(start, end)| {
+            let start = if start == now {
+                "now".to_string()
+            } else {
+                time(start)
+            };
+            if end == midnight {
+                format!("{}+", start)
+            } else {
+                format!("{}–{}", start, time(end))
+            }
+        })
+        .collect();
+    if ranges == ["now+"] {
+        return "free rest of day".to_string();
+    }
+    format!("free {}", ranges.join(", "))
+}
+
+/// Busy time within `[from, to)`, with overlapping and back-to-back events merged.
+pub fn busy_blocks(
+    events: &[Event],
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+    let blocks = events
+        .iter()
+        .filter_map(busy_block)
+        .map(|(start, end)| (start.max(from), end.min(to)))
+        .filter(|(start, end)| start < end)
+        .collect();
+    merge_blocks(blocks)
+}
+
+/// Sorts time blocks and joins the ones that overlap or touch.
+fn merge_blocks(
+    mut blocks: Vec<(NaiveDateTime, NaiveDateTime)>,
+) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+    blocks.sort();
+    let mut merged: Vec<(NaiveDateTime, NaiveDateTime)> = Vec::new();
+    for (start, end) in blocks {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Hours to propose meeting times in, Monday to Friday, and when the workday ends.
+/// Configured with `working_hours`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkingHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl Default for WorkingHours {
+    fn default() -> Self {
+        WorkingHours {
+            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+        }
+    }
+}
+
+impl WorkingHours {
+    /// Parses a 24-hour range: "9-17", "09:30-18:00".
+    pub fn parse(s: &str) -> Option<Self> {
+        let time = |t: &str| {
+            let t = t.trim();
+            match t.parse::<u32>() {
+                Ok(hour) => NaiveTime::from_hms_opt(hour, 0, 0),
+                Err(_) => NaiveTime::parse_from_str(t, "%H:%M").ok(),
+            }
+        };
+        let (start, end) = s.split_once('-')?;
+        let hours = WorkingHours {
+            start: time(start)?,
+            end: time(end)?,
+        };
+        (hours.start < hours.end).then_some(hours)
+    }
+
+    /// Whether the workday is over at `now`.
+    pub fn is_over(&self, now: NaiveDateTime) -> bool {
+        now.time() >= self.end
+    }
+}
+
+/// What's left to show when off the clock (past the end of working hours): just the first
+/// timed event of the next day that has one, so tonight's list doesn't show tomorrow's meetings.
+pub fn off_clock_events<'a>(
+    events: &[&'a Event],
+    now: NaiveDateTime,
+    hours: &WorkingHours,
+) -> Option<Vec<&'a Event>> {
+    if !hours.is_over(now) {
+        return None;
+    }
+    let first = events
+        .iter()
+        .find(|e| !e.is_overlay && !e.is_all_day && e.start.date() > now.date());
+    Some(first.copied().into_iter().collect())
+}
+
+/// Hosts whose links `o` opens without asking, unless `link_allowlist` says otherwise.
+const DEFAULT_LINK_HOSTS: &[&str] = &[
+    "zoom.us",
+    "meet.google.com",
+    "teams.microsoft.com",
+    "teams.live.com",
+];
+
+/// Links `o` opens without asking; anything else needs a second `o`, since feeds can carry
+/// any URL. Configured with `link_allowlist`: hosts (which cover their subdomains, over http
+/// and https only), schemes like "https:", or "*" for everything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkAllowlist {
+    /// Lowercase hosts.
+    pub hosts: Vec<String>,
+    /// Lowercase schemes, without the colon.
+    pub schemes: Vec<String>,
+    pub any: bool,
+}
+
+impl Default for LinkAllowlist {
+    fn default() -> Self {
+        LinkAllowlist {
+            hosts: DEFAULT_LINK_HOSTS.iter().map(|h| h.to_string()).collect(),
+            schemes: Vec::new(),
+            any: false,
+        }
+    }
+}
+
+impl LinkAllowlist {
+    /// Parses `link_allowlist` ("zoom.us, meet.google.com, https:").
+    pub fn parse(s: &str) -> Self {
+        let mut allowlist = LinkAllowlist {
+            hosts: Vec::new(),
+            schemes: Vec::new(),
+            any: false,
+        };
+        for entry in s.split(',').map(|e| e.trim().to_lowercase()) {
+            if entry == "*" {
+                allowlist.any = true;
+            } else if let Some(scheme) = entry.strip_suffix("://").or(entry.strip_suffix(':')) {
+                allowlist.schemes.push(scheme.to_string());
+            } else if !entry.is_empty() {
+                allowlist.hosts.push(entry);
+            }
+        }
+        allowlist
+    }
+
+    pub fn allows(&self, url: &str) -> bool {
+        if self.any {
+            return true;
+        }
+        let Some((scheme, rest)) = url.split_once("://") else {
+            return false;
+        };
+        if self.schemes.iter().any(|s| s.eq_ignore_ascii_case(scheme)) {
+            return true;
+        }
+        // "ms-msdt://zoom.us/..." isn't a web link to zoom.us
+        if !scheme.eq_ignore_ascii_case("https") && !scheme.eq_ignore_ascii_case("http") {
+            return false;
+        }
+        let host = link_host(rest);
+        self.hosts.iter().any(|allowed| {
+            host == *allowed
+                || host
+                    .strip_suffix(allowed.as_str())
+                    .is_some_and(|sub| sub.ends_with('.'))
+        })
+    }
+}
+
+/// Lowercase host of a URL after its "scheme://". Userinfo and port are dropped, so
+/// "zoom.us@evil.example" is evil.example's link. Like browsers, `\` ends the authority too.
+pub fn link_host(rest: &str) -> String {
+    let authority = rest.split(['/', '\\', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    let host = host.split(':').next().unwrap_or_default();
+    host.to_lowercase()
+}
+
+/// Travel time to events with a physical location. Configured with `travel_minutes` and
+/// `travel_overrides` ("office=10, airport=60": minutes for locations containing the name).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TravelTimes {
+    pub default_minutes: Option<u32>,
+    /// (lowercase location fragment, minutes), first match wins.
+    pub overrides: Vec<(String, u32)>,
+}
+
+impl TravelTimes {
+    /// Parses `travel_overrides`; malformed entries are skipped.
+    pub fn parse_overrides(s: &str) -> Vec<(String, u32)> {
+        s.split([',', ';'])
+            .filter_map(|entry| {
+                let (name, minutes) = entry.split_once('=')?;
+                let name = name.trim().to_lowercase();
+                (!name.is_empty()).then_some((name, minutes.trim().parse().ok()?))
+            })
+            .collect()
+    }
+
+    /// Minutes to get to the event, for timed events somewhere physical.
+    pub fn minutes_for(&self, event: &Event) -> Option<u32> {
+        if event.is_all_day || event.is_overlay || event.is_video_call() {
+            return None;
+        }
+        let location = event.location.as_deref()?.trim().to_lowercase();
+        // A bare link isn't somewhere to travel to
+        if location.is_empty() || location.starts_with("http") {
+            return None;
+        }
+        self.overrides
+            .iter()
+            .find(|(name, _)| location.contains(name.as_str()))
+            .map(|(_, minutes)| *minutes)
+            .or(self.default_minutes)
+            .filter(|&minutes| minutes > 0)
+    }
+
+    /// When to leave for the event ("leave by"), if it needs travel.
+    pub fn leave_by(&self, event: &Event) -> Option<NaiveDateTime> {
+        let minutes = self.minutes_for(event)?;
+        Some(event.start - chrono::Duration::minutes(minutes as i64))
+    }
+}
+
+/// Rounds up to the next quarter hour, so proposals land on tidy times.
+fn round_up_to_quarter(t: NaiveDateTime) -> NaiveDateTime {
+    let extra = (15 - t.minute() % 15) % 15;
+    let t = t.with_second(0).unwrap_or(t);
+    t + chrono::Duration::minutes(extra as i64)
+}
+
+/// Start times of the first `count` free slots of `minutes` within working hours, from `now`
+/// until `until`. Busy time on every calendar counts, overlays included; days off (weekends
+/// and all-day out-of-office) are skipped. Each free gap offers its earliest start.
+pub fn propose_times(
+    events: &[&Event],
+    now: NaiveDateTime,
+    minutes: i64,
+    hours: &WorkingHours,
+    until: NaiveDateTime,
+    count: usize,
+) -> Vec<NaiveDateTime> {
+    let length = chrono::Duration::minutes(minutes);
+    let busy = merge_blocks(
+        events
+            .iter()
+            .filter(|e| !e.is_all_day && e.is_busy())
+            .filter_map(|e| Some((e.start, e.end?)))
+            .collect(),
+    );
+    let is_day_off = |date: NaiveDate| {
+        date.weekday().number_from_monday() > 5
+            || events
+                .iter()
+                .any(|e| e.is_all_day && e.is_out_of_office() && e.is_active_on(date))
+    };
+
+    let mut slots = Vec::new();
+    let mut date = now.date();
+    while slots.len() < count && date.and_time(hours.start) < until {
+        if !is_day_off(date) {
+            let day_end = date.and_time(hours.end);
+            let day_start = round_up_to_quarter(date.and_time(hours.start).max(now));
+            let mut free_from = day_start;
+            for &(start, end) in busy.iter().filter(|(s, e)| *e > day_start && *s < day_end) {
+                if start - free_from >= length {
+                    slots.push(free_from);
+                }
+                free_from = free_from.max(round_up_to_quarter(end));
+            }
+            if day_end - free_from >= length {
+                slots.push(free_from);
+            }
+        }
+        date += chrono::Duration::days(1);
+    }
+    slots.truncate(count);
+    slots
+}
+
+/// One proposed slot per line. (e.g., "tomorrow 10a–10:30a")
+pub fn fmt_proposals(
+    slots: &[NaiveDateTime],
+    minutes: i64,
+    today: NaiveDate,
+    use_12h: bool,
+    lang: Language,
+) -> String {
+    if slots.is_empty() {
+        return format!("No free {} slots in working hours", fmt_duration(minutes));
+    }
+    let time = |t: NaiveDateTime| fmt_time_compact(t.hour(), t.minute(), use_12h);
+    slots
+        .iter()
+        .map(|&start| {
+            let end = start + chrono::Duration::minutes(minutes);
+            let day = fmt_day_header(start.date(), today, lang);
+            format!("{} {}–{}", day, time(start), time(end))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Busy times for today (from `now`) and the next `days - 1` days, without any event details,
+/// for sharing. (e.g., "mon jan 15: busy 9a–10a, 1p–2:30p, free otherwise")
+pub fn fmt_availability(events: &[Event], now: NaiveDateTime, days: i64, use_12h: bool) -> String {
+    let time = |t: NaiveDateTime| fmt_time_compact(t.hour(), t.minute(), use_12h);
+    (0..days)
+        .map(|d| {
+            let date = now.date() + chrono::Duration::days(d);
+            let day_start = date.and_hms_opt(0, 0, 0).unwrap();
+            let blocks = busy_blocks(
+                events,
+                day_start.max(now),
+                day_start + chrono::Duration::days(1),
+            );
+            let label = date.format("%a %b %-d").to_string().to_lowercase();
+            if blocks.is_empty() {
+                return format!("{}: free", label);
+            }
+            let busy: Vec<String> = blocks
+                .iter()
+                .map(|&(start, end)| format!("{}–{}", time(start), time(end)))
+                .collect();
+            format!("{}: busy {}, free otherwise", label, busy.join(", "))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// How booked a day is, for the week density row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DayLoad {
+    Free,
+    /// Under 2 hours of meetings.
+    Light,
+    /// Under 4 hours.
+    Busy,
+    Packed,
+}
+
+/// Load of a day from the busy time of its timed events (overlaps counted once).
+pub fn day_load(events: &[Event], date: NaiveDate) -> DayLoad {
+    let day_start = date.and_hms_opt(0, 0, 0).unwrap();
+    let day_end = day_start + chrono::Duration::days(1);
+    let minutes: i64 = busy_blocks(events, day_start, day_end)
+        .iter()
+        .map(|(start, end)| (*end - *start).num_minutes())
+        .sum();
+    match minutes {
+        0 => DayLoad::Free,
+        1..120 => DayLoad::Light,
+        120..240 => DayLoad::Busy,
+        _ => DayLoad::Packed,
+    }
+}
+
+/// How much of a day's working hours is booked, for the busy gauge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DayBooking {
+    /// Busy minutes within working hours (overlaps counted once).
+    pub booked: i64,
+    /// Of those, the minutes already behind `now`.
+    pub done: i64,
+    /// Length of the working-hours window.
+    pub window: i64,
+}
+
+impl DayBooking {
+    pub fn percent(&self) -> i64 {
+        if self.window == 0 {
+            return 0;
+        }
+        (self.booked * 100 + self.window / 2) / self.window
+    }
+}
+
+/// Today's working hours split into equal cells, for the day-progress bar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DayProgress {
+    /// Whether a busy event covers any of each cell.
+    pub busy: Vec<bool>,
+    /// Cells wholly behind `now`; the next one holds it, if the day isn't over.
+    pub done: usize,
+}
+
+/// Where `now` sits in today's working hours, `cells` wide, with meetings marked. Weekends
+/// have no working day to show.
+pub fn day_progress(
+    events: &[Event],
+    now: NaiveDateTime,
+    hours: &WorkingHours,
+    cells: usize,
+) -> Option<DayProgress> {
+    if now.weekday().number_from_monday() > 5 || cells == 0 {
+        return None;
+    }
+    let start = now.date().and_time(hours.start);
+    let window = (now.date().and_time(hours.end) - start).num_seconds();
+    let cell_start = |i: usize| start + chrono::Duration::seconds(window * i as i64 / cells as i64);
+    let blocks = busy_blocks(events, start, now.date().and_time(hours.end));
+    let busy = (0..cells)
+        .map(|i| {
+            let (from, to) = (cell_start(i), cell_start(i + 1));
+            blocks.iter().any(|&(start, end)| start < to && end > from)
+        })
+        .collect();
+    let done = (0..cells).take_while(|&i| cell_start(i + 1) <= now).count();
+    Some(DayProgress { busy, done })
+}
+
+/// Booked share of today's working hours, with the part already done at `now`. Weekends have
+/// no working hours to book.
+pub fn day_booking(
+    events: &[Event],
+    now: NaiveDateTime,
+    hours: &WorkingHours,
+) -> Option<DayBooking> {
+    if now.weekday().number_from_monday() > 5 {
+        return None;
+    }
+    let (start, end) = (
+        now.date().and_time(hours.start),
+        now.date().and_time(hours.end),
+    );
+    let blocks = busy_blocks(events, start, end);
+    let minutes = |(from, to): (NaiveDateTime, NaiveDateTime)| (to - from).num_minutes().max(0);
+    Some(DayBooking {
+        booked: blocks.iter().map(|&block| minutes(block)).sum(),
+        done: blocks
+            .iter()
+            .map(|&(from, to)| minutes((from, to.min(now))))
+            .sum(),
+        window: (end - start).num_minutes(),
+    })
+}
+
+/// Formats the busy gauge's text. (e.g., "62% booked · 2 hr to go")
+pub fn fmt_booking(booking: &DayBooking) -> String {
+    let percent = booking.percent();
+    match booking.booked - booking.done {
+        _ if booking.booked == 0 => "0% booked".to_string(),
+        0 => format!("{}% booked · all done", percent),
+        left => format!("{}% booked · {} to go", percent, fmt_duration(left)),
+    }
+}
+
+/// Weeks (Monday first) of the month containing `date`; days outside the month are `None`.
+pub fn month_weeks(date: NaiveDate) -> Vec<[Option<NaiveDate>; 7]> {
+    let first = date.with_day(1).unwrap();
+    let monday = first - chrono::Duration::days(first.weekday().num_days_from_monday() as i64);
+    let mut weeks = Vec::new();
+    let mut day = monday;
+    while day.month() == first.month() || day < first {
+        let mut week = [None; 7];
+        for slot in week.iter_mut() {
+            *slot = (day.month() == first.month()).then_some(day);
+            day += chrono::Duration::days(1);
+        }
+        weeks.push(week);
+    }
+    weeks
+}
+
+/// Parses UTC offset string (e.g., "-0500", "+0530") to minutes.
+pub fn parse_utc_offset(s: &str) -> Option<i32> {
+    let s = s.trim();
+    if s.len() != 5 {
+        return None;
+    }
+    let sign = match s.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let hours: i32 = s[1..3].parse().ok()?;
+    let minutes: i32 = s[3..5].parse().ok()?;
+    Some(sign * (hours * 60 + minutes))
+}
+
+/// Parses "YYYY-MM-DD HH:MM" (or "YYYY-MM-DD HH:MM:SS", from shell `date`) to NaiveDateTime.
+pub fn parse_datetime(dt: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(dt, DATETIME_FMT)
+        .or_else(|_| NaiveDateTime::parse_from_str(dt, DATETIME_SECS_FMT))
+        .ok()
+}
+
+/// Formats hour/minute as "HH:MM" or "H:MM am/pm".
+pub fn fmt_time(hour: u32, minute: u32, use_12h: bool) -> String {
+    if !use_12h {
+        return format!("{:02}:{:02}", hour, minute);
+    }
+
+    let (hour_12, period) = match hour {
+        0 => (12, "am"),
+        1..=11 => (hour, "am"),
+        12 => (12, "pm"),
+        _ => (hour - 12, "pm"),
+    };
+
+    format!("{}:{:02} {}", hour_12, minute, period)
+}
+
+/// Formats hour/minute compactly for narrow columns: "14:30", or "2:30p" / "9a".
+pub fn fmt_time_compact(hour: u32, minute: u32, use_12h: bool) -> String {
+    if !use_12h {
+        return format!("{:02}:{:02}", hour, minute);
+    }
+    let suffix = if hour < 12 { "a" } else { "p" };
+    let hour = match hour % 12 {
+        0 => 12,
+        h => h,
+    };
+    if minute == 0 {
+        format!("{}{}", hour, suffix)
+    } else {
+        format!("{}:{:02}{}", hour, minute, suffix)
+    }
+}
+
+/// Formats datetime as absolute display.
+/// (e.g., "jan 15 10:00 am" or "jan 15" for all-day)
+pub fn fmt_datetime(dt: NaiveDateTime, use_12h: bool) -> String {
+    let is_all_day = dt.hour() == 0 && dt.minute() == 0;
+    let date = dt.format("%b %-d").to_string().to_lowercase();
+
+    if is_all_day {
+        date
+    } else {
+        format!("{} {}", date, fmt_time(dt.hour(), dt.minute(), use_12h))
+    }
+}
+
+/// Formats an event's time span for the detail view.
+/// (e.g., "10:00 am – 11:30 am", "jan 15 10:00 pm – jan 16 2:00 am", or "jan 15 – jan 17")
+pub fn fmt_event_range(event: &Event, use_12h: bool) -> String {
+    let time = |t: NaiveDateTime| fmt_time(t.hour(), t.minute(), use_12h);
+    let date = |t: NaiveDateTime| t.format("%b %-d").to_string().to_lowercase();
+    match event.end {
+        // All-day ends are exclusive (the day after the last one)
+        Some(end) if event.is_all_day && end.date() > event.start.date().succ_opt().unwrap() => {
+            format!(
+                "{} – {}",
+                date(event.start),
+                date(end - chrono::Duration::days(1))
+            )
+        }
+        _ if event.is_all_day => format!("{} · all day", date(event.start)),
+        Some(end) if end.date() == event.start.date() => {
+            format!("{} – {}", time(event.start), time(end))
+        }
+        Some(end) => format!(
+            "{} {} – {} {}",
+            date(event.start),
+            time(event.start),
+            date(end),
+            time(end)
+        ),
+        None => time(event.start),
+    }
+}
+
+/// Summarizes invitees' responses. (e.g., "4 attendees · 2 accepted · 1 declined")
+pub fn fmt_attendance(attendees: &[Attendee]) -> Option<String> {
+    if attendees.is_empty() {
+        return None;
+    }
+    let count = |status: &str| {
+        attendees
+            .iter()
+            .filter(|a| a.partstat.as_deref() == Some(status))
+            .count()
+    };
+    let plural = if attendees.len() == 1 { "" } else { "s" };
+    let mut parts = vec![format!("{} attendee{}", attendees.len(), plural)];
+    for (status, label) in [
+        ("ACCEPTED", "accepted"),
+        ("TENTATIVE", "maybe"),
+        ("DECLINED", "declined"),
+    ] {
+        match count(status) {
+            0 => {}
+            n => parts.push(format!("{} {}", n, label)),
+        }
+    }
+    Some(parts.join(" · "))
+}
+
+/// The day `t` belongs to when days roll over at `day_start` instead of midnight: with a 4 AM
+/// start, 1 AM Tuesday is still Monday night.
+pub fn day_of(t: NaiveDateTime, day_start: NaiveTime) -> NaiveDate {
+    (t - day_start.signed_duration_since(NaiveTime::MIN)).date()
+}
+
+/// Formats a date as a day group header.
+/// (e.g., "today", "tomorrow", or "tuesday, jan 22")
+pub fn fmt_day_header(event_date: NaiveDate, today: NaiveDate, lang: Language) -> String {
+    let days_diff = (event_date - today).num_days();
+    match days_diff {
+        0 => lang.strings().today.to_string(),
+        1 => lang.strings().tomorrow.to_string(),
+        _ => event_date.format("%A, %b %-d").to_string().to_lowercase(),
+    }
+}
+
+/// When a birthday or anniversary falls: "today", "tomorrow", or "sat jan 20 · in 5 days".
+pub fn fmt_occasion_day(date: NaiveDate, today: NaiveDate, lang: Language) -> String {
+    match (date - today).num_days() {
+        ..=0 => lang.strings().today.to_string(),
+        1 => lang.strings().tomorrow.to_string(),
+        days => format!("{} · in {} days", date.format("%a %b %-d"), days).to_lowercase(),
+    }
+}
+
+/// Formats the date context line under the header.
+/// (e.g., "monday, january 15, 2024 · day 15 · week 3 · 16 days left in january")
+pub fn fmt_date_context(today: NaiveDate) -> String {
+    let next_month = match today.month() {
+        12 => NaiveDate::from_ymd_opt(today.year() + 1, 1, 1),
+        month => NaiveDate::from_ymd_opt(today.year(), month + 1, 1),
+    };
+    let days_left = next_month.map_or(0, |first| (first - today).num_days() - 1);
+    let left = match days_left {
+        0 => "last day of".to_string(),
+        1 => "1 day left in".to_string(),
+        n => format!("{} days left in", n),
+    };
+    format!(
+        "{} · day {} · week {} · {} {}",
+        today.format("%A, %B %-d, %Y"),
+        today.ordinal(),
+        today.iso_week().week(),
+        left,
+        today.format("%B")
+    )
+    .to_lowercase()
+}
+
+/// Formats a timed event's start and end as clock times, for peeking past relative times.
+/// (e.g., "14:30–15:00" or "2:30 pm–3:00 pm"; just the start without an end)
+pub fn fmt_time_range(event: &Event, use_12h: bool) -> Option<String> {
+    if event.is_all_day {
+        return None;
+    }
+    let start = fmt_time(event.start.hour(), event.start.minute(), use_12h);
+    Some(match event.end {
+        Some(end) => format!("{}–{}", start, fmt_time(end.hour(), end.minute(), use_12h)),
+        None => start,
+    })
+}
+
+/// Formats a flight label with route and arrival time.
+/// (e.g., "AA123 SFO→JFK · lands 5:30 pm")
+pub fn fmt_flight(flight: &Flight, arrival: Option<NaiveDateTime>, use_12h: bool) -> String {
+    let mut label = flight.number.clone();
+    if let Some((from, to)) = &flight.route {
+        label.push_str(&format!(" {}→{}", from, to));
+    }
+    if let Some(arrival) = arrival {
+        let time = fmt_time(arrival.hour(), arrival.minute(), use_12h);
+        label.push_str(&format!(" · lands {}", time));
+    }
+    label
+}
+
+/// Formats an event for compact mode and the `next-event` pipe.
+/// (e.g., "📹 Standup in 10 min" or "• Lunch now")
+pub fn fmt_next_event(
+    event: &Event,
+    now: NaiveDateTime,
+    use_12h: bool,
+    lang: Language,
+    day_start: NaiveTime,
+    icons: &Icons,
+) -> String {
+    let icon = event.icon(icons);
+    match event.end {
+        Some(end) if event.is_in_progress(now) => {
+            format!("{} {} · {}", icon, event.summary, fmt_time_left(end, now))
+        }
+        _ if event.is_in_progress(now) => {
+            format!("{} {} {}", icon, event.summary, lang.strings().now)
+        }
+        _ => {
+            let when = fmt_relative_time(event.start, now, use_12h, lang, day_start);
+            format!("{} {} {}", icon, event.summary, when)
+        }
+    }
+}
+
+/// One dense line for the "next three" widget. (e.g., "now 📹 Standup", "2:30p • 1:1",
+/// or "tmrw 9a • Planning")
+pub fn fmt_widget_line(
+    event: &Event,
+    now: NaiveDateTime,
+    use_12h: bool,
+    lang: Language,
+    day_start: NaiveTime,
+    icons: &Icons,
+) -> String {
+    let time = fmt_time_compact(event.start.hour(), event.start.minute(), use_12h);
+    let days = (day_of(event.start, day_start) - day_of(now, day_start)).num_days();
+    let when = match days {
+        _ if event.is_in_progress(now) => lang.strings().now.to_string(),
+        0 => time,
+        1 => format!("{} {}", lang.strings().tmrw, time),
+        2..=6 => format!(
+            "{} {}",
+            event.start.format("%a").to_string().to_lowercase(),
+            time
+        ),
+        _ => format!("{} {}", event.start.format("%-m/%-d"), time),
+    };
+    format!("{} {} {}", when, event.icon(icons), event.summary)
+}
+
+/// A length of time in words, for screen readers. (e.g., "1 minute" or "2 hours 30 minutes")
+pub fn fmt_spoken_duration(minutes: i64) -> String {
+    let unit = |n: i64, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+    match (minutes / 60, minutes % 60) {
+        (0, m) => unit(m, "minute"),
+        (h, 0) => unit(h, "hour"),
+        (h, m) => format!("{} {}", unit(h, "hour"), unit(m, "minute")),
+    }
+}
+
+/// One event as a plain sentence for screen readers, with no icons or columns.
+/// (e.g., "Standup, starts in 10 minutes at 9:30 am, video call")
+pub fn describe_event(
+    event: &Event,
+    now: NaiveDateTime,
+    use_12h: bool,
+    day_start: NaiveTime,
+) -> String {
+    let time = |t: NaiveDateTime| fmt_time(t.hour(), t.minute(), use_12h);
+    let day = |t: NaiveDateTime| match (day_of(t, day_start) - day_of(now, day_start)).num_days() {
+        0 => "today".to_string(),
+        1 => "tomorrow".to_string(),
+        _ => t.format("on %A, %B %-d").to_string(),
+    };
+    let mut parts = vec![event.summary.clone()];
+    parts.push(match event.end {
+        _ if event.is_all_day => format!("all day {}", day(event.start)),
+        Some(end) if event.has_ended(now) => {
+            format!(
+                "ended {} ago",
+                fmt_spoken_duration((now - end).num_minutes())
+            )
+        }
+        Some(end) if event.is_in_progress(now) => format!(
+            "in progress, ends in {} at {}",
+            fmt_spoken_duration((end - now).num_minutes().max(1)),
+            time(end)
+        ),
+        None if event.start <= now => format!("started at {}", time(event.start)),
+        _ if event.start.date() == now.date() => format!(
+            "starts in {} at {}",
+            fmt_spoken_duration((event.start - now).num_minutes().max(1)),
+            time(event.start)
+        ),
+        _ => format!("starts {} at {}", day(event.start), time(event.start)),
+    });
+    if event.is_video_call() {
+        parts.push("video call".to_string());
+    } else if let Some(location) = event.location.as_deref().filter(|l| !l.is_empty()) {
+        parts.push(format!("at {}", location));
+    }
+    match event.busy_status {
+        BusyStatus::Tentative => parts.push("tentative".to_string()),
+        BusyStatus::Free => parts.push("marked free".to_string()),
+        BusyStatus::OutOfOffice => parts.push("out of office".to_string()),
+        BusyStatus::Busy => {}
+    }
+    parts.join(", ")
+}
+
+/// Formats the time until an event ends. (e.g., "18 min left" or "1 hr 5 min left")
+pub fn fmt_time_left(end: NaiveDateTime, now: NaiveDateTime) -> String {
+    match (end - now).num_minutes().max(0) {
+        0 => "<1 min left".to_string(),
+        minutes => format!("{} left", fmt_duration(minutes)),
+    }
+}
+
+/// Formats how long ago an event ended. (e.g., "ended 5 min ago" or "ended just now")
+pub fn fmt_ended_ago(end: NaiveDateTime, now: NaiveDateTime) -> String {
+    match (now - end).num_minutes().max(0) {
+        0 => "ended just now".to_string(),
+        minutes => format!("ended {} ago", fmt_duration(minutes)),
+    }
+}
+
+/// Formats how long ago an event started, for a call not yet joined. (e.g., "started 5 min ago")
+pub fn fmt_started_ago(start: NaiveDateTime, now: NaiveDateTime) -> String {
+    format!(
+        "started {} ago",
+        fmt_duration((now - start).num_minutes().max(1))
+    )
+}
+
+/// Formats a length of time. (e.g., "45 min", "1 hr", or "1 hr 30 min")
+pub fn fmt_duration(minutes: i64) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{} min", m),
+        (h, 0) => format!("{} hr", h),
+        (h, m) => format!("{} hr {} min", h, m),
+    }
+}
+
+/// Formats a live countdown to `target`. (e.g., "7:32" or "1:07:32")
+pub fn fmt_countdown(target: NaiveDateTime, now: NaiveDateTime) -> String {
+    let secs = (target - now).num_seconds().max(0);
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+/// Formats the out-of-office banner.
+/// (e.g., "OOO until 5:00 pm", "OOO until thu", or "OOO until jan 22")
+pub fn fmt_ooo_banner(until: NaiveDateTime, now: NaiveDateTime, use_12h: bool) -> String {
+    let days_diff = (until.date() - now.date()).num_days();
+    let when = if days_diff == 0 {
+        fmt_time(until.hour(), until.minute(), use_12h)
+    } else if days_diff < 7 {
+        until.format("%a").to_string().to_lowercase()
+    } else {
+        until.format("%b %-d").to_string().to_lowercase()
+    };
+    format!("OOO until {}", when)
+}
+
+/// Formats event time for display within a day group.
+/// All-day events return "all day". Today uses relative time, other days just the time.
+pub fn fmt_time_in_group(
+    event_dt: NaiveDateTime,
+    now_dt: NaiveDateTime,
+    is_today: bool,
+    is_all_day: bool,
+    use_12h: bool,
+    lang: Language,
+    day_start: NaiveTime,
+) -> String {
+    if is_all_day {
+        return lang.strings().all_day.to_string();
+    }
+
+    if is_today {
+        fmt_relative_time(event_dt, now_dt, use_12h, lang, day_start)
+    } else {
+        fmt_time(event_dt.hour(), event_dt.minute(), use_12h)
+    }
+}
+
+/// Time label for a timed event running past midnight, as shown on `date`: "11:00 pm → …" on
+/// its first day, "… → 1:00 am" on its last, "… → …" in between. `None` for same-day events.
+pub fn fmt_overnight(
+    event: &Event,
+    date: NaiveDate,
+    use_12h: bool,
+    compact: bool,
+) -> Option<String> {
+    let end = event.end?;
+    let next_midnight = event.start.date().succ_opt()?.and_time(NaiveTime::MIN);
+    if event.is_all_day || end <= next_midnight || !event.is_active_on(date) {
+        return None;
+    }
+    let time = |t: NaiveDateTime| match compact {
+        true => fmt_time_compact(t.hour(), t.minute(), use_12h),
+        false => fmt_time(t.hour(), t.minute(), use_12h),
+    };
+    let from = if event.start.date() == date {
+        time(event.start)
+    } else {
+        "…".to_string()
+    };
+    let until = if end.date() == date {
+        time(end)
+    } else {
+        "…".to_string()
+    };
+    let arrow = if compact { "→" } else { " → " };
+    Some(format!("{}{}{}", from, arrow, until))
+}
+
+/// Formats event time relative to now.
+/// (e.g., "now", "in 30 min", "today 5 pm", "tmrw 9:00 am", or absolute)
+/// Note: Caller should handle all-day events before calling this function.
+pub fn fmt_relative_time(
+    event_dt: NaiveDateTime,
+    now_dt: NaiveDateTime,
+    use_12h: bool,
+    lang: Language,
+    day_start: NaiveTime,
+) -> String {
+    let minutes = event_dt.signed_duration_since(now_dt).num_minutes();
+
+    // Past events or >24h away: absolute format
+    if !(0..=24 * 60).contains(&minutes) {
+        return fmt_datetime(event_dt, use_12h);
+    }
+
+    let is_tomorrow = day_of(event_dt, day_start) != day_of(now_dt, day_start);
+    let words = lang.strings();
+
+    match minutes {
+        0 => words.now.to_string(),
+        1..=9 => words.in_minutes(minutes),
+        10..=55 => words.in_minutes(((minutes + 2) / 5) * 5),
+        56..=299 => {
+            let time = fmt_time(event_dt.hour(), event_dt.minute(), use_12h);
+            let whole_hours = minutes / 60;
+            let remainder = minutes % 60;
+
+            // Show .5 if within 10 min of half hour (20-40 min past)
+            let relative = if (20..=40).contains(&remainder) {
+                format!("{}.5 hrs", whole_hours)
+            } else {
+                // Round to nearest hour (>40 min rounds up)
+                let hours = if remainder > 40 {
+                    whole_hours + 1
+                } else {
+                    whole_hours
+                };
+                if hours == 1 {
+                    "1 hr".to_string()
+                } else {
+                    format!("{} hrs", hours)
+                }
+            };
+
+            format!("{} ({})", time, relative)
+        }
+        _ if is_tomorrow => {
+            let time = fmt_time(event_dt.hour(), event_dt.minute(), use_12h);
+            format!("{} {}", words.tmrw, time)
+        }
+        _ => {
+            let time = fmt_time(event_dt.hour(), event_dt.minute(), use_12h);
+            format!("{} {}", words.today, time)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    const ICS_TIMED_EVENT: &str = indoc! {"
+        BEGIN:VCALENDAR
+        VERSION:2.0
+        BEGIN:VEVENT
+        DTSTART:20240115T100000
+        DTEND:20240115T110000
+        SUMMARY:Team Standup
+        LOCATION:https://zoom.us/j/123
+        END:VEVENT
+        END:VCALENDAR
+    "};
+
+    const ICS_ALL_DAY_EVENT: &str = indoc! {"
+        BEGIN:VCALENDAR
+        VERSION:2.0
+        BEGIN:VEVENT
+        DTSTART;VALUE=DATE:20240115
+        DTEND;VALUE=DATE:20240116
+        SUMMARY:Company Holiday
+        END:VEVENT
+        END:VCALENDAR
+    "};
+
+    const ICS_UTC_EVENT: &str = indoc! {"
+        BEGIN:VCALENDAR
+        VERSION:2.0
+        BEGIN:VEVENT
+        DTSTART:20240115T150000Z
+        DTEND:20240115T160000Z
+        SUMMARY:UTC Meeting
+        END:VEVENT
+        END:VCALENDAR
+    "};
+
+    const ICS_MULTIPLE_EVENTS: &str = indoc! {"
+        BEGIN:VCALENDAR
+        VERSION:2.0
+        BEGIN:VEVENT
+        DTSTART:20240115T100000
+        SUMMARY:First Event
+        END:VEVENT
+        BEGIN:VEVENT
+        DTSTART:20240115T140000
+        SUMMARY:Second Event
+        END:VEVENT
+        END:VCALENDAR
+    "};
+
+    const ICS_BUSY_STATUS: &str = indoc! {"
+        BEGIN:VCALENDAR
+        VERSION:2.0
+        BEGIN:VEVENT
+        DTSTART:20240115T100000
+        SUMMARY:Focus Time
+        X-MICROSOFT-CDO-BUSYSTATUS:FREE
+        END:VEVENT
+        BEGIN:VEVENT
+        DTSTART:20240115T110000
+        SUMMARY:Maybe Lunch
+        X-MICROSOFT-CDO-BUSYSTATUS:TENTATIVE
+        TRANSP:TRANSPARENT
+        END:VEVENT
+        BEGIN:VEVENT
+        DTSTART:20240115T120000
+        SUMMARY:Vacation
+        X-MICROSOFT-CDO-BUSYSTATUS:OOF
+        END:VEVENT
+        BEGIN:VEVENT
+        DTSTART:20240115T130000
+        SUMMARY:Reminder
+        TRANSP:TRANSPARENT
+        END:VEVENT
+        BEGIN:VEVENT
+        DTSTART:20240115T140000
+        SUMMARY:Meeting
+        END:VEVENT
+        END:VCALENDAR
+    "};
+
+    const ICS_RECURRING: &str = indoc! {"
+        BEGIN:VCALENDAR
+        VERSION:2.0
+        BEGIN:VEVENT
+        UID:standup@example.com
+        DTSTART:20240101T100000
+        DTEND:20240101T101500
+        RRULE:FREQ=WEEKLY;BYDAY=MO,WE
+        EXDATE:20240117T100000
+        SUMMARY:Standup
+        END:VEVENT
+        BEGIN:VEVENT
+        UID:standup@example.com
+        RECURRENCE-ID:20240122T100000
+        DTSTART:20240122T110000
+        DTEND:20240122T111500
+        SUMMARY:Standup (moved)
+        END:VEVENT
+        BEGIN:VEVENT
+        UID:review@example.com
+        DTSTART:20240116T150000
+        RDATE:20240118T150000,20240119T150000
+        SUMMARY:Review
+        END:VEVENT
+        END:VCALENDAR
+    "};
+
+    const ICS_TZID: &str = indoc! {"
+        BEGIN:VCALENDAR
+        VERSION:2.0
+        BEGIN:VTIMEZONE
+        TZID:Europe/Berlin
+        BEGIN:DAYLIGHT
+        TZOFFSETFROM:+0100
+        TZOFFSETTO:+0200
+        DTSTART:19810329T020000
+        RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU
+        END:DAYLIGHT
+        BEGIN:STANDARD
+        TZOFFSETFROM:+0200
+        TZOFFSETTO:+0100
+        DTSTART:19961027T030000
+        RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU
+        END:STANDARD
+        END:VTIMEZONE
+        BEGIN:VEVENT
+        DTSTART;TZID=Europe/Berlin:20240115T150000
+        DTEND;TZID=Europe/Berlin:20240115T160000
+        SUMMARY:Winter Sync
+        END:VEVENT
+        BEGIN:VEVENT
+        DTSTART;TZID=Europe/Berlin:20240701T150000
+        SUMMARY:Summer Sync
+        END:VEVENT
+        END:VCALENDAR
+    "};
+
+    /// All-day events as midnight DATE-TIMEs: Outlook's flagged ones, and a UTC feed's.
+    const ICS_MIDNIGHT_ALL_DAY: &str = indoc! {"
+        BEGIN:VCALENDAR
+        VERSION:2.0
+        BEGIN:VEVENT
+        DTSTART;TZID=Europe/Berlin:20240115T000000
+        DTEND;TZID=Europe/Berlin:20240116T000000
+        X-MICROSOFT-CDO-ALLDAYEVENT:TRUE
+        SUMMARY:Vacation
+        END:VEVENT
+        BEGIN:VEVENT
+        DTSTART:20240117T000000Z
+        DTEND:20240119T000000Z
+        SUMMARY:Conference
+        END:VEVENT
+        BEGIN:VEVENT
+        DTSTART:20240120T000000Z
+        DTEND:20240120T010000Z
+        SUMMARY:Late Call
+        END:VEVENT
+        END:VCALENDAR
+    "};
+
+    const ICS_ALARMS: &str = indoc! {"
+        BEGIN:VCALENDAR
+        VERSION:2.0
+        BEGIN:VEVENT
+        DTSTART:20240115T100000
+        SUMMARY:Planning
+        BEGIN:VALARM
+        ACTION:DISPLAY
+        TRIGGER:-PT10M
+        END:VALARM
+        BEGIN:VALARM
+        ACTION:DISPLAY
+        TRIGGER;RELATED=START:-P1D
+        END:VALARM
+        END:VEVENT
+        BEGIN:VEVENT
+        DTSTART:20240115T140000
+        SUMMARY:Retro
+        BEGIN:VALARM
+        ACTION:DISPLAY
+        TRIGGER;RELATED=END:-PT5M
+        END:VALARM
+        BEGIN:VALARM
+        ACTION:DISPLAY
+        TRIGGER;VALUE=DATE-TIME:20240115T130000Z
+        END:VALARM
+        END:VEVENT
+        END:VCALENDAR
+    "};
+
+    const ICS_PRIVATE: &str = indoc! {"
+        BEGIN:VCALENDAR
+        VERSION:2.0
+        BEGIN:VEVENT
+        DTSTART:20240115T100000
+        SUMMARY:Doctor
+        LOCATION:https://zoom.us/j/123
+        CLASS:PRIVATE
+        END:VEVENT
+        BEGIN:VEVENT
+        DTSTART:20240115T120000
+        SUMMARY:Interview
+        CLASS:CONFIDENTIAL
+        END:VEVENT
+        BEGIN:VEVENT
+        DTSTART:20240115T140000
+        SUMMARY:Team Sync
+        CLASS:PUBLIC
+        END:VEVENT
+        END:VCALENDAR
+    "};
+
+    const ICS_FILTERS: &str = indoc! {"
+        BEGIN:VCALENDAR
+        VERSION:2.0
+        BEGIN:VEVENT
+        DTSTART:20240115T100000
+        SUMMARY:Standup
+        ATTENDEE;PARTSTAT=ACCEPTED:mailto:me@example.com
+        END:VEVENT
+        BEGIN:VEVENT
+        DTSTART:20240115T110000
+        SUMMARY:Retro
+        STATUS:CANCELLED
+        END:VEVENT
+        BEGIN:VEVENT
+        DTSTART:20240115T110000
+        SUMMARY:All Hands
+        ORGANIZER;CN=\"Big Boss\":mailto:boss@example.com
+        ATTENDEE;CN=Me;PARTSTAT=declined:MAILTO:Me@Example.com
+        ATTENDEE;PARTSTAT=ACCEPTED:mailto:boss@example.com
+        END:VEVENT
+        BEGIN:VEVENT
+        DTSTART:20240115T120000
+        SUMMARY:Lunch
+        END:VEVENT
+        END:VCALENDAR
+    "};
+
+    fn fmt(event: &str, now: &str) -> String {
+        let event_dt = parse_datetime(event).unwrap();
+        let now_dt = parse_datetime(now).unwrap();
+        fmt_relative_time(event_dt, now_dt, true, Language::default(), NaiveTime::MIN)
+    }
+
+    #[test]
+    fn test_parse_timed_event() {
+        let events = parse_ics(ICS_TIMED_EVENT.as_bytes(), &LocalZone::default()).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].summary, "Team Standup");
+        assert_eq!(events[0].start.hour(), 10);
+        assert_eq!(events[0].start.minute(), 0);
+        assert_eq!(
+            events[0].location,
+            Some("https://zoom.us/j/123".to_string())
+        );
+        assert!(events[0].is_video_call());
+    }
+
+    #[test]
+    fn test_parse_all_day_event() {
+        let events = parse_ics(ICS_ALL_DAY_EVENT.as_bytes(), &LocalZone::default()).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].summary, "Company Holiday");
+        // All-day events should have 00:00 time
+        assert_eq!(events[0].start.hour(), 0);
+        assert_eq!(events[0].start.minute(), 0);
+    }
+
+    #[test]
+    fn test_parse_utc_event() {
+        // With offset 0, UTC time stays as-is (15:00 UTC -> 15:00)
+        let events = parse_ics(ICS_UTC_EVENT.as_bytes(), &LocalZone::default()).unwrap();
+        assert_eq!(events[0].start.hour(), 15);
+
+        // With EST offset (-300 min), UTC time is converted (15:00 UTC -> 10:00 EST)
+        let events = parse_ics(
+            ICS_UTC_EVENT.as_bytes(),
+            &LocalZone {
+                utc_offset_minutes: -300,
+                tzid: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(events[0].start.hour(), 10);
+    }
+
+    #[test]
+    fn test_parse_tzid_event() {
+        // Berlin is +0100 in winter and +0200 in summer; London (current offset 0) isn't in
+        // the feed, so the current offset applies to both
+        let events = parse_ics(
+            ICS_TZID.as_bytes(),
+            &LocalZone {
+                utc_offset_minutes: 0,
+                tzid: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(events[0].start, parse_datetime("2024-01-15 14:00").unwrap());
+        assert_eq!(events[0].end, parse_datetime("2024-01-15 15:00"));
+        assert_eq!(events[1].start, parse_datetime("2024-07-01 13:00").unwrap());
+
+        // The user's own zone keeps its wall-clock time
+        let local = LocalZone {
+            utc_offset_minutes: 60,
+            tzid: Some("Europe/Berlin".to_string()),
+        };
+        let events = parse_ics(ICS_TZID.as_bytes(), &local).unwrap();
+        assert_eq!(events[1].start, parse_datetime("2024-07-01 15:00").unwrap());
+    }
+
+    #[test]
+    fn test_parse_midnight_all_day() {
+        // Converted to EST, each would start the evening before
+        let local = LocalZone {
+            utc_offset_minutes: -300,
+            tzid: None,
+        };
+        let events = parse_ics(ICS_MIDNIGHT_ALL_DAY.as_bytes(), &local).unwrap();
+        assert!(events[0].is_all_day);
+        assert_eq!(events[0].start, parse_datetime("2024-01-15 00:00").unwrap());
+        assert_eq!(events[0].end, parse_datetime("2024-01-16 00:00"));
+        assert!(events[1].is_all_day);
+        assert_eq!(events[1].start, parse_datetime("2024-01-17 00:00").unwrap());
+        assert_eq!(events[1].end, parse_datetime("2024-01-19 00:00"));
+        // An hour from midnight is a timed event
+        assert!(!events[2].is_all_day);
+        assert_eq!(events[2].start, parse_datetime("2024-01-19 19:00").unwrap());
+    }
+
+    #[test]
+    fn test_parse_web_page() {
+        let login = "<!DOCTYPE html>\n<html><body>Sign in</body></html>";
+        let result = parse_ics(login.as_bytes(), &LocalZone::default());
+        assert_eq!(
+            result.err().as_deref(),
+            Some("Server returned a web page — auth expired?")
+        );
+        assert!(is_web_page("\n  <HTML>"));
+        assert!(!is_web_page(
+            "\u{feff}BEGIN:VCALENDAR\n<html> in a description"
+        ));
+    }
+
+    #[test]
+    fn test_parse_alarms() {
+        let events = parse_ics(ICS_ALARMS.as_bytes(), &LocalZone::default()).unwrap();
+        assert_eq!(events[0].alarms, vec![10, 1440]);
+        assert!(events[1].alarms.is_empty());
+
+        assert_eq!(parse_duration_minutes("-PT15M"), Some(-15));
+        assert_eq!(parse_duration_minutes("P1DT2H30M"), Some(1590));
+        assert_eq!(parse_duration_minutes("-P1W"), Some(-10080));
+        assert_eq!(parse_duration_minutes("PT0S"), Some(0));
+        assert_eq!(parse_duration_minutes("-PT"), None);
+        assert_eq!(parse_duration_minutes("15M"), None);
+    }
+
+    #[test]
+    fn test_fmt_time_compact() {
+        assert_eq!(fmt_time_compact(9, 0, true), "9a");
+        assert_eq!(fmt_time_compact(14, 30, true), "2:30p");
+        assert_eq!(fmt_time_compact(0, 15, true), "12:15a");
+        assert_eq!(fmt_time_compact(14, 30, false), "14:30");
+    }
+
+    #[test]
+    fn test_next_event() {
+        let dt = |s| parse_datetime(s).unwrap();
+        let events = vec![
+            Event {
+                summary: "Holiday".to_string(),
+                start: dt("2024-01-15 00:00"),
+                is_all_day: true,
+                ..Default::default()
+            },
+            Event {
+                summary: "Standup".to_string(),
+                start: dt("2024-01-15 10:00"),
+                end: Some(dt("2024-01-15 10:15")),
+                location: Some("https://meet.google.com/abc".to_string()),
+                ..Default::default()
+            },
+            Event {
+                summary: "Lunch".to_string(),
+                start: dt("2024-01-15 12:00"),
+                ..Default::default()
+            },
+        ];
+        let now = dt("2024-01-15 09:50");
+        let next = next_event(&events, now).unwrap();
+        assert_eq!(
+            fmt_next_event(
+                next,
+                now,
+                true,
+                Language::default(),
+                NaiveTime::MIN,
+                &Icons::default()
+            ),
+            "📹 Standup in 10 min"
+        );
+
+        let now = dt("2024-01-15 10:05");
+        let next = next_event(&events, now).unwrap();
+        assert_eq!(
+            fmt_next_event(
+                next,
+                now,
+                true,
+                Language::default(),
+                NaiveTime::MIN,
+                &Icons::default()
+            ),
+            "📹 Standup · 10 min left"
+        );
+        assert_eq!(current_event(&events, now).unwrap().summary, "Standup");
+
+        let widget: Vec<String> = upcoming_events(&events, now)
+            .map(|e| {
+                fmt_widget_line(
+                    e,
+                    now,
+                    true,
+                    Language::default(),
+                    NaiveTime::MIN,
+                    &Icons::default(),
+                )
+            })
+            .collect();
+        assert_eq!(widget, ["now 📹 Standup", "12p • Lunch"]);
+        let later = Event {
+            start: dt("2024-01-16 09:00"),
+            ..events[2].clone()
+        };
+        assert_eq!(
+            fmt_widget_line(
+                &later,
+                now,
+                false,
+                Language::default(),
+                NaiveTime::MIN,
+                &Icons::default()
+            ),
+            "tmrw 09:00 • Lunch"
+        );
+
+        let now = dt("2024-01-15 10:30");
+        assert_eq!(next_event(&events, now).unwrap().summary, "Lunch");
+        assert!(current_event(&events, now).is_none());
+        assert!(next_event(&events, dt("2024-01-15 12:30")).is_none());
+
+        assert_eq!(status_glyph(&events, dt("2024-01-15 09:45")), "🟢");
+        assert_eq!(status_glyph(&events, dt("2024-01-15 09:50")), "🟡");
+        assert_eq!(status_glyph(&events, dt("2024-01-15 10:05")), "🔴");
+    }
+
+    #[test]
+    fn test_month_weeks() {
+        let date = |s: &str| parse_datetime(&format!("{} 00:00", s)).unwrap().date();
+        // January 2024 starts on a Monday and spans five weeks
+        let weeks = month_weeks(date("2024-01-15"));
+        assert_eq!(weeks.len(), 5);
+        assert_eq!(weeks[0][0], Some(date("2024-01-01")));
+        assert_eq!(weeks[4][2], Some(date("2024-01-31")));
+        assert_eq!(weeks[4][3], None);
+
+        // September 2024 starts on a Sunday and ends on a Monday: six weeks
+        let weeks = month_weeks(date("2024-09-30"));
+        assert_eq!(weeks.len(), 6);
+        assert_eq!(weeks[0][..6], [None; 6]);
+        assert_eq!(weeks[0][6], Some(date("2024-09-01")));
+        assert_eq!(weeks[5][0], Some(date("2024-09-30")));
+
+        // February 2021 fits exactly in four weeks
+        assert_eq!(month_weeks(date("2021-02-01")).len(), 4);
+    }
+
+    #[test]
+    fn test_fmt_date_context() {
+        let date = |s: &str| parse_datetime(&format!("{} 00:00", s)).unwrap().date();
+        assert_eq!(
+            fmt_date_context(date("2024-01-15")),
+            "monday, january 15, 2024 · day 15 · week 3 · 16 days left in january"
+        );
+        // ISO weeks: Dec 30, 2024 is in week 1 of 2025
+        assert_eq!(
+            fmt_date_context(date("2024-12-30")),
+            "monday, december 30, 2024 · day 365 · week 1 · 1 day left in december"
+        );
+        assert!(fmt_date_context(date("2024-02-29")).ends_with("last day of february"));
+    }
+
+    #[test]
+    fn test_day_load() {
+        let dt = |s: &str| parse_datetime(s).unwrap();
+        let meeting = |start: &str, end: &str| Event {
+            start: dt(start),
+            end: Some(dt(end)),
+            ..Default::default()
+        };
+        let day = dt("2024-01-15 00:00").date();
+        assert_eq!(day_load(&[], day), DayLoad::Free);
+
+        let events = vec![meeting("2024-01-15 09:00", "2024-01-15 10:00")];
+        assert_eq!(day_load(&events, day), DayLoad::Light);
+
+        // Overlapping meetings count once: 9:00-12:00 is 3 hours
+        let events = vec![
+            meeting("2024-01-15 09:00", "2024-01-15 11:00"),
+            meeting("2024-01-15 10:00", "2024-01-15 12:00"),
+        ];
+        assert_eq!(day_load(&events, day), DayLoad::Busy);
+
+        // Overnight events only count their part of the day; free and all-day events don't count
+        let events = vec![
+            meeting("2024-01-14 20:00", "2024-01-15 05:00"),
+            Event {
+                busy_status: BusyStatus::Free,
+                ..meeting("2024-01-15 12:00", "2024-01-15 14:00")
+            },
+            Event {
+                is_all_day: true,
+                ..meeting("2024-01-15 00:00", "2024-01-16 00:00")
+            },
+        ];
+        assert_eq!(day_load(&events, day), DayLoad::Packed);
+        assert_eq!(day_load(&events, day.succ_opt().unwrap()), DayLoad::Free);
+    }
+
+    #[test]
+    fn test_day_booking() {
+        let dt = |s: &str| parse_datetime(s).unwrap();
+        let meeting = |start: &str, end: &str| Event {
+            start: dt(start),
+            end: Some(dt(end)),
+            ..Default::default()
+        };
+        let hours = WorkingHours::default();
+        // 8:00-10:00 only counts from 9:00; 16:30-18:00 only until 17:00
+        let events = vec![
+            meeting("2024-01-15 08:00", "2024-01-15 10:00"),
+            meeting("2024-01-15 13:00", "2024-01-15 15:00"),
+            meeting("2024-01-15 16:30", "2024-01-15 18:00"),
+        ];
+        let booking = day_booking(&events, dt("2024-01-15 14:00"), &hours).unwrap();
+        assert_eq!(
+            booking,
+            DayBooking {
+                booked: 210,
+                done: 120,
+                window: 480
+            }
+        );
+        assert_eq!(fmt_booking(&booking), "44% booked · 1 hr 30 min to go");
+
+        let booking = day_booking(&events, dt("2024-01-15 18:00"), &hours).unwrap();
+        assert_eq!(fmt_booking(&booking), "44% booked · all done");
+        let booking = day_booking(&[], dt("2024-01-15 18:00"), &hours).unwrap();
+        assert_eq!(fmt_booking(&booking), "0% booked");
+        // Saturday
+        assert_eq!(day_booking(&events, dt("2024-01-20 10:00"), &hours), None);
+    }
+
+    #[test]
+    fn test_day_progress() {
+        let dt = |s: &str| parse_datetime(s).unwrap();
+        let meeting = |start: &str, end: &str| Event {
+            start: dt(start),
+            end: Some(dt(end)),
+            ..Default::default()
+        };
+        let hours = WorkingHours::default();
+        // An hour a cell, 9:00-17:00
+        let events = vec![
+            meeting("2024-01-15 09:30", "2024-01-15 10:00"),
+            meeting("2024-01-15 13:00", "2024-01-15 15:00"),
+        ];
+        let progress = day_progress(&events, dt("2024-01-15 11:15"), &hours, 8).unwrap();
+        let busy: String = progress
+            .busy
+            .iter()
+            .map(|&b| if b { '#' } else { '-' })
+            .collect();
+        assert_eq!(busy, "#---##--");
+        assert_eq!(progress.done, 2);
+
+        let day = |now| day_progress(&events, dt(now), &hours, 8).unwrap().done;
+        assert_eq!(day("2024-01-15 07:00"), 0);
+        assert_eq!(day("2024-01-15 17:00"), 8);
+        // Saturday
+        assert_eq!(
+            day_progress(&events, dt("2024-01-20 10:00"), &hours, 8),
+            None
+        );
+    }
+
+    #[test]
+    fn test_conflicts_and_free_gaps() {
+        let dt = |s: &str| parse_datetime(s).unwrap();
+        let meeting = |start: &str, end: &str| Event {
+            start: dt(start),
+            end: Some(dt(end)),
+            ..Default::default()
+        };
+        let events = vec![
+            meeting("2024-01-15 10:00", "2024-01-15 11:00"),
+            meeting("2024-01-15 10:30", "2024-01-15 11:30"),
+            meeting("2024-01-15 11:30", "2024-01-15 12:00"), // back-to-back isn't a conflict
+            Event {
+                busy_status: BusyStatus::Free,
+                ..meeting("2024-01-15 10:00", "2024-01-15 12:00")
+            },
+            meeting("2024-01-15 14:00", "2024-01-15 14:05"),
+            meeting("2024-01-15 14:15", "2024-01-15 17:00"),
+        ];
+        let refs: Vec<&Event> = events.iter().collect();
+        assert_eq!(
+            find_conflicts(&refs),
+            vec![true, true, false, false, false, false]
+        );
+
+        let now = dt("2024-01-15 09:00");
+        let gaps = free_gaps(&events, now);
+        // 14:05-14:15 is too short to list
+        assert_eq!(fmt_free_gaps(&gaps, now, true), "free now–10a, 12p–2p, 5p+");
+        let now = dt("2024-01-15 10:45");
+        let gaps = free_gaps(&events, now);
+        assert_eq!(fmt_free_gaps(&gaps, now, false), "free 12:00–14:00, 17:00+");
+        let now = dt("2024-01-15 18:00");
+        assert_eq!(
+            fmt_free_gaps(&free_gaps(&events, now), now, true),
+            "free rest of day"
+        );
+        let now = dt("2024-01-15 23:50");
+        assert_eq!(
+            fmt_free_gaps(&free_gaps(&events, now), now, true),
+            "no free time left today"
+        );
+
+        // Focus timers stop 5 minutes before the next busy block (not the free event)
+        let timer = |now: &str| focus_timer_end(&events, dt(now), 5);
+        assert_eq!(timer("2024-01-15 09:00"), Ok(dt("2024-01-15 09:55")));
+        assert_eq!(timer("2024-01-15 12:10"), Ok(dt("2024-01-15 13:55")));
+        assert_eq!(timer("2024-01-15 10:15"), Err("In a meeting".to_string()));
+        assert!(timer("2024-01-15 09:56").is_err());
+        assert!(timer("2024-01-15 17:30").is_err());
+    }
+
+    #[test]
+    fn test_fmt_availability() {
+        let dt = |s: &str| parse_datetime(s).unwrap();
+        let meeting = |summary: &str, start: &str, end: &str| Event {
+            summary: summary.to_string(),
+            start: dt(start),
+            end: Some(dt(end)),
+            ..Default::default()
+        };
+        let events = vec![
+            meeting("Standup", "2024-01-15 09:00", "2024-01-15 09:30"),
+            meeting("1:1", "2024-01-15 09:30", "2024-01-15 10:00"),
+            meeting("Design review", "2024-01-15 13:00", "2024-01-15 14:30"),
+            meeting("Offsite", "2024-01-16 23:00", "2024-01-17 01:00"),
+        ];
+        let now = dt("2024-01-15 08:00");
+        assert_eq!(
+            fmt_availability(&events, now, 3, true),
+            "mon jan 15: busy 9a–10a, 1p–2:30p, free otherwise\n\
+             tue jan 16: busy 11p–12a, free otherwise\n\
+             wed jan 17: busy 12a–1a, free otherwise"
+        );
+        // Today only counts from now on
+        let now = dt("2024-01-15 13:30");
+        assert_eq!(
+            fmt_availability(&events, now, 1, false),
+            "mon jan 15: busy 13:30–14:30, free otherwise"
+        );
+        assert_eq!(fmt_availability(&[], now, 1, false), "mon jan 15: free");
+    }
+
+    #[test]
+    fn test_propose_times() {
+        let dt = |s: &str| parse_datetime(s).unwrap();
+        let meeting = |start: &str, end: &str| Event {
+            summary: "Meeting".to_string(),
+            start: dt(start),
+            end: Some(dt(end)),
+            ..Default::default()
+        };
+        let events = [
+            meeting("2024-01-15 09:00", "2024-01-15 10:10"),
+            meeting("2024-01-15 10:30", "2024-01-15 16:30"),
+            // Overlays (a teammate's busy time) block slots too
+            Event {
+                is_overlay: true,
+                ..meeting("2024-01-16 09:00", "2024-01-16 12:00")
+            },
+            // Out all day Wednesday
+            Event {
+                summary: "OOO".to_string(),
+                busy_status: BusyStatus::OutOfOffice,
+                is_all_day: true,
+                ..meeting("2024-01-17 00:00", "2024-01-18 00:00")
+            },
+        ];
+        let events: Vec<&Event> = events.iter().collect();
+        let hours = WorkingHours::default();
+        let now = dt("2024-01-15 08:00"); // Monday
+        let until = dt("2024-01-22 00:00");
+
+        // 10:10 rounds up to 10:15, leaving 15 minutes before 10:30
+        let slots = propose_times(&events, now, 15, &hours, until, 4);
+        assert_eq!(
+            slots,
+            [
+                dt("2024-01-15 10:15"),
+                dt("2024-01-15 16:30"),
+                dt("2024-01-16 12:00"),
+                dt("2024-01-18 09:00"),
+            ]
+        );
+        let slots = propose_times(&events, now, 60, &hours, until, 3);
+        assert_eq!(
+            fmt_proposals(&slots, 60, now.date(), true, Language::default()),
+            "tomorrow 12p–1p\nthursday, jan 18 9a–10a\nfriday, jan 19 9a–10a"
+        );
+        // The weekend is skipped, and nothing fits a 9-hour day
+        assert_eq!(
+            propose_times(&events, dt("2024-01-19 17:00"), 30, &hours, until, 3),
+            []
+        );
+        assert_eq!(
+            fmt_proposals(&[], 540, now.date(), true, Language::default()),
+            "No free 9 hr slots in working hours"
+        );
+    }
+
+    #[test]
+    fn test_travel_times() {
+        let travel = TravelTimes {
+            default_minutes: Some(20),
+            overrides: TravelTimes::parse_overrides("HQ=10; airport = 90, bad, =5, gym=x"),
+        };
+        assert_eq!(
+            travel.overrides,
+            [("hq".to_string(), 10), ("airport".to_string(), 90)]
+        );
+        let at = |location: &str| Event {
+            start: parse_datetime("2024-01-15 14:00").unwrap(),
+            location: Some(location.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(travel.minutes_for(&at("Cafe Luna, 5th Ave")), Some(20));
+        assert_eq!(travel.minutes_for(&at("SFO Airport")), Some(90));
+        assert_eq!(
+            travel.leave_by(&at("HQ, room 4")),
+            parse_datetime("2024-01-15 13:50")
+        );
+        // Video calls and bare links need no travel
+        assert_eq!(travel.minutes_for(&at("https://zoom.us/j/123")), None);
+        assert_eq!(travel.minutes_for(&at("https://example.com/room")), None);
+        assert_eq!(travel.minutes_for(&Event::default()), None);
+        assert_eq!(TravelTimes::default().minutes_for(&at("Cafe Luna")), None);
+    }
+
+    #[test]
+    fn test_parse_working_hours() {
+        let hours = WorkingHours::parse("9:30-18").unwrap();
+        assert_eq!(hours.start, NaiveTime::from_hms_opt(9, 30, 0).unwrap());
+        assert_eq!(hours.end, NaiveTime::from_hms_opt(18, 0, 0).unwrap());
+        assert_eq!(WorkingHours::parse("9-17"), Some(WorkingHours::default()));
+        assert_eq!(WorkingHours::parse("17-9"), None);
+        assert_eq!(WorkingHours::parse("nine to five"), None);
+    }
+
+    #[test]
+    fn test_off_clock_events() {
+        let event = |summary: &str, start: &str, is_all_day| Event {
+            summary: summary.to_string(),
+            start: parse_datetime(start).unwrap(),
+            is_all_day,
+            ..Default::default()
+        };
+        let events = [
+            event("Dinner", "2024-01-15 19:00", false),
+            event("Holiday", "2024-01-16 00:00", true),
+            event("Standup", "2024-01-16 10:00", false),
+            event("Sync", "2024-01-16 11:00", false),
+        ];
+        let refs: Vec<&Event> = events.iter().collect();
+        let hours = WorkingHours::default();
+        let at = |s| parse_datetime(s).unwrap();
+        assert!(off_clock_events(&refs, at("2024-01-15 16:59"), &hours).is_none());
+        let left = off_clock_events(&refs, at("2024-01-15 17:00"), &hours).unwrap();
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].summary, "Standup");
+        assert!(off_clock_events(&refs[..2], at("2024-01-15 18:00"), &hours)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_fmt_time_left() {
+        let now = parse_datetime("2024-01-15 10:00").unwrap();
+        let left = |end| fmt_time_left(parse_datetime(end).unwrap(), now);
+        assert_eq!(left("2024-01-15 10:18"), "18 min left");
+        assert_eq!(left("2024-01-15 11:00"), "1 hr left");
+        assert_eq!(left("2024-01-15 11:05"), "1 hr 5 min left");
+        assert_eq!(left("2024-01-15 10:00"), "<1 min left");
+    }
+
+    #[test]
+    fn test_fmt_ended_ago() {
+        let now = parse_datetime("2024-01-15 10:00").unwrap();
+        let ago = |end| fmt_ended_ago(parse_datetime(end).unwrap(), now);
+        assert_eq!(ago("2024-01-15 09:55"), "ended 5 min ago");
+        assert_eq!(ago("2024-01-15 08:30"), "ended 1 hr 30 min ago");
+        assert_eq!(ago("2024-01-15 10:00"), "ended just now");
+
+        let started = |start| fmt_started_ago(parse_datetime(start).unwrap(), now);
+        assert_eq!(started("2024-01-15 09:55"), "started 5 min ago");
+        assert_eq!(started("2024-01-15 09:59:30"), "started 1 min ago");
+    }
+
+    #[test]
+    fn test_describe_event() {
+        let now = parse_datetime("2024-01-15 10:00").unwrap();
+        let at = |s| parse_datetime(s).unwrap();
+        let describe = |event: &Event| describe_event(event, now, true, NaiveTime::MIN);
+        let standup = Event {
+            summary: "Standup".to_string(),
+            start: at("2024-01-15 10:10"),
+            end: Some(at("2024-01-15 10:25")),
+            location: Some("https://zoom.us/j/123".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            describe(&standup),
+            "Standup, starts in 10 minutes at 10:10 am, video call"
+        );
+        let lunch = Event {
+            summary: "Lunch".to_string(),
+            start: at("2024-01-15 09:00"),
+            end: Some(at("2024-01-15 11:30")),
+            location: Some("Cafe".to_string()),
+            busy_status: BusyStatus::Tentative,
+            ..Default::default()
+        };
+        assert_eq!(
+            describe(&lunch),
+            "Lunch, in progress, ends in 1 hour 30 minutes at 11:30 am, at Cafe, tentative"
+        );
+        let planning = Event {
+            summary: "Planning".to_string(),
+            start: at("2024-01-17 09:00"),
+            end: Some(at("2024-01-17 10:00")),
+            ..Default::default()
+        };
+        assert_eq!(
+            describe(&planning),
+            "Planning, starts on Wednesday, January 17 at 9:00 am"
+        );
+        let offsite = Event {
+            summary: "Offsite".to_string(),
+            start: at("2024-01-16 00:00"),
+            is_all_day: true,
+            ..Default::default()
+        };
+        assert_eq!(describe(&offsite), "Offsite, all day tomorrow");
+        assert_eq!(fmt_spoken_duration(1), "1 minute");
+        assert_eq!(fmt_spoken_duration(120), "2 hours");
+        assert_eq!(fmt_spoken_duration(61), "1 hour 1 minute");
+    }
+
+    #[test]
+    fn test_fmt_countdown() {
+        let now = parse_datetime("2024-01-15 10:00").unwrap();
+        let countdown = |secs| fmt_countdown(now + chrono::Duration::seconds(secs), now);
+        assert_eq!(countdown(452), "7:32");
+        assert_eq!(countdown(5), "0:05");
+        assert_eq!(countdown(3600 + 7 * 60 + 32), "1:07:32");
+        assert_eq!(countdown(-10), "0:00");
+        let clock = parse_datetime("2024-01-15 09:52:28").unwrap();
+        assert_eq!(fmt_countdown(now, clock), "7:32");
+        assert_eq!(fmt_duration(45), "45 min");
+        assert_eq!(fmt_duration(90), "1 hr 30 min");
+    }
+
+    #[test]
+    fn test_parse_multiple_events() {
+        let events = parse_ics(ICS_MULTIPLE_EVENTS.as_bytes(), &LocalZone::default()).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].summary, "First Event");
+        assert_eq!(events[1].summary, "Second Event");
+    }
+
+    #[test]
+    fn test_parse_concatenated_calendars() {
+        // Local sources print each matched file in turn
+        let data = format!("{}\n{}", ICS_MULTIPLE_EVENTS, ICS_PRIVATE);
+        let events = parse_ics(data.as_bytes(), &LocalZone::default()).unwrap();
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[0].summary, "First Event");
+        assert_eq!(events[4].summary, "Team Sync");
+        assert_eq!(split_calendars(ICS_MULTIPLE_EVENTS).len(), 1);
+
+        // A broken file among them is skipped; on its own it's still an error
+        let broken = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nSUMMARY:Broken\n";
+        let data = format!("{}\n{}{}", ICS_MULTIPLE_EVENTS, broken, ICS_PRIVATE);
+        let events = parse_ics(data.as_bytes(), &LocalZone::default()).unwrap();
+        assert_eq!(events.len(), 5);
+        assert!(parse_ics(broken.as_bytes(), &LocalZone::default()).is_err());
+    }
+
+    #[test]
+    fn test_parse_color_hints() {
+        let ics = indoc! {"
+            BEGIN:VCALENDAR
+            VERSION:2.0
+            X-APPLE-CALENDAR-COLOR:#FF2968FF
+            BEGIN:VEVENT
+            SUMMARY:Standup
+            DTSTART:20240115T100000
+            END:VEVENT
+            BEGIN:VEVENT
+            SUMMARY:Retro
+            COLOR:turquoise
+            DTSTART:20240115T140000
+            END:VEVENT
+            END:VCALENDAR
+        "};
+        let events = parse_ics(ics.as_bytes(), &LocalZone::default()).unwrap();
+        assert_eq!(events[0].color.as_deref(), Some("#FF2968FF"));
+        assert_eq!(events[1].color.as_deref(), Some("turquoise"));
+
+        let events = parse_ics(ICS_PRIVATE.as_bytes(), &LocalZone::default()).unwrap();
+        assert!(events[0].color.is_none());
+    }
+
+    #[test]
+    fn test_sanitize_text() {
+        let clean = |s| sanitize_text(s, false);
+        assert_eq!(clean("\x1b[31mRed\x1b[0m alert"), "Red alert");
+        assert_eq!(clean("\u{9b}2JWiped"), "Wiped");
+        assert_eq!(clean("\x1b]0;pwned\x07Title"), "Title");
+        assert_eq!(clean("\x1b]52;c;ZXZpbA==\x1b\\Copy"), "Copy");
+        assert_eq!(clean("Bell\x07 and\tback\x08space"), "Bell and backspace");
+        assert_eq!(clean("Two\nlines"), "Two lines");
+        assert_eq!(sanitize_text("Two\r\nlines", true), "Two\nlines");
+        assert_eq!(clean("Café ☕ 1:1"), "Café ☕ 1:1");
+
+        let mut event = Event {
+            summary: "\x1b[2J\x1b[HStandup".to_string(),
+            location: Some("Room\x1b[5m 4".to_string()),
+            organizer: Some(Attendee {
+                email: "dana@example.com".to_string(),
+                partstat: None,
+                name: Some("Dana\x1b]8;;https://evil.example\x07".to_string()),
+            }),
+            ..Default::default()
+        };
+        event.sanitize();
+        assert_eq!(event.summary, "Standup");
+        assert_eq!(event.location.as_deref(), Some("Room 4"));
+        assert_eq!(event.organizer.unwrap().name.as_deref(), Some("Dana"));
+    }
+
+    #[test]
+    fn test_parse_modified() {
+        let ics = indoc! {"
+            BEGIN:VCALENDAR
+            VERSION:2.0
+            BEGIN:VEVENT
+            SUMMARY:Standup
+            DTSTART:20240115T100000
+            DTSTAMP:20240110T080000Z
+            LAST-MODIFIED:20240114T120000Z
+            END:VEVENT
+            BEGIN:VEVENT
+            SUMMARY:Retro
+            DTSTART:20240115T140000
+            DTSTAMP:20240114T230000Z
+            END:VEVENT
+            END:VCALENDAR
+        "};
+        let events = parse_ics(ics.as_bytes(), &LocalZone::default()).unwrap();
+        let dt = |s: &str| parse_datetime(s).unwrap();
+        assert_eq!(events[0].modified, Some(dt("2024-01-14 12:00")));
+        assert_eq!(events[1].modified, Some(dt("2024-01-14 23:00")));
+        assert!(events[0].is_new(dt("2024-01-15 09:00")));
+        assert!(!events[0].is_new(dt("2024-01-15 12:00")));
+
+        // Every event stamped with the export time: the stamp says nothing
+        let exported = ics.replace("20240110T080000Z", "20240114T230000Z");
+        let events = parse_ics(exported.as_bytes(), &LocalZone::default()).unwrap();
+        assert_eq!(events[0].modified, Some(dt("2024-01-14 12:00")));
+        assert_eq!(events[1].modified, None);
+    }
+
+    #[test]
+    fn test_parse_busy_status() {
+        let events = parse_ics(ICS_BUSY_STATUS.as_bytes(), &LocalZone::default()).unwrap();
+        let statuses: Vec<BusyStatus> = events.iter().map(|e| e.busy_status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                BusyStatus::Free,
+                BusyStatus::Tentative, // busy status wins over TRANSP
+                BusyStatus::OutOfOffice,
+                BusyStatus::Free,
+                BusyStatus::Busy,
+            ]
+        );
+        assert!(!events[0].is_busy());
+        assert!(events[1].is_busy());
+
+        // Free blocks are dropped from overlays
+        let overlay = anonymize(events);
+        assert_eq!(overlay.len(), 3);
+    }
+
+    #[test]
+    fn test_out_of_office() {
+        let ooo = |summary: &str, busy_status| Event {
+            summary: summary.into(),
+            busy_status,
+            ..Default::default()
+        };
+        assert!(ooo("Meeting", BusyStatus::OutOfOffice).is_out_of_office());
+        assert!(ooo("OOO - dentist", BusyStatus::Busy).is_out_of_office());
+        assert!(ooo("Out of Office", BusyStatus::Free).is_out_of_office());
+        assert!(ooo("Jane's PTO", BusyStatus::Free).is_out_of_office());
+        assert!(!ooo("Zoom call", BusyStatus::Busy).is_out_of_office());
+        assert!(!ooo("Office hours", BusyStatus::Busy).is_out_of_office());
+    }
+
+    #[test]
+    fn test_ooo_until() {
+        let now = parse_datetime("2024-01-15 10:00").unwrap(); // monday
+        let events = vec![
+            Event {
+                summary: "Vacation".into(),
+                start: parse_datetime("2024-01-15 00:00").unwrap(),
+                end: parse_datetime("2024-01-18 00:00"),
+                is_all_day: true,
+                ..Default::default()
+            },
+            Event {
+                summary: "Standup".into(),
+                start: parse_datetime("2024-01-15 09:00").unwrap(),
+                end: parse_datetime("2024-01-20 00:00"),
+                ..Default::default()
+            },
+        ];
+        let until = ooo_until(&events, now).unwrap();
+        assert_eq!(fmt_ooo_banner(until, now, true), "OOO until thu");
+        assert_eq!(
+            ooo_until(&events, parse_datetime("2024-01-18 10:00").unwrap()),
+            None
+        );
+
+        let afternoon = parse_datetime("2024-01-15 17:00").unwrap();
+        assert_eq!(fmt_ooo_banner(afternoon, now, true), "OOO until 5:00 pm");
+        let later = parse_datetime("2024-01-29 00:00").unwrap();
+        assert_eq!(fmt_ooo_banner(later, now, true), "OOO until jan 29");
+    }
+
+    #[test]
+    fn test_flight_detection() {
+        let event = |summary: &str, description: Option<&str>| Event {
+            summary: summary.into(),
+            description: description.map(|d| d.into()),
+            ..Default::default()
+        };
+
+        let gmail = event("Flight to London (BA 117)", Some("LHR to JFK"));
+        assert_eq!(
+            gmail.flight(),
+            Some(Flight {
+                number: "BA117".into(),
+                route: Some(("LHR".into(), "JFK".into())),
+            })
+        );
+
+        let compact = event("AA123 SFO-JFK", None);
+        assert_eq!(
+            compact.flight(),
+            Some(Flight {
+                number: "AA123".into(),
+                route: Some(("SFO".into(), "JFK".into())),
+            })
+        );
+
+        assert_eq!(event("B6 42 flight", None).flight().unwrap().number, "B642");
+        // Spaced codes need the "flight" keyword to avoid false positives
+        assert_eq!(event("Q3 2024 planning", None).flight(), None);
+        assert_eq!(event("Team Standup", None).flight(), None);
+        // So do unspaced ones, or a route
+        for summary in ["FY2025 kickoff", "CS101 lecture", "PR123 review"] {
+            assert_eq!(event(summary, None).flight(), None, "{}", summary);
+        }
+        assert_eq!(
+            event("UA90", Some("EWR to TLV")).flight().unwrap().number,
+            "UA90"
+        );
+        assert_eq!(event("Flight UA90", None).flight().unwrap().number, "UA90");
+    }
+
+    #[test]
+    fn test_fmt_flight() {
+        let flight = Flight {
+            number: "AA123".into(),
+            route: Some(("SFO".into(), "JFK".into())),
+        };
+        let arrival = parse_datetime("2024-01-15 17:30");
+        assert_eq!(
+            fmt_flight(&flight, arrival, true),
+            "AA123 SFO→JFK · lands 5:30 pm"
+        );
+        let no_route = Flight {
+            number: "BA117".into(),
+            route: None,
+        };
+        assert_eq!(fmt_flight(&no_route, None, false), "BA117");
     }
 
-    let is_tomorrow = event_dt.date() != now_dt.date();
-
-    match minutes {
-        0 => "now".to_string(),
-        1..=9 => format!("in {} min", minutes),
-        10..=55 => format!("in {} min", ((minutes + 2) / 5) * 5),
-        56..=299 => {
-            let time = fmt_time(event_dt.hour(), event_dt.minute(), use_12h);
-            let whole_hours = minutes / 60;
-            let remainder = minutes % 60;
+    #[test]
+    fn test_anonymize() {
+        let events = parse_ics(ICS_TIMED_EVENT.as_bytes(), &LocalZone::default()).unwrap();
+        let events = anonymize(events);
+        assert_eq!(events[0].summary, "busy");
+        assert_eq!(events[0].location, None);
+        assert_eq!(events[0].start.hour(), 10);
+        assert!(events[0].is_overlay);
+        assert!(!events[0].is_video_call());
+    }
 
-            // Show .5 if within 10 min of half hour (20-40 min past)
-            let relative = if (20..=40).contains(&remainder) {
-                format!("{}.5 hrs", whole_hours)
-            } else {
-                // Round to nearest hour (>40 min rounds up)
-                let hours = if remainder > 40 {
-                    whole_hours + 1
-                } else {
-                    whole_hours
-                };
-                if hours == 1 {
-                    "1 hr".to_string()
-                } else {
-                    format!("{} hrs", hours)
-                }
-            };
+    #[test]
+    fn test_occasions() {
+        let ics = indoc! {"
+            BEGIN:VCALENDAR
+            VERSION:2.0
+            BEGIN:VEVENT
+            DTSTART;VALUE=DATE:20240120
+            SUMMARY:Sam's birthday
+            CATEGORIES:Personal,Birthday
+            END:VEVENT
+            BEGIN:VEVENT
+            DTSTART;VALUE=DATE:20240125
+            SUMMARY:Wedding anniversary
+            CATEGORIES:Family
+            CATEGORIES:ANNIVERSARY
+            END:VEVENT
+            BEGIN:VEVENT
+            DTSTART:20240115T100000
+            SUMMARY:Birthday planning
+            END:VEVENT
+            END:VCALENDAR
+        "};
+        let events = parse_ics(ics.as_bytes(), &LocalZone::default()).unwrap();
+        assert_eq!(events[0].categories, ["Personal", "Birthday"]);
+        assert!(events[0].is_occasion());
+        assert!(events[1].is_occasion());
+        assert!(!events[2].is_occasion());
 
-            format!("{} ({})", time, relative)
-        }
-        _ if is_tomorrow => {
-            let time = fmt_time(event_dt.hour(), event_dt.minute(), use_12h);
-            format!("tmrw {}", time)
-        }
-        _ => {
-            let time = fmt_time(event_dt.hour(), event_dt.minute(), use_12h);
-            format!("today {}", time)
-        }
+        let today = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let lang = Language::English;
+        assert_eq!(fmt_occasion_day(today, today, lang), "today");
+        assert_eq!(
+            fmt_occasion_day(today.succ_opt().unwrap(), today, lang),
+            "tomorrow"
+        );
+        let sat = NaiveDate::from_ymd_opt(2024, 1, 20).unwrap();
+        assert_eq!(fmt_occasion_day(sat, today, lang), "sat jan 20 · in 5 days");
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use indoc::indoc;
+    #[test]
+    fn test_hide_private() {
+        let events = parse_ics(ICS_PRIVATE.as_bytes(), &LocalZone::default()).unwrap();
+        assert!(events[0].is_private);
+        assert!(events[1].is_private);
+        assert!(!events[2].is_private);
 
-    const ICS_TIMED_EVENT: &str = indoc! {"
-        BEGIN:VCALENDAR
-        VERSION:2.0
-        BEGIN:VEVENT
-        DTSTART:20240115T100000
-        DTEND:20240115T110000
-        SUMMARY:Team Standup
-        LOCATION:https://zoom.us/j/123
-        END:VEVENT
-        END:VCALENDAR
-    "};
+        let events = hide_private(events);
+        assert_eq!(events[0].summary, "private");
+        assert_eq!(events[0].location, None);
+        assert_eq!(events[0].icon(&Icons::default()), "🔒");
+        assert_eq!(events[1].summary, "private");
+        assert_eq!(events[2].summary, "Team Sync");
+    }
 
-    const ICS_ALL_DAY_EVENT: &str = indoc! {"
-        BEGIN:VCALENDAR
-        VERSION:2.0
-        BEGIN:VEVENT
-        DTSTART;VALUE=DATE:20240115
-        DTEND;VALUE=DATE:20240116
-        SUMMARY:Company Holiday
-        END:VEVENT
-        END:VCALENDAR
-    "};
+    #[test]
+    fn test_event_details() {
+        let events = parse_ics(ICS_FILTERS.as_bytes(), &LocalZone::default()).unwrap();
+        let all_hands = &events[2];
+        let organizer = all_hands.organizer.as_ref().unwrap();
+        assert_eq!(organizer.display_name(), "Big Boss");
+        assert_eq!(all_hands.attendees[1].display_name(), "boss@example.com");
+        assert_eq!(
+            fmt_attendance(&all_hands.attendees).as_deref(),
+            Some("2 attendees · 1 accepted · 1 declined")
+        );
+        assert_eq!(fmt_attendance(&events[3].attendees), None);
 
-    const ICS_UTC_EVENT: &str = indoc! {"
-        BEGIN:VCALENDAR
-        VERSION:2.0
-        BEGIN:VEVENT
-        DTSTART:20240115T150000Z
-        DTEND:20240115T160000Z
-        SUMMARY:UTC Meeting
-        END:VEVENT
-        END:VCALENDAR
-    "};
+        let event = |start: &str, end: &str, is_all_day| Event {
+            start: parse_datetime(start).unwrap(),
+            end: parse_datetime(end),
+            is_all_day,
+            ..Default::default()
+        };
+        let range = |e: Event| fmt_event_range(&e, true);
+        assert_eq!(
+            range(event("2024-01-15 10:00", "2024-01-15 11:30", false)),
+            "10:00 am – 11:30 am"
+        );
+        assert_eq!(
+            range(event("2024-01-15 22:00", "2024-01-16 02:00", false)),
+            "jan 15 10:00 pm – jan 16 2:00 am"
+        );
+        assert_eq!(
+            range(event("2024-01-15 00:00", "2024-01-16 00:00", true)),
+            "jan 15 · all day"
+        );
+        assert_eq!(
+            range(event("2024-01-15 00:00", "2024-01-18 00:00", true)),
+            "jan 15 – jan 17"
+        );
+    }
 
-    const ICS_MULTIPLE_EVENTS: &str = indoc! {"
-        BEGIN:VCALENDAR
-        VERSION:2.0
-        BEGIN:VEVENT
-        DTSTART:20240115T100000
-        SUMMARY:First Event
-        END:VEVENT
-        BEGIN:VEVENT
-        DTSTART:20240115T140000
-        SUMMARY:Second Event
-        END:VEVENT
-        END:VCALENDAR
-    "};
+    #[test]
+    fn test_filter_events() {
+        let events = parse_ics(ICS_FILTERS.as_bytes(), &LocalZone::default()).unwrap();
+        assert!(events[1].is_cancelled);
+        assert_eq!(
+            events[2].attendees[0],
+            Attendee {
+                email: "me@example.com".to_string(),
+                partstat: Some("DECLINED".to_string()),
+                name: Some("Me".to_string()),
+            }
+        );
+        let summaries = |filter: &EventFilter| -> Vec<String> {
+            filter_events(events.clone(), filter)
+                .into_iter()
+                .map(|e| e.summary)
+                .collect()
+        };
 
-    fn fmt(event: &str, now: &str) -> String {
-        let event_dt = parse_datetime(event).unwrap();
-        let now_dt = parse_datetime(now).unwrap();
-        fmt_relative_time(event_dt, now_dt, true)
+        // Cancelled events are always dropped; declines need my_email
+        let filter = EventFilter::default();
+        assert_eq!(summaries(&filter), vec!["Standup", "All Hands", "Lunch"]);
+        let filter = EventFilter {
+            my_email: Some("ME@example.com".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(summaries(&filter), vec!["Standup", "Lunch"]);
+
+        let filter = EventFilter {
+            exclude: Regex::new("(?i)lunch").ok(),
+            ..Default::default()
+        };
+        assert_eq!(summaries(&filter), vec!["Standup", "All Hands"]);
+        let filter = EventFilter {
+            include: Regex::new("^(Standup|Lunch)$").ok(),
+            exclude: Regex::new("Lunch").ok(),
+            ..Default::default()
+        };
+        assert_eq!(summaries(&filter), vec!["Standup"]);
     }
 
     #[test]
-    fn test_parse_timed_event() {
-        let events = parse_ics(ICS_TIMED_EVENT.as_bytes(), 0).unwrap();
-        assert_eq!(events.len(), 1);
-        assert_eq!(events[0].summary, "Team Standup");
-        assert_eq!(events[0].start.hour(), 10);
-        assert_eq!(events[0].start.minute(), 0);
+    fn test_meeting_size() {
+        let person = |email: &str| Attendee {
+            email: email.to_string(),
+            partstat: None,
+            name: None,
+        };
+        let meeting = |summary: &str, guests: usize| Event {
+            summary: summary.to_string(),
+            organizer: Some(person("boss@example.com")),
+            attendees: (0..guests)
+                .map(|n| person(&format!("guest{}@example.com", n)))
+                .collect(),
+            ..Default::default()
+        };
+        assert_eq!(meeting("Focus", 0).meeting_size(10), None);
         assert_eq!(
-            events[0].location,
-            Some("https://zoom.us/j/123".to_string())
+            meeting("1:1", 1).meeting_size(10),
+            Some(MeetingSize::OneOnOne)
         );
-        assert!(events[0].is_video_call());
+        assert_eq!(
+            meeting("Sync", 4).meeting_size(10),
+            Some(MeetingSize::Small)
+        );
+        assert_eq!(
+            meeting("All Hands", 9).meeting_size(10),
+            Some(MeetingSize::Large)
+        );
+        // An organizer who is also listed as an attendee counts once
+        let mut listed = meeting("1:1", 1);
+        listed.attendees.push(person("BOSS@example.com"));
+        assert_eq!(listed.headcount(), 2);
+
+        assert_eq!(
+            MeetingSize::parse_list("1:1, large, huge"),
+            [MeetingSize::OneOnOne, MeetingSize::Large]
+        );
+
+        let events = vec![
+            meeting("1:1", 1),
+            meeting("Sync", 4),
+            meeting("All Hands", 20),
+        ];
+        let summaries = |filter: &EventFilter| -> Vec<String> {
+            filter_events(events.clone(), filter)
+                .into_iter()
+                .map(|e| e.summary)
+                .collect()
+        };
+        let filter = EventFilter {
+            hide_sizes: vec![MeetingSize::Large],
+            ..Default::default()
+        };
+        assert_eq!(summaries(&filter), vec!["1:1", "Sync"]);
+        // 1:1s survive patterns that would drop them
+        let filter = EventFilter {
+            include: Regex::new("^All").ok(),
+            keep_one_on_ones: true,
+            ..Default::default()
+        };
+        assert_eq!(summaries(&filter), vec!["1:1", "All Hands"]);
     }
 
     #[test]
-    fn test_parse_all_day_event() {
-        let events = parse_ics(ICS_ALL_DAY_EVENT.as_bytes(), 0).unwrap();
-        assert_eq!(events.len(), 1);
-        assert_eq!(events[0].summary, "Company Holiday");
-        // All-day events should have 00:00 time
-        assert_eq!(events[0].start.hour(), 0);
-        assert_eq!(events[0].start.minute(), 0);
+    fn test_filter_preset() {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 15)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+        let timed = |summary: &str, minutes: i64| Event {
+            summary: summary.to_string(),
+            start,
+            end: Some(start + chrono::Duration::minutes(minutes)),
+            ..Default::default()
+        };
+        let all_day = Event {
+            summary: "Offsite".to_string(),
+            start,
+            is_all_day: true,
+            ..Default::default()
+        };
+
+        let everything = FilterPreset::default();
+        assert!(everything.allows(&all_day, "default"));
+
+        let work = FilterPreset {
+            calendars: vec!["work".to_string()],
+            exclude: Regex::new("(?i)focus").ok(),
+            ..Default::default()
+        };
+        assert!(work.allows(&timed("Standup", 15), "work"));
+        assert!(!work.allows(&timed("Standup", 15), "personal"));
+        assert!(!work.allows(&timed("Focus time", 120), "work"));
+
+        let meetings = FilterPreset {
+            min_minutes: Some(15),
+            max_minutes: Some(60),
+            ..Default::default()
+        };
+        assert!(meetings.allows(&timed("Sync", 30), "default"));
+        assert!(meetings.allows(&timed("Sync", 60), "default"));
+        assert!(!meetings.allows(&timed("Ping", 5), "default"));
+        assert!(!meetings.allows(&timed("Workshop", 180), "default"));
+        assert!(!meetings.allows(&all_day, "default"));
     }
 
     #[test]
-    fn test_parse_utc_event() {
-        // With offset 0, UTC time stays as-is (15:00 UTC -> 15:00)
-        let events = parse_ics(ICS_UTC_EVENT.as_bytes(), 0).unwrap();
-        assert_eq!(events[0].start.hour(), 15);
+    fn test_organized() {
+        let by = |email: &str| Event {
+            summary: email.to_string(),
+            organizer: Some(Attendee {
+                email: email.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let events = vec![
+            by("me@example.com"),
+            by("boss@example.com"),
+            Event::default(),
+        ];
+        assert!(events[0].is_organized_by("ME@example.com"));
+        assert!(!events[2].is_organized_by("me@example.com"));
 
-        // With EST offset (-300 min), UTC time is converted (15:00 UTC -> 10:00 EST)
-        let events = parse_ics(ICS_UTC_EVENT.as_bytes(), -300).unwrap();
-        assert_eq!(events[0].start.hour(), 10);
+        let organized = |mine: bool| -> Vec<String> {
+            let filter = EventFilter {
+                my_email: Some("me@example.com".to_string()),
+                organized: Some(mine),
+                ..Default::default()
+            };
+            filter_events(events.clone(), &filter)
+                .into_iter()
+                .map(|e| e.summary)
+                .collect()
+        };
+        assert_eq!(organized(true), ["me@example.com"]);
+        assert_eq!(organized(false), ["boss@example.com", ""]);
     }
 
     #[test]
-    fn test_parse_multiple_events() {
-        let events = parse_ics(ICS_MULTIPLE_EVENTS.as_bytes(), 0).unwrap();
-        assert_eq!(events.len(), 2);
-        assert_eq!(events[0].summary, "First Event");
-        assert_eq!(events[1].summary, "Second Event");
+    fn test_expand_recurring() {
+        let events = parse_ics(ICS_RECURRING.as_bytes(), &LocalZone::default()).unwrap();
+        assert!(events[0].recurrence.is_some());
+
+        let from = parse_datetime("2024-01-15 10:05").unwrap();
+        let to = parse_datetime("2024-01-25 00:00").unwrap();
+        let mut expanded = expand_recurring(events, from, to);
+        expanded.sort_by_key(|e| e.start);
+
+        let got: Vec<String> = expanded
+            .iter()
+            .map(|e| format!("{} {}", e.start.format("%m-%d %H:%M"), e.summary))
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                "01-15 10:00 Standup", // in progress at `from`
+                "01-16 15:00 Review",
+                // 01-17 excluded by EXDATE
+                "01-18 15:00 Review",
+                "01-19 15:00 Review",
+                "01-22 11:00 Standup (moved)", // override replaces 01-22 10:00
+                "01-24 10:00 Standup",
+            ]
+        );
+        assert_eq!(expanded[0].end, parse_datetime("2024-01-15 10:15"));
+
+        // Every occurrence knows its series
+        assert_eq!(
+            expanded[0].fmt_series().as_deref(),
+            Some("weekly on mon/wed")
+        );
+        assert_eq!(expanded[1].fmt_series().as_deref(), Some("repeats"));
+        assert!(expanded[4].is_recurring()); // the moved override
+        assert_eq!(Event::default().fmt_series(), None);
+    }
+
+    #[test]
+    fn test_collapse_series() {
+        let events = parse_ics(ICS_RECURRING.as_bytes(), &LocalZone::default()).unwrap();
+        let from = parse_datetime("2024-01-15 10:05").unwrap();
+        let to = parse_datetime("2024-01-25 00:00").unwrap();
+        let mut expanded = expand_recurring(events, from, to);
+        expanded.push(Event {
+            summary: "Dentist".to_string(),
+            start: parse_datetime("2024-01-20 09:00").unwrap(),
+            ..Default::default()
+        });
+
+        let summaries = |now: &str| -> Vec<String> {
+            let now = parse_datetime(now).unwrap();
+            collapse_series(expanded.clone(), Some(now))
+                .iter()
+                .filter(|e| e.start >= now || e.is_in_progress(now))
+                .map(|e| format!("{} {}", e.start.format("%m-%d"), e.summary))
+                .collect()
+        };
+        assert_eq!(
+            summaries("2024-01-15 10:05"),
+            vec!["01-15 Standup", "01-16 Review", "01-20 Dentist"]
+        );
+        // Once an occurrence ends, the next one takes its place
+        assert_eq!(
+            summaries("2024-01-15 10:30"),
+            vec!["01-16 Review", "01-20 Dentist", "01-22 Standup (moved)"]
+        );
     }
 
     #[test]
@@ -351,6 +3708,7 @@ mod tests {
             end: None,
             location: Some("https://zoom.us/j/123".into()),
             is_all_day: false,
+            ..Default::default()
         };
         let meet = Event {
             summary: "Call".into(),
@@ -358,6 +3716,7 @@ mod tests {
             end: None,
             location: Some("https://meet.google.com/abc".into()),
             is_all_day: false,
+            ..Default::default()
         };
         let teams = Event {
             summary: "Call".into(),
@@ -365,6 +3724,7 @@ mod tests {
             end: None,
             location: Some("https://teams.microsoft.com/l/meetup".into()),
             is_all_day: false,
+            ..Default::default()
         };
         let office = Event {
             summary: "Meeting".into(),
@@ -372,6 +3732,7 @@ mod tests {
             end: None,
             location: Some("Conference Room A".into()),
             is_all_day: false,
+            ..Default::default()
         };
         let none = Event {
             summary: "Meeting".into(),
@@ -379,6 +3740,7 @@ mod tests {
             end: None,
             location: None,
             is_all_day: false,
+            ..Default::default()
         };
 
         assert!(zoom.is_video_call());
@@ -388,6 +3750,60 @@ mod tests {
         assert!(!none.is_video_call());
     }
 
+    #[test]
+    fn test_meeting_url() {
+        let event = |location: Option<&str>, description: Option<&str>| Event {
+            location: location.map(|l| l.into()),
+            description: description.map(|d| d.into()),
+            ..Default::default()
+        };
+
+        let zoom = event(Some("https://zoom.us/j/123"), None);
+        assert_eq!(zoom.meeting_url().as_deref(), Some("https://zoom.us/j/123"));
+
+        // Video link in description wins over other URLs
+        let described = event(
+            Some("Room 4 (https://rooms.example.com/4)"),
+            Some("Agenda: https://docs.example.com/a.\nJoin: <https://meet.google.com/abc-def>"),
+        );
+        assert_eq!(
+            described.meeting_url().as_deref(),
+            Some("https://meet.google.com/abc-def")
+        );
+
+        let plain = event(None, Some("Notes at https://docs.example.com/a."));
+        assert_eq!(
+            plain.meeting_url().as_deref(),
+            Some("https://docs.example.com/a")
+        );
+
+        assert_eq!(event(Some("Conference Room A"), None).meeting_url(), None);
+    }
+
+    #[test]
+    fn test_link_allowlist() {
+        let allowlist = LinkAllowlist::default();
+        assert!(allowlist.allows("https://zoom.us/j/123"));
+        assert!(allowlist.allows("https://acme.zoom.us/j/123"));
+        assert!(allowlist.allows("https://meet.google.com:443/abc-def"));
+        assert!(!allowlist.allows("https://notzoom.us/j/123"));
+        assert!(!allowlist.allows("https://zoom.us@evil.example/j/123"));
+        assert!(!allowlist.allows("https://zoom.us.evil.example/"));
+        // Browsers go to evil.example
+        assert!(!allowlist.allows("https://evil.example\\@zoom.us/"));
+        // Hosts only cover web links
+        assert!(!allowlist.allows("ms-msdt://zoom.us/j/123"));
+        assert!(!allowlist.allows("file://zoom.us/j/123"));
+        assert!(LinkAllowlist::parse("zoom.us, zoommtg:").allows("zoommtg://zoom.us/join"));
+
+        let allowlist = LinkAllowlist::parse("Example.com, https:");
+        assert_eq!(allowlist.hosts, ["example.com"]);
+        assert!(allowlist.allows("https://anything.test/"));
+        assert!(allowlist.allows("http://docs.example.com/a"));
+        assert!(!allowlist.allows("http://zoom.us/j/1"));
+        assert!(LinkAllowlist::parse("*").allows("http://anything.test/"));
+    }
+
     #[test]
     fn test_is_in_progress() {
         let event = Event {
@@ -396,6 +3812,7 @@ mod tests {
             end: parse_datetime("2024-01-15 11:00"),
             location: None,
             is_all_day: false,
+            ..Default::default()
         };
 
         // Before start
@@ -416,6 +3833,7 @@ mod tests {
             end: None,
             location: None,
             is_all_day: false,
+            ..Default::default()
         };
         assert!(!no_end.is_in_progress(parse_datetime("2024-01-15 10:30").unwrap()));
 
@@ -434,6 +3852,7 @@ mod tests {
             ),
             location: None,
             is_all_day: false,
+            ..Default::default()
         };
         // At 10:00:15, event hasn't started yet (starts at 10:00:30)
         let now_before = NaiveDate::from_ymd_opt(2024, 1, 15)
@@ -443,6 +3862,31 @@ mod tests {
         assert!(!event_with_secs.is_in_progress(now_before));
     }
 
+    #[test]
+    fn test_duration_grade() {
+        let event = |start: &str, end: &str| Event {
+            start: parse_datetime(start).unwrap(),
+            end: parse_datetime(end),
+            ..Default::default()
+        };
+        let grade = |end| event("2024-01-15 10:00", end).duration_grade();
+        assert_eq!(grade("2024-01-15 10:30"), Some(DurationGrade::Short));
+        assert_eq!(grade("2024-01-15 11:00"), Some(DurationGrade::Medium));
+        assert_eq!(grade("2024-01-15 12:00"), Some(DurationGrade::Medium));
+        assert_eq!(grade("2024-01-15 12:30"), Some(DurationGrade::Long));
+
+        let all_day = Event {
+            is_all_day: true,
+            ..event("2024-01-15 00:00", "2024-01-16 00:00")
+        };
+        assert_eq!(all_day.duration_grade(), None);
+        let no_end = Event {
+            end: None,
+            ..event("2024-01-15 10:00", "2024-01-15 11:00")
+        };
+        assert_eq!(no_end.duration_grade(), None);
+    }
+
     #[test]
     fn test_is_active_on() {
         // Multi-day all-day event: Jan 15-18 (3 days)
@@ -452,6 +3896,7 @@ mod tests {
             end: parse_datetime("2024-01-18 00:00"),
             location: None,
             is_all_day: true,
+            ..Default::default()
         };
         assert!(!multi_day.is_active_on(NaiveDate::from_ymd_opt(2024, 1, 14).unwrap()));
         assert!(multi_day.is_active_on(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap()));
@@ -466,6 +3911,7 @@ mod tests {
             end: parse_datetime("2024-01-16 00:00"),
             location: None,
             is_all_day: true,
+            ..Default::default()
         };
         assert!(!single_day.is_active_on(NaiveDate::from_ymd_opt(2024, 1, 14).unwrap()));
         assert!(single_day.is_active_on(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap()));
@@ -478,6 +3924,7 @@ mod tests {
             end: parse_datetime("2024-01-15 11:00"),
             location: None,
             is_all_day: false,
+            ..Default::default()
         };
         assert!(!timed.is_active_on(NaiveDate::from_ymd_opt(2024, 1, 14).unwrap()));
         assert!(timed.is_active_on(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap()));
@@ -490,11 +3937,40 @@ mod tests {
             end: parse_datetime("2024-01-16 01:00"),
             location: None,
             is_all_day: false,
+            ..Default::default()
         };
         assert!(!overnight.is_active_on(NaiveDate::from_ymd_opt(2024, 1, 14).unwrap()));
         assert!(overnight.is_active_on(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap()));
         assert!(overnight.is_active_on(NaiveDate::from_ymd_opt(2024, 1, 16).unwrap())); // spans into this day
         assert!(!overnight.is_active_on(NaiveDate::from_ymd_opt(2024, 1, 17).unwrap()));
+
+        let day = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        assert!(!overnight.continues_on(day(15)));
+        assert!(overnight.continues_on(day(16)));
+        assert!(!multi_day.continues_on(day(16)));
+        assert_eq!(
+            fmt_overnight(&overnight, day(15), false, false).as_deref(),
+            Some("23:00 → …")
+        );
+        assert_eq!(
+            fmt_overnight(&overnight, day(16), true, true).as_deref(),
+            Some("…→1a")
+        );
+        let two_nights = Event {
+            end: parse_datetime("2024-01-17 06:00"),
+            ..overnight.clone()
+        };
+        assert_eq!(
+            fmt_overnight(&two_nights, day(16), false, false).as_deref(),
+            Some("… → …")
+        );
+        // Ending at midnight doesn't cross it
+        let until_midnight = Event {
+            end: parse_datetime("2024-01-16 00:00"),
+            ..overnight.clone()
+        };
+        assert_eq!(fmt_overnight(&until_midnight, day(15), false, false), None);
+        assert_eq!(fmt_overnight(&timed, day(15), false, false), None);
     }
 
     #[test]
@@ -590,17 +4066,90 @@ mod tests {
         assert_eq!(fmt("2024-01-16 14:30", "2024-01-15 20:00"), "tmrw 2:30 pm");
     }
 
+    #[test]
+    fn test_day_start() {
+        let dt = |s: &str| parse_datetime(s).unwrap();
+        let four = NaiveTime::from_hms_opt(4, 0, 0).unwrap();
+        assert_eq!(
+            day_of(dt("2024-01-16 01:00"), four),
+            dt("2024-01-15 00:00").date()
+        );
+        assert_eq!(
+            day_of(dt("2024-01-16 04:00"), four),
+            dt("2024-01-16 00:00").date()
+        );
+        // At 1 AM, 9 AM is tomorrow morning rather than later today
+        let relative = fmt_relative_time(
+            dt("2024-01-16 09:00"),
+            dt("2024-01-16 01:00"),
+            true,
+            Language::default(),
+            four,
+        );
+        assert_eq!(relative, "tmrw 9:00 am");
+    }
+
+    #[test]
+    fn test_languages() {
+        let dt = |s: &str| parse_datetime(s).unwrap();
+        let now = dt("2024-01-15 20:00");
+        let relative =
+            |event: &str, lang| fmt_relative_time(dt(event), now, false, lang, NaiveTime::MIN);
+        assert_eq!(relative("2024-01-15 20:05", Language::German), "in 5 min");
+        assert_eq!(
+            relative("2024-01-15 20:30", Language::French),
+            "dans 30 min"
+        );
+        assert_eq!(
+            relative("2024-01-16 09:00", Language::Spanish),
+            "mañana 09:00"
+        );
+        assert_eq!(relative("2024-01-15 20:00", Language::Dutch), "nu");
+        assert_eq!(
+            fmt_time_in_group(
+                now,
+                now,
+                true,
+                true,
+                false,
+                Language::Italian,
+                NaiveTime::MIN
+            ),
+            "tutto il giorno"
+        );
+        assert_eq!(
+            fmt_day_header(now.date(), now.date(), Language::Portuguese),
+            "hoje"
+        );
+    }
+
     #[test]
     fn test_all_day_events() {
         // All-day events get "all day" label via fmt_time_in_group
         let event_dt = parse_datetime("2024-01-15 00:00").unwrap();
         let now_dt = parse_datetime("2024-01-15 10:00").unwrap();
         assert_eq!(
-            fmt_time_in_group(event_dt, now_dt, true, true, true),
+            fmt_time_in_group(
+                event_dt,
+                now_dt,
+                true,
+                true,
+                true,
+                Language::default(),
+                NaiveTime::MIN
+            ),
             "all day"
         );
         assert_eq!(
-            fmt_time_in_group(event_dt, now_dt, false, true, true),
+            fmt_time_in_group(
+                event_dt,
+                now_dt,
+                false,
+                true,
+                true,
+                Language::default(),
+                NaiveTime::MIN
+            ),
             "all day"
         );
     }
@@ -635,6 +4184,7 @@ mod tests {
                 end: parse_datetime("2024-01-15 11:00"),
                 location: None,
                 is_all_day: false,
+                ..Default::default()
             },
             // Fully past: started 08:00, ended 09:00
             Event {
@@ -643,6 +4193,7 @@ mod tests {
                 end: parse_datetime("2024-01-15 09:00"),
                 location: None,
                 is_all_day: false,
+                ..Default::default()
             },
             // Future: starts 14:00
             Event {
@@ -651,6 +4202,7 @@ mod tests {
                 end: parse_datetime("2024-01-15 15:00"),
                 location: None,
                 is_all_day: false,
+                ..Default::default()
             },
             // Past with no end time: started 08:00
             Event {
@@ -659,12 +4211,70 @@ mod tests {
                 end: None,
                 location: None,
                 is_all_day: false,
+                ..Default::default()
             },
         ];
 
-        let filtered = filter_future(events, Some(now), 10);
+        let filtered = filter_future(events, Some(now), &EventWindow::default());
         let summaries: Vec<&str> = filtered.iter().map(|e| e.summary.as_str()).collect();
 
         assert_eq!(summaries, vec!["In Progress", "Future"]);
     }
+
+    #[test]
+    fn test_filter_future_window() {
+        let dt = |s: &str| parse_datetime(s).unwrap();
+        let meeting = |summary: &str, start: &str, end: &str| Event {
+            summary: summary.to_string(),
+            start: dt(start),
+            end: Some(dt(end)),
+            ..Default::default()
+        };
+        let events = vec![
+            meeting("Standup", "2024-01-15 09:00", "2024-01-15 09:15"),
+            meeting("Design review", "2024-01-15 13:00", "2024-01-15 14:00"),
+            meeting("Planning", "2024-01-16 23:00", "2024-01-16 23:30"),
+            meeting("Offsite", "2024-01-17 09:00", "2024-01-17 17:00"),
+        ];
+        let now = dt("2024-01-15 09:20");
+        let summaries = |window: EventWindow| -> Vec<String> {
+            filter_future(events.clone(), Some(now), &window)
+                .into_iter()
+                .map(|e| e.summary)
+                .collect()
+        };
+
+        // Standup ended 5 minutes ago: kept with a 10-minute grace, dropped with none
+        let grace = EventWindow {
+            keep_past_minutes: 10,
+            ..Default::default()
+        };
+        assert_eq!(
+            summaries(grace),
+            ["Standup", "Design review", "Planning", "Offsite"]
+        );
+        assert_eq!(summaries(EventWindow::default())[0], "Design review");
+
+        // Lookahead runs through the end of the last included day
+        let today = EventWindow {
+            lookahead_days: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(summaries(today), ["Design review"]);
+        let tomorrow = EventWindow {
+            lookahead_days: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(summaries(tomorrow), ["Design review", "Planning"]);
+
+        // Past the last representable date: no limit rather than a panic
+        let forever = EventWindow {
+            lookahead_days: Some(u32::MAX),
+            occasion_days: u32::MAX,
+            ..Default::default()
+        };
+        assert_eq!(forever.until(now), None);
+        assert_eq!(forever.occasions_until(now), NaiveDateTime::MAX);
+        assert_eq!(summaries(forever).len(), 3);
+    }
 }
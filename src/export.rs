@@ -0,0 +1,189 @@
+use crate::calendar::{self, Event};
+use crate::command;
+use crate::reminder;
+use crate::store::EventStore;
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use icalendar::{Calendar, Component, EventLike};
+
+/// Export file on the host (expanded by `sh`), overridable with `export_file`.
+const EXPORT_FILE: &str = "$HOME/zj-cal-export.ics";
+
+/// Name of the calendar `w` exports.
+pub const EXPORT_NAME: &str = "zj-cal export";
+/// Name of the digest feed written after each refresh.
+pub const DIGEST_NAME: &str = "zj-cal digest";
+
+/// Days of upcoming events the digest covers, unless `digest_days` says otherwise.
+pub const DEFAULT_DIGEST_DAYS: u32 = 7;
+
+/// An ICS calendar of `events`, with floating (local) times. Each occurrence gets its own
+/// UID, so importing a few occurrences of a series doesn't collapse them into one.
+pub fn to_ics(name: &str, events: &[&Event]) -> String {
+    let mut calendar = Calendar::new();
+    calendar.name(name);
+    for event in events {
+        let mut vevent = icalendar::Event::new();
+        vevent.uid(&reminder::key(event)).summary(&event.summary);
+        if event.is_all_day {
+            vevent.starts(event.start.date());
+            if let Some(end) = event.end {
+                vevent.ends(end.date());
+            }
+        } else {
+            vevent.starts(event.start);
+            if let Some(end) = event.end {
+                vevent.ends(end);
+            }
+        }
+        if let Some(ref location) = event.location {
+            vevent.location(location);
+        }
+        if let Some(ref description) = event.description {
+            vevent.description(description);
+        }
+        calendar.push(vevent.done());
+    }
+    calendar.done().to_string()
+}
+
+/// A day's events for the `agenda` pipe command, one per line ("9:30 am – 10:15 am  Standup").
+pub fn agenda_text(events: &[&Event], use_12h: bool) -> String {
+    if events.is_empty() {
+        return "No events".to_string();
+    }
+    events
+        .iter()
+        .map(|e| format!("{}  {}", calendar::fmt_event_range(e, use_12h), e.summary))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A day's events as a JSON array for scripts, with local times ("2024-01-15T09:30:00").
+/// `calendar_of` names each event's calendar.
+pub fn agenda_json<'a>(events: &[&Event], calendar_of: impl Fn(&Event) -> &'a str) -> String {
+    let time = |t: NaiveDateTime| t.format("%Y-%m-%dT%H:%M:%S").to_string();
+    let events: Vec<serde_json::Value> = events
+        .iter()
+        .map(|e| {
+            serde_json::json!({
+                "title": e.summary,
+                "start": time(e.start),
+                "end": e.end.map(time),
+                "all_day": e.is_all_day,
+                "location": e.location,
+                "link": e.meeting_url(),
+                "calendar": calendar_of(e),
+            })
+        })
+        .collect();
+    serde_json::Value::Array(events).to_string()
+}
+
+/// Events for the digest feed: timed and all-day events on today through the `days`th day
+/// from today (0 = today only).
+pub fn digest_events(store: &EventStore, today: NaiveDate, days: u32) -> Vec<&Event> {
+    let from = today.and_time(NaiveTime::MIN);
+    store.in_range(from, from + Duration::days(days as i64 + 1))
+}
+
+/// Shell command that writes `data` to `path`, or the export file (`~/` expanded, directories
+/// created), and prints the path written. `data` goes in pieces (see `command::chunks`).
+pub fn write_command(path: Option<&str>, data: String) -> Vec<String> {
+    let script = format!(
+        r#"f=${{1:-{}}}; shift; case $f in "~/"*) f="$HOME/${{f#"~/"}}" ;; esac
+mkdir -p -- "$(dirname -- "$f")" && printf '%s' "$@" > "$f" && printf '%s\n' "$f""#,
+        EXPORT_FILE
+    );
+    let args = std::iter::once(path.unwrap_or_default().to_string());
+    command::sh(&script, args.chain(command::chunks(&data)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::{parse_datetime, parse_ics};
+    use crate::timezone::LocalZone;
+
+    #[test]
+    fn test_to_ics() {
+        let standup = Event {
+            summary: "Standup, daily".to_string(),
+            start: parse_datetime("2024-01-15 10:00").unwrap(),
+            end: parse_datetime("2024-01-15 10:15"),
+            location: Some("Room 4".to_string()),
+            uid: Some("standup@example.com".to_string()),
+            ..Default::default()
+        };
+        let offsite = Event {
+            summary: "Offsite".to_string(),
+            start: parse_datetime("2024-01-16 00:00").unwrap(),
+            end: parse_datetime("2024-01-18 00:00"),
+            is_all_day: true,
+            ..Default::default()
+        };
+        let ics = to_ics(EXPORT_NAME, &[&standup, &offsite]);
+        assert!(ics.contains("UID:standup@example.com@2024-01-15 10:00:00"));
+
+        let events = parse_ics(ics.as_bytes(), &LocalZone::default()).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].summary, "Standup, daily");
+        assert_eq!(events[0].start, standup.start);
+        assert_eq!(events[0].end, standup.end);
+        assert_eq!(events[0].location.as_deref(), Some("Room 4"));
+        assert!(events[1].is_all_day);
+        assert_eq!(events[1].end, offsite.end);
+    }
+
+    #[test]
+    fn test_agenda() {
+        let standup = Event {
+            summary: "Standup".to_string(),
+            start: parse_datetime("2024-01-15 09:30").unwrap(),
+            end: parse_datetime("2024-01-15 10:15"),
+            location: Some("https://zoom.us/j/123".to_string()),
+            ..Default::default()
+        };
+        let offsite = Event {
+            summary: "Offsite \"day\"".to_string(),
+            start: parse_datetime("2024-01-15 00:00").unwrap(),
+            end: parse_datetime("2024-01-16 00:00"),
+            is_all_day: true,
+            source: 1,
+            ..Default::default()
+        };
+        let events = [&offsite, &standup];
+        assert_eq!(
+            agenda_text(&events, true),
+            "jan 15 · all day  Offsite \"day\"\n9:30 am – 10:15 am  Standup"
+        );
+        assert_eq!(agenda_text(&[], true), "No events");
+
+        let json = agenda_json(&events, |e| ["work", "home"][e.source]);
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed[0]["title"], "Offsite \"day\"");
+        assert_eq!(parsed[0]["all_day"], true);
+        assert_eq!(parsed[0]["calendar"], "home");
+        assert_eq!(parsed[1]["start"], "2024-01-15T09:30:00");
+        assert_eq!(parsed[1]["end"], "2024-01-15T10:15:00");
+        assert_eq!(parsed[1]["link"], "https://zoom.us/j/123");
+        assert_eq!(parsed[1]["location"], "https://zoom.us/j/123");
+        assert_eq!(agenda_json(&[], |_| ""), "[]");
+    }
+
+    #[test]
+    fn test_digest_events() {
+        let event = |start: &str| Event {
+            start: parse_datetime(start).unwrap(),
+            ..Default::default()
+        };
+        let events = EventStore::from(vec![
+            event("2024-01-15 10:00"),
+            event("2024-01-16 23:30"),
+            event("2024-01-17 00:00"),
+        ]);
+        let today = parse_datetime("2024-01-15 00:00").unwrap().date();
+        assert_eq!(digest_events(&events, today, 1).len(), 2);
+        assert_eq!(digest_events(&events, today, 0).len(), 1);
+        assert_eq!(digest_events(&events, today, 7).len(), 3);
+    }
+}
@@ -0,0 +1,733 @@
+//! Native end-to-end tests: a `State` driven through `load` and `update` with the events
+//! Zellij would send, so fetch, parse, and render run together outside the host.
+
+use crate::config::Config;
+use crate::ctx::Ctx;
+use crate::pipe::PipeCommand;
+use crate::{State, View, TIME_TICK_SECS};
+use indoc::indoc;
+use std::collections::BTreeMap;
+use zellij_tile::prelude::*;
+
+const FEED: &str = indoc! {"
+    BEGIN:VCALENDAR
+    VERSION:2.0
+    BEGIN:VEVENT
+    UID:standup@example.com
+    DTSTART:20240115T093000
+    DTEND:20240115T101500
+    SUMMARY:Standup
+    END:VEVENT
+    BEGIN:VEVENT
+    UID:lunch@example.com
+    DTSTART:20240115T120000
+    DTEND:20240115T130000
+    SUMMARY:Lunch with Sam
+    END:VEVENT
+    BEGIN:VEVENT
+    UID:review@example.com
+    DTSTART:20240116T140000Z
+    DTEND:20240116T150000Z
+    SUMMARY:Review
+    END:VEVENT
+    END:VCALENDAR
+"};
+
+/// A loaded plugin with permissions granted. Commands it runs go nowhere (see the host stub
+/// in main.rs); tests answer them by sending the results Zellij would.
+struct Harness {
+    state: State,
+}
+
+impl Harness {
+    fn load(config: &[(&str, &str)]) -> Self {
+        Self::load_at(config, None)
+    }
+
+    /// Loads with the in-plugin clock (see `utc_offset`) reading `utc` rather than the system's.
+    fn load_at(config: &[(&str, &str)], utc: Option<&str>) -> Self {
+        let mut state = State {
+            fake_utc: utc.and_then(crate::calendar::parse_datetime),
+            ..State::default()
+        };
+        state.load(
+            config
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<BTreeMap<_, _>>(),
+        );
+        state.update(Event::PermissionRequestResult(PermissionStatus::Granted));
+        Harness { state }
+    }
+
+    /// Fires the timer set for the soonest job.
+    fn timer(&mut self, elapsed: f64) {
+        self.state.update(Event::Timer(elapsed));
+    }
+
+    /// Finishes the command that was run with `ctx`.
+    fn finish(&mut self, ctx: Ctx, exit_code: i32, stdout: &str, stderr: &str) {
+        self.state.update(Event::RunCommandResult(
+            Some(exit_code),
+            stdout.as_bytes().to_vec(),
+            stderr.as_bytes().to_vec(),
+            ctx.into_map(),
+        ));
+    }
+
+    /// Answers the `date` command with a local time (in UTC).
+    fn time(&mut self, local: &str) {
+        self.finish(Ctx::TimeFetch, 0, &format!("{} +0000\nUTC\n", local), "");
+    }
+
+    /// Answers a calendar's curl fetch with an HTTP status and body.
+    fn fetch(&mut self, calendar: &str, http_code: u16, body: &str) {
+        let stderr = format!("zj-cal-fetch-info: {} 0 https://example.com/\n", http_code);
+        let ctx = Ctx::IcsFetch {
+            calendar: calendar.to_string(),
+        };
+        self.finish(ctx, 0, body, &stderr);
+    }
+
+    /// Presses a key without modifiers.
+    fn key(&mut self, key: BareKey) {
+        self.state.update(Event::Key(KeyWithModifier::new(key)));
+    }
+
+    fn screen(&mut self, rows: usize, cols: usize) -> String {
+        self.state.draw(rows, cols).plain()
+    }
+}
+
+#[test]
+fn test_fetch_and_render() {
+    let mut harness = Harness::load(&[("ics_url", "https://example.com/main.ics")]);
+    let expected = indoc! {"
+        📅 Calendar
+        ────────────────────────────────────────
+        No upcoming events"};
+    assert_eq!(harness.screen(12, 40), expected);
+
+    harness.timer(0.1);
+    harness.time("2024-01-15 10:00:00");
+    assert!(harness.state.loading);
+    harness.fetch("default", 200, FEED);
+    assert!(!harness.state.loading);
+    let expected = indoc! {"
+        📅 Calendar 10:00 am · in meeting · 15...
+        ────────────────────────────────────────
+        today
+          now • Standup
+          12:00 pm (2 hrs) • Lunch with Sam
+        tomorrow
+          2:00 pm • Review"};
+    assert_eq!(harness.screen(12, 40), expected);
+}
+
+#[test]
+fn test_empty_states() {
+    let empty = "BEGIN:VCALENDAR\nVERSION:2.0\nEND:VCALENDAR\n";
+    let screen = |config: &[(&str, &str)]| {
+        let mut config = config.to_vec();
+        config.push(("ics_url", "https://example.com/main.ics"));
+        let mut harness = Harness::load(&config);
+        harness.timer(0.1);
+        harness.time("2024-01-15 10:00:00");
+        // Still loading isn't empty yet
+        let loading = harness.screen(12, 40);
+        harness.fetch("default", 200, empty);
+        (loading, harness.screen(12, 40), harness.screen(1, 40))
+    };
+
+    let (_, full, compact) = screen(&[]);
+    assert!(full.ends_with("No upcoming events"));
+    assert_eq!(compact, "📅 No upcoming events");
+
+    let (_, full, compact) = screen(&[("empty_state", "celebrate")]);
+    assert!(full.ends_with("🎉 Nothing scheduled"));
+    assert_eq!(compact, "📅 🎉 Nothing scheduled");
+
+    let (loading, full, compact) = screen(&[("hide_empty", "true")]);
+    assert!(loading.starts_with("📅 Calendar 10:00 am ↻"));
+    assert_eq!((full.as_str(), compact.as_str()), ("", ""));
+
+    let (_, full, compact) = screen(&[("empty_state", "header")]);
+    assert_eq!(full, "📅 Calendar 10:00 am");
+    assert_eq!(compact, "📅 Calendar");
+}
+
+#[test]
+fn test_inbox() {
+    let mut harness = Harness::load(&[
+        ("ics_url", "https://example.com/main.ics"),
+        ("inbox_dir", "~/invites"),
+    ]);
+    harness.timer(0.1);
+    harness.time("2024-01-15 10:00:00");
+    harness.fetch("default", 200, FEED);
+    let invite = indoc! {"
+        zj-cal-inbox-file: /home/me/invites/offsite.ics
+        BEGIN:VCALENDAR
+        BEGIN:VEVENT
+        UID:offsite@example.com
+        DTSTART:20240118T090000
+        SUMMARY:Offsite
+        END:VEVENT
+        END:VCALENDAR
+        zj-cal-inbox-file: /home/me/invites/empty.ics
+        BEGIN:VCALENDAR
+        END:VCALENDAR
+    "};
+    harness.finish(Ctx::InboxRead, 0, invite, "");
+    assert_eq!(harness.state.inbox.len(), 1);
+    let screen = harness.screen(12, 80);
+    assert!(screen.contains("📥 Offsite · jan 18 9:00 am · i import, I dismiss"));
+
+    // Importing needs somewhere to put it
+    harness.key(BareKey::Char('i'));
+    assert_eq!(harness.state.inbox.len(), 1);
+    assert!(harness.screen(12, 80).contains("Set inbox_calendar"));
+
+    harness.key(BareKey::Char('I'));
+    assert!(harness.state.inbox.is_empty());
+    let ctx = Ctx::InboxArchive {
+        file: "offsite.ics".to_string(),
+        imported: 0,
+    };
+    harness.finish(ctx, 0, "", "");
+    let screen = harness.screen(12, 80);
+    assert!(!screen.contains("📥"));
+    assert!(screen.contains("Archived offsite.ics"));
+}
+
+#[test]
+fn test_quiet_ticks() {
+    // Zone rules put the clock in the plugin, so ticks run no commands
+    let config = [
+        ("ics_url", "https://example.com/main.ics"),
+        ("utc_offset", "+00:00"),
+    ];
+    let mut harness = Harness::load_at(&config, Some("2024-01-15 10:00"));
+    harness.timer(0.1);
+    harness.fetch("default", 200, FEED);
+    // The tick after new data recomputes once
+    harness.state.fake_utc = crate::calendar::parse_datetime("2024-01-15 10:00:15");
+    harness.timer(TIME_TICK_SECS);
+    harness.state.render(12, 40);
+
+    // Another in the same minute recomputes nothing (the emptied store stays empty) and draws
+    // nothing
+    let events = harness.state.store.events().len();
+    harness.state.store = Default::default();
+    harness.state.fake_utc = crate::calendar::parse_datetime("2024-01-15 10:00:30");
+    assert!(!harness.state.update(Event::Timer(TIME_TICK_SECS)));
+    assert!(harness.state.store.events().is_empty());
+
+    // The next minute is recomputed and drawn
+    harness.state.fake_utc = crate::calendar::parse_datetime("2024-01-15 10:01");
+    assert!(harness.state.update(Event::Timer(TIME_TICK_SECS)));
+    assert_eq!(harness.state.store.events().len(), events);
+    assert!(harness.screen(12, 40).contains("10:01 am"));
+}
+
+#[test]
+fn test_refresh() {
+    let mut harness = Harness::load(&[
+        ("ics_url", "https://example.com/main.ics"),
+        ("refresh_interval", "60"),
+    ]);
+    harness.timer(0.1);
+    harness.time("2024-01-15 10:00:00");
+    harness.fetch("default", 200, FEED);
+
+    // Ticks in between only read the clock
+    harness.timer(TIME_TICK_SECS);
+    harness.time("2024-01-15 10:00:30");
+    assert!(!harness.state.loading);
+
+    // An unchanged feed keeps its events
+    harness.timer(TIME_TICK_SECS);
+    harness.time("2024-01-15 10:01:00");
+    assert!(harness.state.loading);
+    harness.fetch("default", 304, "");
+    assert_eq!(harness.state.store.events().len(), 3);
+    assert!(harness.screen(12, 40).contains("Lunch with Sam"));
+
+    // A failed fetch shows its error over the events still held
+    harness.timer(TIME_TICK_SECS);
+    harness.time("2024-01-15 10:01:30");
+    harness.timer(TIME_TICK_SECS);
+    harness.time("2024-01-15 10:02:00");
+    let ctx = Ctx::IcsFetch {
+        calendar: "default".to_string(),
+    };
+    harness.finish(ctx, 6, "", "curl: (6) Could not resolve host: example.com");
+    let expected = indoc! {"
+        📅 Calendar 10:02 am · in meeting · 13...
+        ────────────────────────────────────────
+        stale since 10:01 am
+        today
+          now • Standup
+          12:00 pm (2 hrs) • Lunch with Sam
+        tomorrow
+          2:00 pm • Review"};
+    assert_eq!(harness.screen(12, 40), expected);
+}
+
+#[test]
+fn test_rate_limited() {
+    let mut harness = Harness::load(&[("ics_url", "https://example.com/main.ics")]);
+    harness.timer(0.1);
+    harness.time("2024-01-15 10:00:00");
+    let stderr = "zj-cal-fetch-info: 429 0 https://example.com/\nzj-cal-retry-after: 90\n";
+    let ctx = Ctx::IcsFetch {
+        calendar: "default".to_string(),
+    };
+    harness.finish(ctx, 0, "Too Many Requests", stderr);
+    let expected = indoc! {"
+        📅 Calendar 10:00 am
+        ────────────────────────────────────────
+        Rate limited, retrying at 10:01 am"};
+    assert_eq!(harness.screen(12, 40), expected);
+
+    // Retried on the tick after `Retry-After` passes
+    harness.timer(TIME_TICK_SECS);
+    harness.time("2024-01-15 10:00:30");
+    harness.timer(TIME_TICK_SECS);
+    harness.time("2024-01-15 10:01:00");
+    assert!(!harness.state.loading);
+    harness.timer(TIME_TICK_SECS);
+    harness.time("2024-01-15 10:01:30");
+    assert!(harness.state.loading);
+    harness.fetch("default", 200, FEED);
+    assert!(harness.state.sources[0].error.is_none());
+    assert!(harness.screen(12, 40).contains("Lunch with Sam"));
+}
+
+#[test]
+fn test_wake_from_sleep() {
+    let mut harness = Harness::load(&[("ics_url", "https://example.com/main.ics")]);
+    harness.timer(0.1);
+    harness.time("2024-01-15 10:00:00");
+    harness.fetch("default", 200, FEED);
+
+    harness.timer(TIME_TICK_SECS);
+    harness.time("2024-01-15 10:00:30");
+    assert!(!harness.state.loading);
+
+    // The next tick lands an hour later: refetch without waiting for the refresh interval
+    harness.timer(TIME_TICK_SECS);
+    harness.time("2024-01-15 11:30:00");
+    assert!(harness.state.loading);
+    harness.fetch("default", 200, FEED);
+    assert!(!harness.state.loading);
+    assert!(harness.screen(12, 40).contains("Lunch with Sam"));
+}
+
+#[test]
+fn test_search() {
+    let mut harness = Harness::load(&[("ics_url", "https://example.com/main.ics")]);
+    harness.timer(0.1);
+    harness.time("2024-01-15 10:00:00");
+    harness.fetch("default", 200, FEED);
+
+    // While typing, `j` is text rather than "next event"
+    harness.key(BareKey::Char('/'));
+    for c in "lnch j".chars() {
+        harness.key(BareKey::Char(c));
+    }
+    assert_eq!(harness.state.search.as_deref(), Some("lnch j"));
+    harness.key(BareKey::Backspace);
+    harness.key(BareKey::Backspace);
+    harness.key(BareKey::Enter);
+    let expected = indoc! {"
+        📅 Calendar 10:00 am · in meeting · 15...
+        ────────────────────────────────────────
+        /lnch · 1 match
+        today
+          12:00 pm (2 hrs) • Lunch with Sam"};
+    assert_eq!(harness.screen(12, 40), expected);
+    assert_eq!(harness.state.selected, Some(0));
+
+    // Esc clears the search first
+    harness.key(BareKey::Esc);
+    assert_eq!(harness.state.search, None);
+    assert!(harness.screen(12, 40).contains("Standup"));
+}
+
+#[test]
+fn test_filter_presets() {
+    let mut harness = Harness::load(&[
+        ("ics_url", "https://example.com/main.ics"),
+        ("preset_min_minutes_long", "60"),
+        ("preset_exclude_no_lunch", "(?i)lunch"),
+        ("preset_key_no_lunch", "N"),
+    ]);
+    harness.timer(0.1);
+    harness.time("2024-01-15 10:00:00");
+    harness.fetch("default", 200, FEED);
+
+    // `F` goes through the presets by name, then back to everything
+    harness.key(BareKey::Char('F'));
+    let screen = harness.screen(12, 60);
+    assert!(screen.contains("· ⧩ long"));
+    assert!(!screen.contains("Standup"));
+    assert!(screen.contains("Lunch with Sam"));
+    harness.key(BareKey::Char('F'));
+    assert!(!harness.screen(12, 60).contains("Lunch with Sam"));
+    harness.key(BareKey::Char('F'));
+    assert_eq!(harness.state.preset, None);
+    assert!(harness.screen(12, 60).contains("Standup"));
+
+    // A preset's own key switches it on and off
+    harness.key(BareKey::Char('N'));
+    assert!(harness.screen(12, 60).contains("· ⧩ no_lunch"));
+    harness.key(BareKey::Char('N'));
+    assert!(harness.screen(12, 60).contains("Lunch with Sam"));
+}
+
+/// A CalDAV REPORT answer holding one event.
+fn multistatus(uid: &str, summary: &str, start: &str, end: &str) -> String {
+    format!(
+        indoc! {r#"
+            <d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+            <d:response><d:propstat><d:prop><c:calendar-data>BEGIN:VCALENDAR
+            VERSION:2.0
+            BEGIN:VEVENT
+            UID:{}
+            SUMMARY:{}
+            DTSTART:{}
+            DTEND:{}
+            END:VEVENT
+            END:VCALENDAR
+            </c:calendar-data></d:prop></d:propstat></d:response>
+            </d:multistatus>
+        "#},
+        uid, summary, start, end
+    )
+}
+
+#[test]
+fn test_caldav_chunks() {
+    let mut harness = Harness::load(&[
+        ("caldav_url", "https://dav.example.com/cal/"),
+        ("caldav_chunk_days", "7"),
+    ]);
+    harness.timer(0.1);
+    harness.time("2024-01-15 10:00:00");
+    harness.timer(0.1);
+    let remaining = |harness: &Harness| {
+        harness.state.sources[0]
+            .chunks
+            .as_ref()
+            .map(|chunks| chunks.remaining.len())
+    };
+    // 30 days ahead, a week at a time
+    assert_eq!(remaining(&harness), Some(4));
+
+    // The first week shows while the next is asked for
+    let standup = multistatus("standup", "Standup", "20240115T113000", "20240115T120000");
+    harness.fetch("default", 207, &standup);
+    assert!(harness.screen(12, 60).contains("Standup"));
+    assert!(harness.state.loading);
+    assert_eq!(remaining(&harness), Some(3));
+
+    // An event spanning two weeks comes back in both, but is listed once
+    harness.fetch("default", 207, &standup);
+    let review = multistatus("review", "Review", "20240124T140000", "20240124T150000");
+    harness.fetch("default", 207, &review);
+    let screen = harness.screen(12, 60);
+    assert_eq!(screen.matches("Standup").count(), 1);
+    assert!(screen.contains("Review"));
+
+    // A failed chunk ends the fetch, keeping the weeks already in
+    harness.fetch("default", 500, "");
+    assert_eq!(remaining(&harness), None);
+    assert!(harness.state.sources[0].error.is_some());
+    assert_eq!(harness.state.store.events().len(), 2);
+}
+
+#[test]
+fn test_caldav_chunks_refresh() {
+    let mut harness = Harness::load(&[
+        ("caldav_url", "https://dav.example.com/cal/"),
+        ("caldav_chunk_days", "7"),
+        ("refresh_interval", "60"),
+    ]);
+    harness.timer(0.1);
+    harness.time("2024-01-15 10:00:00");
+    harness.timer(0.1);
+    // One event a week
+    let weeks = [
+        multistatus("standup", "Standup", "20240115T113000", "20240115T120000"),
+        multistatus("review", "Review", "20240124T140000", "20240124T150000"),
+        multistatus("planning", "Planning", "20240201T090000", "20240201T100000"),
+        multistatus("offsite", "Offsite", "20240207T090000", "20240207T170000"),
+        multistatus("retro", "Retro", "20240212T160000", "20240212T170000"),
+    ];
+    for week in &weeks {
+        harness.fetch("default", 207, week);
+    }
+    assert!(!harness.state.loading);
+    assert_eq!(harness.state.store.events().len(), 5);
+
+    harness.timer(TIME_TICK_SECS);
+    harness.time("2024-01-15 10:00:30");
+    harness.timer(TIME_TICK_SECS);
+    harness.time("2024-01-15 10:01:00");
+    harness.timer(0.1);
+    assert!(harness.state.sources[0].chunks.is_some());
+
+    // The weeks not refetched yet keep their events
+    harness.fetch("default", 207, &weeks[0]);
+    assert_eq!(harness.state.store.events().len(), 5);
+    assert!(harness.screen(12, 60).contains("Review"));
+
+    // Even when a later chunk fails
+    harness.fetch("default", 500, "");
+    assert!(harness.state.sources[0].error.is_some());
+    assert_eq!(harness.state.store.events().len(), 5);
+}
+
+#[test]
+fn test_peek_times() {
+    let mut harness = Harness::load(&[("ics_url", "https://example.com/main.ics")]);
+    harness.timer(0.1);
+    harness.time("2024-01-15 10:00:00");
+    harness.fetch("default", 200, FEED);
+
+    harness.key(BareKey::Char('T'));
+    assert!(harness
+        .screen(12, 40)
+        .contains("12:00 pm–1:00 pm • Lunch with Sam"));
+    // Back to relative times once the peek runs out
+    harness.timer(5.0);
+    assert!(harness
+        .screen(12, 40)
+        .contains("12:00 pm (2 hrs) • Lunch with Sam"));
+}
+
+#[test]
+fn test_edit_url() {
+    let mut harness = Harness::load(&[("ics_url", "https://example.com/main.ics")]);
+    harness.timer(0.1);
+    harness.time("2024-01-15 10:00:00");
+    harness.fetch("default", 200, FEED);
+
+    // Starts from the current URL
+    harness.key(BareKey::Char('U'));
+    for _ in "main.ics".chars() {
+        harness.key(BareKey::Backspace);
+    }
+    for c in "new.ics".chars() {
+        harness.key(BareKey::Char(c));
+    }
+    assert!(harness
+        .screen(12, 40)
+        .contains("URL: https://example.com/new.ics▏"));
+    // A long URL shows its end
+    assert!(harness
+        .screen(12, 30)
+        .contains("URL: …s://example.com/new.ics▏"));
+    harness.key(BareKey::Enter);
+    assert_eq!(harness.state.sources[0].url, "https://example.com/new.ics");
+    harness.fetch("default", 200, FEED);
+    assert_eq!(harness.state.notice.as_deref(), Some("ICS URL updated"));
+
+    // A URL whose fetch fails is reverted, keeping the events
+    harness.key(BareKey::Char('U'));
+    harness.key(BareKey::Char('x'));
+    harness.key(BareKey::Enter);
+    let ctx = Ctx::IcsFetch {
+        calendar: "default".to_string(),
+    };
+    harness.finish(
+        ctx,
+        22,
+        "",
+        "zj-cal-fetch-info: 404 0 https://example.com/\n",
+    );
+    assert_eq!(harness.state.sources[0].url, "https://example.com/new.ics");
+    assert!(harness.state.sources[0].error.is_none());
+    assert!(harness.screen(12, 40).contains("Lunch with Sam"));
+
+    // Not a URL at all, or cancelled
+    harness.key(BareKey::Char('U'));
+    for _ in 0..40 {
+        harness.key(BareKey::Backspace);
+    }
+    harness.key(BareKey::Char('x'));
+    harness.key(BareKey::Enter);
+    assert_eq!(harness.state.sources[0].url, "https://example.com/new.ics");
+    harness.key(BareKey::Char('U'));
+    harness.key(BareKey::Esc);
+    assert_eq!(harness.state.url_input, None);
+}
+
+#[test]
+fn test_skip_unchanged_frames() {
+    let mut harness = Harness::load(&[("ics_url", "https://example.com/main.ics")]);
+    harness.timer(0.1);
+    harness.time("2024-01-15 10:00:00");
+    harness.fetch("default", 200, FEED);
+    harness.state.render(12, 40);
+
+    // The same time again changes nothing on screen
+    let ctx = Ctx::TimeFetch.into_map();
+    let same = Event::RunCommandResult(
+        Some(0),
+        b"2024-01-15 10:00:30 +0000\nUTC\n".to_vec(),
+        Vec::new(),
+        ctx.clone(),
+    );
+    assert!(!harness.state.update(same));
+    let next = Event::RunCommandResult(
+        Some(0),
+        b"2024-01-15 10:01:00 +0000\nUTC\n".to_vec(),
+        Vec::new(),
+        ctx,
+    );
+    assert!(harness.state.update(next));
+
+    // The frame that was compared is the one printed, not drawn again
+    let (_, _, drawn) = harness.state.next_frame.clone().unwrap();
+    harness.state.render(12, 40);
+    assert!(harness.state.next_frame.is_none());
+    assert_eq!(harness.state.last_frame.as_ref().unwrap().2, drawn);
+}
+
+#[test]
+fn test_cycle_source_errors() {
+    let mut harness = Harness::load(&[
+        ("ics_url", "https://example.com/main.ics"),
+        ("ics_url_club", "https://example.com/club.ics"),
+        ("ics_url_home", "https://example.com/home.ics"),
+    ]);
+    harness.timer(0.1);
+    harness.time("2024-01-15 10:00:00");
+    harness.fetch("default", 200, FEED);
+    for calendar in ["club", "home"] {
+        let ctx = Ctx::IcsFetch {
+            calendar: calendar.to_string(),
+        };
+        harness.finish(ctx, 6, "", "curl: (6) Could not resolve host");
+    }
+    let error_line =
+        |harness: &mut Harness| harness.screen(12, 60).lines().nth(2).unwrap().to_string();
+    assert_eq!(error_line(&mut harness), "today");
+
+    // `e` steps through the failing calendars, then hides the error again
+    harness.key(BareKey::Char('e'));
+    assert!(error_line(&mut harness).starts_with("club: "));
+    harness.key(BareKey::Char('e'));
+    assert!(error_line(&mut harness).starts_with("home: "));
+    harness.key(BareKey::Char('e'));
+    assert_eq!(error_line(&mut harness), "today");
+
+    // Settings applied again start over without a shown error
+    harness.key(BareKey::Char('e'));
+    let config = [("ics_url", "https://example.com/main.ics")];
+    let config = config.map(|(k, v)| (k.to_string(), v.to_string()));
+    harness
+        .state
+        .apply_config(Config::from(BTreeMap::from(config)));
+    assert_eq!(harness.state.error_source, None);
+}
+
+#[test]
+fn test_max_events() {
+    let mut harness = Harness::load(&[
+        ("ics_url", "https://example.com/main.ics"),
+        ("max_events", "1"),
+    ]);
+    harness.timer(0.1);
+    harness.time("2024-01-15 10:00:00");
+    harness.fetch("default", 200, FEED);
+    // Only the list is capped: lunch is still busy time
+    let screen = harness.screen(12, 40);
+    assert!(screen.contains("Standup") && !screen.contains("Lunch"));
+    assert_eq!(harness.state.store.events().len(), 3);
+    let at = |s| crate::calendar::parse_datetime(s).unwrap();
+    let lunch = harness
+        .state
+        .store
+        .in_range(at("2024-01-15 12:00"), at("2024-01-15 12:30"));
+    assert_eq!(lunch[0].summary, "Lunch with Sam");
+}
+
+#[test]
+fn test_collapse_series() {
+    let feed = indoc! {"
+        BEGIN:VCALENDAR
+        VERSION:2.0
+        BEGIN:VEVENT
+        UID:standup@example.com
+        DTSTART:20240115T113000
+        DTEND:20240115T114500
+        RRULE:FREQ=DAILY;COUNT=30
+        SUMMARY:Standup
+        END:VEVENT
+        BEGIN:VEVENT
+        UID:offsite@example.com
+        DTSTART:20240205T090000
+        DTEND:20240205T170000
+        SUMMARY:Offsite
+        END:VEVENT
+        END:VCALENDAR
+    "};
+    let mut harness = Harness::load(&[
+        ("ics_url", "https://example.com/main.ics"),
+        ("collapse_series", "true"),
+        ("max_events", "5"),
+    ]);
+    harness.timer(0.1);
+    harness.time("2024-01-15 10:00:00");
+    harness.fetch("default", 200, feed);
+    // A month of standups collapses to one line before the list is capped
+    let screen = harness.screen(12, 40);
+    assert_eq!(screen.matches("Standup").count(), 1);
+    assert!(screen.contains("Offsite"));
+}
+
+#[test]
+fn test_propose_ignores_filters() {
+    let mut harness = Harness::load(&[("ics_url", "https://example.com/main.ics")]);
+    harness.timer(0.1);
+    harness.time("2024-01-15 10:00:00");
+    harness.fetch("default", 200, FEED);
+    let propose = || PipeCommand::Propose {
+        minutes: 120,
+        count: 2,
+    };
+    let (slots, _) = harness.state.run_pipe_command(propose());
+    // Lunch leaves no two hours this morning
+    assert_eq!(slots, "today 1p–3p\ntomorrow 9a–11a");
+
+    // Searching or picking a month day narrows the list, not the busy time
+    harness.state.search = Some("Review".to_string());
+    harness.state.view = View::Month;
+    assert_eq!(harness.state.run_pipe_command(propose()), (slots, false));
+}
+
+#[test]
+fn test_agenda_range() {
+    let mut harness = Harness::load(&[
+        ("ics_url", "https://example.com/main.ics"),
+        ("lookahead_days", "1"),
+    ]);
+    harness.timer(0.1);
+    harness.time("2024-01-15 10:00:00");
+    harness.fetch("default", 200, FEED);
+    let mut agenda = |day: &str| {
+        let command = PipeCommand::parse(&format!("agenda {}", day)).unwrap();
+        harness.state.run_pipe_command(command).0
+    };
+    assert!(agenda("today").contains("Lunch with Sam"));
+    assert!(agenda("tomorrow").contains("Review"));
+
+    // Days the store doesn't cover say so rather than listing nothing
+    for day in ["2024-01-14", "2024-01-17"] {
+        let expected = format!("{} is outside the synced range", day);
+        assert_eq!(agenda(day), expected);
+    }
+}
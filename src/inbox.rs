@@ -0,0 +1,200 @@
+//! Invites dropped into `inbox_dir` (saved from email, say): read every minute, previewed at
+//! the top of the pane, then imported into `inbox_calendar` or dismissed, and archived.
+
+use crate::calendar::Event;
+use crate::command;
+
+/// Starts each file in `read_command`'s output, followed by the file's path.
+const FILE_MARKER: &str = "zj-cal-inbox-file: ";
+
+/// An `.ics` file waiting in the inbox.
+#[derive(Clone)]
+pub struct Invite {
+    pub path: String,
+    /// The file's events and time zones, to append to the import calendar.
+    pub components: String,
+    pub events: Vec<Event>,
+}
+
+impl Invite {
+    /// The file's name, without its directory.
+    pub fn file_name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or(&self.path)
+    }
+}
+
+/// Shell command that prints every `.ics` file in `dir`, each after a marker line with its path.
+pub fn read_command(dir: &str) -> Vec<String> {
+    let script = format!(
+        r#"d=$1; case $d in "~/"*) d="$HOME/${{d#"~/"}}" ;; esac
+for f in "$d"/*.ics; do
+  [ -f "$f" ] || continue
+  printf '{}%s\n' "$f"
+  cat -- "$f"
+  echo
+done"#,
+        FILE_MARKER
+    );
+    command::sh(&script, [dir])
+}
+
+/// Splits `read_command`'s output into (path, contents) pairs.
+pub fn parse_listing(data: &[u8]) -> Vec<(String, String)> {
+    let text = String::from_utf8_lossy(data);
+    let mut files: Vec<(String, String)> = Vec::new();
+    for line in text.split_inclusive('\n') {
+        match line.strip_prefix(FILE_MARKER) {
+            Some(path) => files.push((path.trim_end().to_string(), String::new())),
+            None => {
+                if let Some((_, contents)) = files.last_mut() {
+                    contents.push_str(line);
+                }
+            }
+        }
+    }
+    files
+}
+
+/// The `VEVENT` and `VTIMEZONE` blocks of an ICS file, as lines ending in CRLF, for appending
+/// inside another calendar.
+pub fn components(ics: &str) -> String {
+    let mut out = String::new();
+    let mut inside: Option<&str> = None;
+    for line in ics.lines().map(|l| l.trim_end_matches('\r')) {
+        if inside.is_none() {
+            inside = ["VEVENT", "VTIMEZONE"]
+                .into_iter()
+                .find(|name| line.strip_prefix("BEGIN:") == Some(name));
+        }
+        let Some(name) = inside else {
+            continue;
+        };
+        out.push_str(line);
+        out.push_str("\r\n");
+        if line.strip_prefix("END:") == Some(name) {
+            inside = None;
+        }
+    }
+    out
+}
+
+/// Shell command that appends `components` to `calendar` (creating it if needed) when given,
+/// then moves `file` into `archive` under a timestamped name.
+pub fn archive_command(
+    file: &str,
+    archive: &str,
+    calendar: Option<&str>,
+    components: &str,
+) -> Vec<String> {
+    let script = r#"cal=$1 archive=$2 file=$3
+case $cal in "~/"*) cal="$HOME/${cal#"~/"}" ;; esac
+case $archive in "~/"*) archive="$HOME/${archive#"~/"}" ;; esac
+if [ -n "$cal" ]; then
+  mkdir -p -- "$(dirname -- "$cal")" || exit 1
+  if [ -s "$cal" ]; then
+    sed -e '/^END:VCALENDAR/d' -- "$cal" > "$cal.tmp"
+  else
+    printf 'BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//zj-cal//inbox//EN\r\n' > "$cal.tmp"
+  fi && printf '%sEND:VCALENDAR\r\n' "$4" >> "$cal.tmp" && mv -- "$cal.tmp" "$cal" || exit 1
+fi
+mkdir -p -- "$archive" && mv -- "$file" "$archive/$(date +%Y%m%d%H%M%S)-$(basename -- "$file")""#;
+    command::sh(
+        script,
+        [calendar.unwrap_or_default(), archive, file, components],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    const INVITE: &str = indoc! {"
+        BEGIN:VCALENDAR
+        VERSION:2.0
+        METHOD:REQUEST
+        BEGIN:VTIMEZONE
+        TZID:Europe/Berlin
+        END:VTIMEZONE
+        BEGIN:VEVENT
+        UID:review@example.com
+        DTSTART;TZID=Europe/Berlin:20240116T140000
+        SUMMARY:Design review
+        BEGIN:VALARM
+        TRIGGER:-PT10M
+        END:VALARM
+        END:VEVENT
+        END:VCALENDAR
+    "};
+
+    #[test]
+    fn test_parse_listing() {
+        let listing = format!(
+            "{m}/in/a.ics\nBEGIN:VCALENDAR\nEND:VCALENDAR\n\n{m}/in/b c.ics\nX\n",
+            m = FILE_MARKER
+        );
+        let files = parse_listing(listing.as_bytes());
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].0, "/in/a.ics");
+        assert_eq!(files[0].1, "BEGIN:VCALENDAR\nEND:VCALENDAR\n\n");
+        assert_eq!(files[1], ("/in/b c.ics".to_string(), "X\n".to_string()));
+        assert!(parse_listing(b"").is_empty());
+    }
+
+    #[test]
+    fn test_components() {
+        let components = components(INVITE);
+        assert!(components.starts_with("BEGIN:VTIMEZONE\r\nTZID:Europe/Berlin\r\n"));
+        // The alarm's END doesn't end the event
+        assert!(components.ends_with("END:VALARM\r\nEND:VEVENT\r\n"));
+        assert!(!components.contains("METHOD"));
+        assert!(!components.contains("VCALENDAR"));
+    }
+
+    #[test]
+    fn test_import() {
+        let dir = std::env::temp_dir().join(format!("zj-cal-inbox-{}", std::process::id()));
+        let inbox = dir.join("in");
+        std::fs::create_dir_all(&inbox).unwrap();
+        let file = inbox.join("invite; $(id).ics");
+        std::fs::write(&file, INVITE).unwrap();
+        let calendar = dir.join("local.ics");
+        let archive = dir.join("archive");
+        let run = |cmd: Vec<String>| {
+            let output = std::process::Command::new(&cmd[0])
+                .args(&cmd[1..])
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "{:?}", output);
+            output.stdout
+        };
+
+        let listing = run(read_command(inbox.to_str().unwrap()));
+        let files = parse_listing(&listing);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].0, file.to_str().unwrap());
+
+        // Imported twice: the calendar is created, then appended to
+        let components = components(&files[0].1);
+        for _ in 0..2 {
+            std::fs::write(&file, INVITE).unwrap();
+            let cmd = archive_command(
+                file.to_str().unwrap(),
+                archive.to_str().unwrap(),
+                Some(calendar.to_str().unwrap()),
+                &components,
+            );
+            assert_eq!(command::check(&cmd), Ok(()));
+            run(cmd);
+        }
+        let merged = std::fs::read_to_string(&calendar).unwrap();
+        assert!(merged.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert_eq!(merged.matches("BEGIN:VEVENT").count(), 2);
+        assert_eq!(merged.matches("END:VCALENDAR").count(), 1);
+        assert!(merged.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+        assert!(!file.exists());
+        assert!(std::fs::read_dir(&archive).unwrap().count() >= 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
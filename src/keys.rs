@@ -0,0 +1,130 @@
+use zellij_tile::prelude::{BareKey, KeyModifier, KeyWithModifier};
+
+/// User actions triggered from the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Down,
+    Up,
+    First,
+    Last,
+    Deselect,
+    OpenLink,
+    CopyLink,
+    /// Copy a details-free summary of busy times, for sharing.
+    CopyAvailability,
+    /// Expand or collapse the selected event's details.
+    ToggleDetails,
+    /// Show the selected event's link as a QR code.
+    ToggleQr,
+    ToggleDebug,
+    /// Pin or unpin the selected event, keeping it at the top.
+    TogglePin,
+    /// Mark or unmark the selected event for a batch action.
+    ToggleMark,
+    /// Hide the marked events (or the selected one) for this session.
+    HideEvents,
+    /// Hide every occurrence of the selected recurring event, for good.
+    HideSeries,
+    /// Show hidden events and series again.
+    UnhideEvents,
+    /// Copy the marked events' (or the selected one's) times and titles.
+    CopyEvents,
+    /// Write the marked events (or the selected one) to an ICS file.
+    ExportEvents,
+    /// Show the next failing calendar's error (several calendars only show a health strip).
+    CycleSourceError,
+    CycleView,
+    ToggleFocus,
+    /// Start a focus timer that ends before the next meeting, or cancel it.
+    ToggleTimer,
+    /// Hide the rest of the day's events after working hours, or show them again.
+    ToggleOffClock,
+    /// Switch to the next filter preset, and after the last back to none.
+    CyclePreset,
+    /// Switch to this filter preset (by index), or off it if it's active.
+    UsePreset(usize),
+    /// Jump to a day of this week (0 = Monday), from the keys `1`-`7`.
+    JumpToDay(u32),
+    /// Move the month view's selected day by this many days.
+    ShiftDay(i64),
+    /// Start typing a search query.
+    Search,
+    /// Show absolute start–end times in place of relative ones for a few seconds.
+    PeekTimes,
+    /// Edit the default calendar's URL, which is test-fetched before it's kept.
+    EditUrl,
+    /// Import the first invite in the inbox into the local calendar, and archive it.
+    ImportInvite,
+    /// Archive the first invite in the inbox without importing it.
+    DismissInvite,
+}
+
+/// Keys while typing text: a search query, or a calendar URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextInput {
+    Char(char),
+    Backspace,
+    /// Stop typing, keeping the text (a search keeps its matches listed).
+    Done,
+    /// Throw the text away.
+    Cancel,
+}
+
+/// Maps a key press to an action (vim-style keys and arrows).
+pub fn action_for(key: &KeyWithModifier) -> Option<Action> {
+    if !key.has_no_modifiers() {
+        return None;
+    }
+    let action = match key.bare_key {
+        BareKey::Down | BareKey::Char('j') => Action::Down,
+        BareKey::Up | BareKey::Char('k') => Action::Up,
+        BareKey::Home | BareKey::Char('g') => Action::First,
+        BareKey::End | BareKey::Char('G') => Action::Last,
+        BareKey::Esc => Action::Deselect,
+        BareKey::Enter | BareKey::Char('o') => Action::OpenLink,
+        BareKey::Char('y') => Action::CopyLink,
+        BareKey::Char('a') => Action::CopyAvailability,
+        BareKey::Char(' ') => Action::ToggleDetails,
+        BareKey::Char('q') => Action::ToggleQr,
+        BareKey::Char('d') => Action::ToggleDebug,
+        BareKey::Char('e') => Action::CycleSourceError,
+        BareKey::Char('m') => Action::ToggleMark,
+        BareKey::Char('p') => Action::TogglePin,
+        BareKey::Char('x') => Action::HideEvents,
+        BareKey::Char('X') => Action::HideSeries,
+        BareKey::Char('u') => Action::UnhideEvents,
+        BareKey::Char('c') => Action::CopyEvents,
+        BareKey::Char('w') => Action::ExportEvents,
+        BareKey::Char('v') => Action::CycleView,
+        BareKey::Char('f') => Action::ToggleFocus,
+        BareKey::Char('F') => Action::CyclePreset,
+        BareKey::Char('t') => Action::ToggleTimer,
+        BareKey::Char('z') => Action::ToggleOffClock,
+        BareKey::Left | BareKey::Char('h') => Action::ShiftDay(-1),
+        BareKey::Right | BareKey::Char('l') => Action::ShiftDay(1),
+        BareKey::Char('H') => Action::ShiftDay(-7),
+        BareKey::Char('L') => Action::ShiftDay(7),
+        BareKey::Char(c @ '1'..='7') => Action::JumpToDay(c as u32 - '1' as u32),
+        BareKey::Char('/') => Action::Search,
+        BareKey::Char('T') => Action::PeekTimes,
+        BareKey::Char('U') => Action::EditUrl,
+        BareKey::Char('i') => Action::ImportInvite,
+        BareKey::Char('I') => Action::DismissInvite,
+        _ => return None,
+    };
+    Some(action)
+}
+
+/// Maps a key press while typing text; every printable key is text.
+pub fn text_input_for(key: &KeyWithModifier) -> Option<TextInput> {
+    let input = match key.bare_key {
+        BareKey::Enter => TextInput::Done,
+        BareKey::Esc => TextInput::Cancel,
+        BareKey::Backspace => TextInput::Backspace,
+        BareKey::Char(c) if key.has_no_modifiers() || key.has_modifiers(&[KeyModifier::Shift]) => {
+            TextInput::Char(c)
+        }
+        _ => return None,
+    };
+    Some(input)
+}
@@ -0,0 +1,147 @@
+/// Language of the relative-time words ("now", "in 5 min", "today", "tmrw", "all day").
+/// Configured with `language` (or `locale`); dates and everything else stay in English.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+    French,
+    Spanish,
+    Italian,
+    Portuguese,
+    Dutch,
+}
+
+/// One language's words.
+pub struct Strings {
+    pub now: &'static str,
+    /// `{}` is replaced by the minutes.
+    pub in_minutes: &'static str,
+    pub today: &'static str,
+    pub tomorrow: &'static str,
+    /// Short form, before a time.
+    pub tmrw: &'static str,
+    pub all_day: &'static str,
+}
+
+const ENGLISH: Strings = Strings {
+    now: "now",
+    in_minutes: "in {} min",
+    today: "today",
+    tomorrow: "tomorrow",
+    tmrw: "tmrw",
+    all_day: "all day",
+};
+
+const GERMAN: Strings = Strings {
+    now: "jetzt",
+    in_minutes: "in {} min",
+    today: "heute",
+    tomorrow: "morgen",
+    tmrw: "morgen",
+    all_day: "ganztägig",
+};
+
+const FRENCH: Strings = Strings {
+    now: "maintenant",
+    in_minutes: "dans {} min",
+    today: "aujourd'hui",
+    tomorrow: "demain",
+    tmrw: "demain",
+    all_day: "toute la journée",
+};
+
+const SPANISH: Strings = Strings {
+    now: "ahora",
+    in_minutes: "en {} min",
+    today: "hoy",
+    tomorrow: "mañana",
+    tmrw: "mañana",
+    all_day: "todo el día",
+};
+
+const ITALIAN: Strings = Strings {
+    now: "ora",
+    in_minutes: "tra {} min",
+    today: "oggi",
+    tomorrow: "domani",
+    tmrw: "domani",
+    all_day: "tutto il giorno",
+};
+
+const PORTUGUESE: Strings = Strings {
+    now: "agora",
+    in_minutes: "em {} min",
+    today: "hoje",
+    tomorrow: "amanhã",
+    tmrw: "amanhã",
+    all_day: "dia inteiro",
+};
+
+const DUTCH: Strings = Strings {
+    now: "nu",
+    in_minutes: "over {} min",
+    today: "vandaag",
+    tomorrow: "morgen",
+    tmrw: "morgen",
+    all_day: "hele dag",
+};
+
+impl Language {
+    /// Parses a language code or locale name: "de", "de_DE.UTF-8", "pt-BR", "french".
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().to_ascii_lowercase();
+        let code = s.split(['_', '-', '.']).next().unwrap_or_default();
+        match code {
+            "en" | "english" | "c" | "posix" => Some(Self::English),
+            "de" | "german" | "deutsch" => Some(Self::German),
+            "fr" | "french" | "français" => Some(Self::French),
+            "es" | "spanish" | "español" => Some(Self::Spanish),
+            "it" | "italian" | "italiano" => Some(Self::Italian),
+            "pt" | "portuguese" | "português" => Some(Self::Portuguese),
+            "nl" | "dutch" | "nederlands" => Some(Self::Dutch),
+            _ => None,
+        }
+    }
+
+    pub fn strings(self) -> &'static Strings {
+        match self {
+            Self::English => &ENGLISH,
+            Self::German => &GERMAN,
+            Self::French => &FRENCH,
+            Self::Spanish => &SPANISH,
+            Self::Italian => &ITALIAN,
+            Self::Portuguese => &PORTUGUESE,
+            Self::Dutch => &DUTCH,
+        }
+    }
+}
+
+impl Strings {
+    /// "in 5 min", in this language.
+    pub fn in_minutes(&self, minutes: i64) -> String {
+        self.in_minutes.replace("{}", &minutes.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Language::parse("de"), Some(Language::German));
+        assert_eq!(Language::parse("de_DE.UTF-8"), Some(Language::German));
+        assert_eq!(Language::parse("pt-BR"), Some(Language::Portuguese));
+        assert_eq!(Language::parse(" French "), Some(Language::French));
+        assert_eq!(Language::parse("C.UTF-8"), Some(Language::English));
+        assert_eq!(Language::parse("klingon"), None);
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(Language::default().strings().in_minutes(5), "in 5 min");
+        assert_eq!(Language::Spanish.strings().in_minutes(10), "en 10 min");
+        assert_eq!(Language::German.strings().all_day, "ganztägig");
+    }
+}
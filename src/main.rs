@@ -1,72 +1,216 @@
 #[macro_use]
 mod ctx;
+mod backend;
+mod cache;
+mod caldav;
 mod calendar;
+mod command;
 mod config;
-use chrono::{NaiveDate, NaiveDateTime, Timelike};
-use config::Config;
+mod export;
+#[cfg(test)]
+mod harness;
+mod inbox;
+mod keys;
+mod locale;
+mod notes;
+mod pipe;
+mod profile;
+mod qr;
+mod recurrence;
+mod reminder;
+mod render;
+mod scheduler;
+mod search;
+mod source;
+mod store;
+mod theme;
+mod timezone;
+mod tzif;
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use config::{Config, View};
 use ctx::Ctx;
-use owo_colors::OwoColorize;
-use std::collections::BTreeMap;
+use keys::Action;
+use owo_colors::AnsiColors;
+use pipe::PipeCommand;
+use scheduler::{Job, Scheduler};
+use source::{FetchInfo, Source};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use store::EventStore;
+use theme::Theme;
+use timezone::LocalZone;
+use tzif::ZoneRules;
 use zellij_tile::prelude::*;
 
 /// Interval between timer ticks (updates time display, may trigger calendar refresh).
 pub const TIME_TICK_SECS: f64 = 30.0;
 
+/// Interval between ticks in focus mode, so the countdown runs live.
+const FOCUS_TICK_SECS: f64 = 1.0;
+
+/// How long `T` shows absolute times before going back to relative ones.
+const PEEK_SECS: f64 = 5.0;
+
+/// A jump this long between ticks means the machine slept, so calendars are refreshed at once.
+const WAKE_GAP_MINUTES: i64 = 5;
+
+/// How long after a reminder or event boundary its early tick lands.
+const WAKEUP_SLACK_SECS: f64 = 1.0;
+
+/// Description lines shown in an expanded event before the rest is cut off.
+const DETAIL_DESCRIPTION_LINES: usize = 6;
+
+/// Most events that can be pinned; pins are for the one or two that matter.
+const MAX_PINS: usize = 3;
+
+/// How far ahead recurring events are expanded, unless `lookahead_days` reaches further.
+const RECURRENCE_LOOKAHEAD_DAYS: i64 = 30;
+
+/// How far ahead `propose` looks for free slots, unless `lookahead_days` is set.
+const PROPOSE_LOOKAHEAD_DAYS: i64 = 14;
+
+/// How long parsed events are reused while the feed answers 304 Not Modified. Stored events
+/// are already trimmed to what was upcoming at parse time, so they need a periodic re-parse.
+const CONDITIONAL_FETCH_MAX_AGE_HOURS: i64 = 6;
+
 /// Save fetched ICS files for debugging. (Path: `/tmp/zj-cal/`)
 /// Set ZJ_CAL_DEBUG_ICS=1 at build time.
 const DEBUG_SAVE_ICS: bool = option_env!("ZJ_CAL_DEBUG_ICS").is_some();
 
 #[derive(Default)]
 struct State {
-    events: Vec<calendar::Event>,
-    ics_url: String,
+    sources: Vec<Source>,
+    store: EventStore, // Merged from all sources
     ics_url_resolved: bool,
-    calendar_refresh_ticks: u32, // Fetch calendar every N time ticks
-    error: Option<String>,
+    refresh_secs: f64, // Between calendar refreshes
     loading: bool,
     permission_granted: bool,
     current_time: Option<NaiveDateTime>,
-    utc_offset_minutes: i32,
-    ticks_until_calendar: u32,
+    clock: Option<NaiveDateTime>, // `current_time` with seconds, advanced by timers
+    local_zone: LocalZone,
+    zone_rules: Option<ZoneRules>, // When known, the clock is computed in-plugin
+    #[cfg(test)]
+    fake_utc: Option<NaiveDateTime>, // What the in-plugin clock reads in tests, in place of the system's
+    timezone: Option<String>, // Configured zone name, overriding the host's
+    scheduler: Scheduler,
+    fetch_queue: VecDeque<usize>, // Source indices; fetched one per tick to stagger curl calls
     use_12h_time: bool,
+    language: locale::Language,
+    day_start: NaiveTime, // When "today" rolls over (`day_starts_at`), midnight by default
+    selected: Option<usize>, // Index into `display_events()`
+    scroll: usize,        // First displayed event index
+    notice: Option<String>, // Transient feedback line (cleared on next key press)
+    search: Option<String>, // Query narrowing the list to fuzzy matches
+    searching: bool,      // Keys type into `search`
+    url_input: Option<String>, // Keys type into a replacement URL for the default calendar
+    url_trial: Option<String>, // URL to put back if the replacement's first fetch fails
+    persist_url: bool,    // Save a replacement URL once it works
+    peek: bool,           // Absolute times in place of relative ones, until `Job::EndPeek`
+    show_debug: bool,
+    error_source: Option<usize>, // Failing source whose error is shown under the health strip
+    expanded: bool,              // Selected event shows its detail block
+    show_qr: bool,               // QR code of the selected event's link in place of the list
+    view: View,
+    month_day: Option<NaiveDate>, // Month view's selected day (None = today)
+    max_payload_mb: u64,
+    caldav_chunk_days: u32, // CalDAV ranges are requested this many days at a time
+    compact: bool,          // One-line mode (also used whenever the pane is a single row)
+    next_three: bool,       // Three-line widget mode, whatever the pane size
+    screen_reader: bool,    // Plain sentences in place of icons and columns
+    empty_state: config::EmptyState,
+    status_glyph: bool,
+    duration_colors: bool,
+    new_badge: bool,    // "new" after events changed in the last day
+    date_line: bool,    // Header line with today's date in the year and month
+    density_row: bool,  // Footer shading this week's days by meeting load
+    free_gaps: bool,    // Footer listing the rest of today's free time
+    busy_gauge: bool,   // Footer gauge of today's booked working hours
+    day_progress: bool, // Header bar through today's working hours, meetings marked
+    dim_meetings: Vec<calendar::MeetingSize>, // Meeting sizes whose titles are dimmed
+    collapse_series: bool, // Only the next occurrence of each recurring series
+    focus: bool,        // Pane dedicated to the next event, with a live countdown
+    theme: Theme,
+    show_private: bool,
+    filter: calendar::EventFilter,
+    presets: Vec<calendar::FilterPreset>,
+    preset: Option<usize>,             // Active preset, hiding events from view
+    window: calendar::EventWindow,     // Lookahead, event limit, and how long ended events linger
+    birthday_calendar: Option<String>, // Calendar listed in the birthdays section
+    working_hours: calendar::WorkingHours,
+    travel: calendar::TravelTimes,
+    link_allowlist: calendar::LinkAllowlist,
+    pending_link: Option<String>, // Link outside the allowlist that a second `o` opens
+    timer_buffer_minutes: u32,
+    late_minutes: u32,       // How long an unjoined call shows "started 5 min ago"
+    joined: HashSet<String>, // Keys of events whose link was opened
+    off_clock: bool,         // After working hours, list only the next day's first event
+    timer_until: Option<NaiveDateTime>, // A running focus timer's end
+    profile_pending: bool,   // Waiting for the profiles file before fetching anything
+    layout_config: BTreeMap<String, String>, // Layout settings, layered over the profile
+    remind_minutes: Option<u32>,
+    remind_notify: bool,
+    ooo_suppress_reminders: bool,
+    notified: HashSet<String>, // `reminder::key`s of events that already alerted
+    start_action: reminder::StartAction,
+    started: HashSet<String>, // Keys of flagged events whose start action already ran
+    notes: BTreeMap<NaiveDate, String>, // Day notes, shown under the agenda's day headers
+    pinned: Vec<String>,      // `Event::pin_id`s, shown above the list
+    marked: HashSet<String>,  // Keys of events marked for a batch action
+    hidden: HashSet<String>,  // Keys of events hidden this session
+    hidden_series: Vec<String>, // `Event::pin_id`s of recurring series hidden for good
+    export_file: Option<String>,
+    digest_file: Option<String>,
+    inbox_dir: Option<String>,
+    inbox_calendar: Option<String>,
+    inbox_archive: Option<String>,
+    inbox: Vec<inbox::Invite>, // Invites waiting in `inbox_dir`, previewed until handled
+    digest_days: u32,
+    digest_pending: bool, // Events were parsed since the digest was last written
+    alerts: Vec<String>,  // Keys of alerting events, shown until they start or Esc
+    last_frame: Option<(usize, usize, String)>, // Rows, columns, and text last printed
+    next_frame: Option<(usize, usize, String)>, // Drawn by `frame_changed`, for `render` to print
+    last_tick: Option<NaiveDateTime>, // Clock at the last tick, to notice sleep
+    ticked: Option<NaiveDateTime>, // Minute the events were last recomputed for by a tick
 }
 
 register_plugin!(State);
 
+/// Native test builds have no Zellij host to link against; plugin commands are no-ops.
+#[cfg(test)]
+#[no_mangle]
+extern "C" fn host_run_plugin_command() {}
+
 impl ZellijPlugin for State {
     fn load(&mut self, configuration: BTreeMap<String, String>) {
-        let config = Config::from(configuration);
-
-        self.ics_url = config.ics_url;
-        self.ics_url_resolved = !self.ics_url.is_empty();
-        self.use_12h_time = config.use_12h_time;
-        self.calendar_refresh_ticks = (config.refresh_interval_secs / TIME_TICK_SECS).ceil() as u32;
-        self.ticks_until_calendar = 0; // Fetch immediately on first tick
-
-        log!(
-            "load() ics_url={}, refresh_interval={}s (every {} ticks)",
-            if self.ics_url.is_empty() {
-                "unset"
-            } else {
-                "[REDACTED]"
-            },
-            config.refresh_interval_secs,
-            self.calendar_refresh_ticks
-        );
+        // With a profile, settings are applied again once the profiles file is read
+        if let Some(profile) = configuration.get("profile").filter(|p| !p.is_empty()) {
+            log!("load() waiting for profile {}", profile);
+            self.profile_pending = true;
+            self.layout_config = configuration.clone();
+        }
+        self.apply_config(Config::from(configuration));
+        // With `utc_offset`, the time is known before any command runs
+        self.sync_clock();
 
         // Request necessary permissions
-        request_permission(&[PermissionType::RunCommands]);
+        request_permission(&[
+            PermissionType::RunCommands,
+            PermissionType::ReadCliPipes,
+            PermissionType::MessageAndLaunchOtherPlugins,
+            PermissionType::ChangeApplicationState,
+        ]);
 
         // Subscribe to events
         subscribe(&[
             EventType::Timer,
+            EventType::Key,
             EventType::RunCommandResult,
             EventType::PermissionRequestResult,
         ]);
     }
 
     fn update(&mut self, event: Event) -> bool {
-        match event {
+        self.next_frame = None;
+        let should_render = match event {
             Event::PermissionRequestResult(status) => {
                 log!("PermissionRequestResult: {:?}", status);
                 if status == PermissionStatus::Granted && !self.permission_granted {
@@ -74,37 +218,195 @@ impl ZellijPlugin for State {
                     // Use a short delay to let permission system fully initialize
                     // This works around a race condition in Zellij
                     log!("Permission granted, scheduling fetch...");
-                    set_timeout(0.1);
+                    if self.zone_rules.is_none() || self.timezone.is_some() {
+                        self.read_zone();
+                    }
+                    if self.profile_pending {
+                        self.read_profile();
+                    } else {
+                        self.restore_caches();
+                    }
+                    self.scheduler.schedule(Job::Tick, 0.1);
+                    self.arm_timer();
                 } else if status != PermissionStatus::Granted {
                     log!("Permission NOT granted");
                 }
                 true
             }
-            Event::Timer(_) => {
-                if !self.ics_url_resolved {
-                    self.fetch_ics_url_from_env();
-                } else {
-                    self.fetch_time();
+            Event::Timer(elapsed) => {
+                let before = self.scheduler.now();
+                let shown = self.tick_view();
+                let jobs = self.scheduler.fire(elapsed);
+                let quiet = jobs.iter().all(|job| {
+                    matches!(
+                        job,
+                        Job::Tick | Job::Refresh | Job::Reminder | Job::Boundary
+                    )
+                });
+                // Between ticks the clock runs on the timers
+                if let Some(clock) = self.clock.as_mut() {
+                    let secs = self.scheduler.now() - before;
+                    *clock += chrono::Duration::milliseconds((secs * 1000.0) as i64);
                 }
-                set_timeout(TIME_TICK_SECS);
-                true
+                for job in jobs {
+                    self.run_job(job);
+                }
+                self.arm_timer();
+                !quiet || self.tick_view() != shown
             }
+            Event::Key(key) if self.url_input.is_some() => match keys::text_input_for(&key) {
+                Some(input) => {
+                    self.handle_url_input(input);
+                    true
+                }
+                None => false,
+            },
+            Event::Key(key) if self.searching => match keys::text_input_for(&key) {
+                Some(input) => {
+                    self.handle_search_input(input);
+                    true
+                }
+                None => false,
+            },
+            Event::Key(key) => match self.preset_action(&key).or_else(|| keys::action_for(&key)) {
+                Some(action) => {
+                    self.notice = None;
+                    self.handle_action(action);
+                    true
+                }
+                None => false,
+            },
             Event::RunCommandResult(exit_code, stdout, stderr, ctx) => {
                 match Ctx::from_map(&ctx) {
                     Ok(Ctx::TimeFetch) => {
+                        let shown = self.tick_view();
                         self.handle_time_fetch(exit_code, stdout, stderr);
+                        if self.tick_view() == shown {
+                            return false;
+                        }
+                    }
+                    Ok(Ctx::ZoneRead) => {
+                        self.handle_zone_read(exit_code, stdout, stderr);
                     }
                     Ok(Ctx::IcsFetchEnv) => {
                         self.handle_env_fetch(exit_code, stdout, stderr);
                     }
-                    Ok(Ctx::IcsFetch) => {
-                        self.handle_ics_fetch(exit_code, stdout, stderr);
+                    Ok(Ctx::IcsFetch { calendar }) => {
+                        self.handle_ics_output(&calendar, exit_code, stdout, stderr);
+                    }
+                    Ok(Ctx::IcsFetchFile { calendar, path }) => {
+                        self.handle_ics_fetch_file(&calendar, exit_code, stderr, path);
+                    }
+                    Ok(Ctx::CacheRead { calendar }) => {
+                        self.handle_cache_read(&calendar, exit_code, stdout);
+                    }
+                    Ok(Ctx::OpenLink) => {
+                        if exit_code != Some(0) {
+                            let err = String::from_utf8_lossy(&stderr).trim().to_string();
+                            log!("Link command failed: {}", err);
+                            self.notice = Some(format!("Link command failed: {}", err));
+                        }
+                    }
+                    Ok(Ctx::Export { events }) => {
+                        let path = String::from_utf8_lossy(&stdout).trim().to_string();
+                        self.notice = Some(if exit_code == Some(0) {
+                            format!("Exported {} event(s) to {}", events, path)
+                        } else {
+                            let err = String::from_utf8_lossy(&stderr).trim().to_string();
+                            log!("Export failed: {}", err);
+                            format!("Export failed: {}", err)
+                        });
+                    }
+                    Ok(Ctx::InboxRead) => {
+                        self.handle_inbox_read(exit_code, stdout, stderr);
+                    }
+                    Ok(Ctx::InboxArchive { file, imported }) => {
+                        self.handle_inbox_archive(exit_code, stderr, &file, imported);
+                    }
+                    Ok(Ctx::DigestWrite) => {
+                        if exit_code != Some(0) {
+                            log!("Digest write failed: {}", String::from_utf8_lossy(&stderr));
+                        }
+                    }
+                    Ok(Ctx::Copy) => {
+                        if exit_code != Some(0) {
+                            let err = String::from_utf8_lossy(&stderr).trim().to_string();
+                            log!("Copy failed: {}", err);
+                            self.notice = Some("Copy failed: no clipboard tool found".to_string());
+                        }
+                    }
+                    Ok(Ctx::ProfileRead) => {
+                        self.handle_profile_read(exit_code, stdout, stderr);
+                    }
+                    Ok(Ctx::Notify) => {
+                        if exit_code != Some(0) {
+                            log!("Notification failed: {}", String::from_utf8_lossy(&stderr));
+                        }
+                    }
+                    Ok(Ctx::NotesRead) => {
+                        // No notes file yet is normal
+                        if exit_code == Some(0) {
+                            self.notes = notes::parse(&stdout);
+                        }
+                    }
+                    Ok(Ctx::NotesWrite) => {
+                        if exit_code != Some(0) {
+                            let err = String::from_utf8_lossy(&stderr).trim().to_string();
+                            log!("Failed to save notes: {}", err);
+                            self.notice = Some(format!("Failed to save notes: {}", err));
+                        }
                     }
-                    Ok(Ctx::IcsFetchFile { path }) => {
-                        self.handle_ics_fetch_file(exit_code, stderr, path);
+                    Ok(Ctx::UrlRead) => {
+                        // Nothing saved yet is normal
+                        if exit_code == Some(0) {
+                            self.handle_url_read(&stdout);
+                        }
                     }
-                    Ok(Ctx::IcsReadFile { .. }) => {
-                        self.handle_ics_read_file(exit_code, stdout, stderr);
+                    Ok(Ctx::UrlWrite) => {
+                        if exit_code != Some(0) {
+                            let err = String::from_utf8_lossy(&stderr).trim().to_string();
+                            log!("Failed to save the ICS URL: {}", err);
+                            self.notice = Some(format!("Failed to save the ICS URL: {}", err));
+                        }
+                    }
+                    Ok(Ctx::HiddenSeriesRead) => {
+                        // Nothing hidden yet is normal
+                        if exit_code == Some(0) {
+                            self.hidden_series = cache::parse_ids(&stdout);
+                            self.merge_events();
+                        }
+                    }
+                    Ok(Ctx::HiddenSeriesWrite) => {
+                        if exit_code != Some(0) {
+                            let err = String::from_utf8_lossy(&stderr).trim().to_string();
+                            log!("Failed to save hidden series: {}", err);
+                            self.notice = Some(format!("Failed to save hidden series: {}", err));
+                        }
+                    }
+                    Ok(Ctx::PinsRead) => {
+                        // No pins file yet is normal
+                        if exit_code == Some(0) {
+                            self.pinned = cache::parse_ids(&stdout);
+                        }
+                    }
+                    Ok(Ctx::PinsWrite) => {
+                        if exit_code != Some(0) {
+                            let err = String::from_utf8_lossy(&stderr).trim().to_string();
+                            log!("Failed to save pins: {}", err);
+                            self.notice = Some(format!("Failed to save pins: {}", err));
+                        }
+                    }
+                    Ok(Ctx::CacheClear) => {
+                        self.handle_cache_clear(exit_code, stderr);
+                    }
+                    Ok(Ctx::CacheWrite { calendar }) => {
+                        if exit_code != Some(0) {
+                            log!(
+                                "Failed to write cache for {}: {}",
+                                calendar,
+                                String::from_utf8_lossy(&stderr)
+                            );
+                        }
                     }
                     Err(err) => {
                         log!("Invalid context: {}", err);
@@ -113,129 +415,1326 @@ impl ZellijPlugin for State {
                 true
             }
             _ => false,
+        };
+        should_render && self.frame_changed()
+    }
+
+    fn pipe(&mut self, pipe_message: PipeMessage) -> bool {
+        if pipe_message.name != pipe::PIPE_NAME {
+            return false;
+        }
+        self.next_frame = None;
+        let payload = pipe_message.payload.unwrap_or_default();
+        log!("pipe: {}", payload.trim());
+        let (output, should_render) = match PipeCommand::parse(&payload) {
+            Ok(command) => self.run_pipe_command(command),
+            Err(err) => (err, false),
+        };
+        match pipe_message.source {
+            PipeSource::Cli(_) => {
+                cli_pipe_output(&pipe_message.name, &format!("{}\n", output));
+                unblock_cli_pipe_input(&pipe_message.name);
+            }
+            // Reply to the sending plugin (e.g. a status bar) under the same pipe name
+            PipeSource::Plugin(plugin_id) => pipe_message_to_plugin(
+                MessageToPlugin::new(pipe::PIPE_NAME)
+                    .with_destination_plugin_id(plugin_id)
+                    .with_payload(output),
+            ),
+            PipeSource::Keybind => {}
         }
+        should_render && self.frame_changed()
     }
 
     fn render(&mut self, rows: usize, cols: usize) {
-        let width = cols.min(50);
+        // The frame the update was judged by, unless the pane has been resized since
+        let frame = match self.next_frame.take() {
+            Some((r, c, frame)) if (r, c) == (rows, cols) => frame,
+            _ => self.draw(rows, cols).to_string(),
+        };
+        print!("{}", frame);
+        self.last_frame = Some((rows, cols, frame));
+    }
+}
 
-        if self.ics_url.is_empty() {
-            if !self.ics_url_resolved {
-                println!("{} {}", "📅 Calendar".blue().bold(), "↻".yellow());
+impl State {
+    /// What a tick can change on screen: the minute, the spinner, and (when the minute turns
+    /// or the data changed since) the recomputed events. Ticks that leave it alone aren't
+    /// drawn at all, so a pane costs next to nothing between minutes.
+    fn tick_view(&self) -> (Option<NaiveDateTime>, bool, Option<NaiveDateTime>) {
+        (self.current_time, self.loading, self.ticked)
+    }
+
+    /// Whether the pane would show anything new if drawn again at the last frame's size.
+    /// Ticks that change nothing on screen skip the redraw, which some terminals flicker on.
+    /// A changed frame is kept for `render`, so it isn't drawn twice.
+    fn frame_changed(&mut self) -> bool {
+        let Some((rows, cols, _)) = self.last_frame else {
+            return true;
+        };
+        let frame = self.draw(rows, cols).to_string();
+        if self
+            .last_frame
+            .as_ref()
+            .is_some_and(|(_, _, last)| *last == frame)
+        {
+            return false;
+        }
+        self.next_frame = Some((rows, cols, frame));
+        true
+    }
+
+    /// Applies plugin settings (at load, and again once a profile is read).
+    fn apply_config(&mut self, config: Config) {
+        self.sources = config.calendars.into_iter().map(Source::from).collect();
+        self.error_source = None;
+        self.ics_url_resolved = !self.sources[0].url.is_empty();
+        self.use_12h_time = config.use_12h_time;
+        self.language = config.language;
+        self.day_start = config.day_start;
+        if let Some(offset) = config.utc_offset {
+            self.zone_rules = Some(ZoneRules::fixed(offset));
+        }
+        self.timezone = config.timezone;
+        if self.timezone.is_some() {
+            self.local_zone.tzid = self.timezone.clone();
+        }
+        self.view = config.view;
+        self.max_payload_mb = config.max_payload_mb;
+        self.caldav_chunk_days = config.caldav_chunk_days;
+        self.compact = config.compact;
+        self.next_three = config.next_three;
+        self.screen_reader = config.screen_reader;
+        self.empty_state = config.empty_state;
+        self.status_glyph = config.status_glyph;
+        self.duration_colors = config.duration_colors;
+        self.new_badge = config.new_badge;
+        self.density_row = config.density_row;
+        self.date_line = config.date_line;
+        self.focus = config.focus_mode;
+        self.collapse_series = config.collapse_series;
+        self.window = config.window;
+        self.working_hours = config.working_hours;
+        self.travel = config.travel;
+        self.link_allowlist = config.link_allowlist;
+        self.timer_buffer_minutes = config.timer_buffer_minutes;
+        self.late_minutes = config.late_minutes;
+        self.off_clock = config.off_clock;
+        self.export_file = config.export_file;
+        self.digest_file = config.digest_file;
+        self.inbox_dir = config.inbox_dir;
+        self.inbox_calendar = config.inbox_calendar;
+        self.inbox_archive = config.inbox_archive;
+        self.digest_days = config.digest_days;
+        self.free_gaps = config.free_gaps;
+        self.busy_gauge = config.busy_gauge;
+        self.day_progress = config.day_progress;
+        self.dim_meetings = config.dim_meetings;
+        self.theme = config.theme;
+        self.show_private = config.show_private;
+        self.persist_url = config.persist_url;
+        self.filter = config.filter;
+        self.presets = config.presets;
+        self.birthday_calendar = config.birthday_calendar;
+        self.preset = config.preset;
+        self.remind_minutes = config.remind_minutes;
+        self.remind_notify = config.remind_notify;
+        self.ooo_suppress_reminders = config.ooo_suppress_reminders;
+        self.start_action = config.start_action;
+        self.refresh_secs = config.refresh_interval_secs;
+        self.scheduler.cancel(Job::Refresh); // Fetch on the next tick
+
+        log!(
+            "load() calendars=[{}], refresh_interval={}s",
+            self.sources
+                .iter()
+                .map(|s| {
+                    let auth = if s.auth.is_empty() { "" } else { " (auth)" };
+                    format!("{}={}{}", s.name, redact(&s.url), auth)
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+            config.refresh_interval_secs
+        );
+    }
+
+    /// Events in display order: calendars plus overlay busy blocks, merged by start time, up
+    /// to `max_events` (the store keeps the rest for queries).
+    fn display_events(&self) -> Vec<&calendar::Event> {
+        // The month view lists just its selected day
+        let month_day = self.month_day().filter(|_| self.view == View::Month);
+        let events = match month_day {
+            Some(day) => self.store.on_day(day),
+            None => self.store.all(),
+        };
+        let mut events = match self.current_time.filter(|_| self.off_clock) {
+            Some(now) => {
+                calendar::off_clock_events(&events, now, &self.working_hours).unwrap_or(events)
+            }
+            None => events,
+        };
+        if let Some(preset) = self.preset.map(|idx| &self.presets[idx]) {
+            events.retain(|e| preset.allows(e, &self.sources[e.source].name));
+        }
+        if let Some(query) = self.search.as_deref().filter(|q| !q.trim().is_empty()) {
+            events.retain(|e| search::score_event(query, e).is_some());
+        }
+        events.truncate(self.window.max_events);
+        events
+    }
+
+    /// The preset bound to `key`, if any; these keys win over the built-in ones.
+    fn preset_action(&self, key: &KeyWithModifier) -> Option<Action> {
+        let BareKey::Char(c) = key.bare_key else {
+            return None;
+        };
+        if !key.has_no_modifiers() && !key.has_modifiers(&[KeyModifier::Shift]) {
+            return None;
+        }
+        let idx = self.presets.iter().position(|p| p.key == Some(c))?;
+        Some(Action::UsePreset(idx))
+    }
+
+    /// Switches to preset `idx` (`None` for no preset), selecting from the top again.
+    fn switch_preset(&mut self, idx: Option<usize>) {
+        self.preset = idx;
+        self.selected = None;
+        self.scroll = 0;
+        self.notice = Some(match idx {
+            Some(idx) => format!("Filter: {}", self.presets[idx].name),
+            None => "Showing all events".to_string(),
+        });
+    }
+
+    /// Edits the search query, selecting the best match as it changes.
+    fn handle_search_input(&mut self, input: keys::TextInput) {
+        let query = self.search.get_or_insert_with(String::new);
+        match input {
+            keys::TextInput::Char(c) => query.push(c),
+            keys::TextInput::Backspace => {
+                query.pop();
+            }
+            keys::TextInput::Done => {
+                self.searching = false;
+                if query.trim().is_empty() {
+                    self.search = None;
+                }
+                return;
+            }
+            keys::TextInput::Cancel => {
+                self.searching = false;
+                self.search = None;
+                self.selected = None;
+                self.scroll = 0;
                 return;
             }
-            println!("{}", "⚠ No ICS URL configured".yellow());
-            println!();
-            println!("Add to your plugin config:");
-            println!("  ics_url \"https://...\"");
-            println!();
-            println!("Or set environment variable:");
-            println!("  export ZJ_CAL_ICS_URL=\"https://...\"");
-            return;
         }
+        let query = query.clone();
+        let best = self
+            .display_events()
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| Some((search::score_event(&query, e)?, i)))
+            // Highest score, earliest event on ties
+            .max_by_key(|&(score, i)| (score, std::cmp::Reverse(i)))
+            .map(|(_, i)| i);
+        self.selected = best;
+        self.scroll = 0;
+        self.expanded = false;
+        self.show_qr = false;
+    }
 
-        // Header - show time as soon as we have it, with optional loading indicator
-        print!("{} ", "📅 Calendar".blue().bold());
-        if let Some(now) = self.current_time {
-            let time_str = calendar::fmt_time(now.hour(), now.minute(), self.use_12h_time);
-            print!("{}", time_str.dimmed());
-            if self.loading {
-                println!(" {}", "↻".yellow());
-            } else {
-                println!();
+    /// Edits the replacement URL; Enter tries it (see `try_url`).
+    fn handle_url_input(&mut self, input: keys::TextInput) {
+        let Some(url) = self.url_input.as_mut() else {
+            return;
+        };
+        match input {
+            keys::TextInput::Char(c) => url.push(c),
+            keys::TextInput::Backspace => {
+                url.pop();
             }
-        } else if self.loading {
-            println!("{}", "↻".yellow());
-        } else {
-            println!();
+            keys::TextInput::Done => {
+                let url = self.url_input.take().unwrap_or_default();
+                self.try_url(url.trim());
+            }
+            keys::TextInput::Cancel => self.url_input = None,
         }
-        println!("{}", "─".repeat(width));
+    }
 
-        // Error display
-        if let Some(ref err) = self.error {
-            println!("{}", truncate(err, width).red());
+    /// Points the default calendar at `url` and fetches it right away. The old URL is kept
+    /// aside until the fetch is in (see `finish_url_trial`).
+    fn try_url(&mut self, url: &str) {
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            self.notice = Some("Not an http(s) URL, kept the old one".to_string());
+            return;
+        }
+        if url == self.sources[0].url {
             return;
         }
+        log!("Trying a new URL for {}", self.sources[0].name);
+        let source = &mut self.sources[0];
+        let old = std::mem::replace(&mut source.url, url.to_string());
+        // The first trial's URL is the one that worked
+        self.url_trial.get_or_insert(old);
+        // Another feed's validators could get a 304 for events it never sent
+        source.validators = Default::default();
+        source.error = None;
+        source.retry_at = None;
+        self.ics_url_resolved = true;
+        self.fetch_queue.retain(|&idx| idx != 0);
+        self.notice = Some("Testing the new URL...".to_string());
+        self.loading = true;
+        self.fetch_source(0);
+    }
 
-        // Events
-        if self.events.is_empty() {
-            println!("{}", "No upcoming events".dimmed());
+    /// Settles a URL set with `U` once its first fetch is in: kept (and saved, with
+    /// `persist_url`) if it worked, else the old one is put back.
+    fn finish_url_trial(&mut self) {
+        let Some(old) = self.url_trial.take() else {
             return;
+        };
+        let source = &mut self.sources[0];
+        match source.error.take() {
+            None => {
+                log!("New URL for {} works", source.name);
+                self.notice = Some("ICS URL updated".to_string());
+                if self.persist_url {
+                    let cmd = cache::write_url_command(&source.url);
+                    command::run(&cmd, Ctx::UrlWrite);
+                }
+            }
+            Some(err) => {
+                log!("New URL for {} failed, reverting: {}", source.name, err);
+                source.url = old;
+                self.notice = Some(format!("New URL failed ({}), kept the old one", err));
+            }
         }
+    }
 
-        // Reserve: 1 header + 1 separator + 1 "+more" + 1 buffer for floating mode
-        let max_lines = rows.saturating_sub(4);
-        let now = self.current_time.unwrap_or_default();
-        let today = now.date();
-        let mut current_group: Option<NaiveDate> = None;
-        let mut lines_used = 0;
-        let mut events_shown = 0;
+    /// Applies a URL saved with `persist_url`, which wins over the configured one.
+    fn handle_url_read(&mut self, stdout: &[u8]) {
+        let url = String::from_utf8_lossy(stdout).trim().to_string();
+        if url.is_empty() || url == self.sources[0].url {
+            return;
+        }
+        log!("Using the saved URL for {}", self.sources[0].name);
+        let source = &mut self.sources[0];
+        source.url = url;
+        source.events.clear();
+        source.validators = Default::default();
+        self.ics_url_resolved = true;
+        self.merge_events();
+        self.restore_cache(0);
+        if !self.fetch_queue.contains(&0) {
+            self.fetch_queue.push_back(0);
+        }
+    }
 
-        for event in &self.events {
-            let active_today = event.is_active_on(today);
-            let event_date = if active_today {
-                today
-            } else {
-                event.start.date()
-            };
+    /// Lists the invites waiting in `inbox_dir`, if one is set.
+    fn read_inbox(&self) {
+        if let Some(dir) = &self.inbox_dir {
+            command::run(&inbox::read_command(dir), Ctx::InboxRead);
+        }
+    }
 
-            // Print group header if day changed
-            if current_group != Some(event_date) {
-                // (need room for header + at least 1 event)
-                if lines_used + 2 > max_lines {
-                    break;
+    /// Replaces the pending invites with the files just listed; ones without events are skipped.
+    fn handle_inbox_read(&mut self, exit_code: Option<i32>, stdout: Vec<u8>, stderr: Vec<u8>) {
+        if exit_code != Some(0) {
+            log!("Inbox read failed: {}", String::from_utf8_lossy(&stderr));
+            return;
+        }
+        self.inbox = inbox::parse_listing(&stdout)
+            .into_iter()
+            .filter_map(|(path, contents)| {
+                match calendar::parse_ics(contents.as_bytes(), &self.local_zone) {
+                    Ok(events) if !events.is_empty() => {
+                        let mut events = events;
+                        events.sort_by_key(|e| e.start);
+                        let components = inbox::components(&contents);
+                        Some(inbox::Invite {
+                            path,
+                            components,
+                            events,
+                        })
+                    }
+                    Ok(_) => {
+                        log!("No events in {}, skipping", path);
+                        None
+                    }
+                    Err(err) => {
+                        log!("Can't parse {}: {}", path, err);
+                        None
+                    }
                 }
-                let header = calendar::fmt_day_header(event_date, today);
-                println!("{}", header.bold());
-                current_group = Some(event_date);
-                lines_used += 1;
+            })
+            .collect();
+    }
+
+    /// Archives the first pending invite, importing it into `inbox_calendar` first if asked.
+    fn archive_invite(&mut self, import: bool) {
+        if self.inbox.is_empty() {
+            self.notice = Some("No invites waiting".to_string());
+            return;
+        }
+        let calendar = match (import, &self.inbox_calendar) {
+            (true, None) => {
+                self.notice = Some("Set inbox_calendar to import invites".to_string());
+                return;
             }
+            (true, calendar) => calendar.as_deref(),
+            (false, _) => None,
+        };
+        let invite = self.inbox.remove(0);
+        let archive = match &self.inbox_archive {
+            Some(archive) => archive.clone(),
+            None => format!("{}/archive", self.inbox_dir.as_deref().unwrap_or(".")),
+        };
+        let cmd = inbox::archive_command(&invite.path, &archive, calendar, &invite.components);
+        let imported = if import { invite.events.len() } else { 0 };
+        let file = invite.file_name().to_string();
+        command::run(&cmd, Ctx::InboxArchive { file, imported });
+    }
 
-            if lines_used >= max_lines {
-                break;
+    /// Reports an archived invite, refetching local calendars after an import. On failure the
+    /// inbox is listed again, so the invite comes back.
+    fn handle_inbox_archive(
+        &mut self,
+        exit_code: Option<i32>,
+        stderr: Vec<u8>,
+        file: &str,
+        imported: usize,
+    ) {
+        if exit_code != Some(0) {
+            let err = String::from_utf8_lossy(&stderr).trim().to_string();
+            log!("Failed to archive {}: {}", file, err);
+            self.notice = Some(format!("Failed to archive {}: {}", file, err));
+            self.read_inbox();
+            return;
+        }
+        if imported == 0 {
+            self.notice = Some(format!("Archived {}", file));
+            return;
+        }
+        self.notice = Some(format!("Imported {} event(s) from {}", imported, file));
+        for idx in 0..self.sources.len() {
+            if self.sources[idx].local_path().is_some() && !self.fetch_queue.contains(&idx) {
+                self.fetch_queue.push_back(idx);
             }
+        }
+    }
 
-            // Format time based on group
-            let is_today = event_date == today;
-            let in_progress = !event.is_all_day && event.is_in_progress(now);
-            let time = if in_progress {
-                "now".to_string()
-            } else {
-                calendar::fmt_time_in_group(
+    /// The day it is at `now`, rolling over at `day_start` rather than midnight.
+    fn today(&self, now: NaiveDateTime) -> NaiveDate {
+        calendar::day_of(now, self.day_start)
+    }
+
+    /// Whether the evening's list is cut down to the next day's first event.
+    fn is_off_clock(&self) -> bool {
+        self.off_clock
+            && self
+                .current_time
+                .is_some_and(|now| self.working_hours.is_over(now))
+    }
+
+    /// How `empty_state` applies now: once every calendar is in without error and none has
+    /// anything to show. A search, prompt, or notice keeps the pane as it is.
+    fn empty_state(&self) -> Option<config::EmptyState> {
+        let mut calendars = self
+            .sources
+            .iter()
+            .filter(|s| !s.overlay && !s.url.is_empty())
+            .peekable();
+        let fetched = calendars.peek().is_some()
+            && calendars.all(|s| s.error.is_none() && s.last_fetched.or(s.cached_at).is_some());
+        let empty = fetched
+            && self.current_time.is_some()
+            && self.store.events().is_empty()
+            && self.store.occasions().is_empty()
+            && self.search.is_none()
+            && self.url_input.is_none()
+            && self.notice.is_none()
+            && !self.show_debug;
+        empty.then_some(self.empty_state)
+    }
+
+    /// What an empty calendar says: "No upcoming events", or the `empty_state "celebrate"` line.
+    fn empty_message(&self) -> &'static str {
+        match self.empty_state {
+            config::EmptyState::Celebrate => "🎉 Nothing scheduled",
+            _ => "No upcoming events",
+        }
+    }
+
+    /// The first waiting invite, for its banner: "Standup · jan 16 09:00 · i import, I dismiss".
+    fn invite_line(&self) -> Option<String> {
+        let invite = self.inbox.first()?;
+        let event = invite.events.first()?;
+        let mut line = format!(
+            "{} · {} · i import, I dismiss",
+            event.summary,
+            calendar::fmt_datetime(event.start, self.use_12h_time)
+        );
+        if invite.events.len() > 1 {
+            line.push_str(&format!(" ({} events)", invite.events.len()));
+        }
+        if self.inbox.len() > 1 {
+            line.push_str(&format!(" · {} more waiting", self.inbox.len() - 1));
+        }
+        Some(line)
+    }
+
+    /// The month view's selected day: `month_day`, but never before today.
+    fn month_day(&self) -> Option<NaiveDate> {
+        let today = self.today(self.current_time?);
+        Some(self.month_day.map_or(today, |day| day.max(today)))
+    }
+
+    /// Events with an active reminder alert, soonest first.
+    fn alerting_events(&self) -> Vec<&calendar::Event> {
+        self.store
+            .events()
+            .iter()
+            .filter(|e| self.alerts.contains(&reminder::key(e)))
+            .collect()
+    }
+
+    /// Fires alerts for events whose reminder time has come, once per event.
+    fn check_reminders(&mut self) {
+        let Some(now) = self.current_time else {
+            return;
+        };
+        let live: HashSet<String> = self.store.events().iter().map(reminder::key).collect();
+        self.notified.retain(|key| live.contains(key));
+        let upcoming: HashSet<String> = self
+            .store
+            .events()
+            .iter()
+            .filter(|e| e.start > now)
+            .map(reminder::key)
+            .collect();
+        self.alerts.retain(|key| upcoming.contains(key));
+
+        if self.ooo_suppress_reminders && calendar::ooo_until(self.store.events(), now).is_some() {
+            return;
+        }
+        let due: Vec<&calendar::Event> = self
+            .store
+            .events()
+            .iter()
+            .filter(|e| {
+                let travel = self.travel.minutes_for(e);
+                reminder::is_due(e, now, self.remind_minutes, travel)
+            })
+            .filter(|e| !self.notified.contains(&reminder::key(e)))
+            .collect();
+        // Several at once get one notification between them rather than one each
+        if self.remind_notify && due.len() > 1 {
+            let (title, body) = reminder::digest(&due, now);
+            log!("Reminder digest: {}", title);
+            command::run(&command::notify(&title, &body), Ctx::Notify);
+        }
+        let single = due.len() == 1;
+        for event in due {
+            let key = reminder::key(event);
+            log!("Reminder: {}", event.summary);
+            if self.remind_notify && single {
+                let mut when = calendar::fmt_relative_time(
                     event.start,
                     now,
-                    is_today,
-                    event.is_all_day,
                     self.use_12h_time,
-                )
-            };
+                    self.language,
+                    self.day_start,
+                );
+                if let Some(leave) = self.leave_note(event) {
+                    when.push_str(&format!(" · {}", leave));
+                }
+                command::run(&command::notify(&event.summary, &when), Ctx::Notify);
+            }
+            self.notified.insert(key.clone());
+            self.alerts.push(key);
+        }
+    }
 
-            // Render event line (indented under group)
-            let summary = truncate(&event.summary, width.saturating_sub(time.len() + 5));
-            let icon = if event.is_video_call() { "📹" } else { "•" };
-            let highlight = time == "now" || (event.is_all_day && active_today);
-            if highlight {
-                println!("  {} {} {}", time.green().bold(), icon, summary.bold());
-            } else {
-                println!("  {} {} {}", time.cyan(), icon, summary);
+    /// Opens the configured pane and switches tabs as flagged events start, once per event.
+    fn run_start_actions(&mut self) {
+        let Some(now) = self.current_time else {
+            return;
+        };
+        let starting: Vec<&calendar::Event> = self
+            .store
+            .events()
+            .iter()
+            .filter(|e| reminder::has_just_started(e, now))
+            .collect();
+        let live: HashSet<String> = starting.iter().map(|e| reminder::key(e)).collect();
+        self.started.retain(|key| live.contains(key));
+        for event in starting {
+            let key = reminder::key(event);
+            if !self.start_action.applies_to(event) || self.started.contains(&key) {
+                continue;
+            }
+            log!("Start action: {}", event.summary);
+            if let Some(tab) = self.start_action.tab.as_deref() {
+                go_to_tab_name(tab);
             }
-            lines_used += 1;
-            events_shown += 1;
+            let args = self.start_action.pane_args(event);
+            if let Some(args) = args.filter(|args| {
+                let checked = command::check(&[&["sh".to_string()], args.as_slice()].concat());
+                if let Err(err) = &checked {
+                    log!("Not starting {}: {}", event.summary, err);
+                }
+                checked.is_ok()
+            }) {
+                let command = CommandToRun {
+                    path: "sh".into(),
+                    args,
+                    cwd: None,
+                };
+                open_command_pane_floating(command, None, BTreeMap::new());
+            }
+            self.started.insert(key);
         }
+    }
 
-        let remaining = self.events.len() - events_shown;
-        if remaining > 0 {
-            println!("{}", format!("  +{} more", remaining).dimmed());
+    fn handle_action(&mut self, action: Action) {
+        if action != Action::OpenLink {
+            self.pending_link = None;
+        }
+        match action {
+            Action::ToggleDebug => {
+                self.show_debug = !self.show_debug;
+                return;
+            }
+            Action::PeekTimes => {
+                self.peek = !self.peek;
+                if self.peek {
+                    self.scheduler.schedule(Job::EndPeek, PEEK_SECS);
+                } else {
+                    self.scheduler.cancel(Job::EndPeek);
+                }
+                self.arm_timer();
+                return;
+            }
+            Action::EditUrl => {
+                self.url_input = Some(self.sources[0].url.clone());
+                return;
+            }
+            Action::Search => {
+                self.searching = true;
+                self.search.get_or_insert_with(String::new);
+                return;
+            }
+            // Esc leaves a search before it clears the selection
+            Action::Deselect if self.search.is_some() => {
+                self.handle_search_input(keys::TextInput::Cancel);
+                return;
+            }
+            Action::ToggleFocus => {
+                self.focus = !self.focus;
+                self.arm_timer();
+                return;
+            }
+            Action::ImportInvite | Action::DismissInvite => {
+                self.archive_invite(action == Action::ImportInvite);
+                return;
+            }
+            Action::ToggleOffClock => {
+                self.off_clock = !self.off_clock;
+                self.selected = None;
+                self.scroll = 0;
+                self.notice = Some(match self.off_clock {
+                    true => "Off the clock: after working hours, only the next day's first event"
+                        .to_string(),
+                    false => "Showing all events".to_string(),
+                });
+                return;
+            }
+            Action::CyclePreset => {
+                if !self.presets.is_empty() {
+                    let next = match self.preset {
+                        None => Some(0),
+                        Some(idx) => Some(idx + 1).filter(|&n| n < self.presets.len()),
+                    };
+                    self.switch_preset(next);
+                }
+                return;
+            }
+            // A preset's key switches it off again
+            Action::UsePreset(idx) => {
+                self.switch_preset(Some(idx).filter(|&idx| self.preset != Some(idx)));
+                return;
+            }
+            Action::ToggleTimer => {
+                if self.timer_until.take().is_some() {
+                    self.notice = Some("Focus timer cancelled".to_string());
+                    return;
+                }
+                let Some(now) = self.clock.or(self.current_time) else {
+                    return;
+                };
+                match calendar::focus_timer_end(self.store.events(), now, self.timer_buffer_minutes)
+                {
+                    Ok(end) => {
+                        let minutes = (end - now).num_minutes();
+                        self.notice = Some(format!(
+                            "Focus timer: {} (t to cancel)",
+                            calendar::fmt_duration(minutes)
+                        ));
+                        self.timer_until = Some(end);
+                        self.arm_timer();
+                    }
+                    Err(err) => self.notice = Some(format!("No focus timer: {}", err)),
+                }
+                return;
+            }
+            Action::UnhideEvents => {
+                let count = self.hidden.len();
+                self.hidden.clear();
+                let series = std::mem::take(&mut self.hidden_series).len();
+                if series > 0 {
+                    self.save_hidden_series();
+                }
+                self.merge_events();
+                self.notice = Some(match series {
+                    0 => format!("{} hidden event(s) shown again", count),
+                    _ => format!(
+                        "{} hidden event(s) and {} series shown again",
+                        count, series
+                    ),
+                });
+                return;
+            }
+            Action::CycleSourceError => {
+                let failing: Vec<usize> = (0..self.sources.len())
+                    .filter(|&idx| {
+                        let source = &self.sources[idx];
+                        !source.overlay && source.error.is_some()
+                    })
+                    .collect();
+                let current = self
+                    .error_source
+                    .and_then(|idx| failing.iter().position(|&f| f == idx));
+                self.error_source = match current {
+                    Some(pos) => failing.get(pos + 1).copied(),
+                    None => failing.first().copied(),
+                };
+                if failing.is_empty() {
+                    self.notice = Some("All calendars fetched fine".to_string());
+                }
+                return;
+            }
+            Action::CycleView => {
+                self.view = self.view.next();
+                self.scroll = 0;
+                self.notice = Some(format!("View: {}", self.view.name()));
+                return;
+            }
+            // Works with an empty calendar too ("free")
+            Action::CopyAvailability => {
+                if let Some(now) = self.current_time {
+                    let availability = calendar::fmt_availability(
+                        self.store.events(),
+                        now,
+                        pipe::AVAILABILITY_DAYS,
+                        self.use_12h_time,
+                    );
+                    command::run(&command::copy(&availability), Ctx::Copy);
+                    self.notice = Some("Availability copied".to_string());
+                }
+                return;
+            }
+            // Days without events can be selected too
+            Action::ShiftDay(days) => {
+                if self.view == View::Month {
+                    if let Some(day) = self.month_day() {
+                        self.month_day = Some(day + chrono::Duration::days(days));
+                        self.selected = None;
+                        self.scroll = 0;
+                        self.expanded = false;
+                        self.show_qr = false;
+                    }
+                }
+                return;
+            }
+            _ => {}
+        }
+        let count = self.display_events().len();
+        if count == 0 {
+            return;
+        }
+        let last = count - 1;
+        match action {
+            Action::Down => {
+                self.selected = Some(self.selected.map_or(0, |i| (i + 1).min(last)));
+            }
+            Action::Up => {
+                self.selected = Some(self.selected.map_or(0, |i| i.saturating_sub(1)));
+            }
+            Action::First => self.selected = Some(0),
+            Action::Last => self.selected = Some(last),
+            Action::TogglePin => {
+                let Some(id) = self
+                    .selected
+                    .and_then(|i| self.display_events().get(i).map(|e| e.pin_id().to_string()))
+                else {
+                    self.notice = Some("Select an event to pin".to_string());
+                    return;
+                };
+                if let Some(pos) = self.pinned.iter().position(|p| *p == id) {
+                    self.pinned.remove(pos);
+                    self.notice = Some("Unpinned".to_string());
+                } else if self.pinned.len() >= MAX_PINS {
+                    self.notice = Some(format!("At most {} pins; unpin one first", MAX_PINS));
+                    return;
+                } else {
+                    self.pinned.push(id);
+                    self.notice = Some("Pinned".to_string());
+                }
+                let cmd = cache::write_pins_command(&self.pinned);
+                command::run(&cmd, Ctx::PinsWrite);
+            }
+            Action::ToggleMark => {
+                let Some(key) = self
+                    .selected
+                    .and_then(|i| self.display_events().get(i).map(|e| reminder::key(e)))
+                else {
+                    self.notice = Some("Select an event to mark".to_string());
+                    return;
+                };
+                if !self.marked.remove(&key) {
+                    self.marked.insert(key);
+                }
+                self.selected = self.selected.map(|i| (i + 1).min(last));
+            }
+            Action::HideEvents => {
+                let keys: Vec<String> =
+                    self.batch_events().into_iter().map(reminder::key).collect();
+                let count = keys.len();
+                self.hidden.extend(keys);
+                self.marked.clear();
+                self.merge_events();
+                self.selected = None;
+                self.notice = Some(format!("{} event(s) hidden (u to show)", count));
+            }
+            Action::HideSeries => {
+                let Some(event) = self
+                    .selected
+                    .and_then(|i| self.display_events().get(i).copied())
+                else {
+                    self.notice = Some("Select a recurring event to hide its series".to_string());
+                    return;
+                };
+                if !event.is_recurring() {
+                    self.notice = Some("Not a recurring event (x hides it)".to_string());
+                    return;
+                }
+                let (id, summary) = (event.pin_id().to_string(), event.summary.clone());
+                self.hidden_series.push(id);
+                self.save_hidden_series();
+                self.merge_events();
+                self.selected = None;
+                self.notice = Some(format!("Every \"{}\" hidden (u to show)", summary));
+            }
+            Action::CopyEvents => {
+                let today = self.today(self.current_time.unwrap_or_default());
+                let lines: Vec<String> = self
+                    .batch_events()
+                    .iter()
+                    .map(|e| {
+                        format!(
+                            "{} · {} · {}",
+                            calendar::fmt_day_header(
+                                group_date(e, today, self.day_start),
+                                today,
+                                self.language
+                            ),
+                            calendar::fmt_event_range(e, self.use_12h_time),
+                            e.summary
+                        )
+                    })
+                    .collect();
+                if !lines.is_empty() {
+                    command::run(&command::copy(&lines.join("\n")), Ctx::Copy);
+                    self.notice = Some(format!("{} event(s) copied", lines.len()));
+                    self.marked.clear();
+                }
+            }
+            Action::ExportEvents => {
+                let events = self.batch_events();
+                if !events.is_empty() {
+                    let ctx = Ctx::Export {
+                        events: events.len(),
+                    };
+                    let cmd = export::write_command(
+                        self.export_file.as_deref(),
+                        export::to_ics(export::EXPORT_NAME, &events),
+                    );
+                    command::run(&cmd, ctx);
+                    self.marked.clear();
+                }
+            }
+            Action::ToggleDebug
+            | Action::UnhideEvents
+            | Action::CycleSourceError
+            | Action::CycleView
+            | Action::ToggleFocus
+            | Action::ToggleTimer
+            | Action::ToggleOffClock
+            | Action::CyclePreset
+            | Action::UsePreset(_)
+            | Action::CopyAvailability
+            | Action::Search
+            | Action::PeekTimes
+            | Action::EditUrl
+            | Action::ImportInvite
+            | Action::DismissInvite
+            | Action::ShiftDay(_) => {}
+            Action::JumpToDay(weekday) => self.jump_to_day(weekday),
+            Action::Deselect => {
+                self.show_qr = false;
+                self.expanded = false;
+                self.selected = None;
+                self.scroll = 0;
+                self.alerts.clear();
+                self.marked.clear();
+            }
+            Action::OpenLink | Action::CopyLink => {
+                let Some((summary, url)) = self.selected_link() else {
+                    return;
+                };
+                if action == Action::OpenLink {
+                    // Feeds can carry any URL, so unknown sites take a second press
+                    let confirmed = self.pending_link.take().is_some_and(|link| link == url);
+                    if !confirmed && !self.link_allowlist.allows(&url) {
+                        let host = url
+                            .split_once("://")
+                            .map(|(_, rest)| calendar::link_host(rest))
+                            .unwrap_or_default();
+                        self.notice = Some(format!("Open {}? Press o again", host));
+                        self.pending_link = Some(url);
+                        return;
+                    }
+                    log!("Opening link for {}", summary);
+                    if let Some(event) = self
+                        .selected
+                        .and_then(|i| self.display_events().get(i).copied())
+                    {
+                        let key = reminder::key(event);
+                        self.joined.insert(key);
+                    }
+                    command::run(&command::open_link(&url), Ctx::OpenLink);
+                } else {
+                    command::run(&command::copy(&url), Ctx::Copy);
+                    self.notice = Some("Link copied".to_string());
+                }
+            }
+            Action::ToggleDetails => self.expanded = !self.expanded,
+            Action::ToggleQr => {
+                self.show_qr = !self.show_qr && self.selected_link().is_some();
+            }
         }
     }
-}
 
-impl State {
+    /// Whether `event` is a call that started in the last `late_minutes` whose link hasn't
+    /// been opened here.
+    fn is_late(&self, event: &calendar::Event, now: NaiveDateTime) -> bool {
+        let minutes = (now - event.start).num_minutes();
+        event.is_in_progress(now)
+            && !event.is_all_day
+            && event.is_busy()
+            && event.meeting_url().is_some()
+            && (1..=self.late_minutes as i64).contains(&minutes)
+            && !self.joined.contains(&reminder::key(event))
+    }
+
+    /// Targets of a batch action: the marked events, or else the selected one.
+    fn batch_events(&self) -> Vec<&calendar::Event> {
+        let events = self.display_events();
+        if self.marked.is_empty() {
+            return self
+                .selected
+                .and_then(|i| events.get(i).copied())
+                .into_iter()
+                .collect();
+        }
+        events
+            .into_iter()
+            .filter(|e| self.marked.contains(&reminder::key(e)))
+            .collect()
+    }
+
+    /// Icon color: the calendar's `ics_color`, else the feed's own color hint.
+    fn event_color(&self, event: &calendar::Event) -> Option<AnsiColors> {
+        self.sources[event.source]
+            .color
+            .or_else(|| event.color.as_deref().and_then(theme::nearest_color))
+    }
+
+    /// Detail block for an expanded event: time span, location, series, people, description.
+    fn detail_lines(&self, event: &calendar::Event, width: usize) -> Vec<String> {
+        let mut lines = vec![calendar::fmt_event_range(event, self.use_12h_time)];
+        if let Some(ref location) = event.location {
+            lines.extend(wrap(&format!("📍 {}", location), width));
+        }
+        if let Some((leave, minutes)) = self.leave_note(event).zip(self.travel.minutes_for(event)) {
+            lines.push(format!(
+                "{} ({} travel)",
+                leave,
+                calendar::fmt_duration(minutes as i64)
+            ));
+        }
+        if let Some(series) = event.fmt_series() {
+            lines.push(format!("↻ {}", series));
+        }
+        if let Some(ref organizer) = event.organizer {
+            lines.push(format!("organized by {}", organizer.display_name()));
+        }
+        if let Some(attendance) = calendar::fmt_attendance(&event.attendees) {
+            lines.push(format!("👥 {}", attendance));
+        }
+        if let Some(ref description) = event.description {
+            let mut description: Vec<String> = description
+                .lines()
+                .filter(|l| !l.trim().is_empty())
+                .flat_map(|l| wrap(l.trim(), width))
+                .collect();
+            if description.len() > DETAIL_DESCRIPTION_LINES {
+                description.truncate(DETAIL_DESCRIPTION_LINES);
+                description.push("…".to_string());
+            }
+            lines.extend(description);
+        }
+        lines
+    }
+
+    /// "leave by 1:40 pm" for events that need travel (see `travel_minutes`).
+    fn leave_note(&self, event: &calendar::Event) -> Option<String> {
+        let leave = self.travel.leave_by(event)?;
+        let time = calendar::fmt_time(leave.hour(), leave.minute(), self.use_12h_time);
+        Some(format!("leave by {}", time))
+    }
+
+    /// The selected event's summary and meeting link, or a notice saying why there isn't one.
+    fn selected_link(&mut self) -> Option<(String, String)> {
+        let Some(event) = self
+            .selected
+            .and_then(|i| self.display_events().get(i).copied())
+        else {
+            self.notice = Some("Select an event first (j/k)".to_string());
+            return None;
+        };
+        let link = event.meeting_url().map(|url| (event.summary.clone(), url));
+        if link.is_none() {
+            self.notice = Some("No link for this event".to_string());
+        }
+        link
+    }
+
+    /// Arms a timer for the soonest job, with `FOCUS_TICK_SECS` countdown ticks in focus
+    /// mode or while a focus timer runs.
+    fn arm_timer(&mut self) {
+        if !(self.focus || self.timer_until.is_some()) {
+            self.scheduler.cancel(Job::Countdown);
+        } else if !self.scheduler.is_scheduled(Job::Countdown) {
+            self.scheduler.schedule(Job::Countdown, FOCUS_TICK_SECS);
+        }
+        if let Some(delay) = self.scheduler.next_timeout() {
+            set_timeout(delay);
+        }
+    }
+
+    /// Runs a due job; recurring ones schedule their next run.
+    fn run_job(&mut self, job: Job) {
+        match job {
+            Job::Tick => {
+                self.scheduler.schedule(Job::Tick, TIME_TICK_SECS);
+                if self.profile_pending {
+                    // Nothing to fetch until the profile's settings are in
+                } else if !self.ics_url_resolved {
+                    self.fetch_ics_url_from_env();
+                } else {
+                    self.fetch_time();
+                }
+            }
+            Job::Countdown => self.check_timer(),
+            Job::EndPeek => self.peek = false,
+            Job::Refresh => {
+                self.scheduler.schedule(Job::Refresh, self.refresh_secs);
+                self.queue_fetches();
+            }
+            // Fetched on the next tick, like every other queued source
+            Job::Retry(idx) => {
+                let Some(source) = self.sources.get_mut(idx) else {
+                    return;
+                };
+                if source.retry_at.take().is_some() {
+                    log!("Retrying rate-limited {}", source.name);
+                    if !self.fetch_queue.contains(&idx) {
+                        self.fetch_queue.push_back(idx);
+                    }
+                }
+            }
+            // The next tick moves up; it's rescheduled from there
+            Job::Reminder | Job::Boundary => self.scheduler.schedule(Job::Tick, 0.0),
+        }
+    }
+
+    /// Moves up ticks for the soonest reminder and the next event start or end, so they land
+    /// on time instead of up to a tick late.
+    fn schedule_wakeups(&mut self) {
+        let Some(clock) = self.clock.or(self.current_time) else {
+            return;
+        };
+        // Just past the time, so the minute has turned when the clock is read
+        let delay = |at: NaiveDateTime| {
+            (at > clock)
+                .then(|| (at - clock).num_milliseconds() as f64 / 1000.0 + WAKEUP_SLACK_SECS)
+        };
+        let events = self.store.events();
+        let reminder = events
+            .iter()
+            .filter(|e| !self.notified.contains(&reminder::key(e)))
+            .filter_map(|e| reminder::due_at(e, self.remind_minutes, self.travel.minutes_for(e)))
+            .filter_map(delay)
+            .min_by(f64::total_cmp);
+        let boundary = events
+            .iter()
+            .filter(|e| !e.is_all_day && !e.is_overlay)
+            .flat_map(|e| [Some(e.start), e.end])
+            .flatten()
+            .filter_map(delay)
+            .min_by(f64::total_cmp);
+        for (job, delay) in [(Job::Reminder, reminder), (Job::Boundary, boundary)] {
+            match delay {
+                Some(delay) => self.scheduler.schedule(job, delay),
+                None => self.scheduler.cancel(job),
+            }
+        }
+    }
+
+    /// Ends the focus timer once its time is up.
+    fn check_timer(&mut self) {
+        let Some(until) = self.timer_until else {
+            return;
+        };
+        let Some(now) = self.clock.filter(|&now| now >= until) else {
+            return;
+        };
+        self.timer_until = None;
+        let mut notice = "⏱ Focus time is up".to_string();
+        if let Some(event) = self.store.next_event(now) {
+            let when = calendar::fmt_relative_time(
+                event.start,
+                now,
+                self.use_12h_time,
+                self.language,
+                self.day_start,
+            );
+            notice.push_str(&format!(" · {} {}", event.summary, when));
+        }
+        self.notice = Some(notice);
+    }
+
+    /// Selects the first event on a day of this week (0 = Monday), switching to the agenda.
+    fn jump_to_day(&mut self, weekday: u32) {
+        let Some(now) = self.current_time else {
+            return;
+        };
+        let today = self.today(now);
+        let monday = today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64);
+        let day = monday + chrono::Duration::days(weekday as i64);
+        match self.view {
+            View::Week => self.view = View::Agenda,
+            View::Month => {
+                self.month_day = Some(day);
+                self.scroll = 0;
+            }
+            View::List | View::Agenda => {}
+        }
+        let first = self
+            .display_events()
+            .iter()
+            .position(|e| group_date(e, today, self.day_start) == day);
+        match first {
+            Some(idx) => self.selected = Some(idx),
+            None => self.notice = Some(format!("No upcoming events on {}", day.format("%A"))),
+        }
+    }
+
+    /// One-line summary of what's next, shared by compact mode and the `next-event` pipe.
+    fn next_event_line(&self) -> String {
+        if self.sources.iter().all(|s| s.url.is_empty()) && self.ics_url_resolved {
+            return "⚠ No ICS URL configured".to_string();
+        }
+        let Some(now) = self.current_time else {
+            return "📅 ↻".to_string();
+        };
+        let line = match self.store.next_event(now) {
+            Some(event) => calendar::fmt_next_event(
+                event,
+                now,
+                self.use_12h_time,
+                self.language,
+                self.day_start,
+                &self.theme.icons,
+            ),
+            None => match self.sources.iter().find_map(|s| s.error.as_ref()) {
+                Some(err) => return format!("⚠ {}", err),
+                None => format!("📅 {}", self.empty_message()),
+            },
+        };
+        if self.status_glyph {
+            format!(
+                "{} {}",
+                calendar::status_glyph(self.store.events(), now),
+                line
+            )
+        } else {
+            line
+        }
+    }
+
+    /// Runs a pipe command, returning its output and whether to re-render.
+    fn run_pipe_command(&mut self, command: PipeCommand) -> (String, bool) {
+        match command {
+            PipeCommand::CacheClear => {
+                let cmd = cache::clear_command();
+                command::run(&cmd, Ctx::CacheClear);
+                ("Cache cleared, refreshing calendars".to_string(), false)
+            }
+            PipeCommand::NextEvent => (self.next_event_line(), false),
+            PipeCommand::Availability { days } => match self.current_time {
+                Some(now) => (
+                    calendar::fmt_availability(self.store.events(), now, days, self.use_12h_time),
+                    false,
+                ),
+                None => ("Calendar not loaded yet".to_string(), false),
+            },
+            PipeCommand::Propose { minutes, count } => match self.current_time {
+                Some(now) => (self.proposals(now, minutes, count), false),
+                None => ("Calendar not loaded yet".to_string(), false),
+            },
+            PipeCommand::Note { day, text } => {
+                let Some(now) = self.current_time else {
+                    return ("Calendar not loaded yet".to_string(), false);
+                };
+                let today = self.today(now);
+                let date = day.resolve(today);
+                let header = calendar::fmt_day_header(date, today, self.language);
+                let output = if text.is_empty() {
+                    self.notes.remove(&date);
+                    format!("Note cleared for {}", header)
+                } else {
+                    self.notes.insert(date, text);
+                    format!("Note saved for {}", header)
+                };
+                let cmd = notes::write_command(&self.notes);
+                command::run(&cmd, Ctx::NotesWrite);
+                (output, true)
+            }
+            PipeCommand::Agenda { day, json } => {
+                let Some(now) = self.current_time else {
+                    return ("Calendar not loaded yet".to_string(), false);
+                };
+                let today = self.today(now);
+                let date = day.resolve(today);
+                // Past days are dropped, and later ones are only partly expanded or fetched
+                let until = self.window.until(now).unwrap_or(self.expansion_until(now));
+                let day_end = date.succ_opt().map(|d| d.and_time(NaiveTime::MIN));
+                if date < today || day_end.is_none_or(|end| end > until) {
+                    let output = format!("{} is outside the synced range", date);
+                    return (output, false);
+                }
+                let events = self.store.on_day(date);
+                let output = if json {
+                    export::agenda_json(&events, |e| &self.sources[e.source].name)
+                } else {
+                    export::agenda_text(&events, self.use_12h_time)
+                };
+                (output, false)
+            }
+        }
+    }
+
+    /// Free slots for a new meeting within working hours, through the lookahead window.
+    fn proposals(&self, now: NaiveDateTime, minutes: i64, count: usize) -> String {
+        let horizon = now + chrono::Duration::days(PROPOSE_LOOKAHEAD_DAYS);
+        let until = self.window.until(now).unwrap_or(horizon);
+        // Busy is busy whatever the list is filtered to
+        let slots = calendar::propose_times(
+            &self.store.all(),
+            now,
+            minutes,
+            &self.working_hours,
+            until,
+            count,
+        );
+        calendar::fmt_proposals(
+            &slots,
+            minutes,
+            now.date(),
+            self.use_12h_time,
+            self.language,
+        )
+    }
+
+    /// Forces a fresh fetch of every calendar once the cache is gone.
+    fn handle_cache_clear(&mut self, exit_code: Option<i32>, stderr: Vec<u8>) {
+        if exit_code != Some(0) {
+            log!(
+                "Failed to clear cache: {}",
+                String::from_utf8_lossy(&stderr)
+            );
+        }
+        self.scheduler.schedule(Job::Refresh, self.refresh_secs);
+        self.queue_fetches();
+        self.loading = true;
+        self.run_next_fetch();
+    }
+
+    /// Reads the profiles file (`profile_file`, or the default), to apply `profile` from.
+    fn read_profile(&self) {
+        let path = self.layout_config.get("profile_file").map(|p| p.as_str());
+        let cmd = profile::read_command(path);
+        command::run(&cmd, Ctx::ProfileRead);
+    }
+
+    /// Layers the layout config over the selected profile, then starts as usual.
+    fn handle_profile_read(&mut self, exit_code: Option<i32>, stdout: Vec<u8>, stderr: Vec<u8>) {
+        self.profile_pending = false;
+        let name = self
+            .layout_config
+            .get("profile")
+            .cloned()
+            .unwrap_or_default();
+        let result = if exit_code == Some(0) {
+            profile::parse(&String::from_utf8_lossy(&stdout), &name)
+        } else {
+            Err(format!(
+                "Can't read profiles: {}",
+                String::from_utf8_lossy(&stderr).trim()
+            ))
+        };
+        match result {
+            Ok(settings) => {
+                log!("Loaded profile {} ({} settings)", name, settings.len());
+                let merged = profile::merge(settings, &self.layout_config);
+                self.apply_config(Config::from(merged));
+            }
+            Err(err) => {
+                log!("{}", err);
+                self.notice = Some(err);
+            }
+        }
+        self.restore_caches();
+    }
+
     /// Fetches ZJ_CAL_ICS_URL from the environment via shell command.
     /// Called once at startup if ics_url is not set in plugin config.
     fn fetch_ics_url_from_env(&mut self) {
         log!("fetch_ics_url_from_env() - reading ZJ_CAL_ICS_URL");
-        run_command(&["printenv", "ZJ_CAL_ICS_URL"], Ctx::IcsFetchEnv.into_map());
+        command::run(&["printenv", "ZJ_CAL_ICS_URL"], Ctx::IcsFetchEnv);
     }
 
     fn handle_env_fetch(&mut self, exit_code: Option<i32>, stdout: Vec<u8>, stderr: Vec<u8>) {
@@ -243,9 +1742,13 @@ impl State {
         self.ics_url_resolved = true;
         if exit_code == Some(0) {
             let url = String::from_utf8_lossy(&stdout).trim().to_string();
-            if !url.is_empty() {
+            // A saved URL (`persist_url`) may have come in first
+            if !self.sources[0].url.is_empty() {
+                log!("Keeping the saved URL over ZJ_CAL_ICS_URL");
+            } else if !url.is_empty() {
                 log!("Got ICS URL from env var ZJ_CAL_ICS_URL");
-                self.ics_url = url;
+                self.sources[0].url = url;
+                self.restore_cache(0);
             } else {
                 log!("ZJ_CAL_ICS_URL is set but empty");
             }
@@ -255,126 +1758,657 @@ impl State {
         self.fetch_time();
     }
 
-    /// Fetches the current local time and UTC offset via shell command.
+    /// Updates the current local time and UTC offset, then runs the tick's refresh work.
+    /// The time comes from the zone rules when they're known, else from the shell's `date`.
     fn fetch_time(&mut self) {
-        log!("fetch_time() - getting current time");
         self.loading = true;
-        // NOTE: We do this via shell because WASM sandbox doesn't have access to timezone info.
-        run_command(&["date", "+%Y-%m-%d %H:%M %z"], Ctx::TimeFetch.into_map());
+        if self.sync_clock() {
+            self.on_time();
+            return;
+        }
+        log!("fetch_time() - getting current time");
+        // NOTE: The WASM sandbox can't see the host's zone files, so this goes via shell.
+        // The second line is the zone name, used to match the feed's VTIMEZONE definitions.
+        let script = r#"date "+%Y-%m-%d %H:%M:%S %z"; echo "${TZ:-$(readlink /etc/localtime)}""#;
+        command::run(&["sh", "-c", script], Ctx::TimeFetch);
     }
 
-    fn fetch_calendar(&mut self) {
-        if self.ics_url.is_empty() {
+    /// Reads the zone's TZif file once, so later ticks need no `date` command.
+    fn read_zone(&mut self) {
+        log!("read_zone() - reading zone rules");
+        let command = tzif::read_command(self.timezone.as_deref());
+        command::run(&command, Ctx::ZoneRead);
+    }
+
+    /// Sets the clock from the system time (UTC) and the zone rules; false without rules.
+    fn sync_clock(&mut self) -> bool {
+        let Some(ref rules) = self.zone_rules else {
+            return false;
+        };
+        let utc = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()
+            .and_then(|since| chrono::DateTime::from_timestamp(since.as_secs() as i64, 0))
+            .map(|t| t.naive_utc());
+        #[cfg(test)]
+        let utc = self.fake_utc.or(utc);
+        let Some(utc) = utc else {
+            return false;
+        };
+        self.local_zone.utc_offset_minutes = rules.offset_at(utc);
+        self.clock =
+            Some(utc + chrono::Duration::minutes(self.local_zone.utc_offset_minutes as i64));
+        self.current_time = self.clock.and_then(|t| t.with_second(0));
+        true
+    }
+
+    fn fetch_source(&mut self, idx: usize) {
+        let source = &self.sources[idx];
+        if source.url.is_empty() {
             return;
         }
+        let backend = backend::for_source(source);
+        // CalDAV asks for the same range that recurrences are expanded over
+        let range_utc = self.current_time.map(|now| {
+            let offset = chrono::Duration::minutes(self.local_zone.utc_offset_minutes as i64);
+            (
+                self.window.keep_from(now) - offset,
+                self.expansion_until(now) - offset,
+            )
+        });
+        if backend.needs_range() && range_utc.is_none() {
+            log!("fetch_source({}) - waiting for the time", source.name);
+            self.fetch_queue.push_back(idx);
+            return;
+        }
+        // A fresh fetch drops what's left of an earlier chunked one
+        self.sources[idx].chunks = None;
+        let range_utc = match range_utc {
+            Some((from, to)) if backend.needs_range() && self.caldav_chunk_days > 0 => {
+                let mut remaining: VecDeque<_> =
+                    caldav::chunk_range(from, to, self.caldav_chunk_days).into();
+                let first = remaining.pop_front();
+                if !remaining.is_empty() {
+                    let source = &self.sources[idx];
+                    let previous = source.events.iter().chain(&source.occasions).cloned();
+                    self.sources[idx].chunks = Some(source::ChunkedFetch {
+                        remaining,
+                        events: Vec::new(),
+                        previous: previous.collect(),
+                    });
+                }
+                first
+            }
+            range_utc => range_utc,
+        };
+        self.run_fetch(idx, range_utc);
+    }
 
-        let mut curl_args = vec!["curl".to_string(), "-sSfL".to_string()];
-
-        let ctx = if DEBUG_SAVE_ICS {
+    /// Runs the backend's fetch command for a source, over `range_utc` if it takes one.
+    fn run_fetch(&mut self, idx: usize, range_utc: Option<(NaiveDateTime, NaiveDateTime)>) {
+        let source = &self.sources[idx];
+        let backend = backend::for_source(source);
+        let save_to = DEBUG_SAVE_ICS.then(|| {
             let timestamp = self
                 .current_time
                 .map(|t| t.format("%Y-%m-%d-%H-%M").to_string())
                 .unwrap_or_else(|| "unknown".to_string());
-            let path = format!("/tmp/zj-cal/{}.ics", timestamp);
-            log!("fetch_calendar() - saving to {}", path);
-            curl_args.push("--create-dirs".to_string());
-            curl_args.push("--output".to_string());
-            curl_args.push(path.clone());
-            Ctx::IcsFetchFile { path }
-        } else {
-            log!("fetch_calendar()");
-            Ctx::IcsFetch
+            if source.name == config::DEFAULT_CALENDAR {
+                format!("/tmp/zj-cal/{}.ics", timestamp)
+            } else {
+                format!("/tmp/zj-cal/{}-{}.ics", source.name, timestamp)
+            }
+        });
+        let conditional = backend.supports_conditional()
+            && source
+                .parsed_at
+                .zip(self.current_time)
+                .is_some_and(|(parsed, now)| {
+                    now - parsed < chrono::Duration::hours(CONDITIONAL_FETCH_MAX_AGE_HOURS)
+                });
+        let request = backend::FetchRequest {
+            range_utc,
+            max_payload_bytes: self.max_payload_mb * 1024 * 1024,
+            conditional,
+            save_to: save_to.as_deref(),
         };
+        log!("fetch_source({}) - {}", source.name, backend.kind());
+        let command = backend.fetch_command(source, &request);
+        command::run(&command.args, command.ctx);
+    }
+
+    /// Queues every configured source for fetching, skipping ones already pending.
+    fn queue_fetches(&mut self) {
+        for idx in 0..self.sources.len() {
+            let source = &self.sources[idx];
+            if source.url.is_empty() || self.fetch_queue.contains(&idx) {
+                continue;
+            }
+            if source.retry_at.is_some() {
+                log!("{} is rate limited, skipping", source.name);
+                continue;
+            }
+            self.fetch_queue.push_back(idx);
+        }
+    }
+
+    /// Starts the next queued fetch (at most one per tick).
+    fn run_next_fetch(&mut self) {
+        match self.fetch_queue.pop_front() {
+            Some(idx) => self.fetch_source(idx),
+            None => self.loading = false,
+        }
+    }
 
-        curl_args.push("--".to_string());
-        curl_args.push(self.ics_url.clone());
+    fn source_index(&self, name: &str) -> Option<usize> {
+        self.sources.iter().position(|s| s.name == name)
+    }
 
-        let curl_args_ref: Vec<&str> = curl_args.iter().map(|s| s.as_str()).collect();
-        run_command(&curl_args_ref, ctx.into_map());
+    fn save_hidden_series(&self) {
+        let cmd = cache::write_hidden_series_command(&self.hidden_series);
+        command::run(&cmd, Ctx::HiddenSeriesWrite);
+    }
+
+    /// Rebuilds the merged events from every source.
+    fn merge_events(&mut self) {
+        self.ticked = None;
+        self.store = EventStore::merge(
+            &self.sources,
+            &self.hidden,
+            &self.hidden_series,
+            self.collapse_series,
+            self.current_time,
+            &self.window,
+        );
     }
 
     fn handle_ics_output(
         &mut self,
+        source: &str,
         exit_code: Option<i32>,
         stdout: Vec<u8>,
         stderr: Vec<u8>,
-        action_label: &str,
-        error_label: &str,
     ) {
-        self.loading = false;
-        if exit_code == Some(0) {
-            log!("{} ({} bytes)", action_label, stdout.len());
-            match calendar::parse_ics(&stdout, self.utc_offset_minutes) {
-                Ok(events) => {
-                    self.events = calendar::filter_future(events, self.current_time, 20);
-                    self.error = None;
+        self.loading = !self.fetch_queue.is_empty();
+        let Some(idx) = self.source_index(source) else {
+            log!("Unknown source: {}", source);
+            return;
+        };
+        let backend = backend::for_source(&self.sources[idx]);
+        let (action_label, error_label) = backend.labels();
+        let stderr = self.record_fetch_info(idx, &stderr);
+        // curl can only refuse early when the size is announced, so check the body too
+        let too_large = stdout.len() as u64 > self.max_payload_mb * 1024 * 1024;
+        let not_modified = self.sources[idx]
+            .fetch_info
+            .as_ref()
+            .is_some_and(|info| info.http_code == source::HTTP_NOT_MODIFIED);
+        if exit_code == Some(0) && not_modified {
+            log!("{} not modified, keeping parsed events", source);
+            let source = &mut self.sources[idx];
+            source.error = None;
+            source.last_fetched = self.current_time;
+        } else if exit_code == Some(source::CURL_FILESIZE_EXCEEDED) || too_large {
+            log!("{} exceeds {} MB, ignoring", source, self.max_payload_mb);
+            let err = format!("Feed exceeds {} MB (max_payload_mb)", self.max_payload_mb);
+            self.set_source_error(idx, err);
+        } else if exit_code == Some(0) {
+            log!("{} from {} ({} bytes)", action_label, source, stdout.len());
+            match backend
+                .extract(stdout)
+                .and_then(|ics| calendar::parse_ics(&ics, &self.local_zone))
+            {
+                Ok(mut events) => {
+                    for event in &mut events {
+                        event.source = idx;
+                    }
+                    if let Some(now) = self.current_time {
+                        let until = self.expansion_until(now);
+                        events =
+                            calendar::expand_recurring(events, self.window.keep_from(now), until);
+                    }
+                    let offset =
+                        chrono::Duration::minutes(self.local_zone.utc_offset_minutes as i64);
+                    if let Some(chunks) = self.sources[idx].chunks.as_mut() {
+                        chunks.events.extend(events);
+                        let mut seen = HashSet::new();
+                        chunks.events.retain(|e| seen.insert(reminder::key(e)));
+                        // Ranges still to come keep the previous fetch's events until they're in
+                        let pending_from = chunks.remaining.front().map(|&(from, _)| from + offset);
+                        let pending = chunks
+                            .previous
+                            .iter()
+                            .filter(|e| pending_from.is_some_and(|from| e.start >= from))
+                            .filter(|e| seen.insert(reminder::key(e)));
+                        events = chunks.events.iter().chain(pending).cloned().collect();
+                    }
+                    let events = calendar::filter_events(events, &self.filter);
+                    let (occasions, events) = self.split_occasions(idx, events);
+                    let events = calendar::filter_future(events, self.current_time, &self.window);
+                    let source = &mut self.sources[idx];
+                    source.events = if source.overlay {
+                        calendar::anonymize(events)
+                    } else if self.show_private {
+                        events
+                    } else {
+                        calendar::hide_private(events)
+                    };
+                    source.occasions = if self.show_private {
+                        occasions
+                    } else {
+                        calendar::hide_private(occasions)
+                    };
+                    source.error = None;
+                    source.last_fetched = self.current_time;
+                    source.parsed_at = self.current_time;
+                    source.validators = source
+                        .fetch_info
+                        .as_ref()
+                        .map(|info| info.validators.clone())
+                        .unwrap_or_default();
+                    self.merge_events();
+                    // Chunks so far are shown while the next one is asked for
+                    let next = self.sources[idx]
+                        .chunks
+                        .as_mut()
+                        .and_then(|chunks| chunks.remaining.pop_front());
+                    if let Some(range) = next {
+                        log!("{}: fetching the next chunk", self.sources[idx].name);
+                        self.loading = true;
+                        self.run_fetch(idx, Some(range));
+                        return;
+                    }
+                    self.sources[idx].chunks = None;
+                    self.save_cache(idx);
+                    self.digest_pending = true;
                 }
                 Err(e) => {
-                    log!("Failed to parse ICS: {}", e);
-                    self.error = Some(e);
+                    log!("Failed to parse ICS from {}: {}", source, e);
+                    self.set_source_error(idx, e);
                 }
             }
         } else {
-            let err_msg = String::from_utf8_lossy(&stderr);
-            self.error = Some(format!("{}: {}", error_label, err_msg));
+            self.set_source_error(idx, format!("{}: {}", error_label, stderr));
+        }
+        if idx == 0 {
+            self.finish_url_trial();
+        }
+        // One digest per refresh, once every queued calendar is in
+        if !self.loading {
+            self.write_digest();
+        }
+    }
+
+    /// Writes the digest feed, if one is configured and events changed since the last write.
+    fn write_digest(&mut self) {
+        let (Some(path), Some(now)) = (self.digest_file.as_deref(), self.current_time) else {
+            return;
+        };
+        if !std::mem::take(&mut self.digest_pending) {
+            return;
+        }
+        let events = export::digest_events(&self.store, now.date(), self.digest_days);
+        log!("Writing digest of {} event(s) to {}", events.len(), path);
+        let cmd = export::write_command(Some(path), export::to_ics(export::DIGEST_NAME, &events));
+        command::run(&cmd, Ctx::DigestWrite);
+    }
+
+    /// Stores curl's fetch info for a source, returning the rest of stderr.
+    fn record_fetch_info(&mut self, idx: usize, stderr: &[u8]) -> String {
+        let (info, rest) = FetchInfo::extract(stderr);
+        if let Some(ref info) = info {
+            if info.redirects > 0 {
+                log!(
+                    "{} redirected {} time(s) (HTTP {})",
+                    self.sources[idx].name,
+                    info.redirects,
+                    info.http_code
+                );
+            }
+            let offset = self.local_zone.utc_offset_minutes;
+            self.sources[idx].retry_at =
+                self.current_time.and_then(|now| info.retry_at(now, offset));
+            if let Some((at, now)) = self.sources[idx].retry_at.zip(self.current_time) {
+                let delay = (at - now).num_seconds() as f64;
+                self.scheduler.schedule(Job::Retry(idx), delay);
+            }
+            self.sources[idx].fetch_info = Some(info.clone());
+        }
+        rest
+    }
+
+    /// Reads cached events for every configured calendar (rendered until the first fetch),
+    /// plus the pins and day notes.
+    fn restore_caches(&mut self) {
+        for idx in 0..self.sources.len() {
+            self.restore_cache(idx);
+        }
+        let cmd = notes::read_command();
+        command::run(&cmd, Ctx::NotesRead);
+        let cmd = cache::read_pins_command();
+        command::run(&cmd, Ctx::PinsRead);
+        let cmd = cache::read_hidden_series_command();
+        command::run(&cmd, Ctx::HiddenSeriesRead);
+        if self.persist_url {
+            let cmd = cache::read_url_command();
+            command::run(&cmd, Ctx::UrlRead);
+        }
+    }
+
+    fn restore_cache(&mut self, idx: usize) {
+        let source = &self.sources[idx];
+        if source.url.is_empty() {
+            return;
+        }
+        let cmd = cache::read_command(&source.name);
+        let ctx = Ctx::CacheRead {
+            calendar: source.name.clone(),
+        };
+        command::run(&cmd, ctx);
+    }
+
+    fn handle_cache_read(&mut self, calendar: &str, exit_code: Option<i32>, stdout: Vec<u8>) {
+        let Some(idx) = self.source_index(calendar) else {
+            return;
+        };
+        let settings = self.cache_settings(idx);
+        let source = &mut self.sources[idx];
+        // A missing cache file is normal; a fresh fetch always wins over the cache
+        if exit_code != Some(0) || source.last_fetched.is_some() {
+            return;
+        }
+        if let Some(cached) = cache::decode(&stdout, &source.url, &settings) {
+            log!(
+                "Restored {} cached events for {}",
+                cached.events.len(),
+                calendar
+            );
+            source.events = cached.events;
+            source.occasions = cached.occasions;
+            // Caches may predate sanitizing
+            for event in source.events.iter_mut().chain(&mut source.occasions) {
+                event.source = idx;
+                event.sanitize();
+            }
+            source.cached_at = cached.fetched_at;
+            source.parsed_at = cached.fetched_at;
+            source.validators = cached.validators;
+            self.merge_events();
         }
     }
 
-    fn handle_ics_fetch(&mut self, exit_code: Option<i32>, stdout: Vec<u8>, stderr: Vec<u8>) {
-        self.handle_ics_output(exit_code, stdout, stderr, "Fetched ICS", "Fetch failed");
+    /// End of the range recurring events are expanded over (and CalDAV queries cover).
+    fn expansion_until(&self, now: NaiveDateTime) -> NaiveDateTime {
+        let horizon = now + chrono::Duration::days(RECURRENCE_LOOKAHEAD_DAYS);
+        let horizon = horizon.max(self.window.occasions_until(now));
+        self.window.until(now).map_or(horizon, |u| u.max(horizon))
+    }
+
+    /// Splits a source's events into birthdays and anniversaries and the rest, once they get
+    /// their own section: every event of `birthday_calendar`, and ones categorized as such.
+    fn split_occasions(
+        &self,
+        idx: usize,
+        events: Vec<calendar::Event>,
+    ) -> (Vec<calendar::Event>, Vec<calendar::Event>) {
+        let source = &self.sources[idx];
+        if self.window.occasion_days == 0 || source.overlay {
+            return (Vec::new(), events);
+        }
+        let all = self.birthday_calendar.as_ref() == Some(&source.name);
+        events.into_iter().partition(|e| all || e.is_occasion())
+    }
+
+    /// Settings that shape the stored events, so a changed filter isn't masked by a 304.
+    fn cache_settings(&self, idx: usize) -> String {
+        let pattern = |re: &Option<regex::Regex>| re.as_ref().map(|re| re.to_string());
+        format!(
+            "overlay={} include={:?} exclude={:?} email={:?} sizes={:?}/{}/{} organized={:?} private={} window={:?} birthdays={:?} lookahead={}",
+            self.sources[idx].overlay,
+            pattern(&self.filter.include),
+            pattern(&self.filter.exclude),
+            self.filter.my_email,
+            self.filter.hide_sizes,
+            self.filter.large_meeting,
+            self.filter.keep_one_on_ones,
+            self.filter.organized,
+            self.show_private,
+            self.window,
+            self.birthday_calendar,
+            RECURRENCE_LOOKAHEAD_DAYS
+        )
+    }
+
+    fn save_cache(&self, idx: usize) {
+        let source = &self.sources[idx];
+        let data = match cache::encode(
+            &source.url,
+            &self.cache_settings(idx),
+            &source.events,
+            &source.occasions,
+            source.parsed_at,
+            &source.validators,
+        ) {
+            Ok(data) => data,
+            Err(e) => {
+                log!("Failed to encode cache for {}: {}", source.name, e);
+                return;
+            }
+        };
+        let cmd = cache::write_command(&source.name, data);
+        let ctx = Ctx::CacheWrite {
+            calendar: source.name.clone(),
+        };
+        command::run(&cmd, ctx);
     }
 
-    fn handle_ics_read_file(&mut self, exit_code: Option<i32>, stdout: Vec<u8>, stderr: Vec<u8>) {
-        self.handle_ics_output(exit_code, stdout, stderr, "Read ICS", "Read failed");
+    /// Records a fetch error. Overlay errors are only logged; the overlay is secondary.
+    fn set_source_error(&mut self, idx: usize, err: String) {
+        let source = &mut self.sources[idx];
+        // A failed chunk ends its fetch; the chunks before it stay listed
+        source.chunks = None;
+        // A rate-limited fetch says when it retries instead of repeating curl's error
+        let err = match source.retry_at {
+            Some(at) => format!(
+                "Rate limited, retrying at {}",
+                calendar::fmt_time(at.hour(), at.minute(), self.use_12h_time)
+            ),
+            None => err,
+        };
+        if source.overlay {
+            log!("Overlay {}", err);
+        } else {
+            source.error = Some(err);
+        }
     }
 
     fn handle_time_fetch(&mut self, exit_code: Option<i32>, stdout: Vec<u8>, stderr: Vec<u8>) {
         if exit_code == Some(0) {
-            // Parse "YYYY-MM-DD HH:MM +/-HHMM" format
-            let output = String::from_utf8_lossy(&stdout).trim().to_string();
-            if let Some((time_str, offset_str)) = output.rsplit_once(' ') {
-                self.current_time = calendar::parse_datetime(time_str);
+            // Parse "YYYY-MM-DD HH:MM:SS +/-HHMM" format, then the zone name on the next line
+            let output = String::from_utf8_lossy(&stdout).to_string();
+            let mut lines = output.lines();
+            if let Some((time_str, offset_str)) =
+                lines.next().and_then(|l| l.trim().rsplit_once(' '))
+            {
+                self.clock = calendar::parse_datetime(time_str);
+                self.current_time = self.clock.and_then(|t| t.with_second(0));
                 if let Some(offset) = calendar::parse_utc_offset(offset_str) {
-                    self.utc_offset_minutes = offset;
+                    self.local_zone.utc_offset_minutes = offset;
                 }
             }
+            self.local_zone.tzid = self
+                .timezone
+                .clone()
+                .or_else(|| lines.next().and_then(LocalZone::parse_tzid));
             log!(
-                "Current time: {:?}, UTC offset: {} min",
+                "Current time: {:?}, UTC offset: {} min, zone: {:?}",
                 self.current_time,
-                self.utc_offset_minutes
+                self.local_zone.utc_offset_minutes,
+                self.local_zone.tzid
             );
-
-            // Fetch calendar when counter reaches 0
-            if self.ticks_until_calendar == 0 {
-                self.ticks_until_calendar = self.calendar_refresh_ticks;
-                self.fetch_calendar();
-            } else {
-                self.ticks_until_calendar -= 1;
-                self.loading = false;
-            }
+            self.on_time();
         } else {
             log!("Failed to get time: {}", String::from_utf8_lossy(&stderr));
             self.loading = false;
         }
     }
 
-    fn handle_ics_fetch_file(&mut self, exit_code: Option<i32>, stderr: Vec<u8>, path: String) {
+    fn handle_zone_read(&mut self, exit_code: Option<i32>, stdout: Vec<u8>, stderr: Vec<u8>) {
+        let (tzid, rules) = tzif::parse_read_output(&stdout);
+        match rules.filter(|_| exit_code == Some(0)) {
+            Some(rules) => {
+                self.local_zone.tzid = self.timezone.clone().or(tzid);
+                self.zone_rules = Some(rules);
+                self.sync_clock();
+                log!(
+                    "Zone rules loaded: {:?}, UTC offset: {} min",
+                    self.local_zone.tzid,
+                    self.local_zone.utc_offset_minutes
+                );
+            }
+            None => log!(
+                "No zone rules, using the date command: {}",
+                String::from_utf8_lossy(&stderr).trim()
+            ),
+        }
+    }
+
+    /// Refresh work once the time is updated: prune, alert, and fetch due calendars.
+    fn on_time(&mut self) {
+        let clock = self.clock.or(self.current_time);
+        let woke = self
+            .last_tick
+            .zip(clock)
+            .is_some_and(|(last, now)| now - last >= chrono::Duration::minutes(WAKE_GAP_MINUTES));
+        self.last_tick = clock;
+        if woke {
+            // Don't wait out the refresh interval that was running when it went to sleep
+            log!("Clock jumped since the last tick (woke from sleep?), refreshing");
+            self.scheduler.cancel(Job::Refresh);
+        }
+
+        // Drop events that ended since the last fetch (and filter restored cache entries).
+        // Within a minute nothing changes unless the data did (which clears `ticked`).
+        if self.ticked.is_none() || self.ticked != self.current_time {
+            self.merge_events();
+            self.check_reminders();
+            self.run_start_actions();
+            self.read_inbox();
+            self.ticked = self.current_time;
+        }
+
+        self.schedule_wakeups();
+
+        // The first tick (after loading or reconfiguring) queues every source; `Job::Refresh`
+        // queues them from then on. Queued sources are fetched one per tick.
+        if !self.scheduler.is_scheduled(Job::Refresh) {
+            self.scheduler.schedule(Job::Refresh, self.refresh_secs);
+            self.queue_fetches();
+        }
+        self.run_next_fetch();
+    }
+
+    fn handle_ics_fetch_file(
+        &mut self,
+        source: &str,
+        exit_code: Option<i32>,
+        stderr: Vec<u8>,
+        path: String,
+    ) {
+        let Some(idx) = self.source_index(source) else {
+            return;
+        };
+        let stderr = self.record_fetch_info(idx, &stderr);
         if exit_code == Some(0) {
-            let read_ctx = Ctx::IcsReadFile { path: path.clone() }.into_map();
-            run_command(&["cat", path.as_str()], read_ctx);
+            let read_ctx = Ctx::IcsFetch {
+                calendar: source.to_string(),
+            };
+            command::run(&["cat", path.as_str()], read_ctx);
         } else {
-            self.loading = false;
-            let err_msg = String::from_utf8_lossy(&stderr);
-            self.error = Some(format!("Fetch failed: {}", err_msg));
+            self.loading = !self.fetch_queue.is_empty();
+            self.set_source_error(idx, format!("Fetch failed: {}", stderr));
+            if idx == 0 {
+                self.finish_url_trial();
+            }
         }
     }
 }
 
-fn truncate(s: &str, max_len: usize) -> String {
-    if s.chars().count() <= max_len {
-        s.to_string()
+/// Display group (day header) for an event; events active today group under today. Timed
+/// events before `day_start` belong to the night before.
+fn group_date(event: &calendar::Event, today: NaiveDate, day_start: NaiveTime) -> NaiveDate {
+    if event.is_active_on(today) {
+        today
+    } else if event.is_all_day {
+        event.start.date()
     } else {
-        let truncated: String = s.chars().take(max_len.saturating_sub(3)).collect();
-        format!("{}...", truncated)
+        calendar::day_of(event.start, day_start)
+    }
+}
+
+/// Adjusts the scroll offset so the selected event (and its day header) fits in `max_lines`.
+fn scroll_to_selection(
+    group_dates: &[NaiveDate],
+    scroll: usize,
+    selected: Option<usize>,
+    max_lines: usize,
+    header_lines: impl Fn(NaiveDate) -> usize,
+) -> usize {
+    let Some(selected) = selected else {
+        return scroll.min(group_dates.len().saturating_sub(1));
+    };
+    let mut scroll = scroll.min(selected);
+    // Lines needed to show events scroll..=selected: one per event plus each day header
+    let lines_needed = |from: usize| {
+        let dates = &group_dates[from..=selected];
+        let headers: usize = dates
+            .windows(2)
+            .filter(|w| w[0] != w[1])
+            .map(|w| header_lines(w[1]))
+            .sum();
+        dates.len() + header_lines(dates[0]) + headers
+    };
+    while scroll < selected && lines_needed(scroll) > max_lines {
+        scroll += 1;
+    }
+    scroll
+}
+
+/// Hides secret URLs in log output.
+fn redact(url: &str) -> &'static str {
+    if url.is_empty() {
+        "unset"
+    } else {
+        "[REDACTED]"
+    }
+}
+
+/// Word-wraps to `width` characters; words longer than a line are split.
+fn wrap(s: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in s.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        loop {
+            let used = line.chars().count();
+            let gap = usize::from(used > 0);
+            if used + gap + word.len() <= width {
+                if gap > 0 {
+                    line.push(' ');
+                }
+                line.extend(word);
+                break;
+            }
+            if used > 0 {
+                lines.push(std::mem::take(&mut line));
+                continue;
+            }
+            lines.push(word.drain(..width).collect());
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
     }
+    lines
 }
@@ -0,0 +1,64 @@
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+
+/// Notes file on the host (expanded by `sh`). Unlike the cache, this is the user's own data.
+const NOTES_FILE: &str = "${XDG_DATA_HOME:-$HOME/.local/share}/zj-cal/notes.txt";
+
+/// Shell command that prints the notes file.
+pub fn read_command() -> Vec<String> {
+    let script = format!("cat \"{}\"", NOTES_FILE);
+    vec!["sh".to_string(), "-c".to_string(), script]
+}
+
+/// Shell command that (over)writes the notes file with `notes`.
+pub fn write_command(notes: &BTreeMap<NaiveDate, String>) -> Vec<String> {
+    let script = format!(
+        "f=\"{}\"; mkdir -p \"$(dirname \"$f\")\" && printf '%s' \"$1\" > \"$f\"",
+        NOTES_FILE
+    );
+    vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        script,
+        "sh".to_string(),
+        encode(notes),
+    ]
+}
+
+/// One `YYYY-MM-DD text` line per day.
+fn encode(notes: &BTreeMap<NaiveDate, String>) -> String {
+    notes
+        .iter()
+        .map(|(date, text)| format!("{} {}\n", date.format("%Y-%m-%d"), text))
+        .collect()
+}
+
+/// Parses the notes file; lines that don't start with a date are skipped.
+pub fn parse(data: &[u8]) -> BTreeMap<NaiveDate, String> {
+    String::from_utf8_lossy(data)
+        .lines()
+        .filter_map(|line| {
+            let (date, text) = line.trim().split_once(' ')?;
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+            Some((date, text.trim().to_string()))
+        })
+        .filter(|(_, text)| !text.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let date = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let notes = BTreeMap::from([
+            (date("2024-01-15"), "Prep slides  for review".to_string()),
+            (date("2024-01-16"), "WFH".to_string()),
+        ]);
+        assert_eq!(parse(encode(&notes).as_bytes()), notes);
+        assert_eq!(write_command(&notes).pop().unwrap(), encode(&notes));
+        assert!(parse(b"someday maybe\n2024-13-01 bad date\n2024-01-15 \n").is_empty());
+    }
+}
@@ -0,0 +1,195 @@
+use chrono::{Duration, NaiveDate};
+
+/// Pipe name for commands, e.g. `zellij pipe --name zj-cal -- cache-clear`.
+pub const PIPE_NAME: &str = "zj-cal";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PipeCommand {
+    /// Wipes the on-disk cache and fetches every calendar again.
+    CacheClear,
+    /// Prints the current or next event on one line (for status bars).
+    NextEvent,
+    /// Prints busy times for the next few days, without event details (for sharing).
+    Availability { days: i64 },
+    /// Lists the next free slots of `minutes` within working hours.
+    Propose { minutes: i64, count: usize },
+    /// Sets the note shown under a day's header (clears it when `text` is empty).
+    Note { day: Day, text: String },
+    /// Prints a day's events from the synced calendars, as text or JSON.
+    Agenda { day: Day, json: bool },
+}
+
+/// A day named in a command: "today", "tomorrow", or "YYYY-MM-DD".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Day {
+    Today,
+    Tomorrow,
+    Date(NaiveDate),
+}
+
+impl Day {
+    fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "today" => Some(Self::Today),
+            "tomorrow" => Some(Self::Tomorrow),
+            s => NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .map(Self::Date),
+        }
+    }
+
+    pub fn resolve(self, today: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Today => today,
+            Self::Tomorrow => today + Duration::days(1),
+            Self::Date(date) => date,
+        }
+    }
+}
+
+/// Days covered by `availability` when no count is given.
+pub const AVAILABILITY_DAYS: i64 = 5;
+
+/// Slots listed by `propose` when no count is given.
+pub const PROPOSE_COUNT: usize = 3;
+
+impl PipeCommand {
+    pub fn parse(payload: &str) -> Result<Self, String> {
+        let mut args = payload.split_whitespace();
+        match args.next() {
+            Some("cache-clear") => Ok(Self::CacheClear),
+            Some("next-event") => Ok(Self::NextEvent),
+            Some("availability") => {
+                let days = match args.next() {
+                    Some(days) => days
+                        .parse()
+                        .ok()
+                        .filter(|days| (1..=31).contains(days))
+                        .ok_or_else(|| format!("Invalid day count: {}", days))?,
+                    None => AVAILABILITY_DAYS,
+                };
+                Ok(Self::Availability { days })
+            }
+            Some("propose") => {
+                let minutes = args
+                    .next()
+                    .ok_or("Usage: propose <minutes> [count]")?
+                    .parse()
+                    .ok()
+                    .filter(|minutes| (5..=8 * 60).contains(minutes))
+                    .ok_or("Duration must be 5-480 minutes")?;
+                let count = match args.next() {
+                    Some(count) => count
+                        .parse()
+                        .ok()
+                        .filter(|count| (1..=20).contains(count))
+                        .ok_or_else(|| format!("Invalid slot count: {}", count))?,
+                    None => PROPOSE_COUNT,
+                };
+                Ok(Self::Propose { minutes, count })
+            }
+            Some("note") => {
+                let day = args
+                    .next()
+                    .ok_or("Usage: note <today|tomorrow|YYYY-MM-DD> [text]")?;
+                let day = Day::parse(day).ok_or_else(|| format!("Invalid day: {}", day))?;
+                let text = args.collect::<Vec<_>>().join(" ");
+                Ok(Self::Note { day, text })
+            }
+            Some("agenda") => {
+                let mut day = Day::Today;
+                let mut json = false;
+                for arg in args {
+                    match arg {
+                        "json" | "--json" => json = true,
+                        "text" => json = false,
+                        arg => {
+                            day = Day::parse(arg).ok_or_else(|| format!("Invalid day: {}", arg))?
+                        }
+                    }
+                }
+                Ok(Self::Agenda { day, json })
+            }
+            Some(cmd) => Err(format!("Unknown command: {}", cmd)),
+            None => Err("Missing command".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            PipeCommand::parse(" cache-clear\n"),
+            Ok(PipeCommand::CacheClear)
+        );
+        assert_eq!(PipeCommand::parse("next-event"), Ok(PipeCommand::NextEvent));
+        assert_eq!(
+            PipeCommand::parse("availability"),
+            Ok(PipeCommand::Availability {
+                days: AVAILABILITY_DAYS
+            })
+        );
+        assert_eq!(
+            PipeCommand::parse("availability 2"),
+            Ok(PipeCommand::Availability { days: 2 })
+        );
+        assert!(PipeCommand::parse("availability 0").is_err());
+        assert!(PipeCommand::parse("availability soon").is_err());
+        assert_eq!(
+            PipeCommand::parse("propose 30"),
+            Ok(PipeCommand::Propose {
+                minutes: 30,
+                count: PROPOSE_COUNT
+            })
+        );
+        assert_eq!(
+            PipeCommand::parse("propose 45 5"),
+            Ok(PipeCommand::Propose {
+                minutes: 45,
+                count: 5
+            })
+        );
+        assert!(PipeCommand::parse("propose").is_err());
+        assert!(PipeCommand::parse("propose 0").is_err());
+        assert!(PipeCommand::parse("propose 30 many").is_err());
+        assert_eq!(
+            PipeCommand::parse("note tomorrow Prep  slides"),
+            Ok(PipeCommand::Note {
+                day: Day::Tomorrow,
+                text: "Prep slides".to_string()
+            })
+        );
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        assert_eq!(
+            PipeCommand::parse("note 2024-01-15"),
+            Ok(PipeCommand::Note {
+                day: Day::Date(date),
+                text: String::new()
+            })
+        );
+        assert_eq!(Day::Tomorrow.resolve(date), date + Duration::days(1));
+        assert!(PipeCommand::parse("note").is_err());
+        assert!(PipeCommand::parse("note someday text").is_err());
+        assert_eq!(
+            PipeCommand::parse("agenda"),
+            Ok(PipeCommand::Agenda {
+                day: Day::Today,
+                json: false
+            })
+        );
+        assert_eq!(
+            PipeCommand::parse("agenda 2024-01-15 json"),
+            Ok(PipeCommand::Agenda {
+                day: Day::Date(date),
+                json: true
+            })
+        );
+        assert!(PipeCommand::parse("agenda 2024-13-01").is_err());
+        assert!(PipeCommand::parse("explode").is_err());
+        assert!(PipeCommand::parse("").is_err());
+    }
+}
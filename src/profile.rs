@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+
+/// Profiles file on the host (expanded by `sh`), overridable with `profile_file`.
+const PROFILE_FILE: &str = "${XDG_CONFIG_HOME:-$HOME/.config}/zj-cal/profiles.conf";
+
+/// Shell command that prints the profiles file.
+pub fn read_command(path: Option<&str>) -> Vec<String> {
+    let script = format!("cat -- \"${{1:-{}}}\"", PROFILE_FILE);
+    vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        script,
+        "sh".to_string(),
+        path.unwrap_or_default().to_string(),
+    ]
+}
+
+/// Settings of profile `name` from a profiles file: `[name]` sections of `key = value` lines.
+/// Lines starting with `#` are comments; values may be double-quoted.
+pub fn parse(data: &str, name: &str) -> Result<BTreeMap<String, String>, String> {
+    let mut settings = None;
+    for line in data.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            if settings.is_some() {
+                break;
+            }
+            if section.trim() == name {
+                settings = Some(BTreeMap::new());
+            }
+            continue;
+        }
+        let Some(ref mut settings) = settings else {
+            continue;
+        };
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid profile line: {}", line))?;
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+        settings.insert(key.trim().to_string(), value.to_string());
+    }
+    settings.ok_or_else(|| format!("Profile not found: {}", name))
+}
+
+/// Layers the layout's own settings over a profile's, so each pane can still override.
+pub fn merge(
+    mut profile: BTreeMap<String, String>,
+    layout: &BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    profile.extend(layout.iter().map(|(k, v)| (k.clone(), v.clone())));
+    profile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    const PROFILES: &str = indoc! {r#"
+        # Shared between panes
+        [work]
+        ics_url = "https://example.com/work.ics"
+        http_header = Authorization: Bearer abc=
+        filter_exclude = (?i)^focus
+
+        [statusline]
+        compact = true
+    "#};
+
+    #[test]
+    fn test_parse() {
+        let work = parse(PROFILES, "work").unwrap();
+        assert_eq!(work["ics_url"], "https://example.com/work.ics");
+        assert_eq!(work["http_header"], "Authorization: Bearer abc=");
+        assert_eq!(work["filter_exclude"], "(?i)^focus");
+        assert!(!work.contains_key("compact"));
+
+        assert_eq!(parse(PROFILES, "statusline").unwrap()["compact"], "true");
+        assert!(parse(PROFILES, "home").is_err());
+        assert!(parse("[work]\nnot a setting", "work").is_err());
+    }
+
+    #[test]
+    fn test_merge() {
+        let profile = parse(PROFILES, "work").unwrap();
+        let layout = BTreeMap::from([
+            ("profile".to_string(), "work".to_string()),
+            ("filter_exclude".to_string(), "lunch".to_string()),
+        ]);
+        let merged = merge(profile, &layout);
+        assert_eq!(merged["ics_url"], "https://example.com/work.ics");
+        assert_eq!(merged["filter_exclude"], "lunch");
+    }
+}
@@ -0,0 +1,52 @@
+use qrcode::{Color, QrCode};
+
+/// Light modules around the code, so scanners can find its edges.
+const QUIET_ZONE: usize = 2;
+
+/// Renders `data` as a QR code in half-block characters (two module rows per line).
+/// Light modules are drawn filled, so the code reads correctly on dark terminal themes.
+pub fn render(data: &str) -> Result<Vec<String>, String> {
+    let code = QrCode::new(data).map_err(|e| format!("Can't make QR code: {}", e))?;
+    let width = code.width();
+    let colors = code.to_colors();
+    let size = width + 2 * QUIET_ZONE;
+    let is_light = |x: usize, y: usize| {
+        let (Some(x), Some(y)) = (x.checked_sub(QUIET_ZONE), y.checked_sub(QUIET_ZONE)) else {
+            return true;
+        };
+        x >= width || y >= width || colors[y * width + x] == Color::Light
+    };
+
+    let lines = (0..size)
+        .step_by(2)
+        .map(|y| {
+            (0..size)
+                .map(
+                    |x| match (is_light(x, y), y + 1 < size && is_light(x, y + 1)) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    },
+                )
+                .collect()
+        })
+        .collect();
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let lines = render("https://meet.google.com/abc-defg-hij").unwrap();
+        let size = lines[0].chars().count();
+        // Square once rows are paired up, framed by the quiet zone
+        assert_eq!(lines.len(), size.div_ceil(2));
+        assert!(lines[0].chars().all(|c| c == '█'));
+        assert!(lines.iter().all(|l| l.chars().count() == size));
+        assert!(lines[..lines.len() - 1].iter().all(|l| l.starts_with("██")));
+    }
+}
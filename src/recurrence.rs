@@ -0,0 +1,609 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Weekday};
+use serde::{Deserialize, Serialize};
+
+/// Upper bound on recurrence periods walked per rule (guards against runaway expansion).
+const MAX_PERIODS: i64 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// Recurrence data for a repeating event (series master or expanded occurrence).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recurrence {
+    pub rule: Option<RRule>,
+    pub exdates: Vec<NaiveDateTime>,
+    pub rdates: Vec<NaiveDateTime>,
+}
+
+/// A parsed RRULE. Supports FREQ, INTERVAL, BYDAY, BYMONTHDAY, BYMONTH, UNTIL, and COUNT.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RRule {
+    pub freq: Frequency,
+    pub interval: u32,
+    /// Weekdays, with an optional ordinal for monthly/yearly rules (e.g., 2TU, -1FR).
+    pub by_day: Vec<(Option<i32>, Weekday)>,
+    pub by_month_day: Vec<i32>,
+    pub by_month: Vec<u32>,
+    pub until: Option<NaiveDateTime>,
+    pub count: Option<u32>,
+}
+
+impl RRule {
+    /// Parses an RRULE value (e.g., "FREQ=WEEKLY;BYDAY=MO,WE;UNTIL=20240301T000000Z").
+    /// A UTC `UNTIL` is converted to local time with `utc_offset_minutes`.
+    pub fn parse(value: &str, utc_offset_minutes: i32) -> Result<Self, String> {
+        let mut freq = None;
+        let mut rule = RRule {
+            freq: Frequency::Daily,
+            interval: 1,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+            until: None,
+            count: None,
+        };
+
+        for part in value.split(';').filter(|p| !p.is_empty()) {
+            let (key, val) = part
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid RRULE part: {}", part))?;
+            let invalid = || format!("Invalid RRULE {}: {}", key, val);
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    freq = Some(match val.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(format!("Unsupported FREQ: {}", val)),
+                    })
+                }
+                "INTERVAL" => rule.interval = val.parse().map_err(|_| invalid())?,
+                "COUNT" => rule.count = Some(val.parse().map_err(|_| invalid())?),
+                "UNTIL" => {
+                    rule.until = Some(parse_until(val, utc_offset_minutes).ok_or_else(invalid)?)
+                }
+                "BYDAY" => {
+                    rule.by_day = val
+                        .split(',')
+                        .map(parse_by_day)
+                        .collect::<Option<_>>()
+                        .ok_or_else(invalid)?
+                }
+                "BYMONTHDAY" => {
+                    rule.by_month_day = val
+                        .split(',')
+                        .map(|d| d.parse().ok())
+                        .collect::<Option<_>>()
+                        .ok_or_else(invalid)?
+                }
+                "BYMONTH" => {
+                    rule.by_month = val
+                        .split(',')
+                        .map(|m| m.parse().ok())
+                        .collect::<Option<_>>()
+                        .ok_or_else(invalid)?
+                }
+                _ => {} // WKST, BYSETPOS, etc. are ignored
+            }
+        }
+
+        rule.freq = freq.ok_or("RRULE missing FREQ")?;
+        rule.interval = rule.interval.max(1);
+        Ok(rule)
+    }
+
+    /// Returns occurrence start times in `[from, to)` for a series starting at `dtstart`.
+    pub fn occurrences(
+        &self,
+        dtstart: NaiveDateTime,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+    ) -> Vec<NaiveDateTime> {
+        let mut result = Vec::new();
+        let mut seen = 0;
+        let start_date = dtstart.date();
+
+        // Without COUNT, earlier periods can't affect the result, so skip ahead
+        let first_period = if self.count.is_none() {
+            self.periods_between(start_date, from.date())
+                .saturating_sub(1)
+                .max(0)
+        } else {
+            0
+        };
+
+        for period in first_period..first_period + MAX_PERIODS {
+            let Some(period_start) = self.period_start(start_date, period) else {
+                break;
+            };
+            if period_start >= to.date() + Duration::days(1) {
+                break;
+            }
+
+            for date in self.period_dates(start_date, period_start) {
+                let occurrence = date.and_time(dtstart.time());
+                if occurrence < dtstart {
+                    continue;
+                }
+                if self.until.is_some_and(|until| occurrence > until)
+                    || self.count.is_some_and(|count| seen >= count)
+                {
+                    return result;
+                }
+                seen += 1;
+                if occurrence >= to {
+                    return result;
+                }
+                if occurrence >= from {
+                    result.push(occurrence);
+                }
+            }
+        }
+        result
+    }
+
+    /// Number of whole periods between two dates (floor).
+    fn periods_between(&self, start: NaiveDate, date: NaiveDate) -> i64 {
+        let interval = self.interval as i64;
+        let periods = match self.freq {
+            Frequency::Daily => (date - start).num_days(),
+            Frequency::Weekly => (week_start(date) - week_start(start)).num_days() / 7,
+            Frequency::Monthly => month_index(date) - month_index(start),
+            Frequency::Yearly => (date.year() - start.year()) as i64,
+        };
+        periods / interval
+    }
+
+    /// First day of the n-th period of the series.
+    fn period_start(&self, start: NaiveDate, period: i64) -> Option<NaiveDate> {
+        let steps = period * self.interval as i64;
+        match self.freq {
+            Frequency::Daily => start.checked_add_signed(Duration::days(steps)),
+            Frequency::Weekly => week_start(start).checked_add_signed(Duration::weeks(steps)),
+            Frequency::Monthly => {
+                let index = month_index(start) + steps;
+                NaiveDate::from_ymd_opt(
+                    index.div_euclid(12) as i32,
+                    index.rem_euclid(12) as u32 + 1,
+                    1,
+                )
+            }
+            Frequency::Yearly => NaiveDate::from_ymd_opt(start.year() + steps as i32, 1, 1),
+        }
+    }
+
+    /// Candidate dates within one period, in chronological order.
+    fn period_dates(&self, start: NaiveDate, period_start: NaiveDate) -> Vec<NaiveDate> {
+        let mut dates: Vec<NaiveDate> = match self.freq {
+            Frequency::Daily => vec![period_start],
+            Frequency::Weekly => {
+                let weekdays = if self.by_day.is_empty() {
+                    vec![start.weekday()]
+                } else {
+                    self.by_day.iter().map(|(_, wd)| *wd).collect()
+                };
+                weekdays
+                    .iter()
+                    .map(|wd| period_start + Duration::days(wd.num_days_from_monday() as i64))
+                    .collect()
+            }
+            Frequency::Monthly => {
+                self.month_dates(start, period_start.year(), period_start.month())
+            }
+            Frequency::Yearly => {
+                let months = if self.by_month.is_empty() {
+                    vec![start.month()]
+                } else {
+                    self.by_month.clone()
+                };
+                months
+                    .iter()
+                    .flat_map(|&m| self.month_dates(start, period_start.year(), m))
+                    .collect()
+            }
+        };
+
+        // BY* rules limit frequencies they don't expand
+        if self.freq == Frequency::Daily && !self.by_day.is_empty() {
+            dates.retain(|d| self.by_day.iter().any(|(_, wd)| *wd == d.weekday()));
+        }
+        if matches!(self.freq, Frequency::Daily | Frequency::Weekly)
+            && !self.by_month_day.is_empty()
+        {
+            dates.retain(|d| {
+                self.by_month_day
+                    .iter()
+                    .any(|&md| month_day(d.year(), d.month(), md) == Some(*d))
+            });
+        }
+        if !self.by_month.is_empty() {
+            dates.retain(|d| self.by_month.contains(&d.month()));
+        }
+
+        dates.sort();
+        dates.dedup();
+        dates
+    }
+
+    /// Dates in a month matching BYDAY/BYMONTHDAY (or the series start's day of month).
+    fn month_dates(&self, start: NaiveDate, year: i32, month: u32) -> Vec<NaiveDate> {
+        let by_month_day: Vec<NaiveDate> = self
+            .by_month_day
+            .iter()
+            .filter_map(|&md| month_day(year, month, md))
+            .collect();
+
+        if self.by_day.is_empty() {
+            if by_month_day.is_empty() {
+                return NaiveDate::from_ymd_opt(year, month, start.day())
+                    .into_iter()
+                    .collect();
+            }
+            return by_month_day;
+        }
+
+        let by_day: Vec<NaiveDate> = self
+            .by_day
+            .iter()
+            .flat_map(|&(ordinal, wd)| match ordinal {
+                Some(n) => nth_weekday(year, month, wd, n).into_iter().collect(),
+                None => weekdays_in_month(year, month, wd),
+            })
+            .collect();
+
+        if by_month_day.is_empty() {
+            by_day
+        } else {
+            by_day
+                .into_iter()
+                .filter(|d| by_month_day.contains(d))
+                .collect()
+        }
+    }
+}
+
+/// Parses an UNTIL value: a DATE, a floating DATE-TIME, or a UTC DATE-TIME.
+fn parse_until(value: &str, utc_offset_minutes: i32) -> Option<NaiveDateTime> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        let dt = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(dt + Duration::minutes(utc_offset_minutes as i64));
+    }
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .ok()
+        .or_else(|| {
+            // A DATE UNTIL includes that whole day
+            let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+            date.and_hms_opt(23, 59, 59)
+        })
+}
+
+/// Parses a BYDAY entry (e.g., "MO", "2TU", "-1FR").
+fn parse_by_day(value: &str) -> Option<(Option<i32>, Weekday)> {
+    let value = value.trim();
+    // Feeds send any text here; a split inside a multibyte character isn't a weekday
+    let split = value.len().checked_sub(2)?;
+    let (ordinal, day) = (value.get(..split)?, value.get(split..)?);
+    let weekday = match day.to_ascii_uppercase().as_str() {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    };
+    let ordinal = match ordinal.trim_start_matches('+') {
+        "" => None,
+        n => Some(n.parse().ok()?),
+    };
+    Some((ordinal, weekday))
+}
+
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+fn month_index(date: NaiveDate) -> i64 {
+    date.year() as i64 * 12 + date.month0() as i64
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .and_then(|d| d.pred_opt())
+        .map(|d| d.day())
+        .unwrap_or(28)
+}
+
+/// Day of month, where negative values count from the end (-1 = last day).
+fn month_day(year: i32, month: u32, day: i32) -> Option<NaiveDate> {
+    let day = if day < 0 {
+        days_in_month(year, month) as i32 + day + 1
+    } else {
+        day
+    };
+    NaiveDate::from_ymd_opt(year, month, u32::try_from(day).ok()?)
+}
+
+fn weekdays_in_month(year: i32, month: u32, weekday: Weekday) -> Vec<NaiveDate> {
+    (1..=days_in_month(year, month))
+        .filter_map(|d| NaiveDate::from_ymd_opt(year, month, d))
+        .filter(|d| d.weekday() == weekday)
+        .collect()
+}
+
+/// n-th weekday of a month, where negative values count from the end (-1 = last).
+fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: i32) -> Option<NaiveDate> {
+    let days = weekdays_in_month(year, month, weekday);
+    let index = if n < 0 {
+        days.len().checked_sub(n.unsigned_abs() as usize)?
+    } else {
+        (n as usize).checked_sub(1)?
+    };
+    days.get(index).copied()
+}
+
+/// Formats a rule for display.
+/// (e.g., "daily", "every 2 weeks on mon/wed", "monthly on the 2nd tue")
+pub fn fmt_rule(rule: &RRule) -> String {
+    let unit = match rule.freq {
+        Frequency::Daily => "day",
+        Frequency::Weekly => "week",
+        Frequency::Monthly => "month",
+        Frequency::Yearly => "year",
+    };
+    let mut text = match (rule.interval, rule.freq) {
+        (1, Frequency::Daily) => "daily".to_string(),
+        (1, _) => format!("{}ly", unit),
+        (n, _) => format!("every {} {}s", n, unit),
+    };
+
+    if !rule.by_day.is_empty() {
+        let days: Vec<String> = rule
+            .by_day
+            .iter()
+            .map(|(ordinal, wd)| {
+                let day = weekday_name(*wd);
+                match ordinal {
+                    Some(-1) => format!("last {}", day),
+                    Some(n) => format!("{} {}", ordinal_suffix(*n), day),
+                    None => day.to_string(),
+                }
+            })
+            .collect();
+        let on = if rule.by_day.iter().any(|(o, _)| o.is_some()) {
+            " on the "
+        } else {
+            " on "
+        };
+        text.push_str(on);
+        text.push_str(&days.join("/"));
+    } else if !rule.by_month_day.is_empty() {
+        let days: Vec<String> = rule
+            .by_month_day
+            .iter()
+            .map(|&d| ordinal_suffix(d))
+            .collect();
+        text.push_str(" on the ");
+        text.push_str(&days.join("/"));
+    }
+    text
+}
+
+fn weekday_name(wd: Weekday) -> &'static str {
+    match wd {
+        Weekday::Mon => "mon",
+        Weekday::Tue => "tue",
+        Weekday::Wed => "wed",
+        Weekday::Thu => "thu",
+        Weekday::Fri => "fri",
+        Weekday::Sat => "sat",
+        Weekday::Sun => "sun",
+    }
+}
+
+fn ordinal_suffix(n: i32) -> String {
+    let suffix = match (n.abs() % 10, n.abs() % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::parse_datetime;
+
+    fn dt(s: &str) -> NaiveDateTime {
+        parse_datetime(s).unwrap()
+    }
+
+    fn occurrences(rule: &str, dtstart: &str, from: &str, to: &str) -> Vec<String> {
+        RRule::parse(rule, 0)
+            .unwrap()
+            .occurrences(dt(dtstart), dt(from), dt(to))
+            .iter()
+            .map(|d| d.format("%Y-%m-%d %H:%M").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let rule = RRule::parse("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,-1FR;COUNT=5", 0).unwrap();
+        assert_eq!(rule.freq, Frequency::Weekly);
+        assert_eq!(rule.interval, 2);
+        assert_eq!(
+            rule.by_day,
+            vec![(None, Weekday::Mon), (Some(-1), Weekday::Fri)]
+        );
+        assert_eq!(rule.count, Some(5));
+
+        let rule = RRule::parse("FREQ=DAILY;UNTIL=20240120T150000Z", -300).unwrap();
+        assert_eq!(rule.until, Some(dt("2024-01-20 10:00")));
+
+        assert!(RRule::parse("INTERVAL=2", 0).is_err());
+        assert!(RRule::parse("FREQ=HOURLY", 0).is_err());
+
+        // Non-ASCII BYDAY values are rejected, not split mid-character
+        for value in ["→", "1→", "MÖ", "→MO"] {
+            assert_eq!(parse_by_day(value), None, "{}", value);
+            let _ = RRule::parse(&format!("FREQ=WEEKLY;BYDAY={}", value), 0);
+        }
+    }
+
+    #[test]
+    fn test_daily() {
+        assert_eq!(
+            occurrences(
+                "FREQ=DAILY;COUNT=3",
+                "2024-01-15 09:00",
+                "2024-01-01 00:00",
+                "2024-02-01 00:00"
+            ),
+            vec!["2024-01-15 09:00", "2024-01-16 09:00", "2024-01-17 09:00"]
+        );
+        // Window clips output; interval skips days
+        assert_eq!(
+            occurrences(
+                "FREQ=DAILY;INTERVAL=2",
+                "2024-01-01 09:00",
+                "2024-01-10 00:00",
+                "2024-01-14 00:00"
+            ),
+            vec!["2024-01-11 09:00", "2024-01-13 09:00"]
+        );
+        // BYDAY limits daily rules (weekdays only)
+        assert_eq!(
+            occurrences(
+                "FREQ=DAILY;BYDAY=MO,TU,WE,TH,FR",
+                "2024-01-19 09:00",
+                "2024-01-19 00:00",
+                "2024-01-23 00:00"
+            ),
+            vec!["2024-01-19 09:00", "2024-01-22 09:00"]
+        );
+    }
+
+    #[test]
+    fn test_weekly() {
+        // Mon/Wed standup, started long ago
+        assert_eq!(
+            occurrences(
+                "FREQ=WEEKLY;BYDAY=MO,WE",
+                "2020-03-02 10:00",
+                "2024-01-15 00:00",
+                "2024-01-22 00:00"
+            ),
+            vec!["2024-01-15 10:00", "2024-01-17 10:00"]
+        );
+        // Biweekly, no BYDAY: uses DTSTART weekday
+        assert_eq!(
+            occurrences(
+                "FREQ=WEEKLY;INTERVAL=2",
+                "2024-01-02 10:00",
+                "2024-01-01 00:00",
+                "2024-02-01 00:00"
+            ),
+            vec!["2024-01-02 10:00", "2024-01-16 10:00", "2024-01-30 10:00"]
+        );
+        // UNTIL is inclusive
+        assert_eq!(
+            occurrences(
+                "FREQ=WEEKLY;UNTIL=20240116T100000",
+                "2024-01-02 10:00",
+                "2024-01-01 00:00",
+                "2024-02-01 00:00"
+            ),
+            vec!["2024-01-02 10:00", "2024-01-09 10:00", "2024-01-16 10:00"]
+        );
+    }
+
+    #[test]
+    fn test_monthly() {
+        // 2nd Tuesday
+        assert_eq!(
+            occurrences(
+                "FREQ=MONTHLY;BYDAY=2TU",
+                "2024-01-09 14:00",
+                "2024-01-01 00:00",
+                "2024-04-01 00:00"
+            ),
+            vec!["2024-01-09 14:00", "2024-02-13 14:00", "2024-03-12 14:00"]
+        );
+        // Last day of month
+        assert_eq!(
+            occurrences(
+                "FREQ=MONTHLY;BYMONTHDAY=-1",
+                "2024-01-31 09:00",
+                "2024-01-01 00:00",
+                "2024-04-01 00:00"
+            ),
+            vec!["2024-01-31 09:00", "2024-02-29 09:00", "2024-03-31 09:00"]
+        );
+        // Day 31 is skipped in shorter months
+        assert_eq!(
+            occurrences(
+                "FREQ=MONTHLY",
+                "2024-01-31 09:00",
+                "2024-01-01 00:00",
+                "2024-04-01 00:00"
+            ),
+            vec!["2024-01-31 09:00", "2024-03-31 09:00"]
+        );
+    }
+
+    #[test]
+    fn test_yearly() {
+        assert_eq!(
+            occurrences(
+                "FREQ=YEARLY",
+                "2020-02-29 00:00",
+                "2020-01-01 00:00",
+                "2025-01-01 00:00"
+            ),
+            vec!["2020-02-29 00:00", "2024-02-29 00:00"]
+        );
+        // Thanksgiving: 4th Thursday of November
+        assert_eq!(
+            occurrences(
+                "FREQ=YEARLY;BYMONTH=11;BYDAY=4TH",
+                "2023-11-23 00:00",
+                "2024-01-01 00:00",
+                "2025-01-01 00:00"
+            ),
+            vec!["2024-11-28 00:00"]
+        );
+    }
+
+    #[test]
+    fn test_fmt_rule() {
+        let fmt = |r: &str| fmt_rule(&RRule::parse(r, 0).unwrap());
+        assert_eq!(fmt("FREQ=DAILY"), "daily");
+        assert_eq!(fmt("FREQ=WEEKLY;BYDAY=MO,WE"), "weekly on mon/wed");
+        assert_eq!(
+            fmt("FREQ=WEEKLY;INTERVAL=2;BYDAY=FR"),
+            "every 2 weeks on fri"
+        );
+        assert_eq!(fmt("FREQ=MONTHLY;BYDAY=2TU"), "monthly on the 2nd tue");
+        assert_eq!(fmt("FREQ=MONTHLY;BYDAY=-1FR"), "monthly on the last fri");
+        assert_eq!(
+            fmt("FREQ=MONTHLY;BYMONTHDAY=1,15"),
+            "monthly on the 1st/15th"
+        );
+    }
+}
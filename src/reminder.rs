@@ -0,0 +1,225 @@
+use crate::calendar::{self, Event};
+use chrono::{Duration, NaiveDateTime};
+use regex::Regex;
+
+/// How long after a flagged event starts its start action can still run. Ticks land within
+/// this, but reloading the plugin mid-meeting doesn't rerun it.
+const START_GRACE_MINUTES: i64 = 2;
+
+/// Identifies an event occurrence across refreshes, so each one alerts only once.
+pub fn key(event: &Event) -> String {
+    let id = event.uid.as_deref().unwrap_or(&event.summary);
+    format!("{}@{}", id, event.start)
+}
+
+/// Whether the event's reminder (`remind_minutes` or one of its VALARMs) has fired by `now`.
+/// Events that need travel count back from their leave time (`travel_minutes` before start).
+/// Events alert until they start; all-day and overlay events never alert.
+pub fn is_due(
+    event: &Event,
+    now: NaiveDateTime,
+    remind_minutes: Option<u32>,
+    travel_minutes: Option<u32>,
+) -> bool {
+    event.start > now && due_at(event, remind_minutes, travel_minutes).is_some_and(|at| at <= now)
+}
+
+/// When the event's reminder first counts as due (see `is_due`), if it has one.
+pub fn due_at(
+    event: &Event,
+    remind_minutes: Option<u32>,
+    travel_minutes: Option<u32>,
+) -> Option<NaiveDateTime> {
+    if event.is_all_day || event.is_overlay {
+        return None;
+    }
+    let leave = event.start - Duration::minutes(travel_minutes.unwrap_or_default() as i64);
+    remind_minutes
+        .map(i64::from)
+        .into_iter()
+        .chain(event.alarms.iter().copied())
+        // Alarms "at start" fire in the minute before, since ticks can't land on the start
+        .map(|lead| leave - Duration::minutes(lead.max(1)))
+        .min()
+}
+
+/// What happens when a flagged event starts. Configured with `on_start_match`,
+/// `on_start_command`, and `on_start_tab`.
+#[derive(Debug, Clone, Default)]
+pub struct StartAction {
+    /// Events whose summary matches are flagged.
+    pub matches: Option<Regex>,
+    /// Opened in a floating pane with `sh -c`; `$1` is the event title, `$2` its meeting link.
+    pub command: Option<String>,
+    /// Tab switched to, by name.
+    pub tab: Option<String>,
+}
+
+impl StartAction {
+    /// Whether the event is flagged and there is something to do.
+    pub fn applies_to(&self, event: &Event) -> bool {
+        (self.command.is_some() || self.tab.is_some())
+            && self
+                .matches
+                .as_ref()
+                .is_some_and(|re| re.is_match(&event.summary))
+    }
+
+    /// Arguments for the floating pane, if a command is configured.
+    pub fn pane_args(&self, event: &Event) -> Option<Vec<String>> {
+        let command = self.command.as_ref()?;
+        Some(vec![
+            "-c".to_string(),
+            command.clone(),
+            "sh".to_string(),
+            event.summary.clone(),
+            event.meeting_url().unwrap_or_default(),
+        ])
+    }
+}
+
+/// Whether the event started within the last `START_GRACE_MINUTES`, so its start action
+/// should run. All-day and overlay events never start anything.
+pub fn has_just_started(event: &Event, now: NaiveDateTime) -> bool {
+    !event.is_all_day
+        && !event.is_overlay
+        && event.start <= now
+        && now < event.start + Duration::minutes(START_GRACE_MINUTES)
+        && event.end.is_none_or(|end| now < end)
+}
+
+/// One notification for several events due at once (say, after waking a laptop): "3 events
+/// in the next 15 min" over their titles, soonest first.
+pub fn digest(events: &[&Event], now: NaiveDateTime) -> (String, String) {
+    let mut events = events.to_vec();
+    events.sort_by_key(|e| e.start);
+    let last = events.last().map_or(now, |e| e.start);
+    // Round up, so an event 14.5 minutes out is "in the next 15 min"
+    let minutes = ((last - now).num_seconds().max(0) + 59) / 60;
+    let title = format!(
+        "{} events in the next {}",
+        events.len(),
+        calendar::fmt_duration(minutes.max(1))
+    );
+    let titles: Vec<&str> = events.iter().map(|e| e.summary.as_str()).collect();
+    (title, titles.join(" · "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::parse_datetime;
+
+    fn event(start: &str, alarms: Vec<i64>) -> Event {
+        Event {
+            summary: "Standup".to_string(),
+            start: parse_datetime(start).unwrap(),
+            alarms,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_is_due() {
+        let now = parse_datetime("2024-01-15 09:50").unwrap();
+        let standup = event("2024-01-15 10:00", vec![]);
+        assert!(is_due(&standup, now, Some(10), None));
+        assert!(!is_due(&standup, now, Some(5), None));
+        assert!(!is_due(&standup, now, None, None));
+
+        // VALARMs apply alongside remind_minutes
+        let with_alarm = event("2024-01-15 10:00", vec![15]);
+        assert!(is_due(&with_alarm, now, None, None));
+        assert!(is_due(&with_alarm, now, Some(5), None));
+
+        // Travel moves the reminder earlier: leave at 9:45, so a 10 min reminder is due by 9:35
+        assert!(is_due(&standup, now, Some(10), Some(15)));
+        let early = parse_datetime("2024-01-15 09:30").unwrap();
+        assert!(!is_due(&standup, early, Some(10), Some(15)));
+        assert!(is_due(&standup, early, Some(10), Some(20)));
+        assert_eq!(
+            due_at(&with_alarm, Some(5), Some(15)),
+            parse_datetime("2024-01-15 09:30")
+        );
+
+        // Started events and all-day events don't alert
+        assert!(!is_due(
+            &event("2024-01-15 09:50", vec![]),
+            now,
+            Some(10),
+            None
+        ));
+        let all_day = Event {
+            is_all_day: true,
+            ..event("2024-01-15 10:00", vec![])
+        };
+        assert!(!is_due(&all_day, now, Some(30), None));
+        assert!(due_at(&all_day, Some(30), None).is_none());
+        assert!(due_at(&standup, None, None).is_none());
+    }
+
+    #[test]
+    fn test_digest() {
+        let now = parse_datetime("2024-01-15 09:50").unwrap();
+        let review = Event {
+            summary: "Review".to_string(),
+            ..event("2024-01-15 10:05", vec![])
+        };
+        let standup = event("2024-01-15 10:00", vec![]);
+        let (title, body) = digest(&[&review, &standup], now);
+        assert_eq!(title, "2 events in the next 15 min");
+        assert_eq!(body, "Standup · Review");
+    }
+
+    #[test]
+    fn test_key() {
+        let standup = event("2024-01-15 10:00", vec![]);
+        assert_eq!(key(&standup), "Standup@2024-01-15 10:00:00");
+        let with_uid = Event {
+            uid: Some("abc@example.com".to_string()),
+            ..standup
+        };
+        assert_eq!(key(&with_uid), "abc@example.com@2024-01-15 10:00:00");
+    }
+
+    #[test]
+    fn test_start_action() {
+        let standup = event("2024-01-15 10:00", vec![]);
+        let at = |s| parse_datetime(s).unwrap();
+        assert!(has_just_started(&standup, at("2024-01-15 10:00")));
+        assert!(has_just_started(&standup, at("2024-01-15 10:01")));
+        assert!(!has_just_started(&standup, at("2024-01-15 09:59")));
+        assert!(!has_just_started(&standup, at("2024-01-15 10:02")));
+        let all_day = Event {
+            is_all_day: true,
+            ..event("2024-01-15 10:00", vec![])
+        };
+        assert!(!has_just_started(&all_day, at("2024-01-15 10:00")));
+
+        let action = StartAction {
+            matches: Regex::new("(?i)standup").ok(),
+            command: Some("nvim ~/notes/standup.md".to_string()),
+            tab: None,
+        };
+        assert!(action.applies_to(&standup));
+        let args = action.pane_args(&standup).unwrap();
+        assert_eq!(args[1], "nvim ~/notes/standup.md");
+        assert_eq!(args[3], "Standup");
+        assert!(!action.applies_to(&Event {
+            summary: "Lunch".to_string(),
+            ..standup.clone()
+        }));
+        // Nothing is flagged without a pattern, and a pattern alone does nothing
+        let unflagged = StartAction {
+            matches: None,
+            ..action.clone()
+        };
+        assert!(!unflagged.applies_to(&standup));
+        let idle = StartAction {
+            command: None,
+            ..action
+        };
+        assert!(!idle.applies_to(&standup));
+        assert!(idle.pane_args(&standup).is_none());
+    }
+}
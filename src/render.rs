@@ -0,0 +1,1957 @@
+use crate::calendar::{self, Event};
+use crate::config::{EmptyState, View};
+use crate::source::Source;
+use crate::{backend, group_date, qr, reminder, scroll_to_selection, search, wrap, State};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use owo_colors::AnsiColors;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Width of the busy gauge's bar.
+const BUSY_GAUGE_CELLS: usize = 10;
+
+/// Birthdays and anniversaries listed at most, below the events.
+const MAX_OCCASION_LINES: usize = 3;
+
+/// Marks events that overlap another (double bookings).
+const CONFLICT_COLOR: AnsiColors = AnsiColors::Magenta;
+
+/// Text attributes of a span. Colors are unset with `color "none"` (see `Theme::paint`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    pub color: Option<AnsiColors>,
+    pub background: Option<AnsiColors>,
+    pub bold: bool,
+    pub dimmed: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub reversed: bool,
+}
+
+/// A run of text in one style.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Line {
+    pub spans: Vec<Span>,
+}
+
+/// A rendered frame: what `render()` prints, and what tests look at.
+#[derive(Debug, Default)]
+pub struct Buffer {
+    pub lines: Vec<Line>,
+}
+
+/// Attribute setters named after owo-colors' (`.bold()`, `.dimmed()`, ...). On a line they
+/// apply to every span, like styling an already-styled string does.
+pub trait Stylize: Sized {
+    fn restyle(self, f: impl Fn(&mut Style)) -> Self;
+
+    fn color(self, color: AnsiColors) -> Self {
+        self.restyle(|s| s.color = Some(color))
+    }
+
+    fn on_color(self, color: AnsiColors) -> Self {
+        self.restyle(|s| s.background = Some(color))
+    }
+
+    fn bold(self) -> Self {
+        self.restyle(|s| s.bold = true)
+    }
+
+    fn dimmed(self) -> Self {
+        self.restyle(|s| s.dimmed = true)
+    }
+
+    fn italic(self) -> Self {
+        self.restyle(|s| s.italic = true)
+    }
+
+    fn underline(self) -> Self {
+        self.restyle(|s| s.underline = true)
+    }
+
+    fn reversed(self) -> Self {
+        self.restyle(|s| s.reversed = true)
+    }
+}
+
+impl Span {
+    pub fn new(text: impl Into<String>) -> Self {
+        Span {
+            text: text.into(),
+            style: Style::default(),
+        }
+    }
+}
+
+impl Stylize for Span {
+    fn restyle(mut self, f: impl Fn(&mut Style)) -> Self {
+        f(&mut self.style);
+        self
+    }
+}
+
+impl From<&str> for Span {
+    fn from(text: &str) -> Self {
+        Span::new(text)
+    }
+}
+
+impl From<String> for Span {
+    fn from(text: String) -> Self {
+        Span::new(text)
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use owo_colors::OwoColorize;
+        let s = self.style;
+        let mut style = owo_colors::Style::new();
+        if let Some(color) = s.color {
+            style = style.color(color);
+        }
+        if let Some(color) = s.background {
+            style = style.on_color(color);
+        }
+        if s.bold {
+            style = style.bold();
+        }
+        if s.dimmed {
+            style = style.dimmed();
+        }
+        if s.italic {
+            style = style.italic();
+        }
+        if s.underline {
+            style = style.underline();
+        }
+        if s.reversed {
+            style = style.reversed();
+        }
+        write!(f, "{}", self.text.style(style))
+    }
+}
+
+impl Line {
+    pub fn new() -> Self {
+        Line::default()
+    }
+
+    pub fn push(&mut self, span: impl Into<Span>) {
+        self.spans.push(span.into());
+    }
+
+    /// Appends `with`'s spans.
+    pub fn append(&mut self, with: Line) {
+        self.spans.extend(with.spans);
+    }
+
+    /// Lines (cells) side by side, `separator` between each.
+    pub fn join(lines: Vec<Line>, separator: &str) -> Line {
+        let mut joined = Line::new();
+        for (i, line) in lines.into_iter().enumerate() {
+            if i > 0 {
+                joined.push(separator);
+            }
+            joined.append(line);
+        }
+        joined
+    }
+
+    /// Cut to `max_len` chars like `truncate`, the "..." styled as the span it cuts.
+    pub fn truncate(mut self, max_len: usize) -> Line {
+        let len: usize = self.spans.iter().map(|s| s.text.chars().count()).sum();
+        if len <= max_len {
+            return self;
+        }
+        let mut room = max_len.saturating_sub(3);
+        let mut spans = Vec::new();
+        for mut span in self.spans.drain(..) {
+            let count = span.text.chars().count();
+            if count < room {
+                room -= count;
+                spans.push(span);
+                continue;
+            }
+            span.text = span.text.chars().take(room).collect::<String>() + "...";
+            spans.push(span);
+            break;
+        }
+        Line { spans }
+    }
+
+    /// The text without styling.
+    #[cfg(test)]
+    pub fn plain(&self) -> String {
+        self.spans.iter().map(|s| s.text.as_str()).collect()
+    }
+}
+
+impl Stylize for Line {
+    fn restyle(mut self, f: impl Fn(&mut Style)) -> Self {
+        self.spans.iter_mut().for_each(|span| f(&mut span.style));
+        self
+    }
+}
+
+impl From<Span> for Line {
+    fn from(span: Span) -> Self {
+        Line { spans: vec![span] }
+    }
+}
+
+impl From<&str> for Line {
+    fn from(text: &str) -> Self {
+        Line::from(Span::new(text))
+    }
+}
+
+impl From<String> for Line {
+    fn from(text: String) -> Self {
+        Line::from(Span::new(text))
+    }
+}
+
+impl<T: Into<Span>> FromIterator<T> for Line {
+    fn from_iter<I: IntoIterator<Item = T>>(spans: I) -> Self {
+        Line {
+            spans: spans.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.spans.iter().try_for_each(|span| write!(f, "{}", span))
+    }
+}
+
+impl Buffer {
+    pub fn push(&mut self, line: impl Into<Line>) {
+        self.lines.push(line.into());
+    }
+
+    pub fn blank(&mut self) {
+        self.lines.push(Line::new());
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// The frame without styling, trailing spaces trimmed (for comparing whole views).
+    #[cfg(test)]
+    pub fn plain(&self) -> String {
+        let lines: Vec<String> = self
+            .lines
+            .iter()
+            .map(|line| line.plain().trim_end().to_string())
+            .collect();
+        lines.join("\n")
+    }
+}
+
+/// Lines are separated, not terminated, so the last row never scrolls the pane.
+impl fmt::Display for Buffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Panes narrower than this get a single glyph instead of wrapped text (see `render_glyph`).
+const MIN_TEXT_COLS: usize = 3;
+
+impl State {
+    /// The frame for a `rows`×`cols` pane.
+    pub(crate) fn draw(&mut self, rows: usize, cols: usize) -> Buffer {
+        let mut out = Buffer::default();
+        if rows == 0 || cols == 0 {
+            return out;
+        }
+        if cols < MIN_TEXT_COLS {
+            self.render_glyph(&mut out, cols);
+            return out;
+        }
+        let empty = self.empty_state();
+        if empty == Some(EmptyState::Blank) {
+            return out;
+        }
+        let width = cols.min(50);
+        let theme = &self.theme;
+        let header = theme.paint(&theme.header_text, theme.header).bold();
+        let spinner = theme.paint("↻", AnsiColors::Yellow);
+
+        if self.screen_reader {
+            self.render_linear(&mut out, rows, cols);
+            return out;
+        }
+
+        if empty == Some(EmptyState::Header) && (self.compact || rows == 1 || self.next_three) {
+            out.push(truncate(&theme.header_text, cols));
+            return out;
+        }
+
+        if self.next_three {
+            self.render_next_three(&mut out, cols);
+            return out;
+        }
+
+        if self.compact || rows == 1 {
+            self.render_compact(&mut out, cols);
+            return out;
+        }
+
+        if self.focus && self.sources.iter().any(|s| !s.url.is_empty()) {
+            self.render_focus(&mut out, rows, cols);
+            return out;
+        }
+
+        if self.sources.iter().all(|s| s.url.is_empty()) {
+            if !self.ics_url_resolved {
+                out.push(Line::from_iter([header, Span::new(" "), spinner]));
+                return out;
+            }
+            out.push(theme.paint("⚠ No ICS URL configured", AnsiColors::Yellow));
+            out.blank();
+            out.push("Add to your plugin config:");
+            out.push("  ics_url \"https://...\"");
+            out.blank();
+            out.push("Or set environment variable:");
+            out.push("  export ZJ_CAL_ICS_URL=\"https://...\"");
+            return out;
+        }
+
+        // Calendars for the health strip and error display
+        let calendars: Vec<(usize, &Source)> = self
+            .sources
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| !s.overlay && !s.url.is_empty())
+            .collect();
+
+        // Header - show time as soon as we have it, with optional loading indicator
+        let mut line = Line::from_iter([header, Span::new(" ")]);
+        if calendars.len() > 1 {
+            line.append(self.health_strip(&calendars));
+            line.push(" ");
+        }
+        if let Some(now) = self.current_time {
+            let time_str = calendar::fmt_time(now.hour(), now.minute(), self.use_12h_time);
+            line.push(Span::new(time_str).dimmed());
+            if let Some(end) = calendar::current_event(self.store.events(), now).and_then(|e| e.end)
+            {
+                let status = format!(" · in meeting · {}", calendar::fmt_time_left(end, now));
+                line.push(theme.paint(status, theme.now));
+            }
+            if self.is_off_clock() {
+                line.push(Span::new(" · 🌙 off the clock").dimmed());
+            }
+            if let Some(idx) = self.preset {
+                line.push(Span::new(format!(" · ⧩ {}", self.presets[idx].name)).dimmed());
+            }
+            if let Some(until) = self.timer_until {
+                let left = calendar::fmt_countdown(until, self.clock.unwrap_or(now));
+                line.push(theme.paint(format!(" · ⏱ {}", left), AnsiColors::Yellow));
+            }
+            if self.loading {
+                line.push(" ");
+                line.push(spinner);
+            }
+        } else if self.loading {
+            line.push(spinner);
+        }
+        out.push(line.truncate(cols));
+        if empty == Some(EmptyState::Header) {
+            return out;
+        }
+        let date_lines = match self.current_time.filter(|_| self.date_line) {
+            Some(now) => {
+                let line = calendar::fmt_date_context(self.today(now));
+                out.push(Span::new(truncate(&line, width)).dimmed());
+                1
+            }
+            None => 0,
+        };
+        let progress_lines = match self.current_time.filter(|_| self.day_progress) {
+            Some(now) => self.render_day_progress(&mut out, now, width),
+            None => 0,
+        };
+        out.push("─".repeat(width));
+
+        if self.show_debug {
+            self.render_debug(&mut out, width);
+            return out;
+        }
+        if self.show_qr {
+            self.render_qr(&mut out, width);
+            return out;
+        }
+
+        // Errors only hide events when every calendar failed; calendars with earlier events
+        // to fall back on only get a subtle staleness note
+        let mut error_lines = 0;
+        let mut failed = 0;
+        for &(idx, source) in &calendars {
+            let Some(ref err) = source.error else {
+                continue;
+            };
+            if source.stale_since().is_none() {
+                failed += 1;
+            }
+            // With several calendars the strip flags failures, and `e` shows one at a time
+            let shown = self.error_source == Some(idx);
+            if calendars.len() > 1 && !shown {
+                continue;
+            }
+            let prefix = if calendars.len() > 1 {
+                format!("{}: ", source.label.as_ref().unwrap_or(&source.name))
+            } else {
+                String::new()
+            };
+            if let Some(since) = source.stale_since() {
+                let time = if self
+                    .current_time
+                    .is_some_and(|now| now.date() == since.date())
+                {
+                    calendar::fmt_time(since.hour(), since.minute(), self.use_12h_time)
+                } else {
+                    calendar::fmt_datetime(since, self.use_12h_time)
+                };
+                let mut line = format!("{}stale since {}", prefix, time);
+                if let Some(at) = source.retry_at {
+                    let at = calendar::fmt_time(at.hour(), at.minute(), self.use_12h_time);
+                    line.push_str(&format!(" · rate limited, retrying at {}", at));
+                }
+                out.push(Span::new(truncate(&line, width)).dimmed());
+            } else {
+                let line = truncate(&format!("{}{}", prefix, err), width);
+                out.push(theme.paint(line, AnsiColors::Red));
+            }
+            error_lines += 1;
+        }
+        if failed > 0 && failed == calendars.len() {
+            if error_lines == 0 {
+                out.push(Span::new("No calendar could be fetched (e: show errors)").dimmed());
+            }
+            return out;
+        }
+
+        // Out-of-office banner
+        let now = self.current_time.unwrap_or_default();
+        let mut banner_lines = 0;
+        if let Some(now) = self.current_time {
+            if let Some(until) = calendar::ooo_until(self.store.events(), now) {
+                let banner = calendar::fmt_ooo_banner(until, now, self.use_12h_time);
+                let banner = format!("🌴 {}", banner);
+                out.push(theme.paint(banner, AnsiColors::Magenta).bold());
+                banner_lines = 1;
+            }
+        }
+
+        // Reminder alerts
+        for event in self.alerting_events() {
+            let mut when = calendar::fmt_relative_time(
+                event.start,
+                now,
+                self.use_12h_time,
+                self.language,
+                self.day_start,
+            );
+            if let Some(leave) = self.leave_note(event) {
+                when.push_str(&format!(" · {}", leave));
+            }
+            let line = Span::new(truncate(&format!("🔔 {} {}", event.summary, when), width));
+            if theme.no_color() {
+                out.push(line.reversed().bold());
+            } else {
+                out.push(
+                    line.color(AnsiColors::Black)
+                        .on_color(AnsiColors::Yellow)
+                        .bold(),
+                );
+            }
+            banner_lines += 1;
+        }
+
+        // First waiting invite
+        if let Some(line) = self.invite_line() {
+            out.push(theme.paint(truncate(&format!("📥 {}", line), width), AnsiColors::Cyan));
+            banner_lines += 1;
+        }
+
+        if let Some(ref notice) = self.notice {
+            out.push(theme.paint(truncate(notice, width), AnsiColors::Yellow));
+            banner_lines += 1;
+        }
+
+        if let Some(ref query) = self.search {
+            let cursor = if self.searching { "▏" } else { "" };
+            let matches = match self.display_events().len() {
+                _ if query.trim().is_empty() => String::new(),
+                1 => " · 1 match".to_string(),
+                n => format!(" · {} matches", n),
+            };
+            let line = truncate(&format!("/{}{}{}", query, cursor, matches), width);
+            out.push(Span::new(line).bold());
+            banner_lines += 1;
+        }
+
+        if let Some(ref url) = self.url_input {
+            // The end is what's being typed, so a long URL loses its start
+            let prompt = "URL: ";
+            // Room for the prompt, an ellipsis, and the cursor
+            let room = width.saturating_sub(prompt.len() + 2);
+            let skip = url.chars().count().saturating_sub(room);
+            let tail: String = url.chars().skip(skip).collect();
+            let ellipsis = if skip > 0 { "…" } else { "" };
+            out.push(Span::new(format!("{}{}{}▏", prompt, ellipsis, tail)).bold());
+            banner_lines += 1;
+        }
+
+        // Reserve: 1 header + 1 separator + 1 "+more" + 1 buffer for floating mode
+        let occasion_lines = match self.view {
+            View::List | View::Agenda => self.store.occasions().len().min(MAX_OCCASION_LINES),
+            View::Week | View::Month => 0,
+        };
+        let footer_lines = usize::from(self.density_row)
+            + usize::from(self.free_gaps)
+            + usize::from(self.busy_gauge)
+            + occasion_lines;
+        let max_lines = rows.saturating_sub(
+            4 + date_lines + progress_lines + banner_lines + error_lines + footer_lines,
+        );
+        let today = self.today(now);
+
+        match self.view {
+            View::Week => self.render_week(&mut out, now, max_lines, cols),
+            View::Month => {
+                let grid_lines = self.render_month(&mut out, today);
+                self.render_list(&mut out, now, max_lines.saturating_sub(grid_lines), width);
+            }
+            View::List | View::Agenda => {
+                let pinned_lines = self.render_pinned(&mut out, now, width);
+                self.render_list(&mut out, now, max_lines.saturating_sub(pinned_lines), width);
+                self.render_occasions(&mut out, today, occasion_lines, width);
+            }
+        }
+        if self.free_gaps {
+            let gaps = self.store.free_slots(now);
+            let line = calendar::fmt_free_gaps(&gaps, now, self.use_12h_time);
+            out.push(Span::new(truncate(&line, width)).dimmed());
+        }
+        if self.busy_gauge {
+            self.render_busy_gauge(&mut out, now, width);
+        }
+        if self.density_row {
+            self.render_density_row(&mut out, today);
+        }
+        out
+    }
+
+    /// Per-calendar health for the header: ✓ when fetched, a red ✗ and the label when failing
+    /// (yellow while older events are still shown), · before the first fetch.
+    fn health_strip(&self, calendars: &[(usize, &Source)]) -> Line {
+        let badges: Vec<Line> = calendars
+            .iter()
+            .map(|&(idx, source)| {
+                let label = source.label.as_ref().unwrap_or(&source.name);
+                let badge = match source.error {
+                    Some(_) if source.stale_since().is_some() => {
+                        let stale = if self.theme.markers() { " [stale]" } else { "" };
+                        let badge = format!("✗ {}{}", label, stale);
+                        self.theme.paint(badge, AnsiColors::Yellow)
+                    }
+                    Some(_) => self.theme.paint(format!("✗ {}", label), AnsiColors::Red),
+                    None if source.last_fetched.or(source.cached_at).is_some() => {
+                        self.theme.paint("✓", AnsiColors::Green)
+                    }
+                    None => Span::new("·").dimmed(),
+                };
+                if self.error_source == Some(idx) {
+                    badge.reversed().into()
+                } else {
+                    badge.into()
+                }
+            })
+            .collect();
+        Line::join(badges, " ")
+    }
+
+    /// QR code of the selected event's meeting link, for joining from a phone.
+    fn render_qr(&self, out: &mut Buffer, width: usize) {
+        let Some(event) = self
+            .selected
+            .and_then(|i| self.display_events().get(i).copied())
+        else {
+            return;
+        };
+        let Some(url) = event.meeting_url() else {
+            return;
+        };
+        let title = truncate(&format!("Scan to join {}", event.summary), width);
+        out.push(Span::new(title).bold());
+        match qr::render(&url) {
+            Ok(lines) => lines.into_iter().for_each(|line| out.push(line)),
+            Err(err) => out.push(self.theme.paint(err, AnsiColors::Red)),
+        }
+        out.push(Span::new("q or Esc to close").dimmed());
+    }
+
+    /// Slivers of a pane: `!` when there's nothing to show or a calendar failed, else the
+    /// free/busy state as 🔴/🟡/🟢, or a colored ● when there's only one column.
+    fn render_glyph(&self, out: &mut Buffer, cols: usize) {
+        let unconfigured = self.ics_url_resolved && self.sources.iter().all(|s| s.url.is_empty());
+        let failed = self.sources.iter().any(|s| !s.overlay && s.error.is_some());
+        if unconfigured || failed {
+            out.push(self.theme.paint("!", AnsiColors::Red).bold());
+            return;
+        }
+        let Some(now) = self.current_time else {
+            return;
+        };
+        let glyph = calendar::status_glyph(self.store.events(), now);
+        if cols >= 2 {
+            out.push(glyph);
+            return;
+        }
+        // Without color, the dot's fill tells busy from soon from free
+        let (color, shape) = match glyph {
+            "🔴" => (AnsiColors::Red, "●"),
+            "🟡" => (AnsiColors::Yellow, "◐"),
+            _ => (AnsiColors::Green, "○"),
+        };
+        let dot = if self.theme.markers() { shape } else { "●" };
+        out.push(self.theme.paint(dot, color));
+    }
+
+    /// Compact mode: just the next event on one line, for status-bar panes.
+    fn render_compact(&self, out: &mut Buffer, cols: usize) {
+        let line = truncate(&self.next_event_line(), cols);
+        let in_progress = self
+            .current_time
+            .and_then(|now| self.store.next_event(now).map(|e| e.is_in_progress(now)))
+            .unwrap_or(false);
+        if in_progress {
+            out.push(self.theme.paint(line, self.theme.now).bold());
+        } else {
+            out.push(line);
+        }
+    }
+
+    /// Widget mode: always three lines, one per upcoming event (blank when there are fewer).
+    fn render_next_three(&self, out: &mut Buffer, cols: usize) {
+        let now = self.current_time.unwrap_or_default();
+        let events = self
+            .current_time
+            .map_or(Vec::new(), |now| self.store.next(now, 3));
+        for event in events {
+            let line = calendar::fmt_widget_line(
+                event,
+                now,
+                self.use_12h_time,
+                self.language,
+                self.day_start,
+                &self.theme.icons,
+            );
+            let line = truncate(&line, cols);
+            if event.is_in_progress(now) {
+                out.push(self.theme.paint(line, self.theme.now).bold());
+            } else {
+                out.push(line);
+            }
+        }
+        // Loading, errors, and no events are told as compact mode tells them
+        if out.len() == 0 {
+            out.push(truncate(&self.next_event_line(), cols));
+        }
+        while out.len() < 3 {
+            out.blank();
+        }
+    }
+
+    /// Screen-reader mode: a status sentence, then one sentence per event, wrapped rather than
+    /// cut off, with no icons, box drawing, or columns to read out.
+    fn render_linear(&self, out: &mut Buffer, rows: usize, cols: usize) {
+        let mut lines = Vec::new();
+        let Some(now) = self.current_time else {
+            out.push("Calendar loading");
+            return;
+        };
+        if self.sources.iter().all(|s| s.url.is_empty()) {
+            lines.push("No calendar configured. Set ics_url in the plugin config.".to_string());
+        }
+        let time = calendar::fmt_time(now.hour(), now.minute(), self.use_12h_time);
+        let mut status = format!("Calendar, {}", time);
+        if self.loading {
+            status.push_str(", refreshing");
+        }
+        lines.push(status);
+        for source in self.sources.iter().filter(|s| !s.overlay) {
+            if let Some(ref err) = source.error {
+                let label = source.label.as_ref().unwrap_or(&source.name);
+                lines.push(format!("Calendar {} failed: {}", label, err));
+            }
+        }
+        if let Some(line) = self.invite_line() {
+            lines.push(format!("Invite: {}", line));
+        }
+        if let Some(ref notice) = self.notice {
+            lines.push(notice.clone());
+        }
+        let mut out_lines: Vec<String> = lines.iter().flat_map(|l| wrap(l, cols)).collect();
+
+        let events = self.display_events();
+        if events.is_empty() && self.empty_state() != Some(EmptyState::Header) {
+            out_lines.push("No upcoming events".to_string());
+        }
+        let mut shown = self.scroll.min(events.len());
+        for (i, event) in events.iter().enumerate().skip(self.scroll) {
+            let mut sentence =
+                calendar::describe_event(event, now, self.use_12h_time, self.day_start);
+            if self.selected == Some(i) {
+                sentence = format!("Selected: {}", sentence);
+            }
+            let wrapped = wrap(&sentence, cols);
+            // Keep a line for saying how many more there are
+            let more = usize::from(i + 1 < events.len());
+            if out_lines.len() + wrapped.len() + more > rows {
+                break;
+            }
+            out_lines.extend(wrapped);
+            shown += 1;
+        }
+        if shown < events.len() {
+            let more = events.len() - shown;
+            let noun = if more == 1 { "event" } else { "events" };
+            out_lines.push(format!("{} more {}", more, noun));
+        }
+        for line in out_lines.into_iter().take(rows) {
+            out.push(line);
+        }
+    }
+
+    /// Focus mode: the current or next event alone, centered, with a live countdown.
+    fn render_focus(&self, out: &mut Buffer, rows: usize, cols: usize) {
+        let theme = &self.theme;
+        let now = self.clock.or(self.current_time);
+        let mut lines: Vec<Span> = Vec::new();
+        match now.and_then(|now| Some((now, self.store.next_event(now)?))) {
+            Some((now, event)) => {
+                let in_progress = event.is_in_progress(now);
+                let color = if in_progress { theme.now } else { theme.time };
+                let title = truncate(
+                    &format!("{} {}", event.icon(&theme.icons), event.summary),
+                    cols,
+                );
+                lines.push(Span::new(title).bold());
+
+                let countdown = match event.end {
+                    Some(end) if in_progress => {
+                        format!("ends in {}", calendar::fmt_countdown(end, now))
+                    }
+                    _ => format!("starts in {}", calendar::fmt_countdown(event.start, now)),
+                };
+                lines.push(theme.paint(countdown, color).bold());
+
+                let start = event.start;
+                let mut when = calendar::fmt_time(start.hour(), start.minute(), self.use_12h_time);
+                if let Some(end) = event.end {
+                    let end_time = calendar::fmt_time(end.hour(), end.minute(), self.use_12h_time);
+                    let minutes = (end - start).num_minutes();
+                    when = format!(
+                        "{}–{} · {}",
+                        when,
+                        end_time,
+                        calendar::fmt_duration(minutes)
+                    );
+                }
+                lines.push(Span::new(when).dimmed());
+
+                if let Some(place) = event.meeting_url().or_else(|| event.location.clone()) {
+                    lines.push(Span::new(truncate(&place, cols)).dimmed());
+                }
+            }
+            None => lines.push(Span::new(self.empty_message()).dimmed()),
+        }
+
+        for _ in 0..rows.saturating_sub(lines.len()) / 2 {
+            out.blank();
+        }
+        for span in lines {
+            let indent = cols.saturating_sub(span.text.chars().count()) / 2;
+            out.push(Line::from_iter([Span::new(" ".repeat(indent)), span]));
+        }
+    }
+
+    /// List and agenda views: one line per event, grouped under day headers in the agenda.
+    fn render_list(
+        &mut self,
+        out: &mut Buffer,
+        now: NaiveDateTime,
+        max_lines: usize,
+        width: usize,
+    ) {
+        let today = self.today(now);
+        let grouped = matches!(self.view, View::Agenda | View::Month);
+        // The month view's events all group under its selected day
+        let month_day = self.month_day().filter(|_| self.view == View::Month);
+        let group = |e: &Event| month_day.unwrap_or_else(|| group_date(e, today, self.day_start));
+
+        // Keep the selected event on screen (the list view has no day headers)
+        let group_dates: Vec<NaiveDate> = self
+            .display_events()
+            .iter()
+            .map(|e| if grouped { group(e) } else { today })
+            .collect();
+        if group_dates.is_empty() {
+            let text = match month_day {
+                Some(day) => format!(
+                    "No events · {}",
+                    calendar::fmt_day_header(day, today, self.language)
+                ),
+                None => self.empty_message().to_string(),
+            };
+            out.push(Span::new(text).dimmed());
+            return;
+        }
+        self.selected = self.selected.map(|i| i.min(group_dates.len() - 1));
+        // Overnight events listed under an earlier day also show under the day they run into
+        let mut carried: BTreeMap<NaiveDate, Vec<usize>> = BTreeMap::new();
+        if grouped && month_day.is_none() {
+            for (idx, event) in self.display_events().iter().enumerate() {
+                let days = group_dates.iter().filter(|&&d| d > group_dates[idx]);
+                for day in days.filter(|&&d| event.continues_on(d) && !event.is_overlay) {
+                    let entry = carried.entry(*day).or_default();
+                    if !entry.contains(&idx) {
+                        entry.push(idx);
+                    }
+                }
+            }
+        }
+        // Day headers take a line, plus one for the day's note and each carried event
+        let header_lines = |date: NaiveDate| {
+            1 + usize::from(grouped && self.notes.contains_key(&date))
+                + carried.get(&date).map_or(0, Vec::len)
+        };
+        self.scroll = scroll_to_selection(
+            &group_dates,
+            self.scroll,
+            self.selected,
+            max_lines,
+            header_lines,
+        );
+
+        let events = self.display_events();
+        let conflicts = self.store.conflicts();
+        let mut current_group: Option<NaiveDate> = None;
+        let mut lines_used = 0;
+        let mut events_shown = 0;
+        let indent = if grouped { "  " } else { "" };
+
+        for (idx, event) in events.iter().enumerate().skip(self.scroll) {
+            let active_today = event.is_active_on(today);
+            let event_date = group(event);
+            let is_selected = self.selected == Some(idx);
+            let conflict = conflicts.contains(&reminder::key(event));
+
+            // Print group header if day changed
+            if grouped && current_group != Some(event_date) {
+                // (need room for header + at least 1 event)
+                if lines_used + header_lines(event_date) + 1 > max_lines {
+                    break;
+                }
+                let mut header = Line::from(
+                    Span::new(calendar::fmt_day_header(event_date, today, self.language)).bold(),
+                );
+                // The top header stays pinned while its day scrolls; note the rows above it
+                let earlier = match current_group {
+                    None => group_dates[..self.scroll]
+                        .iter()
+                        .rev()
+                        .take_while(|d| **d == event_date)
+                        .count(),
+                    Some(_) => 0,
+                };
+                if earlier > 0 {
+                    header.push(Span::new(format!(" · {} earlier", earlier)).dimmed());
+                }
+                out.push(header);
+                current_group = Some(event_date);
+                lines_used += 1;
+                if let Some(note) = self.notes.get(&event_date) {
+                    let line = truncate(&format!("{}✎ {}", indent, note), width);
+                    out.push(Span::new(line).italic().dimmed());
+                    lines_used += 1;
+                }
+                for &carried_idx in carried.get(&event_date).into_iter().flatten() {
+                    let event = events[carried_idx];
+                    let time = calendar::fmt_overnight(event, event_date, self.use_12h_time, false)
+                        .unwrap_or_default();
+                    let line = format!("{}{} {}", indent, time, event.summary);
+                    out.push(Span::new(truncate(&line, width)).dimmed());
+                    lines_used += 1;
+                }
+            }
+
+            if lines_used >= max_lines {
+                break;
+            }
+
+            // Format time based on group (or relative to now in the list view)
+            let is_today = event_date == today;
+            let in_progress = !event.is_all_day && event.is_in_progress(now);
+            let ended = event.has_ended(now);
+            let words = self.language.strings();
+            let late = self.is_late(event, now);
+            let time = if late {
+                calendar::fmt_started_ago(event.start, now)
+            } else if in_progress {
+                words.now.to_string()
+            } else if let Some(end) = event.end.filter(|_| ended) {
+                calendar::fmt_ended_ago(end, now)
+            } else if !grouped && event.is_all_day {
+                calendar::fmt_day_header(event_date, today, self.language)
+            } else if !grouped {
+                calendar::fmt_relative_time(
+                    event.start,
+                    now,
+                    self.use_12h_time,
+                    self.language,
+                    self.day_start,
+                )
+            } else if let Some(span) =
+                calendar::fmt_overnight(event, event_date, self.use_12h_time, false)
+            {
+                span
+            } else {
+                calendar::fmt_time_in_group(
+                    event.start,
+                    now,
+                    is_today,
+                    event.is_all_day,
+                    self.use_12h_time,
+                    self.language,
+                    self.day_start,
+                )
+            };
+
+            let is_now = time == words.now || late;
+            let time = match calendar::fmt_time_range(event, self.use_12h_time) {
+                Some(range) if self.peek => range,
+                _ => time,
+            };
+
+            // Render event line (indented under group)
+            if event.is_overlay {
+                let busy = Span::new("░░ busy").dimmed();
+                let busy = if is_selected { busy.reversed() } else { busy };
+                out.push(Line::from_iter([
+                    Span::new(indent),
+                    Span::new(time).dimmed(),
+                    Span::new(" "),
+                    busy,
+                ]));
+                lines_used += 1;
+                events_shown += 1;
+                continue;
+            }
+            let flight = event.flight();
+            let label = match &flight {
+                Some(f) => calendar::fmt_flight(f, event.end, self.use_12h_time),
+                None => event.summary.clone(),
+            };
+            let source = &self.sources[event.source];
+            let series = if event.is_recurring() { " ↻" } else { "" };
+            // Meetings I run, as opposed to ones I'm invited to
+            let mine = match self.filter.my_email.as_deref() {
+                Some(me) if event.is_organized_by(me) => " ★",
+                _ => "",
+            };
+            let label_tag = source
+                .label
+                .as_ref()
+                .map(|l| format!(" [{}]", l))
+                .unwrap_or_default();
+            let leave = self
+                .leave_note(event)
+                .filter(|_| !in_progress && !ended)
+                .map(|leave| format!(" · {}", leave))
+                .unwrap_or_default();
+            let tag = format!("{}{}{}{}", mine, series, label_tag, leave);
+            let badge = if self.new_badge && !ended && event.is_new(now) {
+                " new"
+            } else {
+                ""
+            };
+            let highlight = is_now || (event.is_all_day && active_today);
+            // What the time's and icon's colors say, spelled out when colors are off
+            let markers: Vec<Span> = [(late, "LATE"), (highlight && !late, "NOW"), (conflict, "!")]
+                .into_iter()
+                .filter(|(shown, _)| *shown)
+                .filter_map(|(_, marker)| self.theme.marker(marker))
+                .collect();
+            let markers_len: usize = markers.iter().map(|m| m.text.len()).sum();
+            let summary = Span::new(truncate(
+                &label,
+                width.saturating_sub(
+                    time.len() + badge.len() + markers_len + tag.chars().count() + 3 + indent.len(),
+                ),
+            ));
+            // Marked events swap their icon for a check; double-booked ones for a warning
+            let icon = if self.marked.contains(&reminder::key(event)) {
+                self.theme.paint("✔", self.theme.now)
+            } else if conflict {
+                self.theme.paint("⚠", CONFLICT_COLOR)
+            } else {
+                let icon = event.icon(&self.theme.icons);
+                self.theme
+                    .paint_icon(icon, event.is_video_call(), self.event_color(event))
+            };
+            let dim_size = event
+                .meeting_size(self.filter.large_meeting)
+                .is_some_and(|size| self.dim_meetings.contains(&size));
+            let summary = match event.busy_status {
+                _ if ended || dim_size => summary.dimmed(),
+                calendar::BusyStatus::Free => summary.dimmed(),
+                calendar::BusyStatus::Tentative => summary.italic(),
+                _ if highlight => summary.bold(),
+                _ => summary,
+            };
+            let summary = if is_selected {
+                summary.reversed()
+            } else {
+                summary
+            };
+            let matched = self
+                .search
+                .as_deref()
+                .filter(|_| flight.is_none())
+                .and_then(|query| search::fuzzy_match(query, &event.summary));
+            let summary = match matched {
+                Some(mut m) => {
+                    // Matches cut off by truncation don't light up the "..."
+                    if summary.text != event.summary {
+                        let shown = summary.text.chars().count().saturating_sub(3);
+                        m.positions.retain(|&p| p < shown);
+                    }
+                    self.highlight_matches(summary, &m.positions)
+                }
+                None => Line::from(summary),
+            };
+            let time = if late {
+                self.theme.paint(time, AnsiColors::Yellow).bold()
+            } else if highlight {
+                self.theme.paint(time, self.theme.now).bold()
+            } else if ended {
+                Span::new(time).dimmed()
+            } else {
+                let color = match event.duration_grade().filter(|_| self.duration_colors) {
+                    _ if conflict => CONFLICT_COLOR,
+                    Some(calendar::DurationGrade::Short) => AnsiColors::Green,
+                    Some(calendar::DurationGrade::Medium) => AnsiColors::Yellow,
+                    Some(calendar::DurationGrade::Long) => AnsiColors::Red,
+                    None => self.theme.time,
+                };
+                self.theme.paint(time, color)
+            };
+            let mut line = Line::from_iter([
+                Span::new(indent),
+                time,
+                Span::new(" "),
+                icon,
+                Span::new(" "),
+            ]);
+            line.append(summary);
+            line.push(self.theme.paint(badge, AnsiColors::Yellow).bold());
+            for marker in markers {
+                line.push(marker);
+            }
+            line.push(Span::new(tag).dimmed());
+            out.push(line);
+            lines_used += 1;
+            events_shown += 1;
+
+            // The selected event's series (or, when expanded, all its details), under it
+            if is_selected {
+                let details = if self.expanded {
+                    self.detail_lines(event, width.saturating_sub(indent.len() + 2))
+                } else {
+                    event
+                        .fmt_series()
+                        .map(|s| format!("↻ {}", s))
+                        .into_iter()
+                        .collect()
+                };
+                for line in details {
+                    if lines_used >= max_lines {
+                        break;
+                    }
+                    let line = truncate(&format!("{}  {}", indent, line), width);
+                    out.push(Span::new(line).dimmed());
+                    lines_used += 1;
+                }
+            }
+        }
+
+        let remaining = events.len() - self.scroll - events_shown;
+        if remaining > 0 {
+            out.push(Span::new(format!("{}+{} more", indent, remaining)).dimmed());
+        }
+    }
+
+    /// Footer gauge of today's booked working hours: the booked part of the bar dims as its
+    /// meetings end.
+    fn render_busy_gauge(&self, out: &mut Buffer, now: NaiveDateTime, width: usize) {
+        let Some(booking) = self.store.booking(now, &self.working_hours) else {
+            out.push(Span::new("no working hours today").dimmed());
+            return;
+        };
+        let cells = |minutes: i64| {
+            let cells = (minutes * BUSY_GAUGE_CELLS as i64 + booking.window / 2) / booking.window;
+            cells as usize
+        };
+        let (done, booked) = (cells(booking.done), cells(booking.booked));
+        let color = match booking.percent() {
+            0..50 => AnsiColors::Green,
+            50..80 => AnsiColors::Yellow,
+            _ => AnsiColors::Red,
+        };
+        let text = calendar::fmt_booking(&booking);
+        out.push(Line::from_iter([
+            Span::new("█".repeat(done)).dimmed(),
+            self.theme.paint("█".repeat(booked - done), color),
+            Span::new("░".repeat(BUSY_GAUGE_CELLS - booked)).dimmed(),
+            Span::new(format!(
+                " {}",
+                truncate(&text, width.saturating_sub(BUSY_GAUGE_CELLS + 1))
+            )),
+        ]));
+    }
+
+    /// Header bar through today's working hours: meetings as heavy segments, the part behind
+    /// dimmed, and ● where now is. Returns the lines used (none on weekends).
+    fn render_day_progress(&self, out: &mut Buffer, now: NaiveDateTime, width: usize) -> usize {
+        let hours = &self.working_hours;
+        let label =
+            |t: NaiveTime| calendar::fmt_time_compact(t.hour(), t.minute(), self.use_12h_time);
+        let (start, end) = (label(hours.start), label(hours.end));
+        let cells = width.saturating_sub(start.len() + end.len() + 2);
+        let Some(progress) = calendar::day_progress(self.store.events(), now, hours, cells) else {
+            return 0;
+        };
+        let working = now.time() >= hours.start && now.time() < hours.end;
+        let mut line = Line::from(Span::new(format!("{} ", start)).dimmed());
+        for (i, &busy) in progress.busy.iter().enumerate() {
+            let glyph = if busy { "━" } else { "─" };
+            line.push(match i.cmp(&progress.done) {
+                Ordering::Equal if working => self.theme.paint("●", self.theme.now).bold(),
+                Ordering::Less => Span::new(glyph).dimmed(),
+                _ if busy => self.theme.paint(glyph, self.theme.time),
+                _ => Span::new(glyph),
+            });
+        }
+        line.push(Span::new(format!(" {}", end)).dimmed());
+        out.push(line);
+        1
+    }
+
+    /// Splits `span` so the characters at `positions` (search matches) stand out.
+    fn highlight_matches(&self, span: Span, positions: &[usize]) -> Line {
+        let mut line = Line::new();
+        let mut run = String::new();
+        let mut run_matched = false;
+        let mut flush = |run: &mut String, matched: bool| {
+            if run.is_empty() {
+                return;
+            }
+            let part = Span {
+                text: std::mem::take(run),
+                style: span.style,
+            };
+            line.push(match matched {
+                true if self.theme.no_color() => part.underline(),
+                true => part.underline().color(AnsiColors::Yellow),
+                false => part,
+            });
+        };
+        for (i, c) in span.text.chars().enumerate() {
+            let matched = positions.contains(&i);
+            if matched != run_matched {
+                flush(&mut run, run_matched);
+                run_matched = matched;
+            }
+            run.push(c);
+        }
+        flush(&mut run, run_matched);
+        line
+    }
+
+    /// Footer of this week's days (Monday first), shaded by how booked each one is.
+    fn render_density_row(&self, out: &mut Buffer, today: NaiveDate) {
+        let monday = today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64);
+        let cells: Vec<Line> = (0..7)
+            .map(|d| {
+                let day = monday + chrono::Duration::days(d);
+                let letter = &day.format("%a").to_string()[..1];
+                let (bar, color) = match calendar::day_load(self.store.events(), day) {
+                    calendar::DayLoad::Free => ("▁", AnsiColors::BrightBlack),
+                    calendar::DayLoad::Light => ("▃", AnsiColors::Green),
+                    calendar::DayLoad::Busy => ("▅", AnsiColors::Yellow),
+                    calendar::DayLoad::Packed => ("▇", AnsiColors::Red),
+                };
+                let cell = Line::from_iter([Span::new(letter), self.theme.paint(bar, color)]);
+                if day == today {
+                    cell.bold().underline()
+                } else if day < today {
+                    cell.dimmed()
+                } else {
+                    cell
+                }
+            })
+            .collect();
+        out.push(Line::join(cells, " "));
+    }
+
+    /// Week view: seven day columns starting today, each listing that day's events.
+    fn render_week(&self, out: &mut Buffer, now: NaiveDateTime, max_lines: usize, cols: usize) {
+        let today = self.today(now);
+        let col_width = (cols.saturating_sub(6) / 7).max(4);
+        let days: Vec<NaiveDate> = (0..7).map(|d| today + chrono::Duration::days(d)).collect();
+        let selected = self
+            .selected
+            .and_then(|i| self.display_events().get(i).copied());
+
+        let header: Vec<Line> = days
+            .iter()
+            .map(|day| {
+                let label = pad(&day.format("%a %-d").to_string().to_lowercase(), col_width);
+                if *day == today {
+                    self.theme.paint(label, self.theme.now).bold().into()
+                } else {
+                    Span::new(label).bold().into()
+                }
+            })
+            .collect();
+        out.push(Line::join(header, " "));
+
+        let columns: Vec<Vec<&Event>> = days
+            .iter()
+            .map(|day| {
+                self.display_events()
+                    .into_iter()
+                    .filter(|e| e.is_active_on(*day))
+                    .collect()
+            })
+            .collect();
+        let tallest = columns.iter().map(|c| c.len()).max().unwrap_or(0);
+        let rows = tallest.min(max_lines.saturating_sub(1).max(1));
+
+        for row in 0..rows {
+            let cells: Vec<Line> = columns
+                .iter()
+                .zip(&days)
+                .map(|(column, day)| {
+                    // The last row reports what didn't fit
+                    if row == rows - 1 && column.len() > rows {
+                        let more = format!("+{}", column.len() - row);
+                        return Span::new(pad(&more, col_width)).dimmed().into();
+                    }
+                    let Some(event) = column.get(row) else {
+                        return " ".repeat(col_width).into();
+                    };
+                    let text = if event.is_overlay {
+                        "░ busy".to_string()
+                    } else if event.is_all_day {
+                        event.summary.clone()
+                    } else {
+                        let time = calendar::fmt_overnight(event, *day, self.use_12h_time, true)
+                            .unwrap_or_else(|| {
+                                calendar::fmt_time_compact(
+                                    event.start.hour(),
+                                    event.start.minute(),
+                                    self.use_12h_time,
+                                )
+                            });
+                        format!("{} {}", time, event.summary)
+                    };
+                    let cell = pad(&truncate(&text, col_width), col_width);
+                    let cell =
+                        if event.is_overlay || event.busy_status == calendar::BusyStatus::Free {
+                            Span::new(cell).dimmed()
+                        } else if event.is_in_progress(now) {
+                            self.theme.paint(cell, self.theme.now).bold()
+                        } else {
+                            Span::new(cell)
+                        };
+                    if selected.is_some_and(|s| std::ptr::eq(s, *event)) {
+                        cell.reversed().into()
+                    } else {
+                        cell.into()
+                    }
+                })
+                .collect();
+            out.push(Line::join(cells, " "));
+        }
+        if tallest == 0 {
+            out.push(Span::new("No events this week").dimmed());
+        }
+    }
+
+    /// Pinned events' next occurrences, above the list. Returns the lines added.
+    fn render_pinned(&self, out: &mut Buffer, now: NaiveDateTime, width: usize) -> usize {
+        let mut lines = 0;
+        for id in &self.pinned {
+            let next = self
+                .store
+                .events()
+                .iter()
+                .filter(|e| e.pin_id() == id && !e.has_ended(now))
+                .min_by_key(|e| e.start);
+            let Some(event) = next else {
+                continue;
+            };
+            let in_progress = event.is_in_progress(now);
+            let when = if let Some(range) =
+                calendar::fmt_time_range(event, self.use_12h_time).filter(|_| self.peek)
+            {
+                range
+            } else if in_progress {
+                self.language.strings().now.to_string()
+            } else {
+                calendar::fmt_relative_time(
+                    event.start,
+                    now,
+                    self.use_12h_time,
+                    self.language,
+                    self.day_start,
+                )
+            };
+            let line = truncate(&format!("📌 {} {}", when, event.summary), width);
+            if in_progress {
+                out.push(self.theme.paint(line, self.theme.now).bold());
+            } else {
+                out.push(Span::new(line).bold());
+            }
+            lines += 1;
+        }
+        lines
+    }
+
+    /// Upcoming birthdays and anniversaries, soonest first, under the events.
+    fn render_occasions(&self, out: &mut Buffer, today: NaiveDate, lines: usize, width: usize) {
+        for event in self.store.occasions().iter().take(lines) {
+            let day = calendar::fmt_occasion_day(event.start.date(), today, self.language);
+            let line = truncate(&format!("🎂 {} · {}", event.summary, day), width);
+            if event.start.date() <= today {
+                out.push(self.theme.paint(line, AnsiColors::Magenta));
+            } else {
+                out.push(Span::new(line).dimmed());
+            }
+        }
+    }
+
+    /// Month grid of the selected day's month, marking days with events. Returns its height.
+    fn render_month(&self, out: &mut Buffer, today: NaiveDate) -> usize {
+        let Some(selected) = self.month_day() else {
+            return 0;
+        };
+        let title = selected.format("%B %Y").to_string().to_lowercase();
+        out.push(Span::new(title).bold());
+        let weekdays = ["mo", "tu", "we", "th", "fr", "sa", "su"].map(|d| pad(d, 3));
+        out.push(Span::new(weekdays.join(" ")).dimmed());
+
+        let weeks = calendar::month_weeks(selected);
+        for week in &weeks {
+            let cells: Vec<Line> = week
+                .iter()
+                .map(|day| {
+                    let Some(day) = *day else {
+                        return " ".repeat(3).into();
+                    };
+                    let has_events = !self.store.on_day(day).is_empty();
+                    let cell = format!("{:>2}{}", day.day(), if has_events { "•" } else { " " });
+                    let cell = if day == selected {
+                        Span::new(cell).reversed()
+                    } else if day == today {
+                        self.theme.paint(cell, self.theme.now).bold()
+                    } else if day < today {
+                        Span::new(cell).dimmed()
+                    } else {
+                        Span::new(cell)
+                    };
+                    cell.into()
+                })
+                .collect();
+            out.push(Line::join(cells, " "));
+        }
+        weeks.len() + 2
+    }
+
+    /// Debug overlay: per-source fetch status, redirects, and final resolved URL.
+    fn render_debug(&self, out: &mut Buffer, width: usize) {
+        out.push(Span::new("Debug (d to close)").bold());
+        for source in &self.sources {
+            if source.url.is_empty() {
+                continue;
+            }
+            let status = match (&source.error, &source.fetch_info) {
+                (Some(_), _) => self.theme.paint("error", AnsiColors::Red),
+                (None, Some(info)) => self
+                    .theme
+                    .paint(format!("HTTP {}", info.http_code), AnsiColors::Green),
+                (None, None) => Span::new("pending").dimmed(),
+            };
+            let summary = format!(
+                " · {} · {} events",
+                backend::for_source(source).kind(),
+                source.events.len()
+            );
+            out.push(Line::from_iter([
+                Span::new(&source.name).bold(),
+                Span::new(" "),
+                status,
+                Span::new(summary),
+            ]));
+            if let Some(ref info) = source.fetch_info {
+                if info.redirects > 0 {
+                    let line = format!("  {} redirects → {}", info.redirects, info.final_url);
+                    out.push(self.theme.paint(truncate(&line, width), AnsiColors::Yellow));
+                }
+            }
+            if let Some(fetched) = source.last_fetched {
+                let time = calendar::fmt_time(fetched.hour(), fetched.minute(), self.use_12h_time);
+                out.push(Line::from_iter([
+                    Span::new("  "),
+                    Span::new(format!("last fetched {}", time)).dimmed(),
+                ]));
+            }
+            if let Some(ref err) = source.error {
+                let err = truncate(err, width.saturating_sub(2));
+                out.push(Line::from_iter([
+                    Span::new("  "),
+                    self.theme.paint(err, AnsiColors::Red),
+                ]));
+            }
+        }
+    }
+}
+
+/// Pads to `width` characters so columns line up.
+fn pad(s: &str, width: usize) -> String {
+    format!("{:<width$}", s, width = width)
+}
+
+fn truncate(s: &str, max_len: usize) -> String {
+    if s.chars().count() <= max_len {
+        s.to_string()
+    } else {
+        let truncated: String = s.chars().take(max_len.saturating_sub(3)).collect();
+        format!("{}...", truncated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::parse_datetime;
+    use crate::config::{CalendarConfig, FetchAuth};
+    use crate::store::EventStore;
+    use crate::theme::ColorMode;
+    use chrono::NaiveTime;
+    use indoc::indoc;
+    use owo_colors::OwoColorize;
+
+    /// A Monday morning with one calendar: standup soon, lunch, planning tomorrow, and an
+    /// offsite on Wednesday.
+    fn state(view: View) -> State {
+        let source = Source::from(CalendarConfig {
+            name: "work".to_string(),
+            url: "https://example.com/work.ics".to_string(),
+            color: None,
+            label: None,
+            overlay: false,
+            caldav: false,
+            auth: FetchAuth::default(),
+        });
+        let event = |summary: &str, start: &str, end: &str| Event {
+            summary: summary.to_string(),
+            start: parse_datetime(start).unwrap(),
+            end: parse_datetime(end),
+            ..Default::default()
+        };
+        State {
+            sources: vec![source],
+            store: EventStore::from(vec![
+                Event {
+                    location: Some("https://zoom.us/j/123".to_string()),
+                    ..event("Standup", "2024-01-15 09:30", "2024-01-15 10:15")
+                },
+                event("Lunch with Sam", "2024-01-15 12:00", "2024-01-15 13:00"),
+                event("Planning", "2024-01-16 09:00", "2024-01-16 10:00"),
+                Event {
+                    is_all_day: true,
+                    ..event("Offsite", "2024-01-17 00:00", "2024-01-18 00:00")
+                },
+            ]),
+            ics_url_resolved: true,
+            current_time: parse_datetime("2024-01-15 10:00"),
+            view,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_agenda() {
+        let frame = state(View::Agenda).draw(12, 40);
+        let expected = indoc! {"
+            📅 Calendar 10:00 · in meeting · 15 mi...
+            ────────────────────────────────────────
+            today
+              now 📹 Standup
+              12:00 (2 hrs) • Lunch with Sam
+            tomorrow
+              09:00 • Planning
+            wednesday, jan 17
+              all day • Offsite"};
+        assert_eq!(frame.plain(), expected);
+
+        // Too short for every day: the rest is counted
+        let frame = state(View::Agenda).draw(7, 40);
+        let expected = indoc! {"
+            📅 Calendar 10:00 · in meeting · 15 mi...
+            ────────────────────────────────────────
+            today
+              now 📹 Standup
+              12:00 (2 hrs) • Lunch with Sam
+              +2 more"};
+        assert_eq!(frame.plain(), expected);
+    }
+
+    #[test]
+    fn test_scrolled_day() {
+        // Scrolled past the standup: today's header stays on top, counting it
+        let mut state = State {
+            scroll: 1,
+            ..state(View::Agenda)
+        };
+        let frame = state.draw(8, 40);
+        let expected = indoc! {"
+            📅 Calendar 10:00 · in meeting · 15 mi...
+            ────────────────────────────────────────
+            today · 1 earlier
+              12:00 (2 hrs) • Lunch with Sam
+            tomorrow
+              09:00 • Planning
+              +1 more"};
+        assert_eq!(frame.plain(), expected);
+        assert!(frame.lines[2].spans[1].style.dimmed);
+    }
+
+    #[test]
+    fn test_list() {
+        let frame = state(View::List).draw(10, 30);
+        let expected = indoc! {"
+            📅 Calendar 10:00 · in meeti...
+            ──────────────────────────────
+            now 📹 Standup
+            12:00 (2 hrs) • Lunch with Sam
+            tmrw 09:00 • Planning
+            wednesday, jan 17 • Offsite"};
+        assert_eq!(frame.plain(), expected);
+
+        // Capped by `max_events`
+        let mut capped = state(View::List);
+        capped.window.max_events = 2;
+        let frame = capped.draw(10, 30);
+        let expected = indoc! {"
+            📅 Calendar 10:00 · in meeti...
+            ──────────────────────────────
+            now 📹 Standup
+            12:00 (2 hrs) • Lunch with Sam"};
+        assert_eq!(frame.plain(), expected);
+    }
+
+    #[test]
+    fn test_week() {
+        let frame = state(View::Week).draw(8, 76);
+        let expected = indoc! {"
+            📅 Calendar 10:00 · in meeting · 15 min left
+            ──────────────────────────────────────────────────
+            mon 15     tue 16     wed 17     thu 18     fri 19     sat 20     sun 21
+            09:30 S... 09:00 P... Offsite
+            12:00 L..."};
+        assert_eq!(frame.plain(), expected);
+    }
+
+    #[test]
+    fn test_month() {
+        let frame = state(View::Month).draw(14, 30);
+        let expected = indoc! {"
+            📅 Calendar 10:00 · in meeti...
+            ──────────────────────────────
+            january 2024
+            mo  tu  we  th  fr  sa  su
+             1   2   3   4   5   6   7
+             8   9  10  11  12  13  14
+            15• 16• 17• 18  19  20  21
+            22  23  24  25  26  27  28
+            29  30  31
+            today
+              now 📹 Standup
+              12:00 (2 hrs) • Lunch wit..."};
+        assert_eq!(frame.plain(), expected);
+    }
+
+    #[test]
+    fn test_small_modes() {
+        // A single row is always compact
+        let frame = state(View::Agenda).draw(1, 40);
+        assert_eq!(frame.plain(), "📹 Standup · 15 min left");
+
+        let mut widget = State {
+            next_three: true,
+            ..state(View::Agenda)
+        };
+        let frame = widget.draw(10, 30);
+        let expected = indoc! {"
+            now 📹 Standup
+            12:00 • Lunch with Sam
+            tmrw 09:00 • Planning"};
+        assert_eq!(frame.plain(), expected);
+
+        let mut focus = State {
+            focus: true,
+            ..state(View::Agenda)
+        };
+        let frame = focus.draw(8, 30);
+        // Centered under two blank rows
+        let expected = [
+            "",
+            "",
+            "          📹 Standup",
+            "        ends in 15:00",
+            "     09:30–10:15 · 45 min",
+            "    https://zoom.us/j/123",
+        ];
+        assert_eq!(frame.plain(), expected.join("\n"));
+
+        let mut unconfigured = State {
+            sources: Vec::new(),
+            ..state(View::Agenda)
+        };
+        let frame = unconfigured.draw(8, 30);
+        assert!(frame
+            .plain()
+            .starts_with("⚠ No ICS URL configured\n\nAdd to"));
+    }
+
+    #[test]
+    fn test_tiny_panes() {
+        let mut state = state(View::Agenda);
+        assert_eq!(state.draw(0, 40).plain(), "");
+        assert_eq!(state.draw(12, 0).plain(), "");
+        // In the standup
+        assert_eq!(state.draw(12, 2).plain(), "🔴");
+        let frame = state.draw(1, 1);
+        assert_eq!(frame.plain(), "●");
+        assert_eq!(frame.lines[0].spans[0].style.color, Some(AnsiColors::Red));
+
+        state.current_time = parse_datetime("2024-01-15 10:30");
+        assert_eq!(state.draw(12, 2).plain(), "🟢");
+
+        state.sources[0].error = Some("HTTP 500".to_string());
+        assert_eq!(state.draw(12, 2).plain(), "!");
+    }
+
+    #[test]
+    fn test_health_strip() {
+        let mut state = state(View::List);
+        state.sources[0].last_fetched = state.current_time;
+        let mut home = Source::from(CalendarConfig {
+            name: "home".to_string(),
+            url: "https://example.com/home.ics".to_string(),
+            color: None,
+            label: None,
+            overlay: false,
+            caldav: false,
+            auth: FetchAuth::default(),
+        });
+        home.error = Some("HTTP 500".to_string());
+        state.sources.push(home);
+
+        // Flagged in the strip, with the error itself left for `e`
+        let frame = state.draw(6, 60);
+        let expected = indoc! {"
+            📅 Calendar ✓ ✗ home 10:00 · in meeting · 15 min left
+            ──────────────────────────────────────────────────
+            now 📹 Standup
+            12:00 (2 hrs) • Lunch with Sam
+            +2 more"};
+        assert_eq!(frame.plain(), expected);
+
+        state.error_source = Some(1);
+        let frame = state.draw(6, 60);
+        let expected = indoc! {"
+            📅 Calendar ✓ ✗ home 10:00 · in meeting · 15 min left
+            ──────────────────────────────────────────────────
+            home: HTTP 500
+            now 📹 Standup
+            +3 more"};
+        assert_eq!(frame.plain(), expected);
+        let badge = frame.lines[0].spans.iter().find(|s| s.text == "✗ home");
+        assert!(badge.unwrap().style.reversed);
+    }
+
+    #[test]
+    fn test_busy_gauge() {
+        let mut gauge = State {
+            busy_gauge: true,
+            ..state(View::Agenda)
+        };
+        let frame = gauge.draw(12, 50);
+        let footer = frame.lines.last().unwrap();
+        // Standup and lunch: 1 hr 45 min of 8 hours, half an hour of it done
+        assert_eq!(footer.plain(), "██░░░░░░░░ 22% booked · 1 hr 15 min to go");
+        assert!(footer.spans[0].style.dimmed);
+        assert_eq!(footer.spans[1].style.color, Some(AnsiColors::Green));
+    }
+
+    #[test]
+    fn test_peek() {
+        let mut peek = State {
+            peek: true,
+            ..state(View::Agenda)
+        };
+        let frame = peek.draw(12, 40);
+        let expected = indoc! {"
+            📅 Calendar 10:00 · in meeting · 15 mi...
+            ────────────────────────────────────────
+            today
+              09:30–10:15 📹 Standup
+              12:00–13:00 • Lunch with Sam
+            tomorrow
+              09:00–10:00 • Planning
+            wednesday, jan 17
+              all day • Offsite"};
+        assert_eq!(frame.plain(), expected);
+        // Still highlighted as in progress
+        assert!(frame.lines[3].spans[1].style.bold);
+    }
+
+    #[test]
+    fn test_occasions() {
+        let mut state = state(View::List);
+        let birthday = |summary: &str, day: &str, next: &str| Event {
+            summary: summary.to_string(),
+            start: parse_datetime(day).unwrap(),
+            end: parse_datetime(next),
+            is_all_day: true,
+            ..Default::default()
+        };
+        state.sources[0].events = state.store.events().to_vec();
+        state.sources[0].occasions = vec![
+            birthday("Kim's birthday", "2024-01-15 00:00", "2024-01-16 00:00"),
+            birthday("Ana's birthday", "2024-01-20 00:00", "2024-01-21 00:00"),
+        ];
+        state.store = EventStore::merge(
+            &state.sources,
+            &Default::default(),
+            &[],
+            false,
+            state.current_time,
+            &calendar::EventWindow {
+                occasion_days: 14,
+                ..Default::default()
+            },
+        );
+        let frame = state.draw(10, 44);
+        let expected = indoc! {"
+            📅 Calendar 10:00 · in meeting · 15 min left
+            ────────────────────────────────────────────
+            now 📹 Standup
+            12:00 (2 hrs) • Lunch with Sam
+            tmrw 09:00 • Planning
+            wednesday, jan 17 • Offsite
+            🎂 Kim's birthday · today
+            🎂 Ana's birthday · sat jan 20 · in 5 days"};
+        assert_eq!(frame.plain(), expected);
+    }
+
+    #[test]
+    fn test_late_start() {
+        let mut late = State {
+            late_minutes: 10,
+            current_time: parse_datetime("2024-01-15 09:35"),
+            ..state(View::Agenda)
+        };
+        let frame = late.draw(12, 40);
+        assert_eq!(frame.lines[3].plain(), "  started 5 min ago 📹 Standup");
+        assert_eq!(
+            frame.lines[3].spans[1].style.color,
+            Some(AnsiColors::Yellow)
+        );
+
+        // Opening the link counts as joining
+        let key = reminder::key(&late.store.events()[0]);
+        late.joined.insert(key);
+        assert_eq!(late.draw(12, 40).lines[3].plain(), "  now 📹 Standup");
+
+        // Long enough in, it's just "now"
+        late.joined.clear();
+        late.current_time = parse_datetime("2024-01-15 09:45");
+        assert_eq!(late.draw(12, 40).lines[3].plain(), "  now 📹 Standup");
+    }
+
+    #[test]
+    fn test_organized_marker() {
+        let mut state = state(View::Agenda);
+        state.filter.my_email = Some("me@example.com".to_string());
+        let mut events: Vec<Event> = state.store.all().into_iter().cloned().collect();
+        events[2].organizer = Some(crate::calendar::Attendee {
+            email: "me@example.com".to_string(),
+            ..Default::default()
+        });
+        state.store = EventStore::from(events);
+        let frame = state.draw(12, 40);
+        assert_eq!(frame.lines[4].plain(), "  12:00 (2 hrs) • Lunch with Sam");
+        assert_eq!(frame.lines[6].plain(), "  09:00 • Planning ★");
+    }
+
+    #[test]
+    fn test_day_start() {
+        // 1 AM Tuesday is still Monday night until 4 AM
+        let mut late = State {
+            day_start: NaiveTime::from_hms_opt(4, 0, 0).unwrap(),
+            current_time: parse_datetime("2024-01-16 01:00"),
+            ..state(View::Agenda)
+        };
+        late.store = EventStore::from(vec![
+            Event {
+                summary: "Deploy".to_string(),
+                start: parse_datetime("2024-01-16 02:00").unwrap(),
+                end: parse_datetime("2024-01-16 03:00"),
+                ..Default::default()
+            },
+            Event {
+                summary: "Planning".to_string(),
+                start: parse_datetime("2024-01-16 09:00").unwrap(),
+                end: parse_datetime("2024-01-16 10:00"),
+                ..Default::default()
+            },
+        ]);
+        let frame = late.draw(12, 40);
+        let expected = indoc! {"
+            📅 Calendar 01:00
+            ────────────────────────────────────────
+            today
+              02:00 (1 hr) • Deploy
+            tomorrow
+              09:00 • Planning"};
+        assert_eq!(frame.plain(), expected);
+    }
+
+    #[test]
+    fn test_search_highlight() {
+        let mut search = State {
+            search: Some("lnch".to_string()),
+            ..state(View::List)
+        };
+        let frame = search.draw(10, 40);
+        let lunch = &frame.lines[3];
+        assert!(lunch.plain().ends_with("Lunch with Sam"));
+        let matched: Vec<&str> = lunch
+            .spans
+            .iter()
+            .filter(|span| span.style.underline)
+            .map(|span| span.text.as_str())
+            .collect();
+        assert_eq!(matched, ["L", "nch"]);
+    }
+
+    #[test]
+    fn test_day_progress() {
+        let mut state = state(View::Agenda);
+        state.day_progress = true;
+        let frame = state.draw(12, 40);
+        assert_eq!(
+            frame.lines[1].plain(),
+            "09:00 ─━━●━─────━━━━────────────── 17:00"
+        );
+        let spans = &frame.lines[1].spans;
+        // Standup, partly behind and partly ahead of now; then lunch ahead
+        assert!(spans[2].style.dimmed && spans[3].style.dimmed);
+        assert_eq!(spans[4].style.color, Some(AnsiColors::Green));
+        assert_eq!(spans[5].style.color, Some(AnsiColors::Cyan));
+        assert_eq!(spans[11].style.color, Some(AnsiColors::Cyan));
+        assert!(frame.lines[2].plain().starts_with('─'));
+    }
+
+    #[test]
+    fn test_screen_reader() {
+        let mut state = state(View::Agenda);
+        state.screen_reader = true;
+        state.selected = Some(1);
+        let expected = indoc! {"
+            Calendar, 10:00
+            Standup, in progress, ends in 15 minutes
+            at 10:15, video call
+            Selected: Lunch with Sam, starts in 2
+            hours at 12:00
+            2 more events"};
+        assert_eq!(state.draw(6, 40).plain(), expected);
+        assert!(state.draw(6, 40).to_string().is_ascii());
+    }
+
+    #[test]
+    fn test_styles() {
+        let frame = state(View::Agenda).draw(12, 40);
+        let now = &frame.lines[3].spans;
+        assert_eq!(now[1].text, "now");
+        assert_eq!(now[1].style.color, Some(AnsiColors::Green));
+        assert!(now[1].style.bold && now[5].style.bold);
+        let lunch = &frame.lines[4].spans;
+        assert_eq!(lunch[1].style.color, Some(AnsiColors::Cyan));
+        assert!(!lunch[5].style.bold);
+        assert!(frame.lines[2].spans[0].style.bold);
+
+        let ansi = frame.to_string();
+        let style = owo_colors::Style::new().green().bold();
+        assert!(ansi.contains(&"now".style(style).to_string()));
+        assert_eq!(ansi.lines().count(), frame.len());
+        assert!(!ansi.ends_with('\n'));
+
+        let mut plain = state(View::Agenda);
+        plain.theme.color = ColorMode::None;
+        let frame = plain.draw(12, 40);
+        let spans = frame.lines.iter().flat_map(|line| &line.spans);
+        assert!(spans.clone().all(|span| span.style.color.is_none()));
+        assert!(spans.clone().any(|span| span.style.bold));
+        // What the colors said is spelled out instead
+        assert_eq!(frame.lines[3].plain(), "  now 📹 Standup [NOW]");
+        assert_eq!(frame.lines[4].plain(), "  12:00 (2 hrs) • Lunch with Sam");
+
+        let mut contrast = state(View::Agenda);
+        contrast.theme.color = ColorMode::HighContrast;
+        let frame = contrast.draw(12, 40);
+        let now = &frame.lines[3].spans;
+        assert_eq!(now[1].style.color, Some(AnsiColors::BrightGreen));
+        assert_eq!(
+            frame.lines[4].spans[1].style.color,
+            Some(AnsiColors::BrightCyan)
+        );
+        assert!(frame.lines[4].spans[1].style.bold);
+    }
+}
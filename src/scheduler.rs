@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+
+/// Jobs due this close after a timer fires run with it (timer times are floats).
+const SLACK_SECS: f64 = 0.001;
+
+/// A timed job. Each is scheduled at most once; scheduling it again moves it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Job {
+    /// Read the clock and run the refresh work (`State::on_time`), every `TIME_TICK_SECS`.
+    Tick,
+    /// Live ticks while focus mode or a focus timer counts down.
+    Countdown,
+    /// Queue every calendar for fetching, every `refresh_interval`.
+    Refresh,
+    /// Fetch a rate-limited source (by index) once its `Retry-After` has passed.
+    Retry(usize),
+    /// Tick early for the soonest reminder, instead of alerting up to a tick late.
+    Reminder,
+    /// Tick early when the next event starts or ends, so "now" and countdowns flip on time.
+    Boundary,
+    /// Go back to relative times after a peek at absolute ones (`T`).
+    EndPeek,
+}
+
+/// Timed jobs run off the host's one kind of timer. Timers can't be cancelled, so a new one
+/// is only set when none already armed fires by the soonest job.
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    now: f64,                 // Seconds since load, as of the last timer
+    jobs: BTreeMap<Job, f64>, // When each job is due
+    armed: Vec<f64>,          // When the timers set will fire, soonest first
+}
+
+impl Scheduler {
+    /// Runs `job` in `delay_secs`, moving it if it was already scheduled.
+    pub fn schedule(&mut self, job: Job, delay_secs: f64) {
+        self.jobs.insert(job, self.now + delay_secs.max(0.0));
+    }
+
+    pub fn cancel(&mut self, job: Job) {
+        self.jobs.remove(&job);
+    }
+
+    pub fn is_scheduled(&self, job: Job) -> bool {
+        self.jobs.contains_key(&job)
+    }
+
+    /// Seconds since load, as of the last timer.
+    pub fn now(&self) -> f64 {
+        self.now
+    }
+
+    /// Takes a timer firing (`elapsed` is how long it slept): advances to its time and returns
+    /// the jobs due by then, soonest first. Recurring jobs schedule themselves again.
+    pub fn fire(&mut self, elapsed: f64) -> Vec<Job> {
+        let at = match self.armed.is_empty() {
+            true => self.now + elapsed,
+            false => self.armed.remove(0),
+        };
+        self.now = self.now.max(at);
+        let mut due: Vec<(Job, f64)> = self
+            .jobs
+            .iter()
+            .filter(|(_, &at)| at <= self.now + SLACK_SECS)
+            .map(|(&job, &at)| (job, at))
+            .collect();
+        due.sort_by(|a, b| a.1.total_cmp(&b.1));
+        due.iter().for_each(|(job, _)| self.cancel(*job));
+        due.into_iter().map(|(job, _)| job).collect()
+    }
+
+    /// The timeout to set for the soonest job, unless a timer already armed fires by then.
+    pub fn next_timeout(&mut self) -> Option<f64> {
+        let due = self.jobs.values().copied().min_by(f64::total_cmp)?;
+        if self.armed.first().is_some_and(|&at| at <= due + SLACK_SECS) {
+            return None;
+        }
+        let at = due.max(self.now);
+        let idx = self.armed.partition_point(|&armed| armed < at);
+        self.armed.insert(idx, at);
+        Some(at - self.now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fire() {
+        let mut scheduler = Scheduler::default();
+        scheduler.schedule(Job::Tick, 30.0);
+        scheduler.schedule(Job::Refresh, 300.0);
+        scheduler.schedule(Job::Retry(1), 10.0);
+        assert_eq!(scheduler.next_timeout(), Some(10.0));
+        // Already armed for the soonest job
+        assert_eq!(scheduler.next_timeout(), None);
+
+        assert_eq!(scheduler.fire(10.0), [Job::Retry(1)]);
+        assert_eq!(scheduler.next_timeout(), Some(20.0));
+        // A sooner job needs its own timer; the later one still fires after
+        scheduler.schedule(Job::Countdown, 1.0);
+        assert_eq!(scheduler.next_timeout(), Some(1.0));
+        assert_eq!(scheduler.fire(1.0), [Job::Countdown]);
+        assert_eq!(scheduler.now(), 11.0);
+
+        scheduler.schedule(Job::Boundary, 19.0);
+        assert_eq!(scheduler.next_timeout(), None);
+        assert_eq!(scheduler.fire(20.0), [Job::Tick, Job::Boundary]);
+        assert_eq!(scheduler.now(), 30.0);
+        assert!(scheduler.is_scheduled(Job::Refresh));
+        scheduler.cancel(Job::Refresh);
+        assert_eq!(scheduler.next_timeout(), None);
+    }
+}
@@ -0,0 +1,113 @@
+use crate::calendar::Event;
+
+/// Points for each matched character.
+const MATCH_SCORE: i64 = 16;
+/// Extra for a character right after the previous match ("rev" in "review").
+const CONSECUTIVE_BONUS: i64 = 12;
+/// Extra for a character starting a word ("dr" in "Design Review").
+const WORD_START_BONUS: i64 = 10;
+/// Lost per skipped character between two matches.
+const GAP_PENALTY: i64 = 1;
+
+/// A fuzzy match of a query in one piece of text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub score: i64,
+    /// Char indices of the matched characters in the text.
+    pub positions: Vec<usize>,
+}
+
+/// Matches `query`'s characters in order (a subsequence), ignoring case and the query's
+/// spaces, so "dsgn rev" finds "Design Review". Earliest matches are taken greedily, then
+/// word starts and runs score higher.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<Match> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if query.is_empty() {
+        return None;
+    }
+    let chars: Vec<char> = text.chars().collect();
+    let mut positions = Vec::with_capacity(query.len());
+    let mut next = 0;
+    for &wanted in &query {
+        let found = (next..chars.len()).find(|&i| chars[i].to_lowercase().eq([wanted]))?;
+        positions.push(found);
+        next = found + 1;
+    }
+
+    let is_word_start = |i: usize| i == 0 || !chars[i - 1].is_alphanumeric();
+    let mut score = 0;
+    for (n, &pos) in positions.iter().enumerate() {
+        score += MATCH_SCORE;
+        if is_word_start(pos) {
+            score += WORD_START_BONUS;
+        }
+        match n.checked_sub(1).map(|prev| positions[prev]) {
+            Some(prev) if pos == prev + 1 => score += CONSECUTIVE_BONUS,
+            Some(prev) => score -= (pos - prev - 1) as i64 * GAP_PENALTY,
+            None => score -= pos as i64 * GAP_PENALTY,
+        }
+    }
+    Some(Match { score, positions })
+}
+
+/// Best score of `query` across the event's summary, location, and organizer.
+pub fn score_event(query: &str, event: &Event) -> Option<i64> {
+    let organizer = event.organizer.as_ref();
+    [
+        Some(event.summary.as_str()),
+        event.location.as_deref(),
+        organizer.and_then(|o| o.name.as_deref()),
+        organizer.map(|o| o.email.as_str()),
+    ]
+    .into_iter()
+    .flatten()
+    .filter_map(|text| fuzzy_match(query, text))
+    .map(|m| m.score)
+    .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::Attendee;
+
+    #[test]
+    fn test_fuzzy_match() {
+        let m = fuzzy_match("dsgn rev", "Design Review").unwrap();
+        assert_eq!(m.positions, vec![0, 2, 4, 5, 7, 8, 9]);
+        assert_eq!(
+            fuzzy_match("REV", "Design Review").unwrap().positions,
+            [7, 8, 9]
+        );
+        assert_eq!(fuzzy_match("xyz", "Design Review"), None);
+        assert_eq!(fuzzy_match("  ", "Design Review"), None);
+
+        // Runs and word starts beat scattered letters
+        let run = fuzzy_match("plan", "Planning").unwrap();
+        let scattered = fuzzy_match("plan", "Pull request and notes").unwrap();
+        assert!(run.score > scattered.score);
+    }
+
+    #[test]
+    fn test_score_event() {
+        let event = Event {
+            summary: "Sync".to_string(),
+            location: Some("Room 4B".to_string()),
+            organizer: Some(Attendee {
+                email: "dana@example.com".to_string(),
+                partstat: None,
+                name: Some("Dana Lee".to_string()),
+            }),
+            ..Default::default()
+        };
+        assert!(score_event("sync", &event).is_some());
+        assert!(score_event("rm4b", &event).is_some());
+        assert!(score_event("dana", &event).is_some());
+        assert!(score_event("example", &event).is_some());
+        assert_eq!(score_event("retro", &event), None);
+    }
+}
@@ -0,0 +1,442 @@
+use crate::calendar::Event;
+use crate::config::{CalendarConfig, FetchAuth};
+use chrono::{Duration, NaiveDateTime};
+use owo_colors::AnsiColors;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Marker prefixing the curl `--write-out` line on stderr.
+const FETCH_INFO_MARKER: &str = "zj-cal-fetch-info:";
+/// Markers prefixing the response's `ETag` and `Last-Modified` headers on stderr.
+const ETAG_MARKER: &str = "zj-cal-etag:";
+const LAST_MODIFIED_MARKER: &str = "zj-cal-last-modified:";
+/// Marker prefixing the `Retry-After` header of a rate-limited response.
+const RETRY_AFTER_MARKER: &str = "zj-cal-retry-after:";
+
+/// curl `--write-out` format reporting status, redirect count, and final URL on stderr,
+/// then the cache validators and `Retry-After` (`%header` needs curl 7.84+; older versions
+/// leave them blank).
+pub const FETCH_INFO_FORMAT: &str =
+    "%{stderr}\nzj-cal-fetch-info: %{http_code} %{num_redirects} %{url_effective}\n\
+    zj-cal-etag: %header{etag}\nzj-cal-last-modified: %header{last-modified}\n\
+    zj-cal-retry-after: %header{retry-after}\n";
+
+/// HTTP status for a conditional fetch of an unchanged feed.
+pub const HTTP_NOT_MODIFIED: u16 = 304;
+
+/// HTTP status for a rate-limited fetch.
+pub const HTTP_TOO_MANY_REQUESTS: u16 = 429;
+
+/// Wait after a 429 without a usable `Retry-After`.
+const DEFAULT_RETRY_AFTER_SECS: i64 = 300;
+
+/// Longest `Retry-After` honored, so a bogus header can't stop refreshes for good.
+const MAX_RETRY_AFTER_SECS: i64 = 24 * 60 * 60;
+
+/// curl's exit code when `--max-filesize` is exceeded.
+pub const CURL_FILESIZE_EXCEEDED: i32 = 63;
+
+/// Prints every file matching the glob in `$1` (with `~/` expanded), one after another; a
+/// directory (a vdir collection) reads its `.ics` files. `IFS` is cleared so paths with
+/// spaces survive the unquoted expansion.
+const READ_FILES_SCRIPT: &str = r#"IFS=''; pattern=$1
+case $pattern in "~/"*) pattern="$HOME/${pattern#"~/"}" ;; esac
+[ -d "$pattern" ] && pattern="${pattern%/}/*.ics"
+found=0
+for f in $pattern; do [ -f "$f" ] || continue; cat "$f" && echo; found=1; done
+[ $found = 1 ] || { echo "No files match $1" >&2; exit 1; }"#;
+
+/// A response's `ETag` and `Last-Modified`, sent back as `If-None-Match` and
+/// `If-Modified-Since` so an unchanged feed answers 304 without a body.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    /// curl args making the fetch conditional (none when the server sent no validators).
+    fn header_args(&self) -> Vec<String> {
+        let etag = self.etag.iter().map(|e| format!("If-None-Match: {}", e));
+        let modified = self
+            .last_modified
+            .iter()
+            .map(|m| format!("If-Modified-Since: {}", m));
+        etag.chain(modified)
+            .flat_map(|header| ["--header".to_string(), header])
+            .collect()
+    }
+}
+
+/// Response metadata for the last fetch, from curl's `--write-out`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchInfo {
+    pub http_code: u16,
+    pub redirects: u32,
+    pub final_url: String,
+    pub validators: Validators,
+    /// The raw `Retry-After` header (seconds or an HTTP date).
+    pub retry_after: Option<String>,
+}
+
+impl FetchInfo {
+    /// Splits curl's stderr into the fetch info line (if any) and the remaining error text.
+    pub fn extract(stderr: &[u8]) -> (Option<Self>, String) {
+        let stderr = String::from_utf8_lossy(stderr);
+        let mut info = None;
+        let mut validators = Validators::default();
+        let mut retry_after = None;
+        let mut rest = Vec::new();
+        let header = |value: &str| Some(value.trim().to_string()).filter(|v| !v.is_empty());
+        for line in stderr.lines() {
+            if let Some(fields) = line.strip_prefix(FETCH_INFO_MARKER) {
+                info = Self::parse(fields);
+            } else if let Some(etag) = line.strip_prefix(ETAG_MARKER) {
+                validators.etag = header(etag);
+            } else if let Some(modified) = line.strip_prefix(LAST_MODIFIED_MARKER) {
+                validators.last_modified = header(modified);
+            } else if let Some(value) = line.strip_prefix(RETRY_AFTER_MARKER) {
+                retry_after = header(value);
+            } else if !line.trim().is_empty() {
+                rest.push(line);
+            }
+        }
+        let info = info.map(|info| Self {
+            validators,
+            retry_after,
+            ..info
+        });
+        (info, rest.join("\n"))
+    }
+
+    fn parse(fields: &str) -> Option<Self> {
+        let mut fields = fields.trim().splitn(3, ' ');
+        Some(Self {
+            http_code: fields.next()?.parse().ok()?,
+            redirects: fields.next()?.parse().ok()?,
+            final_url: fields.next().unwrap_or_default().to_string(),
+            validators: Validators::default(),
+            retry_after: None,
+        })
+    }
+
+    /// For a rate-limited (429) response, the local time to retry at: `Retry-After` seconds
+    /// or HTTP date, else a default wait. `None` for any other response.
+    pub fn retry_at(&self, now: NaiveDateTime, utc_offset_minutes: i32) -> Option<NaiveDateTime> {
+        if self.http_code != HTTP_TOO_MANY_REQUESTS {
+            return None;
+        }
+        let value = self.retry_after.as_deref().unwrap_or_default().trim();
+        let secs = value.parse::<i64>().ok().or_else(|| {
+            let date = NaiveDateTime::parse_from_str(value, "%a, %d %b %Y %H:%M:%S GMT").ok()?;
+            let local = date + Duration::minutes(utc_offset_minutes as i64);
+            Some((local - now).num_seconds())
+        });
+        let secs = secs.unwrap_or(DEFAULT_RETRY_AFTER_SECS);
+        Some(now + Duration::seconds(secs.clamp(0, MAX_RETRY_AFTER_SECS)))
+    }
+}
+
+/// A configured calendar feed and the results of its latest fetch.
+pub struct Source {
+    pub name: String,
+    pub url: String,
+    pub color: Option<AnsiColors>,
+    pub label: Option<String>,
+    pub overlay: bool,
+    pub caldav: bool,
+    pub auth: FetchAuth,
+    pub events: Vec<Event>,
+    /// Upcoming birthdays and anniversaries, kept out of `events` (see `birthday_lookahead_days`).
+    pub occasions: Vec<Event>,
+    pub error: Option<String>,
+    /// Response metadata from the most recent fetch attempt.
+    pub fetch_info: Option<FetchInfo>,
+    /// Time of the last successful fetch (`None` until the first one this session).
+    pub last_fetched: Option<NaiveDateTime>,
+    /// Fetch time of the events restored from the disk cache.
+    pub cached_at: Option<NaiveDateTime>,
+    /// When `events` were parsed from a full response (this session or a restored cache).
+    pub parsed_at: Option<NaiveDateTime>,
+    /// Validators of the response `events` were parsed from.
+    pub validators: Validators,
+    /// Rate limited: no fetches before this.
+    pub retry_at: Option<NaiveDateTime>,
+    /// A CalDAV fetch in time-range chunks, while its later chunks are still to come.
+    pub chunks: Option<ChunkedFetch>,
+}
+
+/// A fetch split into time ranges (`caldav_chunk_days`), requested one after another. Events
+/// are shown as each chunk arrives; ranges not yet refetched keep the previous fetch's.
+#[derive(Default)]
+pub struct ChunkedFetch {
+    /// UTC ranges still to request, soonest first.
+    pub remaining: VecDeque<(NaiveDateTime, NaiveDateTime)>,
+    /// Events of the chunks so far, unfiltered. Events spanning two chunks come back in both.
+    pub events: Vec<Event>,
+    /// The source's events (and occasions) from before this fetch.
+    pub previous: Vec<Event>,
+}
+
+impl From<CalendarConfig> for Source {
+    fn from(config: CalendarConfig) -> Self {
+        Self {
+            name: config.name,
+            url: config.url,
+            color: config.color,
+            label: config.label,
+            overlay: config.overlay,
+            caldav: config.caldav,
+            auth: config.auth,
+            events: Vec::new(),
+            occasions: Vec::new(),
+            error: None,
+            fetch_info: None,
+            last_fetched: None,
+            cached_at: None,
+            parsed_at: None,
+            validators: Validators::default(),
+            retry_at: None,
+            chunks: None,
+        }
+    }
+}
+
+impl Source {
+    /// When the events still shown were fetched, if the latest fetch failed and they're
+    /// being kept as a fallback (`None` when fresh, or the fetch time is unknown).
+    pub fn stale_since(&self) -> Option<NaiveDateTime> {
+        if self.error.is_none() || self.events.is_empty() {
+            return None;
+        }
+        self.last_fetched.or(self.cached_at)
+    }
+
+    /// The path or glob for feeds on disk (`file://...`, `/...`, or `~/...`), read without curl.
+    pub fn local_path(&self) -> Option<&str> {
+        let url = self.url.trim();
+        url.strip_prefix("file://")
+            .or_else(|| (url.starts_with('/') || url.starts_with("~/")).then_some(url))
+    }
+
+    /// The command printing every local file this feed matches (see `local_path`).
+    pub fn read_command(path: &str) -> [&str; 5] {
+        ["sh", "-c", READ_FILES_SCRIPT, "sh", path]
+    }
+
+    /// The curl invocation fetching this feed; `request_args` (e.g., `--output`, or a CalDAV
+    /// REPORT) precede the URL.
+    /// With basic auth, curl runs under `sh` so the password comes from `basic_auth_pass_cmd`
+    /// at fetch time instead of living in the config. A `conditional` fetch sends the
+    /// validators of the events already held.
+    pub fn fetch_command(
+        &self,
+        max_payload_bytes: u64,
+        request_args: &[String],
+        conditional: bool,
+    ) -> Vec<String> {
+        let mut args: Vec<String> = [
+            "-sSfL",
+            "--write-out",
+            FETCH_INFO_FORMAT,
+            "--max-filesize",
+            &max_payload_bytes.to_string(),
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        if let Some(ref header) = self.auth.header {
+            args.push("--header".to_string());
+            args.push(header.clone());
+        }
+        if conditional {
+            args.extend(self.validators.header_args());
+        }
+        args.extend(self.auth.extra_curl_args.iter().cloned());
+        args.extend(request_args.iter().cloned());
+        args.push("--".to_string());
+        args.push(self.url.clone());
+
+        let Some(ref user) = self.auth.basic_auth_user else {
+            return std::iter::once("curl".to_string()).chain(args).collect();
+        };
+        let script = r#"pass=$(sh -c "$1") || exit 1; user=$2; shift 2; exec curl --user "$user:$pass" "$@""#;
+        let pass_cmd = self.auth.basic_auth_pass_cmd.as_deref().unwrap_or("true");
+        ["sh", "-c", script, "sh", pass_cmd, user]
+            .iter()
+            .map(|s| s.to_string())
+            .chain(args)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(auth: FetchAuth) -> Source {
+        source_at("https://example.com/work.ics", auth)
+    }
+
+    fn source_at(url: &str, auth: FetchAuth) -> Source {
+        Source::from(CalendarConfig {
+            name: "work".to_string(),
+            url: url.to_string(),
+            color: None,
+            label: None,
+            overlay: false,
+            caldav: false,
+            auth,
+        })
+    }
+
+    #[test]
+    fn test_fetch_command() {
+        let plain = source(FetchAuth::default()).fetch_command(1024, &[], false);
+        assert_eq!(plain[0], "curl");
+        assert_eq!(
+            &plain[plain.len() - 2..],
+            ["--", "https://example.com/work.ics"]
+        );
+
+        let mut cached = source(FetchAuth::default());
+        cached.validators = Validators {
+            etag: Some("\"abc\"".to_string()),
+            last_modified: Some("Mon, 15 Jan 2024 09:00:00 GMT".to_string()),
+        };
+        let conditional = cached.fetch_command(1024, &[], true);
+        assert_eq!(
+            &conditional[conditional.len() - 6..],
+            [
+                "--header",
+                "If-None-Match: \"abc\"",
+                "--header",
+                "If-Modified-Since: Mon, 15 Jan 2024 09:00:00 GMT",
+                "--",
+                "https://example.com/work.ics"
+            ]
+        );
+        assert_eq!(cached.fetch_command(1024, &[], false), plain);
+
+        let auth = FetchAuth {
+            header: Some("Authorization: Bearer abc".to_string()),
+            basic_auth_user: Some("me".to_string()),
+            basic_auth_pass_cmd: Some("pass show work".to_string()),
+            extra_curl_args: vec!["--insecure".to_string()],
+        };
+        let output = ["--output".to_string(), "/tmp/work.ics".to_string()];
+        let cmd = source(auth).fetch_command(1024, &output, false);
+        assert_eq!(&cmd[..2], ["sh", "-c"]);
+        assert_eq!(&cmd[3..6], ["sh", "pass show work", "me"]);
+        let curl_args = &cmd[6..];
+        assert_eq!(curl_args[0], "-sSfL");
+        let header = curl_args.iter().position(|a| a == "--header").unwrap();
+        assert_eq!(curl_args[header + 1], "Authorization: Bearer abc");
+        assert_eq!(
+            &curl_args[curl_args.len() - 5..],
+            [
+                "--insecure",
+                "--output",
+                "/tmp/work.ics",
+                "--",
+                "https://example.com/work.ics"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_local_path() {
+        let path = |url| {
+            source_at(url, FetchAuth::default())
+                .local_path()
+                .map(String::from)
+        };
+        assert_eq!(path("https://example.com/work.ics"), None);
+        assert_eq!(
+            path("file:///home/me/cal.ics").as_deref(),
+            Some("/home/me/cal.ics")
+        );
+        assert_eq!(path("/srv/cal/*.ics").as_deref(), Some("/srv/cal/*.ics"));
+        assert_eq!(
+            path("~/.calendars/*/*.ics").as_deref(),
+            Some("~/.calendars/*/*.ics")
+        );
+    }
+
+    #[test]
+    fn test_retry_at() {
+        let now = crate::calendar::parse_datetime("2024-01-15 14:00").unwrap();
+        let info = |http_code, retry_after: Option<&str>| FetchInfo {
+            http_code,
+            redirects: 0,
+            final_url: String::new(),
+            validators: Validators::default(),
+            retry_after: retry_after.map(String::from),
+        };
+        let at = |s| crate::calendar::parse_datetime(s);
+        assert_eq!(
+            info(429, Some("300")).retry_at(now, 0),
+            at("2024-01-15 14:05")
+        );
+        // HTTP dates are in GMT; the offset makes them local
+        let date = Some("Mon, 15 Jan 2024 22:10:00 GMT");
+        assert_eq!(info(429, date).retry_at(now, -480), at("2024-01-15 14:10"));
+        let past = Some("Mon, 15 Jan 2024 08:00:00 GMT");
+        assert_eq!(info(429, past).retry_at(now, 0), Some(now));
+        assert_eq!(info(429, None).retry_at(now, 0), at("2024-01-15 14:05"));
+        assert_eq!(
+            info(429, Some("99999999")).retry_at(now, 0),
+            at("2024-01-16 14:00")
+        );
+        assert_eq!(info(503, Some("120")).retry_at(now, 0), None);
+    }
+
+    #[test]
+    fn test_stale_since() {
+        let mut source = source(FetchAuth::default());
+        source.cached_at = crate::calendar::parse_datetime("2024-01-15 09:00");
+        source.error = Some("Fetch failed".to_string());
+        assert_eq!(source.stale_since(), None); // Nothing to fall back on
+
+        source.events.push(Event::default());
+        assert_eq!(source.stale_since(), source.cached_at);
+        source.last_fetched = crate::calendar::parse_datetime("2024-01-15 10:00");
+        assert_eq!(source.stale_since(), source.last_fetched);
+
+        source.error = None;
+        assert_eq!(source.stale_since(), None);
+    }
+
+    #[test]
+    fn test_extract_fetch_info() {
+        let stderr = b"curl: (22) The requested URL returned error: 404\n\nzj-cal-fetch-info: 404 2 https://example.com/login?next=/cal\n";
+        let (info, rest) = FetchInfo::extract(stderr);
+        assert_eq!(
+            info,
+            Some(FetchInfo {
+                http_code: 404,
+                redirects: 2,
+                final_url: "https://example.com/login?next=/cal".into(),
+                validators: Validators::default(),
+                retry_after: None,
+            })
+        );
+        assert_eq!(rest, "curl: (22) The requested URL returned error: 404");
+
+        let stderr = b"\nzj-cal-fetch-info: 304 0 https://example.com/cal.ics\nzj-cal-etag: \"abc\"\nzj-cal-last-modified: \n";
+        let (info, rest) = FetchInfo::extract(stderr);
+        let info = info.unwrap();
+        assert_eq!(info.http_code, HTTP_NOT_MODIFIED);
+        assert_eq!(info.validators.etag.as_deref(), Some("\"abc\""));
+        assert_eq!(info.validators.last_modified, None);
+        assert_eq!(rest, "");
+
+        let stderr = b"curl: (22) The requested URL returned error: 429\n\nzj-cal-fetch-info: 429 0 https://example.com/cal.ics\nzj-cal-retry-after: 120\n";
+        let (info, _) = FetchInfo::extract(stderr);
+        assert_eq!(info.unwrap().retry_after.as_deref(), Some("120"));
+
+        let (info, rest) = FetchInfo::extract(b"curl: (6) Could not resolve host");
+        assert_eq!(info, None);
+        assert_eq!(rest, "curl: (6) Could not resolve host");
+    }
+}
@@ -0,0 +1,310 @@
+use crate::calendar::{self, Event, EventWindow, WorkingHours};
+use crate::reminder;
+use crate::source::Source;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use std::collections::HashSet;
+
+/// Events merged from every source, and the queries views, reminders, exports, and pipes
+/// ask of them. Calendars' events and overlay busy blocks are kept apart, both by start.
+#[derive(Default)]
+pub struct EventStore {
+    events: Vec<Event>,
+    overlay: Vec<Event>,
+    /// Birthdays and anniversaries within `EventWindow::occasion_days`, by start.
+    occasions: Vec<Event>,
+}
+
+impl EventStore {
+    /// Merges the sources' events, without `hidden` ones (by `reminder::key`) or occurrences
+    /// of `hidden_series` (by `Event::pin_id`), trimmed to what `window` keeps at `now`.
+    /// Sources' birthdays and anniversaries are kept apart, through `occasions_until`.
+    pub fn merge(
+        sources: &[Source],
+        hidden: &HashSet<String>,
+        hidden_series: &[String],
+        collapse_series: bool,
+        now: Option<NaiveDateTime>,
+        window: &EventWindow,
+    ) -> Self {
+        let mut events = Vec::new();
+        let mut overlay = Vec::new();
+        let mut occasions = Vec::new();
+        for source in sources {
+            if source.overlay {
+                overlay.extend(source.events.iter().cloned());
+            } else {
+                events.extend(source.events.iter().cloned());
+                occasions.extend(source.occasions.iter().cloned());
+            }
+        }
+        let is_hidden = |e: &Event| {
+            hidden.contains(&reminder::key(e))
+                || (e.is_recurring() && hidden_series.iter().any(|id| id == e.pin_id()))
+        };
+        events.retain(|e| !is_hidden(e));
+        overlay.retain(|e| !is_hidden(e));
+        occasions.retain(|e| !is_hidden(e));
+        if let Some(now) = now {
+            // All-day ones without an end last through their day
+            let today = now.date().and_time(NaiveTime::MIN);
+            let until = window.occasions_until(now);
+            occasions
+                .retain(|e| e.start < until && e.end.map_or(e.start >= today, |end| end > now));
+        }
+        occasions.sort_by_key(|e| e.start);
+        let events = if collapse_series {
+            calendar::collapse_series(events, now)
+        } else {
+            events
+        };
+        EventStore {
+            events: calendar::filter_future(events, now, window),
+            overlay: calendar::filter_future(overlay, now, window),
+            occasions,
+        }
+    }
+
+    /// Upcoming birthdays and anniversaries, by start.
+    pub fn occasions(&self) -> &[Event] {
+        &self.occasions
+    }
+
+    /// Calendars' events (no overlay blocks), by start.
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Calendars' events and overlay blocks together, by start.
+    pub fn all(&self) -> Vec<&Event> {
+        let mut all: Vec<&Event> = self.events.iter().chain(&self.overlay).collect();
+        all.sort_by_key(|e| e.start);
+        all
+    }
+
+    /// Everything active on `day`, overlay blocks included, by start.
+    pub fn on_day(&self, day: NaiveDate) -> Vec<&Event> {
+        let mut all = self.all();
+        all.retain(|e| e.is_active_on(day));
+        all
+    }
+
+    /// Calendars' events overlapping `[from, to)`; events without an end count as instants.
+    pub fn in_range(&self, from: NaiveDateTime, to: NaiveDateTime) -> Vec<&Event> {
+        self.events
+            .iter()
+            .filter(|e| e.start < to && e.end.map_or(e.start >= from, |end| end > from))
+            .collect()
+    }
+
+    /// The next `count` timed events in progress or to come, as one-line displays pick them.
+    pub fn next(&self, now: NaiveDateTime, count: usize) -> Vec<&Event> {
+        calendar::upcoming_events(&self.events, now)
+            .take(count)
+            .collect()
+    }
+
+    pub fn next_event(&self, now: NaiveDateTime) -> Option<&Event> {
+        calendar::next_event(&self.events, now)
+    }
+
+    /// `reminder::key`s of events whose busy time overlaps another's (double bookings).
+    pub fn conflicts(&self) -> HashSet<String> {
+        let events: Vec<&Event> = self.events.iter().collect();
+        events
+            .iter()
+            .zip(calendar::find_conflicts(&events))
+            .filter(|(_, conflict)| *conflict)
+            .map(|(e, _)| reminder::key(e))
+            .collect()
+    }
+
+    /// Free stretches of at least 15 minutes from `now` to the end of today.
+    pub fn free_slots(&self, now: NaiveDateTime) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+        calendar::free_gaps(&self.events, now)
+    }
+
+    /// How much of today's working hours is booked, and how much of that is behind `now`.
+    pub fn booking(
+        &self,
+        now: NaiveDateTime,
+        hours: &WorkingHours,
+    ) -> Option<calendar::DayBooking> {
+        calendar::day_booking(&self.events, now, hours)
+    }
+}
+
+/// A store of just these calendar events, for tests elsewhere.
+#[cfg(test)]
+impl From<Vec<Event>> for EventStore {
+    fn from(mut events: Vec<Event>) -> Self {
+        events.sort_by_key(|e| e.start);
+        EventStore {
+            events,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::parse_datetime;
+    use crate::config::{CalendarConfig, FetchAuth};
+
+    fn event(summary: &str, start: &str, end: &str) -> Event {
+        Event {
+            summary: summary.to_string(),
+            start: parse_datetime(start).unwrap(),
+            end: parse_datetime(end),
+            ..Default::default()
+        }
+    }
+
+    fn source(name: &str, overlay: bool, events: Vec<Event>) -> Source {
+        Source {
+            events,
+            ..Source::from(CalendarConfig {
+                name: name.to_string(),
+                url: format!("https://example.com/{}.ics", name),
+                color: None,
+                label: None,
+                overlay,
+                caldav: false,
+                auth: FetchAuth::default(),
+            })
+        }
+    }
+
+    fn store() -> EventStore {
+        let work = source(
+            "work",
+            false,
+            vec![
+                event("Review", "2024-01-15 14:00", "2024-01-15 15:00"),
+                event("Standup", "2024-01-15 09:30", "2024-01-15 10:00"),
+                event("1:1", "2024-01-15 14:30", "2024-01-15 15:00"),
+                event("Planning", "2024-01-16 09:00", "2024-01-16 10:00"),
+            ],
+        );
+        let partner = source(
+            "partner",
+            true,
+            vec![event("busy", "2024-01-15 12:00", "2024-01-15 13:00")],
+        );
+        let now = parse_datetime("2024-01-15 09:00");
+        EventStore::merge(
+            &[work, partner],
+            &HashSet::new(),
+            &[],
+            false,
+            now,
+            &EventWindow::default(),
+        )
+    }
+
+    #[test]
+    fn test_merge() {
+        let store = store();
+        let summaries = |events: Vec<&Event>| -> Vec<String> {
+            events.iter().map(|e| e.summary.clone()).collect()
+        };
+        assert_eq!(
+            summaries(store.events().iter().collect()),
+            ["Standup", "Review", "1:1", "Planning"]
+        );
+        assert_eq!(
+            summaries(store.all()),
+            ["Standup", "busy", "Review", "1:1", "Planning"]
+        );
+
+        let hidden = HashSet::from([reminder::key(&store.events()[0])]);
+        let work = source("work", false, store.events().to_vec());
+        let now = parse_datetime("2024-01-15 09:00");
+        let merged = EventStore::merge(&[work], &hidden, &[], false, now, &EventWindow::default());
+        assert_eq!(summaries(merged.all()), ["Review", "1:1", "Planning"]);
+
+        // `max_events` only limits the list; queries still see everything
+        let window = EventWindow {
+            max_events: 2,
+            ..Default::default()
+        };
+        let work = source("work", false, store.events().to_vec());
+        let merged = EventStore::merge(&[work], &HashSet::new(), &[], false, now, &window);
+        assert_eq!(merged.events().len(), 4);
+
+        // Hiding a series leaves one-off events with the same ID alone
+        let mut events = store.events().to_vec();
+        for (event, uid) in events
+            .iter_mut()
+            .zip(["standup", "review", "1:1", "standup"])
+        {
+            event.uid = Some(uid.to_string());
+        }
+        events[0].recurrence_id = parse_datetime("2024-01-15 09:30");
+        let work = source("work", false, events);
+        let series = ["standup".to_string()];
+        let merged = EventStore::merge(
+            &[work],
+            &HashSet::new(),
+            &series,
+            false,
+            now,
+            &EventWindow::default(),
+        );
+        assert_eq!(summaries(merged.all()), ["Review", "1:1", "Planning"]);
+
+        // Birthdays are kept apart, for the days the window looks ahead
+        let birthday = |summary: &str, day: &str, next: &str| Event {
+            is_all_day: true,
+            ..event(summary, day, next)
+        };
+        let mut work = source("work", false, Vec::new());
+        work.occasions = vec![
+            birthday("Anniversary", "2024-03-01 00:00", "2024-03-02 00:00"),
+            birthday("Ana's birthday", "2024-01-21 00:00", "2024-01-22 00:00"),
+            birthday("Sam's birthday", "2024-01-14 00:00", "2024-01-15 00:00"),
+            birthday("Kim's birthday", "2024-01-15 00:00", "2024-01-16 00:00"),
+        ];
+        let window = EventWindow {
+            occasion_days: 7,
+            ..Default::default()
+        };
+        let merged = EventStore::merge(&[work], &HashSet::new(), &[], false, now, &window);
+        assert!(merged.all().is_empty());
+        let occasions: Vec<&str> = merged
+            .occasions()
+            .iter()
+            .map(|e| e.summary.as_str())
+            .collect();
+        assert_eq!(occasions, ["Kim's birthday", "Ana's birthday"]);
+    }
+
+    #[test]
+    fn test_queries() {
+        let store = store();
+        let at = |s| parse_datetime(s).unwrap();
+        let day = at("2024-01-16 00:00").date();
+        assert_eq!(store.on_day(day).len(), 1);
+        assert_eq!(store.on_day(at("2024-01-15 00:00").date()).len(), 4);
+
+        let range = store.in_range(at("2024-01-15 09:45"), at("2024-01-15 14:00"));
+        assert_eq!(range.len(), 1);
+        assert_eq!(range[0].summary, "Standup");
+
+        let now = at("2024-01-15 09:45");
+        let next: Vec<&str> = store
+            .next(now, 2)
+            .iter()
+            .map(|e| e.summary.as_str())
+            .collect();
+        assert_eq!(next, ["Standup", "Review"]);
+        assert_eq!(store.next_event(now).unwrap().summary, "Standup");
+
+        let conflicts = store.conflicts();
+        assert_eq!(conflicts.len(), 2);
+        assert!(!conflicts.contains(&reminder::key(&store.events()[0])));
+
+        let slots = store.free_slots(now);
+        assert_eq!(slots[0], (at("2024-01-15 10:00"), at("2024-01-15 14:00")));
+    }
+}
@@ -0,0 +1,293 @@
+use crate::calendar::Icons;
+use crate::render::{Span, Stylize};
+use owo_colors::AnsiColors;
+use std::fmt::Display;
+
+pub const DEFAULT_HEADER_TEXT: &str = "📅 Calendar";
+
+/// Typical RGB values of the terminal colors that color hints map onto.
+const PALETTE: &[(AnsiColors, (u8, u8, u8))] = &[
+    (AnsiColors::Black, (0, 0, 0)),
+    (AnsiColors::Red, (205, 0, 0)),
+    (AnsiColors::Green, (0, 205, 0)),
+    (AnsiColors::Yellow, (205, 205, 0)),
+    (AnsiColors::Blue, (0, 0, 238)),
+    (AnsiColors::Magenta, (205, 0, 205)),
+    (AnsiColors::Cyan, (0, 205, 205)),
+    (AnsiColors::White, (229, 229, 229)),
+    (AnsiColors::BrightBlack, (127, 127, 127)),
+    (AnsiColors::BrightRed, (255, 0, 0)),
+    (AnsiColors::BrightGreen, (0, 255, 0)),
+    (AnsiColors::BrightYellow, (255, 255, 0)),
+    (AnsiColors::BrightBlue, (92, 92, 255)),
+    (AnsiColors::BrightMagenta, (255, 0, 255)),
+    (AnsiColors::BrightCyan, (0, 255, 255)),
+    (AnsiColors::BrightWhite, (255, 255, 255)),
+];
+
+/// Common CSS color names seen in `COLOR` properties (RFC 7986 uses CSS3 names).
+const CSS_COLORS: &[(&str, (u8, u8, u8))] = &[
+    ("black", (0, 0, 0)),
+    ("white", (255, 255, 255)),
+    ("gray", (128, 128, 128)),
+    ("grey", (128, 128, 128)),
+    ("silver", (192, 192, 192)),
+    ("red", (255, 0, 0)),
+    ("maroon", (128, 0, 0)),
+    ("crimson", (220, 20, 60)),
+    ("tomato", (255, 99, 71)),
+    ("coral", (255, 127, 80)),
+    ("salmon", (250, 128, 114)),
+    ("orange", (255, 165, 0)),
+    ("gold", (255, 215, 0)),
+    ("yellow", (255, 255, 0)),
+    ("olive", (128, 128, 0)),
+    ("lime", (0, 255, 0)),
+    ("green", (0, 128, 0)),
+    ("teal", (0, 128, 128)),
+    ("turquoise", (64, 224, 208)),
+    ("aqua", (0, 255, 255)),
+    ("cyan", (0, 255, 255)),
+    ("skyblue", (135, 206, 235)),
+    ("blue", (0, 0, 255)),
+    ("navy", (0, 0, 128)),
+    ("indigo", (75, 0, 130)),
+    ("purple", (128, 0, 128)),
+    ("violet", (238, 130, 238)),
+    ("orchid", (218, 112, 214)),
+    ("magenta", (255, 0, 255)),
+    ("fuchsia", (255, 0, 255)),
+    ("pink", (255, 192, 203)),
+    ("brown", (165, 42, 42)),
+];
+
+/// Maps a calendar's color hint ("#FF2968", "#FF2968FF", "#f00", or a CSS name like
+/// "turquoise") to the nearest terminal color. Near-black shows as gray, since black
+/// disappears on dark backgrounds.
+pub fn nearest_color(hint: &str) -> Option<AnsiColors> {
+    let hint = hint.trim().to_ascii_lowercase();
+    let (r, g, b) = match hint.strip_prefix('#') {
+        Some(hex) => parse_hex(hex)?,
+        None => CSS_COLORS.iter().find(|(name, _)| *name == hint)?.1,
+    };
+    let distance = |(pr, pg, pb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, pr) + d(g, pg) + d(b, pb)
+    };
+    PALETTE
+        .iter()
+        .min_by_key(|(_, rgb)| distance(*rgb))
+        .map(|(color, _)| match color {
+            AnsiColors::Black => AnsiColors::BrightBlack,
+            color => *color,
+        })
+}
+
+/// "RRGGBB", "RRGGBBAA" (Apple's format; alpha ignored), or "RGB".
+fn parse_hex(hex: &str) -> Option<(u8, u8, u8)> {
+    let channel = |i: usize, len: usize| {
+        let value = u8::from_str_radix(hex.get(i * len..(i + 1) * len)?, 16).ok()?;
+        Some(if len == 1 { value * 17 } else { value })
+    };
+    let len = match hex.len() {
+        3 => 1,
+        6 | 8 => 2,
+        _ => return None,
+    };
+    Some((channel(0, len)?, channel(1, len)?, channel(2, len)?))
+}
+
+/// How much the pane leans on color, set with `color`. Both alternatives to full color spell
+/// out what color alone would say, with markers like `[NOW]` and `[!]`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    #[default]
+    Full,
+    /// No colors at all (bold, dim, and reverse video still apply), for monochrome terminals.
+    /// Also set by `no_color "true"`.
+    None,
+    /// Bright colors in bold, for low vision or washed-out screens.
+    HighContrast,
+}
+
+impl ColorMode {
+    /// Parses a `color` setting: "none" or "high-contrast" ("full" for the default).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "full" | "default" | "normal" => Some(Self::Full),
+            "none" | "off" | "mono" | "monochrome" => Some(Self::None),
+            "high-contrast" | "contrast" => Some(Self::HighContrast),
+            _ => None,
+        }
+    }
+}
+
+/// The bright variant of a terminal color; gray becomes white.
+fn brighten(color: AnsiColors) -> AnsiColors {
+    match color {
+        AnsiColors::Red => AnsiColors::BrightRed,
+        AnsiColors::Green => AnsiColors::BrightGreen,
+        AnsiColors::Yellow => AnsiColors::BrightYellow,
+        AnsiColors::Blue => AnsiColors::BrightBlue,
+        AnsiColors::Magenta => AnsiColors::BrightMagenta,
+        AnsiColors::Cyan => AnsiColors::BrightCyan,
+        AnsiColors::White | AnsiColors::BrightBlack => AnsiColors::BrightWhite,
+        color => color,
+    }
+}
+
+/// Colors, icons, and header text. Configured with `color_header`, `color_now`, `color_time`,
+/// `color_video_icon`, `icon_video`, `icon_in_person`, `icon_all_day`, `header_text`,
+/// and `color` (or `no_color`).
+pub struct Theme {
+    pub header: AnsiColors,
+    /// In-progress events, today's column, and the "in meeting" status.
+    pub now: AnsiColors,
+    /// Event start times.
+    pub time: AnsiColors,
+    /// Defaults to the calendar's `ics_color`.
+    pub video_icon: Option<AnsiColors>,
+    pub header_text: String,
+    pub icons: Icons,
+    pub color: ColorMode,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            header: AnsiColors::Blue,
+            now: AnsiColors::Green,
+            time: AnsiColors::Cyan,
+            video_icon: None,
+            header_text: DEFAULT_HEADER_TEXT.to_string(),
+            icons: Icons::default(),
+            color: ColorMode::Full,
+        }
+    }
+}
+
+impl Theme {
+    /// Colors `text`, unless colors are off.
+    pub fn paint(&self, text: impl Display, color: AnsiColors) -> Span {
+        let span = Span::new(text.to_string());
+        match self.color {
+            ColorMode::Full => span.color(color),
+            ColorMode::None => span,
+            ColorMode::HighContrast => span.color(brighten(color)).bold(),
+        }
+    }
+
+    pub fn no_color(&self) -> bool {
+        self.color == ColorMode::None
+    }
+
+    /// Whether colors can't be relied on, so text or shapes say what they would.
+    pub fn markers(&self) -> bool {
+        self.color != ColorMode::Full
+    }
+
+    /// A marker (" [NOW]") saying what a color would, when colors can't be relied on.
+    pub fn marker(&self, text: &str) -> Option<Span> {
+        self.markers()
+            .then(|| Span::new(format!(" [{}]", text)).bold())
+    }
+
+    /// Colors an event icon: video icons use `video_icon`, the rest their calendar's color.
+    pub fn paint_icon(&self, icon: &str, is_video: bool, source_color: Option<AnsiColors>) -> Span {
+        let color = if is_video {
+            self.video_icon.or(source_color)
+        } else {
+            source_color
+        };
+        match color {
+            Some(color) => self.paint(icon, color),
+            None => Span::new(icon),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paint() {
+        let theme = Theme::default();
+        assert_eq!(
+            theme.paint("9:00", AnsiColors::Cyan),
+            Span::new("9:00").color(AnsiColors::Cyan)
+        );
+        assert_ne!(theme.paint("9:00", AnsiColors::Cyan), Span::new("9:00"));
+
+        let plain = Theme {
+            color: ColorMode::None,
+            ..Default::default()
+        };
+        assert_eq!(plain.paint("9:00", AnsiColors::Cyan), Span::new("9:00"));
+        assert_eq!(
+            plain.paint_icon("📹", true, Some(AnsiColors::Red)),
+            Span::new("📹")
+        );
+
+        let contrast = Theme {
+            color: ColorMode::HighContrast,
+            ..Default::default()
+        };
+        assert_eq!(
+            contrast.paint("9:00", AnsiColors::Cyan),
+            Span::new("9:00").color(AnsiColors::BrightCyan).bold()
+        );
+
+        // Markers only stand in for color when it's not full
+        assert_eq!(theme.marker("NOW"), None);
+        assert_eq!(plain.marker("NOW"), Some(Span::new(" [NOW]").bold()));
+        assert!(contrast.marker("!").is_some());
+    }
+
+    #[test]
+    fn test_color_mode() {
+        assert_eq!(ColorMode::parse("none"), Some(ColorMode::None));
+        assert_eq!(
+            ColorMode::parse(" High_Contrast "),
+            Some(ColorMode::HighContrast)
+        );
+        assert_eq!(ColorMode::parse("full"), Some(ColorMode::Full));
+        assert_eq!(ColorMode::parse("rainbow"), None);
+    }
+
+    #[test]
+    fn test_nearest_color() {
+        let color = |hint| nearest_color(hint);
+        // Apple's default calendar colors, with alpha
+        assert!(matches!(color("#FF2968FF"), Some(AnsiColors::BrightRed)));
+        assert!(matches!(color("#1BADF8"), Some(AnsiColors::Cyan)));
+        // Google's blue
+        assert!(matches!(color("#4285f4"), Some(AnsiColors::BrightBlue)));
+        assert!(matches!(color("#0f0"), Some(AnsiColors::BrightGreen)));
+        assert!(matches!(color("Turquoise"), Some(AnsiColors::Cyan)));
+        assert!(matches!(color("navy"), Some(AnsiColors::Blue)));
+        // Black shows as gray, so it stays visible
+        assert!(matches!(color("black"), Some(AnsiColors::BrightBlack)));
+        assert!(color("#12345").is_none());
+        assert!(color("#zzzzzz").is_none());
+        assert!(color("papayawhip").is_none());
+    }
+
+    #[test]
+    fn test_paint_icon() {
+        let theme = Theme {
+            video_icon: Some(AnsiColors::Magenta),
+            ..Default::default()
+        };
+        let red = Some(AnsiColors::Red);
+        assert_eq!(
+            theme.paint_icon("📹", true, red),
+            Span::new("📹").color(AnsiColors::Magenta)
+        );
+        assert_eq!(
+            theme.paint_icon("•", false, red),
+            Span::new("•").color(AnsiColors::Red)
+        );
+        assert_eq!(theme.paint_icon("•", false, None), Span::new("•"));
+    }
+}
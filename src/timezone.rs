@@ -0,0 +1,319 @@
+use crate::calendar::parse_utc_offset;
+use crate::recurrence::RRule;
+use chrono::{Duration, NaiveDateTime};
+use icalendar::{Calendar, CalendarComponent, Component};
+use std::collections::HashMap;
+
+/// TZIDs that mean UTC even when the feed doesn't define them.
+const UTC_TZIDS: &[&str] = &["UTC", "Etc/UTC", "GMT", "Etc/GMT", "Z"];
+
+/// The user's local zone: the current UTC offset, plus the IANA name when the host reports one.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LocalZone {
+    pub utc_offset_minutes: i32,
+    pub tzid: Option<String>,
+}
+
+impl LocalZone {
+    /// Parses the zone name printed by the time fetch (`$TZ` or the `/etc/localtime` link).
+    pub fn parse_tzid(s: &str) -> Option<String> {
+        let s = s.trim().trim_start_matches(':');
+        let name = s.rsplit_once("zoneinfo/").map_or(s, |(_, name)| name);
+        (!name.is_empty()).then(|| name.to_string())
+    }
+}
+
+/// One STANDARD or DAYLIGHT block of a VTIMEZONE.
+#[derive(Debug, Clone)]
+struct Observance {
+    start: NaiveDateTime, // Wall time of the first onset, in `offset_from`
+    offset_from: i32,
+    offset_to: i32,
+    rule: Option<RRule>,
+    rdates: Vec<NaiveDateTime>,
+}
+
+impl Observance {
+    fn parse(component: &impl Component) -> Option<Self> {
+        let start = parse_local(component.property_value("DTSTART")?)?;
+        let offset_to = parse_tz_offset(component.property_value("TZOFFSETTO")?)?;
+        let offset_from = component
+            .property_value("TZOFFSETFROM")
+            .and_then(parse_tz_offset)
+            .unwrap_or(offset_to);
+        let rule = component
+            .property_value("RRULE")
+            .and_then(|r| RRule::parse(r, 0).ok());
+        let rdates = component
+            .multi_properties()
+            .get("RDATE")
+            .into_iter()
+            .flatten()
+            .flat_map(|prop| prop.value().split(','))
+            .filter_map(parse_local)
+            .collect();
+        Some(Observance {
+            start,
+            offset_from,
+            offset_to,
+            rule,
+            rdates,
+        })
+    }
+
+    /// Latest onset at or before `local`.
+    fn last_onset(&self, local: NaiveDateTime) -> Option<NaiveDateTime> {
+        let end = local + Duration::seconds(1);
+        let from_rule = self.rule.as_ref().and_then(|rule| {
+            // Yearly rules have an onset within the past year; fall back to a full scan for
+            // rules that ended (UNTIL) before it
+            rule.occurrences(self.start, local - Duration::days(366), end)
+                .last()
+                .copied()
+                .or_else(|| {
+                    rule.occurrences(self.start, self.start, end)
+                        .last()
+                        .copied()
+                })
+        });
+        std::iter::once(self.start)
+            .chain(self.rdates.iter().copied())
+            .chain(from_rule)
+            .filter(|onset| *onset <= local)
+            .max()
+    }
+}
+
+/// A zone definition from a feed's VTIMEZONE component.
+#[derive(Debug, Clone)]
+pub struct VTimezone {
+    pub tzid: String,
+    observances: Vec<Observance>,
+}
+
+impl VTimezone {
+    pub fn parse(component: &impl Component) -> Option<Self> {
+        let tzid = component.property_value("TZID")?.to_string();
+        let observances: Vec<Observance> = component
+            .components()
+            .iter()
+            .filter(|c| matches!(c.component_kind().as_str(), "STANDARD" | "DAYLIGHT"))
+            .filter_map(Observance::parse)
+            .collect();
+        (!observances.is_empty()).then_some(VTimezone { tzid, observances })
+    }
+
+    /// UTC offset in minutes in effect at the given wall-clock time in this zone.
+    pub fn offset_at(&self, local: NaiveDateTime) -> i32 {
+        self.observances
+            .iter()
+            .filter_map(|o| Some((o.last_onset(local)?, o.offset_to)))
+            .max_by_key(|(onset, _)| *onset)
+            .map(|(_, offset)| offset)
+            .or_else(|| {
+                // Before the first onset, the zone was on the earliest block's "from" offset
+                self.observances
+                    .iter()
+                    .min_by_key(|o| o.start)
+                    .map(|o| o.offset_from)
+            })
+            .unwrap_or(0)
+    }
+}
+
+/// Converts ICS date-times to the user's local wall-clock time.
+pub struct Timezones {
+    zones: HashMap<String, VTimezone>,
+    local: LocalZone,
+}
+
+impl Timezones {
+    pub fn new(calendar: &Calendar, local: &LocalZone) -> Self {
+        let zones = calendar
+            .components
+            .iter()
+            .filter_map(|component| match component {
+                CalendarComponent::Other(other) if other.component_kind() == "VTIMEZONE" => {
+                    VTimezone::parse(other)
+                }
+                _ => None,
+            })
+            .map(|tz| (tz.tzid.clone(), tz))
+            .collect();
+        Timezones {
+            zones,
+            local: local.clone(),
+        }
+    }
+
+    pub fn utc_offset_minutes(&self) -> i32 {
+        self.local.utc_offset_minutes
+    }
+
+    /// Local time for a UTC instant. When the feed defines the user's own zone, its rules give
+    /// the offset on that date; otherwise the current offset is used.
+    pub fn utc_to_local(&self, utc: NaiveDateTime) -> NaiveDateTime {
+        let current = utc + Duration::minutes(self.local.utc_offset_minutes as i64);
+        match self.local_zone() {
+            Some(zone) => utc + Duration::minutes(zone.offset_at(current) as i64),
+            None => current,
+        }
+    }
+
+    /// Local time for a wall-clock time in `tzid`. Zones the feed doesn't define are assumed
+    /// to be local, since there are no rules to convert them with.
+    pub fn zoned_to_local(&self, date_time: NaiveDateTime, tzid: &str) -> NaiveDateTime {
+        let tzid = tzid.trim_matches('"');
+        if self.local.tzid.as_deref() == Some(tzid) {
+            return date_time;
+        }
+        match self.zones.get(tzid) {
+            Some(zone) => {
+                self.utc_to_local(date_time - Duration::minutes(zone.offset_at(date_time) as i64))
+            }
+            None if UTC_TZIDS.contains(&tzid) => self.utc_to_local(date_time),
+            None => date_time,
+        }
+    }
+
+    fn local_zone(&self) -> Option<&VTimezone> {
+        self.zones.get(self.local.tzid.as_deref()?)
+    }
+}
+
+/// Parses a floating DATE-TIME (e.g., "19701025T030000").
+fn parse_local(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value.trim(), "%Y%m%dT%H%M%S").ok()
+}
+
+/// Parses TZOFFSETFROM/TZOFFSETTO ("+0100", "-0500", or with seconds "+053000").
+fn parse_tz_offset(value: &str) -> Option<i32> {
+    let value = value.trim();
+    parse_utc_offset(value.get(..5)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::parse_datetime;
+    use indoc::indoc;
+
+    const ICS_ZONES: &str = indoc! {"
+        BEGIN:VCALENDAR
+        VERSION:2.0
+        BEGIN:VTIMEZONE
+        TZID:America/New_York
+        BEGIN:DAYLIGHT
+        TZOFFSETFROM:-0500
+        TZOFFSETTO:-0400
+        DTSTART:20070311T020000
+        RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU
+        END:DAYLIGHT
+        BEGIN:STANDARD
+        TZOFFSETFROM:-0400
+        TZOFFSETTO:-0500
+        DTSTART:20071104T020000
+        RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU
+        END:STANDARD
+        END:VTIMEZONE
+        BEGIN:VTIMEZONE
+        TZID:Europe/Berlin
+        BEGIN:DAYLIGHT
+        TZOFFSETFROM:+0100
+        TZOFFSETTO:+0200
+        DTSTART:19810329T020000
+        RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU
+        END:DAYLIGHT
+        BEGIN:STANDARD
+        TZOFFSETFROM:+0200
+        TZOFFSETTO:+0100
+        DTSTART:19961027T030000
+        RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU
+        END:STANDARD
+        END:VTIMEZONE
+        END:VCALENDAR
+    "};
+
+    fn dt(s: &str) -> NaiveDateTime {
+        parse_datetime(s).unwrap()
+    }
+
+    fn timezones(local: LocalZone) -> Timezones {
+        let calendar: Calendar = ICS_ZONES.parse().unwrap();
+        Timezones::new(&calendar, &local)
+    }
+
+    #[test]
+    fn test_offset_at() {
+        let tz = timezones(LocalZone::default());
+        let ny = &tz.zones["America/New_York"];
+        assert_eq!(ny.offset_at(dt("2024-01-15 09:00")), -300);
+        assert_eq!(ny.offset_at(dt("2024-03-10 01:59")), -300);
+        assert_eq!(ny.offset_at(dt("2024-03-10 03:00")), -240);
+        assert_eq!(ny.offset_at(dt("2024-07-01 09:00")), -240);
+        assert_eq!(ny.offset_at(dt("2024-11-03 03:00")), -300);
+        // Before the first onset
+        assert_eq!(ny.offset_at(dt("2000-01-01 00:00")), -300);
+
+        let berlin = &tz.zones["Europe/Berlin"];
+        assert_eq!(berlin.offset_at(dt("2024-01-15 09:00")), 60);
+        assert_eq!(berlin.offset_at(dt("2024-07-01 09:00")), 120);
+    }
+
+    #[test]
+    fn test_zoned_to_local() {
+        // Known local zone: DST-aware on both sides, and same-zone times are untouched
+        let tz = timezones(LocalZone {
+            utc_offset_minutes: -300,
+            tzid: Some("America/New_York".to_string()),
+        });
+        let berlin = |s| tz.zoned_to_local(dt(s), "Europe/Berlin");
+        assert_eq!(berlin("2024-01-15 15:00"), dt("2024-01-15 09:00"));
+        assert_eq!(berlin("2024-07-01 15:00"), dt("2024-07-01 09:00"));
+        // US and EU switch on different weeks
+        assert_eq!(berlin("2024-03-20 15:00"), dt("2024-03-20 10:00"));
+        assert_eq!(
+            tz.zoned_to_local(dt("2024-07-01 09:00"), "America/New_York"),
+            dt("2024-07-01 09:00")
+        );
+        assert_eq!(
+            tz.utc_to_local(dt("2024-07-01 13:00")),
+            dt("2024-07-01 09:00")
+        );
+        assert_eq!(
+            tz.zoned_to_local(dt("2024-07-01 13:00"), "Etc/UTC"),
+            dt("2024-07-01 09:00")
+        );
+
+        // Unknown local zone: the current offset applies; unknown event zones stay as-is
+        let tz = timezones(LocalZone {
+            utc_offset_minutes: 60,
+            tzid: None,
+        });
+        assert_eq!(
+            tz.zoned_to_local(dt("2024-01-15 09:00"), "America/New_York"),
+            dt("2024-01-15 15:00")
+        );
+        assert_eq!(
+            tz.zoned_to_local(dt("2024-01-15 09:00"), "Asia/Tokyo"),
+            dt("2024-01-15 09:00")
+        );
+    }
+
+    #[test]
+    fn test_parse_tzid() {
+        assert_eq!(
+            LocalZone::parse_tzid("/usr/share/zoneinfo/America/New_York\n").as_deref(),
+            Some("America/New_York")
+        );
+        assert_eq!(
+            LocalZone::parse_tzid("/var/db/timezone/zoneinfo/Europe/Berlin").as_deref(),
+            Some("Europe/Berlin")
+        );
+        assert_eq!(
+            LocalZone::parse_tzid(":Asia/Tokyo").as_deref(),
+            Some("Asia/Tokyo")
+        );
+        assert_eq!(LocalZone::parse_tzid(""), None);
+    }
+}
@@ -0,0 +1,439 @@
+use crate::timezone::LocalZone;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
+
+/// UTC offset rules of a zone, from a compiled zoneinfo (TZif) file like `/etc/localtime`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ZoneRules {
+    /// Offset before the first transition, in seconds.
+    initial: i32,
+    /// (UTC instant in Unix seconds, offset in seconds from then on), sorted.
+    transitions: Vec<(i64, i32)>,
+    /// Rule for instants after the last transition (the file's POSIX TZ footer).
+    footer: Option<PosixTz>,
+}
+
+impl ZoneRules {
+    /// Parses a TZif file (versions 1-4; the 64-bit section when present).
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if !data.starts_with(b"TZif") {
+            return None;
+        }
+        let header = Header::parse(data.get(20..44)?)?;
+        if data[4] < b'2' {
+            return Self::parse_block(&data[44..], &header, 4, None);
+        }
+        // Skip the 32-bit block for the 64-bit one, which is followed by the footer
+        let v2 = data.get(44 + header.block_len(4)..)?;
+        if !v2.starts_with(b"TZif") {
+            return None;
+        }
+        let header = Header::parse(v2.get(20..44)?)?;
+        let block = v2.get(44..)?;
+        let footer = block
+            .get(header.block_len(8)..)
+            .and_then(|rest| std::str::from_utf8(rest).ok())
+            .and_then(|rest| rest.trim_matches('\n').lines().next())
+            .and_then(PosixTz::parse);
+        Self::parse_block(block, &header, 8, footer)
+    }
+
+    fn parse_block(
+        block: &[u8],
+        header: &Header,
+        time_size: usize,
+        footer: Option<PosixTz>,
+    ) -> Option<Self> {
+        let mut pos = 0;
+        let mut take = |len: usize| {
+            let bytes = block.get(pos..pos + len);
+            pos += len;
+            bytes
+        };
+        let times: Vec<i64> = take(header.timecnt * time_size)?
+            .chunks(time_size)
+            .map(|c| match time_size {
+                4 => i32::from_be_bytes(c.try_into().unwrap()) as i64,
+                _ => i64::from_be_bytes(c.try_into().unwrap()),
+            })
+            .collect();
+        let indices = take(header.timecnt)?.to_vec();
+        let offsets: Vec<i32> = take(header.typecnt * 6)?
+            .chunks(6)
+            .map(|c| i32::from_be_bytes(c[..4].try_into().unwrap()))
+            .collect();
+        let transitions = times
+            .into_iter()
+            .zip(indices)
+            .map(|(time, idx)| Some((time, *offsets.get(idx as usize)?)))
+            .collect::<Option<Vec<_>>>()?;
+        Some(ZoneRules {
+            initial: *offsets.first()?,
+            transitions,
+            footer,
+        })
+    }
+
+    /// A fixed offset, for zones given as just `utc_offset`.
+    pub fn fixed(offset_minutes: i32) -> Self {
+        ZoneRules {
+            initial: offset_minutes * 60,
+            ..Default::default()
+        }
+    }
+
+    /// UTC offset in minutes in effect at a UTC instant.
+    pub fn offset_at(&self, utc: NaiveDateTime) -> i32 {
+        let secs = utc.and_utc().timestamp();
+        let last = self.transitions.iter().rposition(|(t, _)| *t <= secs);
+        let seconds = match (last, &self.footer) {
+            (Some(i), Some(footer)) if i + 1 == self.transitions.len() => footer.offset_at(utc),
+            (None, Some(footer)) if self.transitions.is_empty() => footer.offset_at(utc),
+            (Some(i), _) => self.transitions[i].1,
+            (None, _) => self.initial,
+        };
+        seconds / 60
+    }
+}
+
+/// Shell command that prints the zone name, then the zone's TZif file: `timezone`'s when
+/// configured, else `$TZ`'s or `/etc/localtime`.
+pub fn read_command(timezone: Option<&str>) -> Vec<String> {
+    let script = r#"printf '%s\n' "${1:-${TZ:-$(readlink /etc/localtime)}}"; zone="${1:-${TZ#:}}"; case "$zone" in "") cat /etc/localtime ;; /*) cat "$zone" ;; *) cat "/usr/share/zoneinfo/$zone" ;; esac"#;
+    vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        script.to_string(),
+        "sh".to_string(),
+        timezone.unwrap_or_default().to_string(),
+    ]
+}
+
+/// Splits the read command's output into the zone name and its rules.
+pub fn parse_read_output(stdout: &[u8]) -> (Option<String>, Option<ZoneRules>) {
+    let (name, data) = match stdout.iter().position(|&b| b == b'\n') {
+        Some(idx) => (&stdout[..idx], &stdout[idx + 1..]),
+        None => (stdout, &[][..]),
+    };
+    (
+        LocalZone::parse_tzid(&String::from_utf8_lossy(name)),
+        ZoneRules::parse(data),
+    )
+}
+
+/// Record counts from a TZif header.
+struct Header {
+    isutcnt: usize,
+    isstdcnt: usize,
+    leapcnt: usize,
+    timecnt: usize,
+    typecnt: usize,
+    charcnt: usize,
+}
+
+impl Header {
+    fn parse(counts: &[u8]) -> Option<Self> {
+        let count = |i: usize| -> Option<usize> {
+            Some(u32::from_be_bytes(counts.get(i * 4..i * 4 + 4)?.try_into().ok()?) as usize)
+        };
+        Some(Header {
+            isutcnt: count(0)?,
+            isstdcnt: count(1)?,
+            leapcnt: count(2)?,
+            timecnt: count(3)?,
+            typecnt: count(4)?,
+            charcnt: count(5)?,
+        })
+    }
+
+    /// Bytes of the data block after the header.
+    fn block_len(&self, time_size: usize) -> usize {
+        self.timecnt * (time_size + 1)
+            + self.typecnt * 6
+            + self.charcnt
+            + self.leapcnt * (time_size + 4)
+            + self.isstdcnt
+            + self.isutcnt
+    }
+}
+
+/// A POSIX TZ rule such as "CET-1CEST,M3.5.0,M10.5.0/3" (offsets in seconds east of UTC).
+#[derive(Debug, Clone, PartialEq)]
+struct PosixTz {
+    std_offset: i32,
+    dst: Option<DstRule>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct DstRule {
+    offset: i32,
+    /// When DST starts (in standard time) and ends (in DST), as (day, seconds after midnight).
+    start: (TransitionDay, i32),
+    end: (TransitionDay, i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TransitionDay {
+    /// `Mm.w.d`: weekday `d` (0 = Sunday) of week `w` (5 = last) of month `m`.
+    MonthWeekDay(u32, u32, u32),
+    /// `Jn`: day 1-365, never counting February 29.
+    Julian(u32),
+    /// `n`: day 0-365, counting February 29.
+    Ordinal(u32),
+}
+
+impl PosixTz {
+    fn parse(s: &str) -> Option<Self> {
+        let rest = skip_name(s)?;
+        let (std_offset, rest) = parse_offset(rest)?;
+        // POSIX offsets count west of UTC
+        let std_offset = -std_offset;
+        if rest.is_empty() {
+            return Some(PosixTz {
+                std_offset,
+                dst: None,
+            });
+        }
+        let rest = skip_name(rest)?;
+        let (offset, rest) = match rest.chars().next()? {
+            ',' => (std_offset + 3600, rest),
+            _ => {
+                let (offset, rest) = parse_offset(rest)?;
+                (-offset, rest)
+            }
+        };
+        let mut rules = rest.strip_prefix(',')?.split(',');
+        let start = parse_transition(rules.next()?)?;
+        let end = parse_transition(rules.next()?)?;
+        Some(PosixTz {
+            std_offset,
+            dst: Some(DstRule { offset, start, end }),
+        })
+    }
+
+    fn offset_at(&self, utc: NaiveDateTime) -> i32 {
+        let Some(ref dst) = self.dst else {
+            return self.std_offset;
+        };
+        let year = utc.year();
+        let instant = |(day, secs): (TransitionDay, i32), offset: i32| {
+            Some(day.date(year)?.and_hms_opt(0, 0, 0)? + Duration::seconds((secs - offset) as i64))
+        };
+        let (Some(start), Some(end)) = (
+            instant(dst.start, self.std_offset),
+            instant(dst.end, dst.offset),
+        ) else {
+            return self.std_offset;
+        };
+        // Southern-hemisphere rules start DST late in the year and end it early
+        let in_dst = if start < end {
+            start <= utc && utc < end
+        } else {
+            !(end <= utc && utc < start)
+        };
+        if in_dst {
+            dst.offset
+        } else {
+            self.std_offset
+        }
+    }
+}
+
+impl TransitionDay {
+    fn date(self, year: i32) -> Option<NaiveDate> {
+        match self {
+            TransitionDay::MonthWeekDay(month, week, weekday) => {
+                let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+                let first_weekday = first.weekday().num_days_from_sunday();
+                let day = 1 + (weekday + 7 - first_weekday) % 7 + (week - 1) * 7;
+                // Week 5 means the last one, which may be the 4th
+                let next_month = match month {
+                    12 => NaiveDate::from_ymd_opt(year + 1, 1, 1)?,
+                    _ => NaiveDate::from_ymd_opt(year, month + 1, 1)?,
+                };
+                let days_in_month = (next_month - first).num_days() as u32;
+                let day = if day > days_in_month { day - 7 } else { day };
+                NaiveDate::from_ymd_opt(year, month, day)
+            }
+            TransitionDay::Julian(n) => {
+                let is_leap = NaiveDate::from_ymd_opt(year, 2, 29).is_some();
+                NaiveDate::from_yo_opt(year, if is_leap && n >= 60 { n + 1 } else { n })
+            }
+            TransitionDay::Ordinal(n) => NaiveDate::from_yo_opt(year, n + 1),
+        }
+    }
+}
+
+/// Skips a zone abbreviation: letters ("CEST") or quoted ("<+0330>").
+fn skip_name(s: &str) -> Option<&str> {
+    if let Some(quoted) = s.strip_prefix('<') {
+        return Some(&quoted[quoted.find('>')? + 1..]);
+    }
+    let end = s
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(s.len());
+    (end >= 3).then(|| &s[end..])
+}
+
+/// Parses `[+-]hh[:mm[:ss]]` into seconds, returning the rest of the string.
+fn parse_offset(s: &str) -> Option<(i32, &str)> {
+    let (sign, s) = match s.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let end = s
+        .find(|c: char| !c.is_ascii_digit() && c != ':')
+        .unwrap_or(s.len());
+    let mut secs = 0;
+    for (part, unit) in s[..end].split(':').zip([3600, 60, 1]) {
+        secs += part.parse::<i32>().ok()? * unit;
+    }
+    Some((sign * secs, &s[end..]))
+}
+
+/// Parses a transition like "M3.5.0/3", "J60", or "59/-1"; the time defaults to 02:00.
+fn parse_transition(s: &str) -> Option<(TransitionDay, i32)> {
+    let (day, time) = match s.split_once('/') {
+        Some((day, time)) => (
+            day,
+            parse_offset(time).filter(|(_, rest)| rest.is_empty())?.0,
+        ),
+        None => (s, 2 * 3600),
+    };
+    let day = if let Some(mwd) = day.strip_prefix('M') {
+        let mut parts = mwd.split('.').map(|p| p.parse::<u32>().ok());
+        let (month, week, weekday) = (parts.next()??, parts.next()??, parts.next()??);
+        let valid = (1..=12).contains(&month) && (1..=5).contains(&week) && weekday <= 6;
+        valid.then_some(TransitionDay::MonthWeekDay(month, week, weekday))?
+    } else if let Some(n) = day.strip_prefix('J') {
+        TransitionDay::Julian(n.parse().ok().filter(|n| (1..=365).contains(n))?)
+    } else {
+        TransitionDay::Ordinal(day.parse().ok().filter(|n| *n <= 365)?)
+    };
+    Some((day, time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::parse_datetime;
+
+    fn dt(s: &str) -> NaiveDateTime {
+        parse_datetime(s).unwrap()
+    }
+
+    /// Builds a TZif file with the given transitions, types, and footer.
+    fn tzif(version: u8, transitions: &[(i64, u8)], offsets: &[i32], footer: &str) -> Vec<u8> {
+        let block = |time_size: usize| {
+            let mut header = b"TZif".to_vec();
+            header.push(version);
+            header.extend([0; 15]);
+            let counts = [0, 0, 0, transitions.len(), offsets.len(), 4];
+            for count in counts {
+                header.extend((count as u32).to_be_bytes());
+            }
+            for (time, _) in transitions {
+                match time_size {
+                    4 => header.extend((*time as i32).to_be_bytes()),
+                    _ => header.extend(time.to_be_bytes()),
+                }
+            }
+            header.extend(transitions.iter().map(|(_, idx)| idx));
+            for (i, offset) in offsets.iter().enumerate() {
+                header.extend(offset.to_be_bytes());
+                header.extend([(i > 0) as u8, 0]);
+            }
+            header.extend(b"LMT\0");
+            header
+        };
+        let mut data = block(4);
+        if version >= b'2' {
+            data.extend(block(8));
+            data.extend(format!("\n{}\n", footer).as_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn test_parse_tzif() {
+        // Berlin with two explicit transitions in 2024, then the footer rule
+        let march = dt("2024-03-31 01:00").and_utc().timestamp();
+        let october = dt("2024-10-27 01:00").and_utc().timestamp();
+        let data = tzif(
+            b'2',
+            &[(march, 1), (october, 0)],
+            &[3600, 7200],
+            "CET-1CEST,M3.5.0,M10.5.0/3",
+        );
+        let rules = ZoneRules::parse(&data).unwrap();
+        assert_eq!(rules.offset_at(dt("2024-01-15 09:00")), 60);
+        assert_eq!(rules.offset_at(dt("2024-03-31 00:59")), 60);
+        assert_eq!(rules.offset_at(dt("2024-03-31 01:00")), 120);
+        assert_eq!(rules.offset_at(dt("2024-07-01 09:00")), 120);
+        // After the last transition, the footer applies
+        assert_eq!(rules.offset_at(dt("2024-12-01 09:00")), 60);
+        assert_eq!(rules.offset_at(dt("2030-07-01 09:00")), 120);
+        assert_eq!(rules.offset_at(dt("2030-03-31 00:59")), 60);
+
+        // Slim files may have no transitions at all
+        let slim = tzif(b'2', &[], &[-18000], "EST5EDT,M3.2.0,M11.1.0");
+        let rules = ZoneRules::parse(&slim).unwrap();
+        assert_eq!(rules.offset_at(dt("2024-01-15 14:00")), -300);
+        assert_eq!(rules.offset_at(dt("2024-03-10 06:59")), -300);
+        assert_eq!(rules.offset_at(dt("2024-03-10 07:00")), -240);
+        assert_eq!(rules.offset_at(dt("2024-11-03 05:59")), -240);
+        assert_eq!(rules.offset_at(dt("2024-11-03 06:00")), -300);
+
+        // Version 1 (32-bit only, no footer)
+        let v1 = tzif(b'\0', &[(march, 1)], &[3600, 7200], "");
+        let rules = ZoneRules::parse(&v1).unwrap();
+        assert_eq!(rules.offset_at(dt("2030-01-01 00:00")), 120);
+
+        assert!(ZoneRules::parse(b"not a zone").is_none());
+        assert!(ZoneRules::parse(&data[..60]).is_none());
+    }
+
+    #[test]
+    fn test_parse_read_output() {
+        let mut stdout = b"/usr/share/zoneinfo/Europe/Berlin\n".to_vec();
+        stdout.extend(tzif(b'2', &[], &[3600], "CET-1CEST,M3.5.0,M10.5.0/3"));
+        let (name, rules) = parse_read_output(&stdout);
+        assert_eq!(name.as_deref(), Some("Europe/Berlin"));
+        assert_eq!(rules.unwrap().offset_at(dt("2024-07-01 09:00")), 120);
+
+        // No zone file (exotic `$TZ` or no zoneinfo): the `date` fallback takes over
+        let (name, rules) = parse_read_output(b"EST5EDT\n");
+        assert_eq!(name.as_deref(), Some("EST5EDT"));
+        assert!(rules.is_none());
+        assert_eq!(parse_read_output(b""), (None, None));
+    }
+
+    #[test]
+    fn test_posix_tz() {
+        let offset = |tz: &str, utc: &str| PosixTz::parse(tz).unwrap().offset_at(dt(utc)) / 60;
+        // Southern hemisphere: DST spans the new year
+        let sydney = "AEST-10AEDT,M10.1.0,M4.1.0/3";
+        assert_eq!(offset(sydney, "2024-01-15 00:00"), 660);
+        assert_eq!(offset(sydney, "2024-07-01 00:00"), 600);
+        assert_eq!(offset(sydney, "2024-10-05 15:59"), 600);
+        assert_eq!(offset(sydney, "2024-10-05 16:00"), 660);
+        // Quoted names, half-hour offsets, and no DST
+        assert_eq!(offset("<+0530>-5:30", "2024-07-01 00:00"), 330);
+        assert_eq!(offset("<-03>3", "2024-07-01 00:00"), -180);
+        assert_eq!(offset("UTC0", "2024-07-01 00:00"), 0);
+        assert!(PosixTz::parse("CET-1CEST,M13.5.0,M10.5.0").is_none());
+        assert!(PosixTz::parse("").is_none());
+    }
+
+    #[test]
+    fn test_transition_day() {
+        let date = |d: TransitionDay| d.date(2024).unwrap().to_string();
+        // Last Sunday of March, first Sunday of November
+        assert_eq!(date(TransitionDay::MonthWeekDay(3, 5, 0)), "2024-03-31");
+        assert_eq!(date(TransitionDay::MonthWeekDay(11, 1, 0)), "2024-11-03");
+        assert_eq!(date(TransitionDay::MonthWeekDay(2, 5, 4)), "2024-02-29");
+        assert_eq!(date(TransitionDay::MonthWeekDay(12, 5, 2)), "2024-12-31");
+        // J60 skips Feb 29; plain 59 counts it
+        assert_eq!(date(TransitionDay::Julian(60)), "2024-03-01");
+        assert_eq!(date(TransitionDay::Ordinal(59)), "2024-02-29");
+    }
+}